@@ -0,0 +1,186 @@
+//! Benchmarks for the per-point hot paths.
+//!
+//! Run with `cargo bench --bench leeward`. The benchmarks use simulated points
+//! along the example trajectory, so the fixture size can be changed with the
+//! `LEEWARD_BENCH_POINTS` environment variable (default 10,000).
+//!
+//! To write the fixture to disk, e.g. to profile the command line tool:
+//!
+//! ```text
+//! cargo bench --bench leeward -- --bench-fixture fixture --points 1000000
+//! ```
+
+use criterion::{criterion_group, BatchSize, Criterion, Throughput};
+use leeward::{
+    simulate::{self, Options, Terrain},
+    Config, Dimension, Measurement, Point, Trajectory, Variable,
+};
+use std::{hint::black_box, path::Path};
+
+const DEFAULT_POINTS: usize = 10_000;
+
+/// The elevation of the simulated terrain, just below the example points.
+const ELEVATION: f64 = 2690.;
+
+struct Fixture {
+    trajectory: Trajectory,
+    config: Config,
+    points: Vec<las::Point>,
+}
+
+impl Fixture {
+    fn new(count: usize) -> Fixture {
+        let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+        let config = Config::from_path("data/config.toml").unwrap();
+        let points = trajectory.points();
+        let duration = points[points.len() - 1].time - points[0].time;
+        let options = Options {
+            pulse_rate: count as f64 / duration,
+            ..Default::default()
+        };
+        let mut points = simulate::simulate(
+            &trajectory,
+            config.clone(),
+            &Terrain::Plane(ELEVATION),
+            &options,
+        )
+        .unwrap();
+        points.truncate(count);
+        Fixture {
+            trajectory,
+            config,
+            points,
+        }
+    }
+
+    fn measurements(&self) -> Vec<Measurement<las::Point>> {
+        self.points
+            .iter()
+            .map(|point| {
+                let mut measurement =
+                    Measurement::new(&self.trajectory, point.clone(), self.config.clone()).unwrap();
+                measurement.use_las_scan_angle(true);
+                measurement
+            })
+            .collect()
+    }
+
+    fn write(&self, directory: &Path) {
+        std::fs::create_dir_all(directory).unwrap();
+        std::fs::copy("data/sbet.out", directory.join("sbet.out")).unwrap();
+        std::fs::copy("data/config.toml", directory.join("config.toml")).unwrap();
+        // The example points' header has scales and offsets that fit the simulated points.
+        let reader = las::Reader::from_path("data/points.las").unwrap();
+        let mut builder = las::Builder::from(las::Read::header(&reader).clone());
+        builder.point_format = las::point::Format::new(1).unwrap();
+        let header = builder.into_header().unwrap();
+        let mut writer = las::Writer::from_path(directory.join("points.las"), header).unwrap();
+        for point in &self.points {
+            las::Write::write(&mut writer, point.clone()).unwrap();
+        }
+        writer.close().unwrap();
+    }
+}
+
+fn benchmarks(c: &mut Criterion, fixture: &Fixture) {
+    let measurements = fixture.measurements();
+    let normal = Point::new(0., 0., 1.);
+    let mut group = c.benchmark_group("per_point");
+    group.throughput(Throughput::Elements(fixture.points.len() as u64));
+    group.bench_function("measurement", |b| {
+        b.iter(|| black_box(fixture.measurements()))
+    });
+    // Measurements cache their lidar equation terms, so each iteration gets fresh ones.
+    let fresh = || {
+        measurements
+            .iter()
+            .map(|measurement| measurement.with_config(fixture.config.clone()))
+            .collect::<Vec<_>>()
+    };
+    group.bench_function("body_frame", |b| {
+        b.iter_batched(
+            fresh,
+            |measurements| {
+                for measurement in &measurements {
+                    black_box(measurement.body_frame());
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("jacobian", |b| {
+        b.iter_batched(
+            fresh,
+            |measurements| {
+                for measurement in &measurements {
+                    for variable in Variable::iter() {
+                        for dimension in Dimension::iter() {
+                            black_box(
+                                measurement.partial_derivative_in_body_frame(dimension, variable),
+                            );
+                        }
+                    }
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("tpu", |b| {
+        b.iter_batched(
+            fresh,
+            |measurements| {
+                for measurement in &measurements {
+                    black_box(measurement.tpu(normal).unwrap());
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("tpu_batch", |b| {
+        b.iter_batched(
+            fresh,
+            |measurements| black_box(leeward::tpu_batch(&measurements, normal).unwrap()),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn per_point(c: &mut Criterion) {
+    let count = std::env::var("LEEWARD_BENCH_POINTS")
+        .ok()
+        .map(|count| {
+            count
+                .parse()
+                .expect("LEEWARD_BENCH_POINTS must be a number")
+        })
+        .unwrap_or(DEFAULT_POINTS);
+    benchmarks(c, &Fixture::new(count));
+}
+
+criterion_group!(benches, per_point);
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--bench-fixture") {
+        let directory = args
+            .get(index + 1)
+            .expect("--bench-fixture requires a directory");
+        let count = args
+            .iter()
+            .position(|arg| arg == "--points")
+            .and_then(|index| args.get(index + 1))
+            .map(|count| count.parse().expect("--points must be a number"))
+            .unwrap_or(DEFAULT_POINTS);
+        let fixture = Fixture::new(count);
+        fixture.write(Path::new(directory));
+        eprintln!(
+            "wrote {} points to {}",
+            fixture.points.len(),
+            Path::new(directory).display()
+        );
+        return;
+    }
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,119 @@
+//! Computes TPU for points stored in a simple binary format.
+//!
+//! Each point is four little-endian f64s: x, y, z, and gps time. There's no
+//! header, so the number of points is the file size divided by 32 bytes.
+//!
+//! ```text
+//! cargo run --example xyzt -- data/sbet.out points.xyzt data/config.toml
+//! ```
+//!
+//! Without arguments, the example converts `data/points.las` to this format in
+//! memory and uses the example trajectory and config.
+
+use las::Read as _;
+use leeward::PointSource;
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind, Read, Write},
+};
+
+/// The size of one point, in bytes.
+const RECORD_LENGTH: usize = 32;
+
+#[derive(Clone, Debug)]
+struct Xyzt {
+    x: f64,
+    y: f64,
+    z: f64,
+    time: f64,
+}
+
+impl PointSource for Xyzt {
+    type Extra = ();
+
+    fn time(&self) -> Option<f64> {
+        Some(self.time)
+    }
+
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn z(&self) -> f64 {
+        self.z
+    }
+}
+
+/// Reads points until the end of the stream.
+fn read<R: Read>(mut read: R) -> std::io::Result<Vec<Xyzt>> {
+    let mut points = Vec::new();
+    let mut buf = [0; RECORD_LENGTH];
+    loop {
+        match read.read_exact(&mut buf) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(points),
+            Err(err) => return Err(err),
+        }
+        let value = |i: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&buf[i * 8..(i + 1) * 8]);
+            f64::from_le_bytes(bytes)
+        };
+        points.push(Xyzt {
+            x: value(0),
+            y: value(1),
+            z: value(2),
+            time: value(3),
+        });
+    }
+}
+
+/// Writes a las point as a record, skipping points without time.
+fn write<W: Write>(mut write: W, point: &las::Point) -> std::io::Result<()> {
+    if let Some(time) = point.gps_time {
+        for value in [point.x, point.y, point.z, time].iter() {
+            write.write_all(&value.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (sbet, points, config) = match args.as_slice() {
+        [sbet, xyzt, config] => (
+            sbet.as_str(),
+            read(BufReader::new(File::open(xyzt)?))?,
+            config.as_str(),
+        ),
+        [] => {
+            let mut buf = Vec::new();
+            for point in las::Reader::from_path("data/points.las")?.points() {
+                write(&mut buf, &point?)?;
+            }
+            ("data/sbet.out", read(buf.as_slice())?, "data/config.toml")
+        }
+        _ => return Err("usage: xyzt [<sbet> <xyzt> <config>]".into()),
+    };
+    let (measurements, summary) =
+        leeward::measurements_from_points(&[sbet], points, config, &Default::default())?;
+    eprintln!("{} measurements, {:?}", measurements.len(), summary);
+    println!("x,y,z,time,horizontal_tpu,vertical_tpu");
+    for measurement in measurements {
+        let tpu = measurement.tpu(leeward::Point::new(0., 0., 1.))?;
+        println!(
+            "{},{},{},{},{},{}",
+            measurement.x(),
+            measurement.y(),
+            measurement.z(),
+            measurement.time(),
+            tpu.horizontal,
+            tpu.vertical,
+        );
+    }
+    Ok(())
+}
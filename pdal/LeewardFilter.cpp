@@ -18,6 +18,9 @@ namespace pdal
         this->m_verticalUncertainty = layout->registerOrAssignDim("VerticalUncertainty", Dimension::Type::Float);
         this->m_totalUncertainty = layout->registerOrAssignDim("TotalUncertainty", Dimension::Type::Float);
         this->m_incidenceAngle = layout->registerOrAssignDim("IncidenceAngle", Dimension::Type::Float);
+        this->m_bodyFrameX = layout->registerOrAssignDim("BodyFrameX", Dimension::Type::Double);
+        this->m_bodyFrameY = layout->registerOrAssignDim("BodyFrameY", Dimension::Type::Double);
+        this->m_bodyFrameZ = layout->registerOrAssignDim("BodyFrameZ", Dimension::Type::Double);
     }
 
     void LeewardFilter::addArgs(ProgramArgs &args)
@@ -67,6 +70,13 @@ namespace pdal
             {
                 std::cerr << "filters.leeward: error when creating measurement at time " << point.time << ", skipping..." << std::endl;
             }
+            double body_frame[3];
+            if (leeward_body_frame(leeward, point, body_frame))
+            {
+                view.setField(this->m_bodyFrameX, id, body_frame[0]);
+                view.setField(this->m_bodyFrameY, id, body_frame[1]);
+                view.setField(this->m_bodyFrameZ, id, body_frame[2]);
+            }
         }
         leeward_free(leeward);
     }
@@ -23,5 +23,8 @@ namespace pdal
         Dimension::Id m_verticalUncertainty;
         Dimension::Id m_totalUncertainty;
         Dimension::Id m_incidenceAngle;
+        Dimension::Id m_bodyFrameX;
+        Dimension::Id m_bodyFrameY;
+        Dimension::Id m_bodyFrameZ;
     };
 } // namespace pdal
\ No newline at end of file
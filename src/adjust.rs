@@ -32,14 +32,46 @@
 //! let history = adjust.history();
 //! let last_iteration = history.last().unwrap();
 //! let final_rmse = last_iteration.rmse;
-//! let final_config = last_iteration.config;
-//! assert_eq!(final_config, config);
+//! let final_config = &last_iteration.config;
+//! assert_eq!(final_config, &config);
 //! ```
-use crate::{Config, Dimension, Lasish, Measurement, Variable};
-use anyhow::{anyhow, Error};
+//!
+//! A long adjustment can be saved as a [Checkpoint] and resumed later, with
+//! the same measurements:
+//!
+//! ```
+//! # use leeward::{adjust::Checkpoint, Adjust};
+//! # let measurements = leeward::measurements(
+//! #     "data/sbet.out",
+//! #     "data/points.las",
+//! #     "data/config.toml"
+//! # ).unwrap();
+//! let adjust = Adjust::new(measurements.clone()).unwrap();
+//! let checkpoint = adjust.checkpoint().to_string().unwrap();
+//! let checkpoint: Checkpoint = checkpoint.parse().unwrap();
+//! let adjust = Adjust::from_checkpoint(measurements, checkpoint).unwrap().adjust().unwrap();
+//! ```
+use crate::{
+    Config, Dimension, Error, Measurement, MeasurementFilter, PointSource, Sampler, Variable,
+};
 use nalgebra::{DMatrix, DVector};
+use nalgebra_sparse::{factorization::CscCholesky, CooMatrix, CscMatrix};
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::Read,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
 
 const DEFAULT_TOLERANCE: f64 = 1e-6;
+const DEFAULT_CHUNK_SIZE: usize = 10_000;
+/// Adjustments with more variables than this use sparse normal equations.
+const SPARSE_VARIABLES: usize = 64;
+/// Adjustments with more measurements than this use sparse normal equations.
+const SPARSE_MEASUREMENTS: usize = 10_000_000;
 const BORESIGHT_VARIABLES: [Variable; 3] = [
     Variable::BoresightRoll,
     Variable::BoresightPitch,
@@ -50,30 +82,903 @@ const LEVER_ARM_VARIABLES: [Variable; 3] = [
     Variable::LeverArmY,
     Variable::LeverArmZ,
 ];
+const SCAN_ANGLE_VARIABLES: [Variable; 2] = [Variable::ScanAngleOffset, Variable::ScanAngleScale];
+const STRIP_VARIABLES: [Variable; 3] = [Variable::Roll, Variable::Pitch, Variable::Yaw];
+
+/// Adjust structure.
+#[derive(Debug)]
+pub struct Adjust<L: PointSource> {
+    measurements: Vec<Measurement<L>>,
+    /// Measurements that are set aside as outliers, which can come back in a later iteration.
+    rejected: Vec<Measurement<L>>,
+    rmse: f64,
+    tolerance: f64,
+    outlier_rejection: Option<f64>,
+    chunk_size: usize,
+    variables: Vec<Variable>,
+    fixed: Vec<Variable>,
+    bounds: Vec<(Variable, f64, f64)>,
+    strips: Vec<StripCorrection>,
+    config: Config,
+    history: Vec<Record>,
+}
+
+/// A record of a single iteration.
+///
+/// Records serialize as flat maps with one entry per column, see
+/// [Record::columns]. The config isn't serialized.
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub rmse: f64,
+
+    /// The number of measurements that were set aside as outliers for this iteration.
+    pub rejected: usize,
+
+    pub variables: Vec<Variable>,
+    pub values: Vec<f64>,
+
+    /// The formal standard deviations of the values, from the parameter covariance.
+    ///
+    /// These are NaN if the covariance can't be computed.
+    pub sigmas: Vec<f64>,
+
+    /// The strips' corrections, if this is a strip adjustment.
+    pub strips: Vec<StripCorrection>,
+
+    pub config: Config,
+}
+
+/// One strip's correction to the trajectory's attitude, see [Adjust::adjust_strips].
+///
+/// A strip is the measurements with the same point source id.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StripCorrection {
+    /// The strip's point source id, if its points have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub point_source_id: Option<u16>,
+
+    /// The roll, pitch, and yaw added to the trajectory's attitude, in radians.
+    pub correction: [f64; 3],
+
+    /// The standard deviations of the correction.
+    ///
+    /// These are zero for the reference strip, and NaN if the covariance can't be computed.
+    pub sigmas: [f64; 3],
+}
+
+/// One fold of a cross-validation, see [Adjust::cross_validate].
+///
+/// The rmses are per measurement, i.e. the root mean square of the body frame
+/// residual lengths, so folds of different sizes can be compared. A
+/// validation rmse much larger than the training rmse means the solution is
+/// overfit to the training measurements.
+#[derive(Clone, Debug, Serialize)]
+pub struct Fold {
+    /// The index of the held-out fold.
+    pub fold: usize,
+
+    /// The number of measurements used to estimate the variables.
+    pub training_count: usize,
+
+    /// The number of held-out measurements.
+    pub validation_count: usize,
+
+    /// The rmse of the training measurements with the estimated variables.
+    pub training_rmse: f64,
+
+    /// The rmse of the held-out measurements with the estimated variables.
+    pub validation_rmse: f64,
+
+    /// The estimated config.
+    #[serde(skip)]
+    pub config: Config,
+}
+
+/// The saved state of an adjustment, for resuming it later.
+///
+/// A checkpoint holds the current config, the adjustment settings, and the
+/// history, but not the measurements, which must be provided again when
+/// resuming. Checkpoints are written as TOML.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The configuration to resume from.
+    pub config: Config,
+
+    /// The variables being adjusted.
+    pub variables: Vec<Variable>,
+
+    /// The variables being held at their values.
+    pub fixed: Vec<Variable>,
+
+    /// The bounds on variables, as (variable, min, max).
+    pub bounds: Vec<(Variable, f64, f64)>,
+
+    /// The rmse improvement below which the adjustment stops.
+    pub tolerance: f64,
+
+    /// The outlier rejection threshold, in multiples of the rmse.
+    #[serde(default)]
+    pub outlier_rejection: Option<f64>,
+
+    /// The number of measurements processed at a time.
+    pub chunk_size: usize,
+
+    /// The strips' corrections, if this is a strip adjustment.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strips: Vec<StripCorrection>,
+
+    history: Vec<SavedRecord>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct SavedRecord {
+    rmse: f64,
+    #[serde(default)]
+    rejected: usize,
+    variables: Vec<Variable>,
+    values: Vec<f64>,
+    sigmas: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    strips: Vec<StripCorrection>,
+    config: Config,
+}
+
+/// The normal equations of the least-squares adjustment, `JᵀJ` and `Jᵀr`.
+///
+/// `J` is the jacobian of the body frame residuals `r` with respect to the
+/// variables. The normal equations are accumulated over chunks of
+/// measurements, so the (potentially huge) jacobian is never held in memory.
+/// Measurements can be read, added, and dropped a chunk at a time.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{adjust::NormalEquations, Variable};
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let config = measurements[0].config();
+/// let mut normal_equations = NormalEquations::new(&[Variable::BoresightRoll, Variable::BoresightPitch]);
+/// for chunk in measurements.chunks(100) {
+///     normal_equations.add(chunk);
+/// }
+/// let adjusted = normal_equations.solve(&config).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct NormalEquations {
+    variables: Vec<Variable>,
+    strips: HashMap<Option<u16>, usize>,
+    jtj: Jtj,
+    jtr: DVector<f64>,
+    sum_of_squares: f64,
+    observations: usize,
+}
+
+/// How the normal matrix, `JᵀJ`, is stored.
+///
+/// With many variables, e.g. per-strip parameters, each measurement only
+/// depends on a few of them, so most of `JᵀJ` is zero. Sparse storage only
+/// keeps the nonzero entries and solves with a sparse Cholesky factorization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Storage {
+    /// A dense matrix, best for a handful of variables.
+    #[default]
+    Dense,
+
+    /// A sparse matrix.
+    Sparse,
+}
+
+#[derive(Clone, Debug)]
+enum Jtj {
+    Dense(DMatrix<f64>),
+    /// The nonzero entries of the upper triangle.
+    Sparse(HashMap<(usize, usize), f64>),
+}
+
+impl Storage {
+    /// Returns the storage for an adjustment of this many variables and measurements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::adjust::Storage;
+    /// assert_eq!(Storage::Dense, Storage::select(3, 1000));
+    /// assert_eq!(Storage::Sparse, Storage::select(300, 1000));
+    /// ```
+    pub fn select(variables: usize, measurements: usize) -> Storage {
+        if variables > SPARSE_VARIABLES || measurements > SPARSE_MEASUREMENTS {
+            Storage::Sparse
+        } else {
+            Storage::Dense
+        }
+    }
+}
+
+impl NormalEquations {
+    /// Creates empty normal equations for these variables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::NormalEquations, Variable};
+    /// let normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// assert_eq!(0, normal_equations.observations());
+    /// ```
+    pub fn new(variables: &[Variable]) -> NormalEquations {
+        NormalEquations::with_storage(variables, Storage::Dense)
+    }
+
+    /// Creates empty normal equations for these variables, stored as dense or sparse matrices.
+    ///
+    /// The solutions are the same either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::{NormalEquations, Storage}, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let variables = [Variable::BoresightRoll, Variable::BoresightPitch];
+    /// let mut dense = NormalEquations::new(&variables);
+    /// let mut sparse = NormalEquations::with_storage(&variables, Storage::Sparse);
+    /// dense.add(&measurements);
+    /// sparse.add(&measurements);
+    /// assert_eq!(Storage::Sparse, sparse.storage());
+    /// let difference = dense.step().unwrap() - sparse.step().unwrap();
+    /// assert!(difference.norm() < 1e-9);
+    /// ```
+    pub fn with_storage(variables: &[Variable], storage: Storage) -> NormalEquations {
+        NormalEquations::with_strips(variables, &[], storage)
+    }
+
+    /// Creates empty normal equations for these variables plus a roll, pitch, and yaw bias for each strip.
+    ///
+    /// Strips are identified by their points' point source ids. The
+    /// unknowns are the variables, then each strip's roll, pitch, and yaw,
+    /// in order. Measurements from other strips only observe the variables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::{NormalEquations, Storage}, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut normal_equations =
+    ///     NormalEquations::with_strips(&[Variable::BoresightRoll], &[Some(36)], Storage::Dense);
+    /// normal_equations.add(&measurements);
+    /// assert_eq!(4, normal_equations.unknowns());
+    /// ```
+    pub fn with_strips(
+        variables: &[Variable],
+        strips: &[Option<u16>],
+        storage: Storage,
+    ) -> NormalEquations {
+        let n = variables.len() + 3 * strips.len();
+        NormalEquations {
+            variables: variables.to_vec(),
+            strips: strips
+                .iter()
+                .enumerate()
+                .map(|(i, &strip)| (strip, variables.len() + 3 * i))
+                .collect(),
+            jtj: match storage {
+                Storage::Dense => Jtj::Dense(DMatrix::zeros(n, n)),
+                Storage::Sparse => Jtj::Sparse(HashMap::new()),
+            },
+            jtr: DVector::zeros(n),
+            sum_of_squares: 0.,
+            observations: 0,
+        }
+    }
+
+    /// Returns how the normal matrix is stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::{NormalEquations, Storage}, Variable};
+    /// let normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// assert_eq!(Storage::Dense, normal_equations.storage());
+    /// ```
+    pub fn storage(&self) -> Storage {
+        match self.jtj {
+            Jtj::Dense(_) => Storage::Dense,
+            Jtj::Sparse(_) => Storage::Sparse,
+        }
+    }
+
+    /// Adds a chunk of measurements to the normal equations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::NormalEquations, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// normal_equations.add(&measurements);
+    /// assert_eq!(measurements.len() * 3, normal_equations.observations());
+    /// ```
+    pub fn add<L: PointSource>(&mut self, measurements: &[Measurement<L>]) {
+        let mut row = DVector::zeros(self.unknowns());
+        let mut entries = Vec::with_capacity(self.variables.len() + 3);
+        for measurement in measurements {
+            let residuals = measurement.residuals();
+            let strip = self
+                .strips
+                .get(&measurement.lasish().point_source_id())
+                .copied();
+            for (j, dimension) in Dimension::iter().enumerate() {
+                entries.clear();
+                for (k, &variable) in self.variables.iter().enumerate() {
+                    entries.push((
+                        k,
+                        measurement.partial_derivative_in_body_frame(dimension, variable),
+                    ));
+                }
+                if let Some(offset) = strip {
+                    for (k, &variable) in STRIP_VARIABLES.iter().enumerate() {
+                        entries.push((
+                            offset + k,
+                            measurement.partial_derivative_in_body_frame(dimension, variable),
+                        ));
+                    }
+                }
+                match &mut self.jtj {
+                    Jtj::Dense(jtj) => {
+                        row.fill(0.);
+                        for &(k, value) in &entries {
+                            row[k] = value;
+                        }
+                        jtj.ger(1., &row, &row, 1.);
+                    }
+                    Jtj::Sparse(jtj) => {
+                        entries.retain(|&(_, value)| value != 0.);
+                        for (i, &(a, value_a)) in entries.iter().enumerate() {
+                            for &(b, value_b) in &entries[i..] {
+                                let key = if a <= b { (a, b) } else { (b, a) };
+                                *jtj.entry(key).or_insert(0.) += value_a * value_b;
+                            }
+                        }
+                    }
+                }
+                for &(k, value) in &entries {
+                    self.jtr[k] += residuals[j] * value;
+                }
+                self.sum_of_squares += residuals[j].powi(2);
+            }
+            self.observations += 3;
+        }
+    }
+
+    /// Returns the number of unknowns, the variables plus three for each strip.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::NormalEquations, Variable};
+    /// let normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// assert_eq!(1, normal_equations.unknowns());
+    /// ```
+    pub fn unknowns(&self) -> usize {
+        self.jtr.len()
+    }
+
+    /// Returns the number of observations, three per measurement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::NormalEquations, Variable};
+    /// let normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// assert_eq!(0, normal_equations.observations());
+    /// ```
+    pub fn observations(&self) -> usize {
+        self.observations
+    }
+
+    /// Returns the norm of all the residuals, the same as [Adjust::rmse].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::NormalEquations, Adjust, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// normal_equations.add(&measurements);
+    /// let adjust = Adjust::new(measurements).unwrap();
+    /// assert!((adjust.rmse() - normal_equations.rmse()).abs() < 1e-6);
+    /// ```
+    pub fn rmse(&self) -> f64 {
+        self.sum_of_squares.sqrt()
+    }
+
+    /// Returns the correction to the variables' values that minimizes the residuals.
+    ///
+    /// With strips, the strips' roll, pitch, and yaw corrections follow the variables'.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::NormalEquations, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// normal_equations.add(&measurements);
+    /// let step = normal_equations.step().unwrap();
+    /// assert_eq!(1, step.len());
+    /// ```
+    pub fn step(&self) -> Result<DVector<f64>, Error> {
+        match &self.jtj {
+            Jtj::Dense(jtj) => jtj
+                .clone()
+                .try_inverse()
+                .map(|inverse| -(inverse * &self.jtr))
+                .ok_or(Error::SingularMatrix),
+            Jtj::Sparse(_) => {
+                let step = self.cholesky()?.solve(&self.jtr);
+                Ok(-DVector::from_column_slice(step.as_slice()))
+            }
+        }
+    }
+
+    /// Returns a config with the corrected variables' values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::NormalEquations, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let config = measurements[0].config();
+    /// let mut normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// normal_equations.add(&measurements);
+    /// let adjusted = normal_equations.solve(&config).unwrap();
+    /// assert_ne!(config.boresight.roll, adjusted.boresight.roll);
+    /// ```
+    pub fn solve(&self, config: &Config) -> Result<Config, Error> {
+        let step = self.step()?;
+        let values = config.values(&self.variables)? + step.rows(0, self.variables.len());
+        config.with_values(&self.variables, values.as_slice())
+    }
+
+    /// Returns the covariance matrix of the variables, and of the strips' corrections if there are any.
+    ///
+    /// See [Adjust::parameter_covariance].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{adjust::NormalEquations, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut normal_equations = NormalEquations::new(&[Variable::BoresightRoll]);
+    /// normal_equations.add(&measurements);
+    /// let covariance = normal_equations.covariance().unwrap();
+    /// assert!(covariance[(0, 0)] > 0.);
+    /// ```
+    pub fn covariance(&self) -> Result<DMatrix<f64>, Error> {
+        let parameters = self.unknowns();
+        if self.observations <= parameters {
+            return Err(Error::Invalid(format!(
+                "not enough observations ({}) to estimate the covariance of {} variables",
+                self.observations, parameters
+            )));
+        }
+        let variance = self.sum_of_squares / (self.observations - parameters) as f64;
+        let inverse = match &self.jtj {
+            Jtj::Dense(jtj) => jtj.clone().try_inverse().ok_or(Error::SingularMatrix)?,
+            Jtj::Sparse(_) => self
+                .cholesky()?
+                .solve(&DMatrix::identity(parameters, parameters)),
+        };
+        Ok(inverse * variance)
+    }
+
+    /// Factors the sparse normal matrix.
+    fn cholesky(&self) -> Result<CscCholesky<f64>, Error> {
+        let n = self.unknowns();
+        let mut coo = CooMatrix::new(n, n);
+        if let Jtj::Sparse(jtj) = &self.jtj {
+            for (&(a, b), &value) in jtj {
+                coo.push(a, b, value);
+                if a != b {
+                    coo.push(b, a, value);
+                }
+            }
+        }
+        CscCholesky::factor(&CscMatrix::from(&coo)).map_err(|_| Error::SingularMatrix)
+    }
+}
+
+impl Record {
+    /// Returns this record's column names.
+    ///
+    /// The columns are `rmse`, `rejected`, then each adjusted variable's value, named by
+    /// its display name (e.g. `boresight_roll`), then each strip's roll, pitch,
+    /// and yaw correction (e.g. `strip_36_roll`), then the standard deviations
+    /// of all of those, prefixed with `sigma_` (e.g. `sigma_boresight_roll`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let adjust = Adjust::new(measurements).unwrap();
+    /// let columns = adjust.history()[0].columns();
+    /// assert_eq!(vec!["rmse", "rejected", "boresight_roll", "boresight_pitch", "boresight_yaw",
+    ///     "sigma_boresight_roll", "sigma_boresight_pitch", "sigma_boresight_yaw"], columns);
+    /// ```
+    pub fn columns(&self) -> Vec<String> {
+        let values: Vec<String> = self
+            .variables
+            .iter()
+            .map(|variable| variable.to_string())
+            .chain(self.strips.iter().flat_map(|strip| {
+                STRIP_VARIABLES
+                    .iter()
+                    .map(move |variable| format!("{}_{}", strip.name(), variable))
+            }))
+            .collect();
+        let sigmas: Vec<String> = values
+            .iter()
+            .map(|value| format!("sigma_{}", value))
+            .collect();
+        ["rmse", "rejected"]
+            .iter()
+            .map(|column| column.to_string())
+            .chain(values)
+            .chain(sigmas)
+            .collect()
+    }
+
+    /// Returns this record's values, in the same order as [Record::columns].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let adjust = Adjust::new(measurements).unwrap();
+    /// let record = &adjust.history()[0];
+    /// assert_eq!(record.columns().len(), record.row().len());
+    /// assert_eq!(record.rmse, record.row()[0]);
+    /// ```
+    pub fn row(&self) -> Vec<f64> {
+        [self.rmse, self.rejected as f64]
+            .iter()
+            .copied()
+            .chain(self.values.iter().copied())
+            .chain(self.strips.iter().flat_map(|strip| strip.correction))
+            .chain(self.sigmas.iter().copied())
+            .chain(self.strips.iter().flat_map(|strip| strip.sigmas))
+            .collect()
+    }
+}
+
+impl StripCorrection {
+    fn new(point_source_id: Option<u16>) -> StripCorrection {
+        StripCorrection {
+            point_source_id,
+            correction: [0.; 3],
+            sigmas: [0.; 3],
+        }
+    }
+
+    /// Returns this strip's name, e.g. `strip_36`, or `strip_none` without a point source id.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut adjust = Adjust::new(measurements).unwrap();
+    /// adjust.adjust_strips(true);
+    /// assert_eq!("strip_36", adjust.strips()[0].name());
+    /// ```
+    pub fn name(&self) -> String {
+        match self.point_source_id {
+            Some(id) => format!("strip_{}", id),
+            None => "strip_none".to_string(),
+        }
+    }
+}
+
+impl Serialize for Record {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let columns = self.columns();
+        let mut map = serializer.serialize_map(Some(columns.len()))?;
+        for (column, value) in columns.iter().zip(self.row()) {
+            map.serialize_entry(column, &value)?;
+        }
+        map.end()
+    }
+}
+
+impl Checkpoint {
+    /// Reads a checkpoint from a TOML file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use leeward::adjust::Checkpoint;
+    /// let checkpoint = Checkpoint::from_path("checkpoint.toml").unwrap();
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Checkpoint, Error> {
+        let mut string = String::new();
+        let _ = File::open(path)?.read_to_string(&mut string)?;
+        string.parse()
+    }
+
+    /// Writes this checkpoint as a TOML string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let checkpoint = Adjust::new(measurements).unwrap().checkpoint();
+    /// let string = checkpoint.to_string().unwrap();
+    /// ```
+    #[allow(
+        clippy::inherent_to_string_shadow_display,
+        clippy::wrong_self_convention
+    )]
+    pub fn to_string(&self) -> Result<String, Error> {
+        toml::to_string_pretty(self).map_err(Error::from)
+    }
+
+    /// Writes this checkpoint to a TOML file.
+    pub fn to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path, self.to_string()?).map_err(Error::from)
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Checkpoint, Error> {
+        toml::from_str(s).map_err(Error::from)
+    }
+}
+
+impl From<&Record> for SavedRecord {
+    fn from(record: &Record) -> SavedRecord {
+        SavedRecord {
+            rmse: record.rmse,
+            rejected: record.rejected,
+            variables: record.variables.clone(),
+            values: record.values.clone(),
+            sigmas: record.sigmas.clone(),
+            strips: record.strips.clone(),
+            config: record.config.clone(),
+        }
+    }
+}
+
+impl From<SavedRecord> for Record {
+    fn from(record: SavedRecord) -> Record {
+        Record {
+            rmse: record.rmse,
+            rejected: record.rejected,
+            variables: record.variables,
+            values: record.values,
+            sigmas: record.sigmas,
+            strips: record.strips,
+            config: record.config,
+        }
+    }
+}
+
+impl<L: PointSource> Adjust<L> {
+    /// Creates a new adjust for the provided measurements.
+    ///
+    /// Any constraints in the measurements' config are applied. The config can
+    /// have overrides, and the adjusted values are merged under them for each
+    /// measurement, so a variable that an override sets keeps the override's
+    /// value inside its window.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let adjust = Adjust::new(measurements).unwrap();
+    /// ```
+    pub fn new(measurements: Vec<Measurement<L>>) -> Result<Adjust<L>, Error> {
+        let mut adjust = Adjust::new_iteration(
+            measurements,
+            BORESIGHT_VARIABLES.to_vec(),
+            Vec::new(),
+            vec![],
+            DEFAULT_CHUNK_SIZE,
+        )?;
+        for variable in Variable::iter() {
+            let constraint = adjust.config.constraint(variable);
+            if constraint.fixed {
+                adjust.fix(variable);
+            }
+            if constraint.min.is_some() || constraint.max.is_some() {
+                adjust.bound(
+                    variable,
+                    constraint.min.unwrap_or(f64::NEG_INFINITY),
+                    constraint.max.unwrap_or(f64::INFINITY),
+                )?;
+            }
+        }
+        Ok(adjust)
+    }
+
+    /// Keeps a variable at its current value during the adjustment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Adjust, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut adjust = Adjust::new(measurements).unwrap();
+    /// let yaw = adjust.config().boresight.yaw;
+    /// adjust.fix(Variable::BoresightYaw);
+    /// let adjust = adjust.adjust().unwrap();
+    /// assert_eq!(yaw, adjust.config().boresight.yaw);
+    /// ```
+    pub fn fix(&mut self, variable: Variable) {
+        if !self.fixed.contains(&variable) {
+            self.fixed.push(variable);
+        }
+        self.variables.retain(|&v| v != variable);
+        self.refresh_record();
+    }
+
+    /// Keeps a variable between `min` and `max` during the adjustment.
+    ///
+    /// After each iteration, a variable that has been adjusted outside of its
+    /// bounds is clamped back to them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Adjust, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut adjust = Adjust::new(measurements).unwrap();
+    /// adjust.bound(Variable::BoresightYaw, -0.1, 0.1).unwrap();
+    /// let adjust = adjust.adjust().unwrap();
+    /// assert!(adjust.config().boresight.yaw <= 0.1);
+    /// ```
+    pub fn bound(&mut self, variable: Variable, min: f64, max: f64) -> Result<(), Error> {
+        if min > max {
+            return Err(Error::Invalid(format!(
+                "invalid bounds for {}: {} > {}",
+                variable, min, max
+            )));
+        }
+        self.bounds.retain(|&(v, _, _)| v != variable);
+        self.bounds.push((variable, min, max));
+        Ok(())
+    }
+
+    /// Switch this adjust to adjust the lever arm.
+    ///
+    /// By default, adjusts boresight.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut adjust = Adjust::new(measurements).unwrap();
+    /// adjust.adjust_lever_arm(true);
+    /// ```
+    pub fn adjust_lever_arm(&mut self, adjust_lever_arm: bool) {
+        let variables = if adjust_lever_arm {
+            LEVER_ARM_VARIABLES
+        } else {
+            BORESIGHT_VARIABLES
+        };
+        self.variables = variables
+            .iter()
+            .copied()
+            .filter(|variable| !self.fixed.contains(variable))
+            .collect();
+        self.refresh_record();
+    }
+
+    /// Adjusts exactly these variables, replacing the boresight or lever arm defaults.
+    ///
+    /// Only variables that live in the config can be adjusted. Fixed variables
+    /// are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Adjust, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut adjust = Adjust::new(measurements).unwrap();
+    /// adjust
+    ///     .adjust_variables(&[Variable::BoresightRoll, Variable::LeverArmZ])
+    ///     .unwrap();
+    /// assert!(adjust.adjust_variables(&[Variable::Range]).is_err());
+    /// ```
+    pub fn adjust_variables(&mut self, variables: &[Variable]) -> Result<(), Error> {
+        let _ = self.config.values(variables)?;
+        let mut adjusted = Vec::with_capacity(variables.len());
+        for &variable in variables {
+            if !self.fixed.contains(&variable) && !adjusted.contains(&variable) {
+                adjusted.push(variable);
+            }
+        }
+        self.variables = adjusted;
+        self.refresh_record();
+        Ok(())
+    }
 
-/// Adjust structure.
-#[derive(Debug)]
-pub struct Adjust<L: Lasish> {
-    measurements: Vec<Measurement<L>>,
-    rmse: f64,
-    residuals: DVector<f64>,
-    tolerance: f64,
-    variables: Vec<Variable>,
-    config: Config,
-    history: Vec<Record>,
-}
+    /// Also adjusts the scan angle calibration's offset and scale.
+    ///
+    /// The calibration only applies to las scan angles, so the measurements
+    /// should use them, see [Measurement::use_las_scan_angle].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Adjust, Variable};
+    /// let mut measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// for measurement in &mut measurements {
+    ///     measurement.use_las_scan_angle(true);
+    /// }
+    /// let mut adjust = Adjust::new(measurements).unwrap();
+    /// adjust.adjust_scan_angle(true);
+    /// let adjust = adjust.adjust().unwrap();
+    /// assert!(adjust.history()[0].variables.contains(&Variable::ScanAngleScale));
+    /// ```
+    pub fn adjust_scan_angle(&mut self, adjust_scan_angle: bool) {
+        self.variables
+            .retain(|variable| !SCAN_ANGLE_VARIABLES.contains(variable));
+        if adjust_scan_angle {
+            let fixed = &self.fixed;
+            self.variables.extend(
+                SCAN_ANGLE_VARIABLES
+                    .iter()
+                    .filter(|variable| !fixed.contains(variable)),
+            );
+        }
+        self.refresh_record();
+    }
 
-/// A record of a single iteration.
-#[derive(Clone, Debug)]
-pub struct Record {
-    pub rmse: f64,
-    pub variables: Vec<Variable>,
-    pub values: Vec<f64>,
-    pub config: Config,
-}
+    /// Also estimates a roll, pitch, and yaw correction to the trajectory for each strip.
+    ///
+    /// A strip is the measurements with the same point source id. The
+    /// boresight (or other variables) is common to every strip, while each
+    /// strip's correction absorbs that strip's trajectory attitude bias. A bias
+    /// common to every strip can't be told apart from the boresight, so the
+    /// first strip, by point source id, is the reference and isn't corrected.
+    /// A computed scan angle follows the point, so it absorbs any roll
+    /// correction: measurements should use their las scan angles, see
+    /// [Measurement::use_las_scan_angle].
+    ///
+    /// Turning strips off keeps the corrections that have already been made.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut adjust = Adjust::new(measurements).unwrap();
+    /// adjust.adjust_strips(true);
+    /// let adjust = adjust.adjust().unwrap();
+    /// // The example points are all one strip, the reference.
+    /// assert_eq!(1, adjust.strips().len());
+    /// assert_eq!([0.; 3], adjust.strips()[0].correction);
+    /// ```
+    pub fn adjust_strips(&mut self, adjust_strips: bool) {
+        if !adjust_strips {
+            self.strips.clear();
+        } else if self.strips.is_empty() {
+            let ids: BTreeSet<Option<u16>> = self
+                .measurements
+                .iter()
+                .map(|m| m.lasish().point_source_id())
+                .collect();
+            self.strips = ids.into_iter().map(StripCorrection::new).collect();
+        }
+        self.refresh_record();
+    }
 
-impl<L: Lasish> Adjust<L> {
-    /// Creates a new adjust for the provided measurements.
+    /// Returns the strips' corrections, or an empty slice if this isn't a strip adjustment.
+    ///
+    /// See [Adjust::adjust_strips].
     ///
     /// # Examples
     ///
@@ -81,14 +986,80 @@ impl<L: Lasish> Adjust<L> {
     /// # use leeward::Adjust;
     /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
     /// let adjust = Adjust::new(measurements).unwrap();
+    /// assert!(adjust.strips().is_empty());
     /// ```
-    pub fn new(measurements: Vec<Measurement<L>>) -> Result<Adjust<L>, Error> {
-        Adjust::new_iteration(measurements, BORESIGHT_VARIABLES.to_vec(), vec![])
+    pub fn strips(&self) -> &[StripCorrection] {
+        &self.strips
     }
 
-    /// Switch this adjust to adjust the lever arm.
+    /// Restricts this adjust to measurements whose points have one of these classifications.
     ///
-    /// By default, adjusts boresight.
+    /// The adjustment should usually only see ground (class 2) points. Points
+    /// without a classification, e.g. from tabular sources, are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// // The example points have never been classified.
+    /// let adjust = Adjust::new(measurements).unwrap().with_classes(&[0, 1, 2]).unwrap();
+    /// ```
+    pub fn with_classes(mut self, classes: &[u8]) -> Result<Adjust<L>, Error> {
+        let filter = MeasurementFilter::new().classifications(classes);
+        let measurements = std::mem::take(&mut self.measurements)
+            .into_iter()
+            .filter(|measurement| filter.matches(measurement))
+            .collect();
+        self.with_measurements(measurements)
+    }
+
+    /// Restricts this adjust to a subsample of its measurements.
+    ///
+    /// Calibration quality depends on how the subsample is chosen, e.g.
+    /// stratifying by scan angle keeps the edges of the swath, where the
+    /// boresight is best observed, from being swamped by points near nadir.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Adjust, Decimation};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let sampler = Decimation::ScanAngleStratified { width: 5., count: 50 };
+    /// let adjust = Adjust::new(measurements).unwrap().with_sampler(&sampler).unwrap();
+    /// ```
+    pub fn with_sampler<S: Sampler>(mut self, sampler: &S) -> Result<Adjust<L>, Error> {
+        let measurements = sampler.sample(std::mem::take(&mut self.measurements))?;
+        self.with_measurements(measurements)
+    }
+
+    fn with_measurements(self, measurements: Vec<Measurement<L>>) -> Result<Adjust<L>, Error> {
+        let mut history = self.history;
+        let _ = history.pop();
+        let ids: BTreeSet<Option<u16>> = measurements
+            .iter()
+            .map(|m| m.lasish().point_source_id())
+            .collect();
+        let mut strips = self.strips;
+        strips.retain(|strip| ids.contains(&strip.point_source_id));
+        let mut adjust = Adjust::new_iteration(
+            measurements,
+            self.variables,
+            strips,
+            history,
+            self.chunk_size,
+        )?;
+        adjust.tolerance = self.tolerance;
+        adjust.outlier_rejection = self.outlier_rejection;
+        adjust.fixed = self.fixed;
+        adjust.bounds = self.bounds;
+        Ok(adjust)
+    }
+
+    /// Sets the number of measurements that are processed at a time.
+    ///
+    /// The jacobian is only ever held for one chunk, so this bounds the
+    /// memory used on top of the measurements themselves. Defaults to 10,000.
     ///
     /// # Examples
     ///
@@ -96,54 +1067,365 @@ impl<L: Lasish> Adjust<L> {
     /// # use leeward::Adjust;
     /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
     /// let mut adjust = Adjust::new(measurements).unwrap();
-    /// adjust.adjust_lever_arm(true);
+    /// adjust.chunk_size(100).unwrap();
+    /// assert!(adjust.chunk_size(0).is_err());
     /// ```
-    pub fn adjust_lever_arm(&mut self, adjust_lever_arm: bool) {
-        if adjust_lever_arm {
-            self.variables = LEVER_ARM_VARIABLES.to_vec();
-        } else {
-            self.variables = BORESIGHT_VARIABLES.to_vec();
+    pub fn chunk_size(&mut self, chunk_size: usize) -> Result<(), Error> {
+        if chunk_size == 0 {
+            return Err(Error::Invalid("chunk size must be positive".to_string()));
+        }
+        self.chunk_size = chunk_size;
+        Ok(())
+    }
+
+    /// Rejects outliers between iterations.
+    ///
+    /// After each iteration, measurements whose body frame residual is longer
+    /// than `n_sigma` times the root mean square residual length are set aside,
+    /// so a few bad returns can't steer the solution. Every iteration clips
+    /// all of the measurements again, including the ones that were set aside,
+    /// so a measurement that fits the new solution comes back. The number of
+    /// rejected measurements is recorded in the history. Since the inliers
+    /// change, convergence is checked by comparing consecutive iterations over
+    /// the newer one's inliers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut adjust = Adjust::new(measurements).unwrap();
+    /// adjust.outlier_rejection(3.).unwrap();
+    /// assert!(adjust.outlier_rejection(0.).is_err());
+    /// let adjust = adjust.adjust().unwrap();
+    /// let rejected: usize = adjust.history().iter().map(|record| record.rejected).sum();
+    /// ```
+    pub fn outlier_rejection(&mut self, n_sigma: f64) -> Result<(), Error> {
+        if n_sigma.is_nan() || n_sigma <= 0. {
+            return Err(Error::Invalid(format!(
+                "the outlier rejection threshold must be positive, got {}",
+                n_sigma
+            )));
+        }
+        self.outlier_rejection = Some(n_sigma);
+        Ok(())
+    }
+
+    /// Starts this adjustment from another config's boresight and lever arm.
+    ///
+    /// Use this to warm-start from a previous survey's solution. Everything
+    /// else in the measurements' config is kept, and the history restarts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Adjust, Config};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut previous = Config::from_path("data/config.toml").unwrap();
+    /// previous.boresight.roll = -1.35;
+    /// let adjust = Adjust::new(measurements).unwrap().with_initial(&previous).unwrap();
+    /// assert_eq!(-1.35, adjust.config().boresight.roll);
+    /// ```
+    pub fn with_initial(self, config: &Config) -> Result<Adjust<L>, Error> {
+        let calibration: Vec<Variable> = BORESIGHT_VARIABLES
+            .iter()
+            .chain(&LEVER_ARM_VARIABLES)
+            .copied()
+            .collect();
+        let values = config.values(&calibration)?;
+        let config = self.config.with_values(&calibration, values.as_slice())?;
+        let measurements = with_strip_offsets(
+            self.measurements.iter().chain(&self.rejected),
+            &config,
+            &HashMap::new(),
+        )?;
+        let mut adjust = Adjust::new_iteration(
+            measurements,
+            self.variables,
+            self.strips,
+            Vec::new(),
+            self.chunk_size,
+        )?;
+        adjust.tolerance = self.tolerance;
+        adjust.outlier_rejection = self.outlier_rejection;
+        adjust.fixed = self.fixed;
+        adjust.bounds = self.bounds;
+        Ok(adjust)
+    }
+
+    /// Runs a k-fold cross-validation of this adjustment.
+    ///
+    /// The measurements are split, in order, into `k` contiguous folds. Since
+    /// measurements are usually in time order, each fold is a patch of the
+    /// survey. For each fold, the variables are estimated from the other folds,
+    /// starting from this adjustment's config and settings, and the held-out
+    /// fold's rmse is computed with the estimated config.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let adjust = Adjust::new(measurements).unwrap();
+    /// let folds = adjust.cross_validate(3).unwrap();
+    /// assert_eq!(3, folds.len());
+    /// assert!(adjust.cross_validate(1).is_err());
+    /// ```
+    pub fn cross_validate(&self, k: usize) -> Result<Vec<Fold>, Error> {
+        if k < 2 || k > self.measurements.len() {
+            return Err(Error::Invalid(format!(
+                "cross-validation needs between 2 and {} folds, got {}",
+                self.measurements.len(),
+                k
+            )));
+        }
+        let len = self.measurements.len();
+        let bounds: Vec<usize> = (0..=k).map(|i| i * len / k).collect();
+        let mut folds = Vec::with_capacity(k);
+        for (fold, window) in bounds.windows(2).enumerate() {
+            let (start, end) = (window[0], window[1]);
+            let training = with_strip_offsets(
+                self.measurements[..start]
+                    .iter()
+                    .chain(&self.measurements[end..]),
+                &self.config,
+                &HashMap::new(),
+            )?;
+            let training_count = training.len();
+            let mut adjust = Adjust::new_iteration(
+                training,
+                self.variables.clone(),
+                self.strips.clone(),
+                Vec::new(),
+                self.chunk_size,
+            )?;
+            adjust.tolerance = self.tolerance;
+            adjust.outlier_rejection = self.outlier_rejection;
+            adjust.fixed = self.fixed.clone();
+            adjust.bounds = self.bounds.clone();
+            let adjust = adjust.adjust()?;
+            let config = adjust.config();
+            let offsets = self
+                .strips
+                .iter()
+                .zip(&adjust.strips)
+                .map(|(before, after)| {
+                    let mut offset = after.correction;
+                    for (offset, before) in offset.iter_mut().zip(&before.correction) {
+                        *offset -= before;
+                    }
+                    (after.point_source_id, offset)
+                })
+                .collect();
+            let validation = with_strip_offsets(&self.measurements[start..end], &config, &offsets)?;
+            let mut normal_equations = NormalEquations::new(&self.variables);
+            for chunk in validation.chunks(self.chunk_size) {
+                normal_equations.add(chunk);
+            }
+            folds.push(Fold {
+                fold,
+                training_count,
+                validation_count: validation.len(),
+                training_rmse: adjust.rmse / (training_count as f64).sqrt(),
+                validation_rmse: normal_equations.rmse() / (validation.len() as f64).sqrt(),
+                config,
+            });
+        }
+        Ok(folds)
+    }
+
+    /// Saves this adjustment's state, so it can be resumed with [Adjust::from_checkpoint].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let adjust = Adjust::new(measurements).unwrap();
+    /// let checkpoint = adjust.checkpoint();
+    /// assert_eq!(adjust.config(), checkpoint.config);
+    /// ```
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            config: self.config.clone(),
+            variables: self.variables.clone(),
+            fixed: self.fixed.clone(),
+            bounds: self.bounds.clone(),
+            tolerance: self.tolerance,
+            outlier_rejection: self.outlier_rejection,
+            chunk_size: self.chunk_size,
+            strips: self.strips.clone(),
+            history: self.history.iter().map(SavedRecord::from).collect(),
+        }
+    }
+
+    /// Resumes an adjustment from a checkpoint.
+    ///
+    /// The measurements are given the checkpoint's config, so they should be
+    /// the same measurements that the checkpointed adjustment used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let adjust = Adjust::new(measurements.clone()).unwrap();
+    /// let resumed = Adjust::from_checkpoint(measurements, adjust.checkpoint()).unwrap();
+    /// assert_eq!(adjust.history().len(), resumed.history().len());
+    /// ```
+    pub fn from_checkpoint(
+        measurements: Vec<Measurement<L>>,
+        checkpoint: Checkpoint,
+    ) -> Result<Adjust<L>, Error> {
+        if checkpoint.chunk_size == 0 {
+            return Err(Error::Invalid("chunk size must be positive".to_string()));
         }
+        let offsets = checkpoint
+            .strips
+            .iter()
+            .map(|strip| (strip.point_source_id, strip.correction))
+            .collect();
+        let measurements = with_strip_offsets(&measurements, &checkpoint.config, &offsets)?;
+        let mut history: Vec<Record> = checkpoint.history.into_iter().map(Record::from).collect();
+        // The last record is recomputed from the measurements.
+        let _ = history.pop();
+        let mut adjust = Adjust::new_iteration(
+            measurements,
+            checkpoint.variables,
+            checkpoint.strips,
+            history,
+            checkpoint.chunk_size,
+        )?;
+        adjust.tolerance = checkpoint.tolerance;
+        adjust.outlier_rejection = checkpoint.outlier_rejection;
+        adjust.fixed = checkpoint.fixed;
+        adjust.bounds = checkpoint.bounds;
+        Ok(adjust)
     }
 
     fn new_iteration(
         measurements: Vec<Measurement<L>>,
         variables: Vec<Variable>,
+        strips: Vec<StripCorrection>,
         mut history: Vec<Record>,
+        chunk_size: usize,
     ) -> Result<Adjust<L>, Error> {
         if measurements.is_empty() {
-            return Err(anyhow!("cannot create adjust with no measurements"));
+            return Err(Error::Invalid(
+                "cannot create adjust with no measurements".to_string(),
+            ));
         }
-        let config = measurements[0].config();
-        let mut residuals = DVector::zeros(measurements.len() * 3);
-        for (i, measurement) in measurements.iter().enumerate() {
-            if measurement.config() != config {
-                return Err(anyhow!("not all measurements have the same config"));
-            }
-            let rs = measurement.residuals();
-            for (j, &residual) in rs.iter().enumerate() {
-                residuals[i * 3 + j] = residual;
-            }
+        // Measurements in override windows have different configs, so compare the configs they were resolved from.
+        let config = measurements[0].source_config().clone();
+        if measurements
+            .iter()
+            .any(|measurement| measurement.source_config() != &config)
+        {
+            return Err(Error::Config(
+                "not all measurements have the same config".to_string(),
+            ));
         }
-        let rmse = residuals.norm();
-        let values = config.values(&variables)?;
-        history.push(Record {
-            rmse,
-            variables: variables.clone(),
-            values: values.iter().copied().collect(),
-            config,
-        });
+        let record = Adjust::record(&measurements, &variables, &strips, &config, chunk_size)?;
+        let rmse = record.rmse;
+        let strips = record.strips.clone();
+        history.push(record);
         Ok(Adjust {
             rmse,
-            residuals,
             measurements,
+            rejected: Vec::new(),
             variables,
+            fixed: Vec::new(),
+            bounds: Vec::new(),
+            strips,
             tolerance: DEFAULT_TOLERANCE,
+            outlier_rejection: None,
+            chunk_size,
             history,
             config,
         })
     }
 
+    fn record(
+        measurements: &[Measurement<L>],
+        variables: &[Variable],
+        strips: &[StripCorrection],
+        config: &Config,
+        chunk_size: usize,
+    ) -> Result<Record, Error> {
+        let mut normal_equations =
+            Self::empty_normal_equations(measurements.len(), variables, strips);
+        for chunk in measurements.chunks(chunk_size) {
+            normal_equations.add(chunk);
+        }
+        let values = config.values(variables)?;
+        let mut sigmas: Vec<f64> = normal_equations
+            .covariance()
+            .map(|covariance| {
+                covariance
+                    .diagonal()
+                    .map(f64::sqrt)
+                    .iter()
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_else(|_| vec![f64::NAN; normal_equations.unknowns()]);
+        let mut strip_sigmas = sigmas.split_off(variables.len()).into_iter();
+        let strips = strips
+            .iter()
+            .enumerate()
+            .map(|(i, strip)| {
+                let mut strip = strip.clone();
+                if i > 0 {
+                    for sigma in &mut strip.sigmas {
+                        *sigma = strip_sigmas.next().unwrap_or(f64::NAN);
+                    }
+                }
+                strip
+            })
+            .collect();
+        Ok(Record {
+            rmse: normal_equations.rmse(),
+            rejected: 0,
+            variables: variables.to_vec(),
+            values: values.iter().copied().collect(),
+            sigmas,
+            strips,
+            config: config.clone(),
+        })
+    }
+
+    /// Creates normal equations for the variables and every strip except the first, the reference.
+    fn empty_normal_equations(
+        measurements: usize,
+        variables: &[Variable],
+        strips: &[StripCorrection],
+    ) -> NormalEquations {
+        let strips: Vec<Option<u16>> = strips
+            .iter()
+            .skip(1)
+            .map(|strip| strip.point_source_id)
+            .collect();
+        let storage = Storage::select(variables.len() + 3 * strips.len(), measurements);
+        NormalEquations::with_strips(variables, &strips, storage)
+    }
+
+    /// Recomputes the current history record after the variables change.
+    fn refresh_record(&mut self) {
+        if let Ok(mut record) = Adjust::record(
+            &self.measurements,
+            &self.variables,
+            &self.strips,
+            &self.config,
+            self.chunk_size,
+        ) {
+            self.strips = record.strips.clone();
+            if let Some(last) = self.history.last_mut() {
+                record.rejected = last.rejected;
+                *last = record;
+            }
+        }
+    }
+
     /// Returns the root mean squared error for all the variables.
     ///
     /// # Examples
@@ -169,7 +1451,7 @@ impl<L: Lasish> Adjust<L> {
     /// let config = adjust.config();
     /// ```
     pub fn config(&self) -> Config {
-        self.config
+        self.config.clone()
     }
 
     /// Adjusts these measurements' configuration to optimally align the points.
@@ -183,8 +1465,25 @@ impl<L: Lasish> Adjust<L> {
     /// let adjust = adjust.adjust().unwrap();
     /// ```
     pub fn adjust(self) -> Result<Adjust<L>, Error> {
-        let next = self.next()?;
-        let delta = self.rmse - next.rmse;
+        let (next, previous_rmse) = self.next()?;
+        let delta = previous_rmse - next.rmse;
+        #[cfg(feature = "tracing")]
+        if let (Some(before), Some(after)) = (self.history.last(), next.history.last()) {
+            tracing::info!(
+                iteration = next.history.len() - 1,
+                rmse = after.rmse,
+                rejected = after.rejected,
+                "adjustment iteration"
+            );
+            for ((variable, value), previous) in after
+                .variables
+                .iter()
+                .zip(&after.values)
+                .zip(&before.values)
+            {
+                tracing::debug!(%variable, value, delta = value - previous, "adjusted variable");
+            }
+        }
         if delta < self.tolerance {
             Ok(self)
         } else {
@@ -208,34 +1507,144 @@ impl<L: Lasish> Adjust<L> {
         &self.history
     }
 
-    fn next(&self) -> Result<Adjust<L>, Error> {
-        let mut jacobian = DMatrix::zeros(self.residuals.len(), self.variables.len());
-        for (i, measurement) in self.measurements.iter().enumerate() {
-            for (j, dimension) in Dimension::iter().enumerate() {
-                for (k, &variable) in self.variables.iter().enumerate() {
-                    jacobian[(i * 3 + j, k)] =
-                        measurement.partial_derivative_in_body_frame(dimension, variable);
-                }
+    /// Returns the covariance matrix of the adjusted variables.
+    ///
+    /// This is `(JᵀJ)⁻¹·σ₀²`, where `J` is the jacobian of the residuals with
+    /// respect to the variables and `σ₀²` is the variance of unit weight, the
+    /// sum of squared residuals divided by the degrees of freedom. Rows and
+    /// columns are in the same order as the variables in the history records,
+    /// followed by the roll, pitch, and yaw of each strip after the reference
+    /// strip, see [Adjust::adjust_strips].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Adjust;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let adjust = Adjust::new(measurements).unwrap().adjust().unwrap();
+    /// let covariance = adjust.parameter_covariance().unwrap();
+    /// assert_eq!((3, 3), covariance.shape());
+    /// ```
+    pub fn parameter_covariance(&self) -> Result<DMatrix<f64>, Error> {
+        self.normal_equations().covariance()
+    }
+
+    fn normal_equations(&self) -> NormalEquations {
+        let mut normal_equations =
+            Self::empty_normal_equations(self.measurements.len(), &self.variables, &self.strips);
+        for chunk in self.measurements.chunks(self.chunk_size) {
+            normal_equations.add(chunk);
+        }
+        normal_equations
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(iteration = self.history.len(), measurements = self.measurements.len())
+        )
+    )]
+    /// Returns the next iteration and this iteration's rmse over the next one's measurements.
+    ///
+    /// With outlier rejection the two iterations have different inliers, so
+    /// their rmses are only comparable over the same measurements.
+    fn next(&self) -> Result<(Adjust<L>, f64), Error> {
+        let step = self.normal_equations().step()?;
+        let n = self.variables.len();
+        let mut values = self.config.values(&self.variables)? + step.rows(0, n);
+        for (value, variable) in values.iter_mut().zip(&self.variables) {
+            if let Some(&(_, min, max)) = self.bounds.iter().find(|(v, _, _)| v == variable) {
+                *value = value.clamp(min, max);
             }
         }
-        let values = self.config.values(&self.variables)?;
-        let values = (jacobian.transpose() * &jacobian)
-            .try_inverse()
-            .ok_or(anyhow!("no inverse found"))?
-            * jacobian.transpose()
-            * (&jacobian * values - &self.residuals);
         let config = self
             .config
             .with_values(&self.variables, values.as_slice())?;
-        let measurements = self
-            .measurements
-            .iter()
-            .map(|m| m.with_config(config))
-            .collect();
-        Adjust::new_iteration(measurements, self.variables.clone(), self.history.clone())
+        let mut strips = self.strips.clone();
+        let mut offsets = HashMap::new();
+        for (i, strip) in strips.iter_mut().enumerate().skip(1) {
+            let start = n + 3 * (i - 1);
+            let offset = [step[start], step[start + 1], step[start + 2]];
+            for (correction, offset) in strip.correction.iter_mut().zip(&offset) {
+                *correction += offset;
+            }
+            let _ = offsets.insert(strip.point_source_id, offset);
+        }
+        let mut measurements = with_strip_offsets(
+            self.measurements.iter().chain(&self.rejected),
+            &config,
+            &offsets,
+        )?;
+        let mut rejected = Vec::new();
+        let mut previous_rmse = self.rmse;
+        if let Some(n_sigma) = self.outlier_rejection {
+            let lengths: Vec<f64> = measurements.iter().map(|m| m.residuals().norm()).collect();
+            let rmse =
+                (lengths.iter().map(|l| l.powi(2)).sum::<f64>() / lengths.len() as f64).sqrt();
+            let threshold = n_sigma * rmse;
+            let previous_lengths = self
+                .measurements
+                .iter()
+                .chain(&self.rejected)
+                .map(|m| m.residuals().norm());
+            let (inliers, outliers): (Vec<_>, Vec<_>) = measurements
+                .into_iter()
+                .zip(lengths.into_iter().zip(previous_lengths))
+                .partition(|(_, (length, _))| *length <= threshold);
+            previous_rmse = inliers
+                .iter()
+                .map(|(_, (_, previous))| previous.powi(2))
+                .sum::<f64>()
+                .sqrt();
+            measurements = inliers.into_iter().map(|(m, _)| m).collect();
+            rejected = outliers.into_iter().map(|(m, _)| m).collect();
+        }
+        let mut adjust = Adjust::new_iteration(
+            measurements,
+            self.variables.clone(),
+            strips,
+            self.history.clone(),
+            self.chunk_size,
+        )?;
+        if let Some(record) = adjust.history.last_mut() {
+            record.rejected = rejected.len();
+        }
+        adjust.rejected = rejected;
+        adjust.tolerance = self.tolerance;
+        adjust.outlier_rejection = self.outlier_rejection;
+        adjust.fixed = self.fixed.clone();
+        adjust.bounds = self.bounds.clone();
+        Ok((adjust, previous_rmse))
     }
 }
 
+/// Gives measurements a config, resolved for each one's time, and offsets the attitude of the ones in these strips.
+fn with_strip_offsets<'a, L: PointSource + 'a>(
+    measurements: impl IntoIterator<Item = &'a Measurement<L>>,
+    config: &Config,
+    offsets: &HashMap<Option<u16>, [f64; 3]>,
+) -> Result<Vec<Measurement<L>>, Error> {
+    let configs = config.resolve()?;
+    let source = if config.overrides.is_empty() {
+        None
+    } else {
+        Some(Arc::new(config.clone()))
+    };
+    Ok(measurements
+        .into_iter()
+        .map(|m| {
+            let resolved = configs.at_time(m.lasish().time()).clone();
+            let measurement = m.with_resolved_config(resolved, source.clone());
+            match offsets.get(&m.lasish().point_source_id()) {
+                Some(&offset) => measurement.with_attitude_offset(offset),
+                None => measurement,
+            }
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,11 +1664,265 @@ mod tests {
         assert!(Adjust::new(measurements).is_err());
     }
 
+    #[test]
+    fn overrides() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let times: Vec<f64> = measurements.iter().map(|m| m.time()).collect();
+        let start = times.iter().copied().fold(f64::INFINITY, f64::min);
+        let end = times.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mut config = measurements[0].config();
+        let roll = config.boresight.roll + 1e-3;
+        let mut boresight = toml::Table::new();
+        let _ = boresight.insert("roll".to_string(), toml::Value::Float(roll));
+        let mut values = toml::Table::new();
+        let _ = values.insert("boresight".to_string(), toml::Value::Table(boresight));
+        config.overrides.push(crate::Override {
+            start_time: (start + end) / 2.,
+            end_time: end,
+            values,
+        });
+        let measurements: Vec<_> = measurements
+            .into_iter()
+            .map(|m| m.with_config(config.clone()))
+            .collect();
+        assert!(measurements.iter().any(|m| m.boresight_roll() == roll));
+        let adjust = Adjust::new(measurements.clone()).unwrap().adjust().unwrap();
+        assert!(adjust.history().len() > 1);
+        assert_eq!(config.overrides, adjust.config().overrides);
+        let overridden = adjust
+            .measurements
+            .iter()
+            .filter(|m| m.time() >= (start + end) / 2.)
+            .collect::<Vec<_>>();
+        assert!(!overridden.is_empty());
+        assert!(overridden.iter().all(|m| m.boresight_roll() == roll));
+
+        let resumed = Adjust::from_checkpoint(measurements, adjust.checkpoint()).unwrap();
+        assert_eq!(adjust.config(), resumed.config());
+    }
+
+    #[test]
+    fn with_classes() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let count = measurements.len();
+        let adjust = Adjust::new(measurements)
+            .unwrap()
+            .with_classes(&[0])
+            .unwrap();
+        assert_eq!(count, adjust.measurements.len());
+        assert_eq!(1, adjust.history().len());
+        assert!(adjust.with_classes(&[2]).is_err());
+    }
+
+    #[test]
+    fn constraints_from_config() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut config = measurements[0].config();
+        config.constraints.boresight_yaw.fixed = true;
+        config.constraints.boresight_roll.min = Some(-1.56);
+        let measurements = measurements
+            .into_iter()
+            .map(|m| m.with_config(config.clone()))
+            .collect();
+        let adjust = Adjust::new(measurements).unwrap();
+        assert_eq!(
+            vec![Variable::BoresightRoll, Variable::BoresightPitch],
+            adjust.variables
+        );
+        let adjust = adjust.adjust().unwrap();
+        assert_eq!(config.boresight.yaw, adjust.config().boresight.yaw);
+        assert!(adjust.config().boresight.roll >= -1.56);
+    }
+
+    #[test]
+    fn record_serializes_flat() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let adjust = Adjust::new(measurements).unwrap();
+        let record = &adjust.history()[0];
+        let value = serde_json::to_value(record).unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(record.columns(), object.keys().cloned().collect::<Vec<_>>());
+        assert_eq!(
+            record.config.boresight.roll,
+            object["boresight_roll"].as_f64().unwrap()
+        );
+    }
+
+    #[test]
+    fn adjust_variables() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut adjust = Adjust::new(measurements).unwrap();
+        adjust.fix(Variable::LeverArmY);
+        adjust
+            .adjust_variables(&[
+                Variable::LeverArmX,
+                Variable::LeverArmY,
+                Variable::BoresightYaw,
+                Variable::LeverArmX,
+            ])
+            .unwrap();
+        assert_eq!(
+            vec![Variable::LeverArmX, Variable::BoresightYaw],
+            adjust.history()[0].variables
+        );
+        assert!(adjust.adjust_variables(&[Variable::GnssX]).is_err());
+    }
+
+    #[test]
+    fn resume() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut adjust = Adjust::new(measurements.clone()).unwrap();
+        adjust.bound(Variable::BoresightYaw, -0.1, 0.1).unwrap();
+        let (adjust, _) = adjust.next().unwrap();
+        let checkpoint: Checkpoint = adjust.checkpoint().to_string().unwrap().parse().unwrap();
+        assert_eq!(adjust.checkpoint(), checkpoint);
+        let resumed = Adjust::from_checkpoint(measurements, checkpoint).unwrap();
+        assert_eq!(2, resumed.history().len());
+        assert_eq!(adjust.bounds, resumed.bounds);
+        let (adjusted, resumed) = (adjust.adjust().unwrap(), resumed.adjust().unwrap());
+        assert_eq!(adjusted.config(), resumed.config());
+    }
+
+    #[test]
+    fn outlier_rejection() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let count = measurements.len();
+        let mut adjust = Adjust::new(measurements).unwrap();
+        adjust.outlier_rejection(1.).unwrap();
+        let adjust = adjust.adjust().unwrap();
+        let rejected = adjust.history().last().unwrap().rejected;
+        assert!(rejected > 0);
+        assert_eq!(rejected, adjust.rejected.len());
+        assert_eq!(count - rejected, adjust.measurements.len());
+        // Every iteration clips all of the measurements, so some of the first iteration's outliers come back.
+        assert!(rejected < adjust.history()[1].rejected);
+        // It stopped because another iteration wouldn't improve the fit to the same measurements.
+        let (next, previous_rmse) = adjust.next().unwrap();
+        assert!(previous_rmse - next.rmse() < adjust.tolerance);
+    }
+
+    #[test]
+    fn chunks() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut whole = NormalEquations::new(&BORESIGHT_VARIABLES);
+        whole.add(&measurements);
+        let mut chunked = NormalEquations::new(&BORESIGHT_VARIABLES);
+        for chunk in measurements.chunks(7) {
+            chunked.add(chunk);
+        }
+        assert_eq!(whole.observations(), chunked.observations());
+        let (whole, chunked) = (whole.step().unwrap(), chunked.step().unwrap());
+        assert!((whole - chunked).norm() < 1e-9);
+        let config = Adjust::new(measurements.clone())
+            .unwrap()
+            .adjust()
+            .unwrap()
+            .config();
+        let mut adjust = Adjust::new(measurements).unwrap();
+        adjust.chunk_size(7).unwrap();
+        let chunked = adjust.adjust().unwrap().config();
+        assert!((config.boresight.roll - chunked.boresight.roll).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sparse() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut dense = NormalEquations::new(&BORESIGHT_VARIABLES);
+        dense.add(&measurements);
+        let mut sparse = NormalEquations::with_storage(&BORESIGHT_VARIABLES, Storage::Sparse);
+        sparse.add(&measurements);
+        assert!((dense.step().unwrap() - sparse.step().unwrap()).norm() < 1e-9);
+        let (dense, sparse) = (dense.covariance().unwrap(), sparse.covariance().unwrap());
+        assert!((&dense - &sparse).norm() < 1e-9 * dense.norm());
+        assert!(
+            NormalEquations::with_storage(&BORESIGHT_VARIABLES, Storage::Sparse)
+                .step()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn strips() {
+        use las::Read;
+        let trajectory = crate::Trajectory::from_path("data/sbet.out").unwrap();
+        let config = Config::from_path("data/config.toml").unwrap();
+        let points: Vec<las::Point> = las::Reader::from_path("data/points.las")
+            .unwrap()
+            .points()
+            .map(|point| point.unwrap())
+            .collect();
+        let half = points.len() / 2;
+        let bias = [2e-4, -1e-4, 3e-4];
+        let measurements = |bias: [f64; 3]| -> Vec<Measurement<las::Point>> {
+            points
+                .iter()
+                .enumerate()
+                .map(|(i, point)| {
+                    let mut point = point.clone();
+                    point.point_source_id = if i < half { 1 } else { 2 };
+                    let mut measurement =
+                        Measurement::new(&trajectory, point, config.clone()).unwrap();
+                    // A computed scan angle would absorb any roll correction.
+                    measurement.use_las_scan_angle(true);
+                    if i < half {
+                        measurement
+                    } else {
+                        measurement.with_attitude_offset(bias)
+                    }
+                })
+                .collect()
+        };
+        let adjust_strips = |measurements| {
+            let mut adjust = Adjust::new(measurements).unwrap();
+            // With the example's pitch near -90°, boresight yaw is nearly the same as roll.
+            adjust.fix(Variable::BoresightYaw);
+            adjust.adjust_strips(true);
+            adjust.adjust().unwrap()
+        };
+        let unbiased = adjust_strips(measurements([0.; 3]));
+        let biased = adjust_strips(measurements(bias));
+        assert_eq!(2, biased.strips().len());
+        assert_eq!([0.; 3], biased.strips()[0].sigmas);
+        let (biased_strip, unbiased_strip) = (&biased.strips()[1], &unbiased.strips()[1]);
+        for (i, bias) in bias.iter().enumerate() {
+            assert!(biased_strip.sigmas[i] > 0.);
+            let recovered = biased_strip.correction[i] - unbiased_strip.correction[i];
+            assert!((recovered + bias).abs() < 1e-8);
+        }
+        let record = biased.history().last().unwrap();
+        assert_eq!(record.columns().len(), record.row().len());
+        assert!(record.columns().contains(&"sigma_strip_2_yaw".to_string()));
+        assert_eq!((5, 5), biased.parameter_covariance().unwrap().shape());
+
+        let checkpoint: Checkpoint = biased.checkpoint().to_string().unwrap().parse().unwrap();
+        let resumed = Adjust::from_checkpoint(measurements(bias), checkpoint).unwrap();
+        assert!((resumed.rmse() - biased.rmse()).abs() < 1e-6);
+        assert_eq!(
+            biased.strips()[1].correction,
+            resumed.strips()[1].correction
+        );
+    }
+
     #[test]
     fn adjust() {
         let measurements =
             crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
         let adjust = Adjust::new(measurements).unwrap().adjust().unwrap();
         assert!(adjust.rmse < 14.);
+        let covariance = adjust.parameter_covariance().unwrap();
+        let record = adjust.history().last().unwrap();
+        for (i, sigma) in record.sigmas.iter().enumerate() {
+            assert!(*sigma > 0.);
+            assert_eq!(covariance[(i, i)].sqrt(), *sigma);
+        }
     }
 }
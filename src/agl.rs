@@ -0,0 +1,210 @@
+//! Platform height above ground level along the trajectory.
+//!
+//! The trajectory is sampled in time and each sample's nadir is projected
+//! with the config's UTM zone and ellipsoid. The ground elevation at the nadir
+//! is the median elevation of the points within a search radius, or the
+//! elevation of a DEM. The trajectory altitude is ellipsoidal, so the points or
+//! DEM should be too.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{agl::{self, Options}, Config, Point, Trajectory};
+//! let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+//! let config = Config::from_path("data/config.toml").unwrap();
+//! let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+//! let points = measurements.iter().map(|m| Point::new(m.x(), m.y(), m.z())).collect();
+//! let options = Options { radius: 2000., ..Default::default() };
+//! let profile = agl::from_points(&trajectory, points, &config, &options).unwrap();
+//! assert!(profile[0].agl > 4000.);
+//! ```
+
+use crate::{spatial::KdTree, Config, Dem, Error, Point, Trajectory};
+use serde::Serialize;
+
+/// Options for computing the height above ground level.
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    /// The time between trajectory samples, in seconds.
+    pub interval: f64,
+
+    /// The horizontal search radius for points around the platform's nadir, in meters.
+    pub radius: f64,
+}
+
+/// The platform's height above ground level at one trajectory sample.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Agl {
+    /// The gps time of the trajectory sample.
+    pub time: f64,
+
+    /// The projected x coordinate of the platform.
+    pub x: f64,
+
+    /// The projected y coordinate of the platform.
+    pub y: f64,
+
+    /// The platform's ellipsoidal altitude.
+    pub altitude: f64,
+
+    /// The ground elevation under the platform.
+    pub ground: f64,
+
+    /// The altitude minus the ground elevation.
+    pub agl: f64,
+
+    /// The number of points used for the ground elevation, zero for a DEM.
+    pub count: usize,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            interval: 1.,
+            radius: 5.,
+        }
+    }
+}
+
+/// Computes the height above the points along the trajectory.
+///
+/// Trajectory samples without any points within the search radius of their
+/// nadir are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{agl::{self, Options}, Config, Point, Trajectory};
+/// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+/// let config = Config::from_path("data/config.toml").unwrap();
+/// let points = vec![Point::new(320000., 4181300., 2690.)];
+/// let profile = agl::from_points(&trajectory, points, &config, &Options::default()).unwrap();
+/// assert!(profile.is_empty());
+/// ```
+pub fn from_points(
+    trajectory: &Trajectory,
+    points: Vec<Point>,
+    config: &Config,
+    options: &Options,
+) -> Result<Vec<Agl>, Error> {
+    if options.radius.is_nan() || options.radius <= 0. {
+        return Err(Error::Invalid(format!(
+            "the search radius must be positive, got {}",
+            options.radius
+        )));
+    }
+    let tree = KdTree::new_xy(points);
+    profile(trajectory, config, options, |nadir| {
+        let mut elevations = tree
+            .within(nadir, options.radius)
+            .into_iter()
+            .map(|(index, _)| tree.point(index).z)
+            .collect::<Vec<_>>();
+        if elevations.is_empty() {
+            return None;
+        }
+        elevations.sort_by(f64::total_cmp);
+        let middle = elevations.len() / 2;
+        let median = if elevations.len() % 2 == 0 {
+            (elevations[middle - 1] + elevations[middle]) / 2.
+        } else {
+            elevations[middle]
+        };
+        Some((median, elevations.len()))
+    })
+}
+
+/// Computes the height above a DEM along the trajectory.
+///
+/// Trajectory samples whose nadir is outside of the DEM are skipped. The
+/// search radius is unused.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{agl::{self, Options}, Config, Dem, Trajectory};
+/// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+/// let config = Config::from_path("data/config.toml").unwrap();
+/// let dem = Dem::from_path("data/dem.tif").unwrap();
+/// let profile = agl::from_dem(&trajectory, &dem, &config, &Options::default()).unwrap();
+/// ```
+pub fn from_dem(
+    trajectory: &Trajectory,
+    dem: &Dem,
+    config: &Config,
+    options: &Options,
+) -> Result<Vec<Agl>, Error> {
+    profile(trajectory, config, options, |nadir| {
+        dem.elevation(nadir.x, nadir.y)
+            .map(|elevation| (elevation, 0))
+    })
+}
+
+fn profile<F>(
+    trajectory: &Trajectory,
+    config: &Config,
+    options: &Options,
+    ground: F,
+) -> Result<Vec<Agl>, Error>
+where
+    F: Fn(Point) -> Option<(f64, usize)>,
+{
+    if options.interval.is_nan() || options.interval < 0. {
+        return Err(Error::Invalid(format!(
+            "the interval can't be negative, got {}",
+            options.interval
+        )));
+    }
+    let utm_zone = match config.utm_zone.or_else(|| trajectory.utm_zone()) {
+        Some(utm_zone) => utm_zone,
+        None => return Ok(Vec::new()),
+    };
+    let ellipsoid = config.ellipsoid.ellipsoid();
+    Ok(trajectory
+        .decimate(options.interval)
+        .into_iter()
+        .filter_map(|point| {
+            let geodetic = Point::new(point.longitude, point.latitude, point.altitude);
+            let nadir = ellipsoid.geodetic_to_projected(geodetic, utm_zone);
+            ground(nadir).map(|(ground, count)| Agl {
+                time: point.time,
+                x: nadir.x,
+                y: nadir.y,
+                altitude: point.altitude,
+                ground,
+                agl: point.altitude - ground,
+                count,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Options;
+    use crate::{Config, Point, Trajectory};
+
+    #[test]
+    fn median() {
+        let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+        let config = Config::from_path("data/config.toml").unwrap();
+        let options = Options {
+            interval: 10.,
+            radius: 1.,
+        };
+        let first = &trajectory.points()[0];
+        let nadir = crate::convert::geodetic_to_projected(
+            Point::new(first.longitude, first.latitude, first.altitude),
+            11,
+        );
+        let points = [2690., 2691., 2700.]
+            .iter()
+            .map(|&z| Point::new(nadir.x, nadir.y, z))
+            .collect();
+        let profile = super::from_points(&trajectory, points, &config, &options).unwrap();
+        assert_eq!(1, profile.len());
+        assert_eq!(2691., profile[0].ground);
+        assert_eq!(3, profile[0].count);
+        assert_eq!(first.altitude - 2691., profile[0].agl);
+    }
+}
@@ -0,0 +1,194 @@
+//! Atmospheric correction of laser ranges.
+//!
+//! The laser pulse travels at the group velocity of light in air, not in a
+//! vacuum, so a range computed with the vacuum speed of light is too long.
+//! The group refractive index is computed with the Barrell & Sears formula as
+//! adopted by the IAG (1999).
+
+use serde::{Deserialize, Serialize};
+
+/// Group refractivity coefficients for standard air, for wavelengths in micrometers.
+const A: f64 = 287.6155;
+const B: f64 = 4.88660;
+const C: f64 = 0.06800;
+
+/// Standard temperature (kelvin) and pressure (hPa).
+const STANDARD_TEMPERATURE: f64 = 273.15;
+const STANDARD_PRESSURE: f64 = 1013.25;
+
+/// Atmospheric conditions along the laser path.
+///
+/// # Examples
+///
+/// ```toml
+/// [atmosphere]
+/// temperature = 15.0 # °C
+/// pressure = 1013.25 # hPa
+/// humidity = 60.0 # %
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Atmosphere {
+    /// Air temperature, in degrees Celsius.
+    pub temperature: f64,
+
+    /// Air pressure, in hPa.
+    pub pressure: f64,
+
+    /// Relative humidity, in percent.
+    pub humidity: f64,
+
+    /// Laser wavelength, in micrometers.
+    #[serde(default = "default_wavelength")]
+    pub wavelength: f64,
+
+    /// Uncertainty of the temperature, in degrees Celsius.
+    #[serde(default)]
+    pub temperature_uncertainty: f64,
+
+    /// Uncertainty of the pressure, in hPa.
+    #[serde(default)]
+    pub pressure_uncertainty: f64,
+
+    /// Uncertainty of the relative humidity, in percent.
+    #[serde(default)]
+    pub humidity_uncertainty: f64,
+}
+
+fn default_wavelength() -> f64 {
+    1.064
+}
+
+impl Atmosphere {
+    /// Creates a new atmosphere with a 1064nm laser and no uncertainties.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Atmosphere;
+    /// let atmosphere = Atmosphere::new(15., 1013.25, 60.);
+    /// ```
+    pub fn new(temperature: f64, pressure: f64, humidity: f64) -> Atmosphere {
+        Atmosphere {
+            temperature,
+            pressure,
+            humidity,
+            wavelength: default_wavelength(),
+            temperature_uncertainty: 0.,
+            pressure_uncertainty: 0.,
+            humidity_uncertainty: 0.,
+        }
+    }
+
+    /// Returns the group refractivity, i.e. `(n - 1) * 1e6`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Atmosphere;
+    /// let atmosphere = Atmosphere::new(15., 1013.25, 60.);
+    /// let refractivity = atmosphere.refractivity();
+    /// assert!(refractivity > 270. && refractivity < 280.);
+    /// ```
+    pub fn refractivity(&self) -> f64 {
+        refractivity(
+            self.temperature,
+            self.pressure,
+            self.humidity,
+            self.wavelength,
+        )
+    }
+
+    /// Returns the group refractive index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Atmosphere;
+    /// let atmosphere = Atmosphere::new(15., 1013.25, 60.);
+    /// assert!(atmosphere.refractive_index() > 1.);
+    /// ```
+    pub fn refractive_index(&self) -> f64 {
+        1. + self.refractivity() * 1e-6
+    }
+
+    /// Corrects a range computed with the vacuum speed of light.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Atmosphere;
+    /// let atmosphere = Atmosphere::new(15., 1013.25, 60.);
+    /// let range = atmosphere.correct(1000.);
+    /// assert!(range < 1000.);
+    /// ```
+    pub fn correct(&self, range: f64) -> f64 {
+        range / self.refractive_index()
+    }
+
+    /// Returns the uncertainty of a corrected range due to uncertainty in the atmospheric conditions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Atmosphere;
+    /// let mut atmosphere = Atmosphere::new(15., 1013.25, 60.);
+    /// assert_eq!(0., atmosphere.range_uncertainty(1000.));
+    /// atmosphere.temperature_uncertainty = 1.;
+    /// assert!(atmosphere.range_uncertainty(1000.) > 0.);
+    /// ```
+    pub fn range_uncertainty(&self, range: f64) -> f64 {
+        let partial = |f: &dyn Fn(f64) -> f64, value: f64| {
+            let step = 1e-3;
+            (f(value + step) - f(value - step)) / (2. * step)
+        };
+        let dn_dt = partial(
+            &|t| refractivity(t, self.pressure, self.humidity, self.wavelength),
+            self.temperature,
+        );
+        let dn_dp = partial(
+            &|p| refractivity(self.temperature, p, self.humidity, self.wavelength),
+            self.pressure,
+        );
+        let dn_dh = partial(
+            &|h| refractivity(self.temperature, self.pressure, h, self.wavelength),
+            self.humidity,
+        );
+        let sigma = ((dn_dt * self.temperature_uncertainty).powi(2)
+            + (dn_dp * self.pressure_uncertainty).powi(2)
+            + (dn_dh * self.humidity_uncertainty).powi(2))
+        .sqrt();
+        range * sigma * 1e-6
+    }
+}
+
+fn refractivity(temperature: f64, pressure: f64, humidity: f64, wavelength: f64) -> f64 {
+    let standard = A + B / wavelength.powi(2) + C / wavelength.powi(4);
+    let kelvin = temperature + STANDARD_TEMPERATURE;
+    // Magnus formula for the saturation vapor pressure, in hPa.
+    let saturation = 6.1078 * 10f64.powf(7.5 * temperature / (237.3 + temperature));
+    let vapor_pressure = humidity / 100. * saturation;
+    standard * STANDARD_TEMPERATURE / kelvin * pressure / STANDARD_PRESSURE
+        - 11.27 * vapor_pressure / kelvin
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Atmosphere;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn standard_air() {
+        let atmosphere = Atmosphere::new(0., 1013.25, 0.);
+        assert_relative_eq!(
+            287.6155 + 4.88660 / 1.064f64.powi(2) + 0.068 / 1.064f64.powi(4),
+            atmosphere.refractivity()
+        );
+    }
+
+    #[test]
+    fn correction_shrinks_with_altitude() {
+        let sea_level = Atmosphere::new(15., 1013.25, 50.);
+        let high = Atmosphere::new(-20., 500., 10.);
+        assert!(1000. - sea_level.correct(1000.) > 1000. - high.correct(1000.));
+    }
+}
@@ -2,8 +2,7 @@
 
 #![allow(clippy::missing_safety_doc)]
 
-use crate::{Config, Lasish, Measurement, Point, Trajectory};
-use anyhow::Error;
+use crate::{Config, Error, Measurement, Point, PointSource, RollPitchYaw, Trajectory};
 use libc::c_char;
 use std::{ffi::CStr, ptr};
 
@@ -35,6 +34,43 @@ pub unsafe extern "C" fn leeward_new(sbet: *const c_char, config: *const c_char)
             return ptr::null_mut();
         }
     };
+    unsafe { new(sbet, config) }
+}
+
+/// Creates a new opaque leeward structure for the given trajectory and a configuration toml string.
+///
+/// Useful when the configuration is built up in memory, e.g. from a pipeline's options.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::capi;
+/// # use std::ffi::CString;
+/// let sbet = CString::new("data/sbet.out").unwrap();
+/// let config = CString::new(std::fs::read_to_string("data/config.toml").unwrap()).unwrap();
+/// unsafe {
+///     let leeward = capi::leeward_new_from_strings(sbet.as_ptr(), config.as_ptr());
+///     assert!(!leeward.is_null());
+///     capi::leeward_free(leeward);
+/// }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn leeward_new_from_strings(
+    sbet: *const c_char,
+    config: *const c_char,
+) -> *mut Leeward {
+    let config = unsafe { CStr::from_ptr(config) }.to_string_lossy();
+    let config = match config.parse() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("leeward c api error while parsing config: {}", e);
+            return ptr::null_mut();
+        }
+    };
+    unsafe { new(sbet, config) }
+}
+
+unsafe fn new(sbet: *const c_char, config: Config) -> *mut Leeward {
     let sbet = unsafe { CStr::from_ptr(sbet) }.to_string_lossy();
     let trajectory = match Trajectory::from_path(&*sbet) {
         Ok(config) => config,
@@ -50,6 +86,109 @@ pub unsafe extern "C" fn leeward_new(sbet: *const c_char, config: *const c_char)
     Box::into_raw(Box::new(leeward))
 }
 
+/// Sets the boresight roll, pitch, and yaw, in radians.
+///
+/// Returns false if the leeward pointer is null.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::capi;
+/// # use std::ffi::CString;
+/// let sbet = CString::new("data/sbet.out").unwrap();
+/// let config = CString::new("data/config.toml").unwrap();
+/// unsafe {
+///     let leeward = capi::leeward_new(sbet.as_ptr(), config.as_ptr());
+///     assert!(capi::leeward_set_boresight(leeward, 0., 0., 0.));
+///     capi::leeward_free(leeward);
+/// }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn leeward_set_boresight(
+    leeward: *mut Leeward,
+    roll: f64,
+    pitch: f64,
+    yaw: f64,
+) -> bool {
+    match unsafe { leeward.as_mut() } {
+        Some(leeward) => {
+            leeward.config.boresight = RollPitchYaw::new(roll, pitch, yaw);
+            true
+        }
+        None => {
+            eprintln!("leeward c api error: leeward pointer is null");
+            false
+        }
+    }
+}
+
+/// Sets the lever arm, in meters.
+///
+/// Returns false if the leeward pointer is null.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::capi;
+/// # use std::ffi::CString;
+/// let sbet = CString::new("data/sbet.out").unwrap();
+/// let config = CString::new("data/config.toml").unwrap();
+/// unsafe {
+///     let leeward = capi::leeward_new(sbet.as_ptr(), config.as_ptr());
+///     assert!(capi::leeward_set_lever_arm(leeward, 0.1, 0., -0.2));
+///     capi::leeward_free(leeward);
+/// }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn leeward_set_lever_arm(
+    leeward: *mut Leeward,
+    x: f64,
+    y: f64,
+    z: f64,
+) -> bool {
+    match unsafe { leeward.as_mut() } {
+        Some(leeward) => {
+            leeward.config.lever_arm = Point::new(x, y, z);
+            true
+        }
+        None => {
+            eprintln!("leeward c api error: leeward pointer is null");
+            false
+        }
+    }
+}
+
+/// Sets the UTM zone of the points.
+///
+/// Returns false if the leeward pointer is null.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::capi;
+/// # use std::ffi::CString;
+/// let sbet = CString::new("data/sbet.out").unwrap();
+/// let config = CString::new("data/config.toml").unwrap();
+/// unsafe {
+///     let leeward = capi::leeward_new(sbet.as_ptr(), config.as_ptr());
+///     assert!(capi::leeward_set_utm_zone(leeward, 11));
+///     capi::leeward_free(leeward);
+/// }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn leeward_set_utm_zone(leeward: *mut Leeward, utm_zone: u8) -> bool {
+    match unsafe { leeward.as_mut() } {
+        Some(leeward) => {
+            leeward.config.utm_zone = Some(utm_zone);
+            true
+        }
+        None => {
+            eprintln!("leeward c api error: leeward pointer is null");
+            false
+        }
+    }
+}
+
 /// Returns a measurement, which contains a slew of information about the provided las point.
 ///
 /// # Examples
@@ -106,6 +245,154 @@ pub unsafe extern "C" fn leeward_measurement(
     Box::into_raw(Box::new(measurement))
 }
 
+/// Writes the point's body frame coordinates to `out_xyz`, which must point to three doubles.
+///
+/// Returns false, and leaves `out_xyz` untouched, on error.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::capi;
+/// # use std::ffi::CString;
+/// let sbet = CString::new("data/sbet.out").unwrap();
+/// let config = CString::new("data/config.toml").unwrap();
+/// let point = capi::LeewardPoint {
+///     x: 320000.34,
+///     y: 4181319.35,
+///     z: 2687.58,
+///     scan_angle: 22.,
+///     time: 400825.8057,
+/// };
+/// let mut xyz = [0.; 3];
+/// unsafe {
+///     let leeward = capi::leeward_new(sbet.as_ptr(), config.as_ptr());
+///     assert!(capi::leeward_body_frame(leeward, point, xyz.as_mut_ptr()));
+///     capi::leeward_free(leeward);
+/// }
+/// assert!(xyz[2] > 0.);
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn leeward_body_frame(
+    leeward: *const Leeward,
+    point: LeewardPoint,
+    out_xyz: *mut f64,
+) -> bool {
+    unsafe { write_body_frame(leeward, point, out_xyz, |m| m.body_frame()) }
+}
+
+/// Writes the body frame coordinates modeled by the lidar equation to `out_xyz`, which must point to three doubles.
+///
+/// Returns false, and leaves `out_xyz` untouched, on error.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::capi;
+/// # use std::ffi::CString;
+/// let sbet = CString::new("data/sbet.out").unwrap();
+/// let config = CString::new("data/config.toml").unwrap();
+/// let point = capi::LeewardPoint {
+///     x: 320000.34,
+///     y: 4181319.35,
+///     z: 2687.58,
+///     scan_angle: 22.,
+///     time: 400825.8057,
+/// };
+/// let mut xyz = [0.; 3];
+/// unsafe {
+///     let leeward = capi::leeward_new(sbet.as_ptr(), config.as_ptr());
+///     assert!(capi::leeward_modeled_body_frame(leeward, point, xyz.as_mut_ptr()));
+///     capi::leeward_free(leeward);
+/// }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn leeward_modeled_body_frame(
+    leeward: *const Leeward,
+    point: LeewardPoint,
+    out_xyz: *mut f64,
+) -> bool {
+    unsafe { write_body_frame(leeward, point, out_xyz, |m| m.modeled_body_frame()) }
+}
+
+unsafe fn write_body_frame<F: Fn(&Measurement<LeewardPoint>) -> Point>(
+    leeward: *const Leeward,
+    point: LeewardPoint,
+    out_xyz: *mut f64,
+    f: F,
+) -> bool {
+    if out_xyz.is_null() {
+        eprintln!("leeward c api error: output pointer is null");
+        return false;
+    }
+    let leeward = match unsafe { leeward.as_ref() } {
+        Some(leeward) => leeward,
+        None => {
+            eprintln!("leeward c api error: leeward pointer is null");
+            return false;
+        }
+    };
+    let point = match Measurement::new(&leeward.trajectory, point, leeward.config.clone()) {
+        Ok(measurement) => f(&measurement),
+        Err(err) => {
+            eprintln!("leeward c api error: {}", err);
+            return false;
+        }
+    };
+    let out = unsafe { std::slice::from_raw_parts_mut(out_xyz, 3) };
+    out.copy_from_slice(point.as_slice());
+    true
+}
+
+/// Returns the version of leeward as a nul-terminated string.
+///
+/// The string is static, and must not be freed.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::capi;
+/// # use std::ffi::CStr;
+/// let version = unsafe { CStr::from_ptr(capi::leeward_version()) };
+/// assert_eq!(env!("CARGO_PKG_VERSION"), version.to_str().unwrap());
+/// ```
+#[no_mangle]
+pub extern "C" fn leeward_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Returns true if leeward was built with the named optional feature.
+///
+/// The features are "cli", "parquet", "test_support", "tracing", and "wasm".
+/// Unknown features, and a null name, return false.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::capi;
+/// # use std::ffi::CString;
+/// let name = CString::new("proj").unwrap();
+/// assert!(!unsafe { capi::leeward_has_feature(name.as_ptr()) });
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn leeward_has_feature(name: *const c_char) -> bool {
+    if name.is_null() {
+        return false;
+    }
+    let name = unsafe { CStr::from_ptr(name) }.to_bytes();
+    FEATURES
+        .iter()
+        .any(|&(feature, enabled)| enabled && feature.as_bytes() == name)
+}
+
+/// Every optional feature, and whether leeward was built with it.
+const FEATURES: [(&str, bool); 5] = [
+    ("cli", cfg!(feature = "cli")),
+    ("parquet", cfg!(feature = "parquet")),
+    ("test_support", cfg!(feature = "test_support")),
+    ("tracing", cfg!(feature = "tracing")),
+    ("wasm", cfg!(feature = "wasm")),
+];
+
 /// Free an allocated `LeewardMeasurement` structure.
 #[no_mangle]
 pub unsafe extern "C" fn leeward_measurement_free(measurement: *mut LeewardMeasurement) {
@@ -171,12 +458,14 @@ impl Leeward {
         point: LeewardPoint,
         normal: LeewardNormal,
     ) -> Result<LeewardMeasurement, Error> {
-        Measurement::new(&self.trajectory, point, self.config)
+        Measurement::new(&self.trajectory, point, self.config.clone())
             .and_then(|m| LeewardMeasurement::new(m, normal))
     }
 }
 
-impl Lasish for LeewardPoint {
+impl PointSource for LeewardPoint {
+    type Extra = ();
+
     fn time(&self) -> Option<f64> {
         Some(self.time)
     }
@@ -234,6 +523,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn has_feature() {
+        let has_feature = |name: &str| {
+            let name = CString::new(name).unwrap();
+            unsafe { super::leeward_has_feature(name.as_ptr()) }
+        };
+        assert_eq!(cfg!(feature = "cli"), has_feature("cli"));
+        assert_eq!(cfg!(feature = "parquet"), has_feature("parquet"));
+        assert_eq!(cfg!(feature = "test_support"), has_feature("test_support"));
+        assert_eq!(cfg!(feature = "tracing"), has_feature("tracing"));
+        assert_eq!(cfg!(feature = "wasm"), has_feature("wasm"));
+        assert!(!has_feature("proj"));
+        assert!(!unsafe { super::leeward_has_feature(std::ptr::null()) });
+    }
+
+    #[test]
+    fn new_from_strings() {
+        let sbet = CString::new("data/sbet.out").unwrap();
+        let config = CString::new("utm_zone = 11").unwrap();
+        unsafe {
+            assert!(super::leeward_new_from_strings(sbet.as_ptr(), config.as_ptr()).is_null());
+        }
+    }
+
     #[test]
     fn measurement() {
         let sbet = CString::new("data/sbet.out").unwrap();
@@ -258,4 +571,63 @@ mod tests {
             super::leeward_free(leeward);
         }
     }
+
+    #[test]
+    fn body_frame() {
+        let sbet = CString::new("data/sbet.out").unwrap();
+        let config = CString::new("data/config.toml").unwrap();
+        let point = super::LeewardPoint {
+            x: 320000.34,
+            y: 4181319.35,
+            z: 2687.58,
+            scan_angle: 22.,
+            time: 400825.8057,
+        };
+        let mut body_frame = [0.; 3];
+        let mut modeled = [0.; 3];
+        unsafe {
+            let leeward = super::leeward_new(sbet.as_ptr(), config.as_ptr());
+            assert!(super::leeward_body_frame(
+                leeward,
+                point,
+                body_frame.as_mut_ptr()
+            ));
+            assert!(super::leeward_modeled_body_frame(
+                leeward,
+                point,
+                modeled.as_mut_ptr()
+            ));
+            assert!(!super::leeward_body_frame(
+                leeward,
+                point,
+                std::ptr::null_mut()
+            ));
+            super::leeward_free(leeward);
+            assert!(!super::leeward_body_frame(
+                std::ptr::null(),
+                point,
+                body_frame.as_mut_ptr()
+            ));
+        }
+        unsafe {
+            let leeward = super::leeward_new(sbet.as_ptr(), config.as_ptr());
+            assert!(super::leeward_set_lever_arm(leeward, 1., 2., 3.));
+            let mut shifted = [0.; 3];
+            assert!(super::leeward_modeled_body_frame(
+                leeward,
+                point,
+                shifted.as_mut_ptr()
+            ));
+            super::leeward_free(leeward);
+            assert!(!super::leeward_set_lever_arm(
+                std::ptr::null_mut(),
+                1.,
+                2.,
+                3.
+            ));
+            assert_ne!(modeled, shifted);
+        }
+        let norm = |xyz: [f64; 3]| xyz.iter().map(|n| n.powi(2)).sum::<f64>().sqrt();
+        assert!((norm(body_frame) - norm(modeled)).abs() < 1.);
+    }
 }
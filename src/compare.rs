@@ -0,0 +1,194 @@
+//! Compares two configs against the same measurements.
+//!
+//! Useful for deciding whether a new boresight solution actually improves on
+//! the old one: the residuals and total propagated uncertainty are summarized
+//! for each config, and the change between them is reported.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{compare, Config};
+//! let measurements = leeward::decimated_measurements("data/sbet.out", "data/points.las", "data/config.toml", 10).unwrap();
+//! let first = Config::from_path("data/config.toml").unwrap();
+//! let mut second = first.clone();
+//! second.boresight.roll += 0.001;
+//! for difference in compare::compare(&measurements, first, second, None).unwrap() {
+//!     println!("{}: {} -> {}", difference.name, difference.first, difference.second);
+//! }
+//! ```
+
+use crate::{Config, Dem, Error, Measurement, Point, PointSource};
+use serde::Serialize;
+
+/// Residual and uncertainty statistics for one config.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Statistics {
+    /// The number of measurements.
+    pub count: usize,
+
+    /// The root-mean-square body frame residual in x.
+    pub rms_residual_x: f64,
+
+    /// The root-mean-square body frame residual in y.
+    pub rms_residual_y: f64,
+
+    /// The root-mean-square body frame residual in z.
+    pub rms_residual_z: f64,
+
+    /// The root-mean-square length of the body frame residuals.
+    pub rms_residual: f64,
+
+    /// The mean horizontal total propagated uncertainty.
+    pub mean_horizontal: f64,
+
+    /// The mean vertical total propagated uncertainty.
+    pub mean_vertical: f64,
+
+    /// The mean total propagated uncertainty.
+    pub mean_total: f64,
+}
+
+/// The change in one statistic between two configs.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Difference {
+    /// The name of the statistic.
+    pub name: &'static str,
+
+    /// The statistic with the first config.
+    pub first: f64,
+
+    /// The statistic with the second config.
+    pub second: f64,
+
+    /// The second value minus the first.
+    pub change: f64,
+}
+
+/// Computes residual and uncertainty statistics for measurements with a config.
+///
+/// If a DEM is provided its surface normals are used for the incidence angles,
+/// otherwise the surface is assumed to be horizontal.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{compare, Config};
+/// let measurements = leeward::decimated_measurements("data/sbet.out", "data/points.las", "data/config.toml", 10).unwrap();
+/// let config = Config::from_path("data/config.toml").unwrap();
+/// let statistics = compare::statistics(&measurements, config, None).unwrap();
+/// assert_eq!(measurements.len(), statistics.count);
+/// ```
+pub fn statistics<L: PointSource>(
+    measurements: &[Measurement<L>],
+    config: Config,
+    dem: Option<&Dem>,
+) -> Result<Statistics, Error> {
+    if measurements.is_empty() {
+        return Err(Error::Invalid("no measurements to compare".to_string()));
+    }
+    let mut squared = Point::zeros();
+    let (mut horizontal, mut vertical, mut total) = (0., 0., 0.);
+    for measurement in measurements {
+        let measurement = measurement.with_config(config.clone());
+        squared += measurement.residuals().map(|residual| residual.powi(2));
+        let tpu = if let Some(dem) = dem {
+            measurement.tpu_with_dem(dem)?
+        } else {
+            measurement.tpu(Point::new(0., 0., 1.))?
+        };
+        horizontal += tpu.horizontal;
+        vertical += tpu.vertical;
+        total += tpu.total;
+    }
+    let count = measurements.len();
+    let n = count as f64;
+    Ok(Statistics {
+        count,
+        rms_residual_x: (squared.x / n).sqrt(),
+        rms_residual_y: (squared.y / n).sqrt(),
+        rms_residual_z: (squared.z / n).sqrt(),
+        rms_residual: (squared.sum() / n).sqrt(),
+        mean_horizontal: horizontal / n,
+        mean_vertical: vertical / n,
+        mean_total: total / n,
+    })
+}
+
+/// Compares the statistics of two configs on the same measurements.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{compare, Config};
+/// let measurements = leeward::decimated_measurements("data/sbet.out", "data/points.las", "data/config.toml", 10).unwrap();
+/// let config = Config::from_path("data/config.toml").unwrap();
+/// let differences = compare::compare(&measurements, config.clone(), config, None).unwrap();
+/// assert!(differences.iter().all(|difference| difference.change == 0.));
+/// ```
+pub fn compare<L: PointSource>(
+    measurements: &[Measurement<L>],
+    first: Config,
+    second: Config,
+    dem: Option<&Dem>,
+) -> Result<Vec<Difference>, Error> {
+    let first = statistics(measurements, first, dem)?;
+    let second = statistics(measurements, second, dem)?;
+    let difference = |name, first, second| Difference {
+        name,
+        first,
+        second,
+        change: second - first,
+    };
+    Ok(vec![
+        difference(
+            "rms_residual_x",
+            first.rms_residual_x,
+            second.rms_residual_x,
+        ),
+        difference(
+            "rms_residual_y",
+            first.rms_residual_y,
+            second.rms_residual_y,
+        ),
+        difference(
+            "rms_residual_z",
+            first.rms_residual_z,
+            second.rms_residual_z,
+        ),
+        difference("rms_residual", first.rms_residual, second.rms_residual),
+        difference(
+            "mean_horizontal",
+            first.mean_horizontal,
+            second.mean_horizontal,
+        ),
+        difference("mean_vertical", first.mean_vertical, second.mean_vertical),
+        difference("mean_total", first.mean_total, second.mean_total),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Config;
+
+    #[test]
+    fn adjusted_config_improves() {
+        let measurements = crate::decimated_measurements(
+            "data/sbet.out",
+            "data/points.las",
+            "data/config.toml",
+            10,
+        )
+        .unwrap();
+        let first = Config::from_path("data/config.toml").unwrap();
+        let adjust = crate::Adjust::new(measurements.clone())
+            .unwrap()
+            .adjust()
+            .unwrap();
+        let differences = super::compare(&measurements, first, adjust.config(), None).unwrap();
+        let rms_residual = differences
+            .iter()
+            .find(|difference| difference.name == "rms_residual")
+            .unwrap();
+        assert!(rms_residual.change < 0.);
+    }
+}
@@ -1,21 +1,360 @@
-use crate::{Point, RollPitchYaw, Variable};
-use anyhow::{anyhow, Error};
+use crate::{
+    convert::{Ellipsoid, Helmert, GRS_80, WGS_84},
+    crs::Datum,
+    units, Atmosphere, Error, Matrix3, Point, Preset, RangeErrorModel, Returns, RollPitchYaw,
+    RotationOrder, ScannerModel, Variable,
+};
 use nalgebra::DVector;
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::Read, path::Path};
+use std::{fs::File, io::Read, path::Path, str::FromStr};
 
 /// Configuration structure.
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Config {
-    pub utm_zone: u8,
+    /// The sensor preset that fills in the values not given in the config, see [Preset].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+
+    /// The UTM zone of the points.
+    ///
+    /// If not set, the zone is detected from the las header's CRS or, failing
+    /// that, from the trajectory's longitudes.
+    #[serde(default)]
+    pub utm_zone: Option<u8>,
+
+    /// The horizontal datum of the points, e.g. `nad83`.
+    ///
+    /// Only used to check the las header's CRS. Conversions use the
+    /// [ellipsoid](Config::ellipsoid), which is not set from the datum.
+    #[serde(default)]
+    pub datum: Option<Datum>,
+
+    /// The ellipsoid of the points' geodetic and projected coordinates, defaults to `wgs84`.
+    #[serde(default)]
+    pub ellipsoid: ReferenceEllipsoid,
+
+    /// The epoch of the survey, as a decimal year, e.g. `2019.5`.
+    ///
+    /// Only used to evaluate a time-dependent [datum
+    /// transformation](Config::datum_transformation).
+    #[serde(default)]
+    pub epoch: Option<f64>,
+
+    /// The transformation from the trajectory's datum to the points' datum, e.g. from ITRF2014 to NAD83(2011).
+    ///
+    /// The trajectory's positions are on the WGS84 ellipsoid. If set, they
+    /// are transformed into the points' datum before anything else. The
+    /// rotations are small enough that the trajectory's attitude isn't changed.
+    #[serde(default)]
+    pub datum_transformation: Option<Helmert>,
+
     pub beam_divergence: f64,
     pub lever_arm: Point,
+
+    /// The frame and sign convention of the lever arm, defaults to `body`.
+    #[serde(default)]
+    pub lever_arm_frame: LeverArmFrame,
+
     pub boresight: RollPitchYaw,
+
+    /// The order in which the boresight angles are combined, defaults to `zyx`.
+    #[serde(default)]
+    pub rotation_order: RotationOrder,
+
+    /// The axis convention of the navigation and body frames, defaults to `ned`.
+    ///
+    /// The lever arm and boresight are given in the body frame of this convention.
+    #[serde(default)]
+    pub navigation_frame: NavigationFrame,
+
     pub uncertainty: Uncertainty,
+
+    /// The frame the uncertainty is propagated through, defaults to `navigation`.
+    #[serde(default)]
+    pub propagation: Propagation,
+
+    /// Atmospheric conditions, used for the range uncertainty and to correct raw ranges if provided.
+    #[serde(default)]
+    pub atmosphere: Option<Atmosphere>,
+
+    /// The scanner's deflection geometry, defaults to an oscillating mirror.
+    #[serde(default)]
+    pub scanner: ScannerModel,
+
+    /// The largest incidence angle (in radians) used to compute range uncertainty.
+    ///
+    /// The incidence-induced range uncertainty grows without bound towards
+    /// grazing incidence. Larger angles are capped to this value and the
+    /// [Tpu](crate::Tpu) is flagged as [TpuQuality::HighIncidence](crate::TpuQuality::HighIncidence).
+    #[serde(default)]
+    pub max_incidence_angle: Option<f64>,
+
+    /// How the range and scan angle uncertainties are modeled, defaults to `divergence`.
+    #[serde(default)]
+    pub range_error_model: RangeErrorModel,
+
+    /// How the returns of multi-return pulses are modeled, see [Returns].
+    #[serde(default, skip_serializing_if = "Returns::is_default")]
+    pub returns: Returns,
+
+    /// The scanner's pulse rate, in pulses per second.
+    ///
+    /// Only used to reconstruct times for points that don't have them, see [TimeReconstruction](crate::TimeReconstruction).
+    #[serde(default)]
+    pub pulse_rate: Option<f64>,
+
+    /// Corrections to the las scan angles, for encoder calibration errors.
+    ///
+    /// Only applied when measurements use the las scan angle instead of
+    /// computing it from the point.
+    #[serde(default, skip_serializing_if = "ScanAngleCalibration::is_identity")]
+    pub scan_angle_calibration: ScanAngleCalibration,
+
+    /// Whether the las scan angles are relative to vertical instead of the platform.
+    ///
+    /// Roll-compensating scanners record the scan angle after removing the
+    /// platform's roll. Only used when measurements use the las scan angle, see
+    /// [detect_roll_compensation](crate::detect_roll_compensation).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub scan_angle_is_roll_compensated: bool,
+
+    /// Variables to fix or bound during the adjustment.
+    #[serde(default, skip_serializing_if = "Constraints::is_empty")]
+    pub constraints: Constraints,
+
+    /// Config values for the points in a time window, see [Override].
+    #[serde(default, rename = "override", skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<Override>,
 }
 
-/// Configuration for uncertainty config.
+/// Descriptions of the config's tables and values, keyed by their dotted path.
+///
+/// Override tables use the descriptions of the config's own values.
+const DOCS: [(&str, &str); 65] = [
+    ("preset", "sensor preset for the values not given here"),
+    ("utm_zone", "UTM zone of the points"),
+    ("datum", "horizontal datum, only used to check las headers"),
+    (
+        "ellipsoid",
+        "reference ellipsoid of the points' coordinates",
+    ),
+    ("epoch", "epoch of the survey, decimal year"),
+    ("beam_divergence", "beam divergence, radians"),
+    (
+        "lever_arm",
+        "lever arm from the scanner to the IMU (x, y, z), meters",
+    ),
+    (
+        "lever_arm_frame",
+        "frame and sign convention of the lever arm",
+    ),
+    (
+        "rotation_order",
+        "order the boresight angles are combined in",
+    ),
+    (
+        "navigation_frame",
+        "axis convention of the navigation and body frames",
+    ),
+    ("propagation", "frame the uncertainty is propagated through"),
+    (
+        "max_incidence_angle",
+        "largest incidence angle for range uncertainty, radians",
+    ),
+    (
+        "range_error_model",
+        "how range and scan angle uncertainties are modeled",
+    ),
+    ("pulse_rate", "pulses per second"),
+    (
+        "scan_angle_is_roll_compensated",
+        "las scan angles are relative to vertical",
+    ),
+    ("boresight", "Misalignment between the scanner and the IMU"),
+    ("boresight.roll", "radians"),
+    ("boresight.pitch", "radians"),
+    ("boresight.yaw", "radians"),
+    (
+        "returns",
+        "How the returns of multi-return pulses are modeled",
+    ),
+    ("returns.per_pulse", "returns share their pulse's beam"),
+    (
+        "returns.range",
+        "extra range uncertainty by return number, meters",
+    ),
+    ("uncertainty", "One sigma uncertainties"),
+    ("uncertainty.gnss_x", "meters"),
+    ("uncertainty.gnss_y", "meters"),
+    ("uncertainty.gnss_z", "meters"),
+    ("uncertainty.roll", "radians"),
+    ("uncertainty.pitch", "radians"),
+    ("uncertainty.yaw", "radians"),
+    ("uncertainty.boresight_roll", "radians"),
+    ("uncertainty.boresight_pitch", "radians"),
+    ("uncertainty.boresight_yaw", "radians"),
+    ("uncertainty.lever_arm_x", "meters"),
+    ("uncertainty.lever_arm_y", "meters"),
+    ("uncertainty.lever_arm_z", "meters"),
+    ("uncertainty.range", "meters"),
+    ("uncertainty.scan_angle", "radians"),
+    (
+        "uncertainty.time",
+        "timing between the lidar and the trajectory, seconds",
+    ),
+    ("uncertainty.gnss_frame", "frame of the gnss uncertainties"),
+    ("scanner", "Geometry of the scanner's deflection unit"),
+    ("scanner.mirror_tilt", "radians"),
+    ("scanner.deviation", "radians"),
+    ("atmosphere", "Atmospheric conditions along the laser path"),
+    ("atmosphere.temperature", "°C"),
+    ("atmosphere.pressure", "hPa"),
+    ("atmosphere.humidity", "relative, percent"),
+    ("atmosphere.wavelength", "micrometers"),
+    ("atmosphere.temperature_uncertainty", "°C"),
+    ("atmosphere.pressure_uncertainty", "hPa"),
+    ("atmosphere.humidity_uncertainty", "percent"),
+    (
+        "scan_angle_calibration",
+        "Correction to las scan angles, scale * angle + offset",
+    ),
+    ("scan_angle_calibration.offset", "radians"),
+    ("scan_angle_calibration.scale", "unitless"),
+    (
+        "datum_transformation",
+        "Helmert transformation from the trajectory's datum to the points'",
+    ),
+    ("datum_transformation.translation", "meters"),
+    (
+        "datum_transformation.rotation",
+        "radians, position vector convention",
+    ),
+    ("datum_transformation.scale", "unitless"),
+    ("datum_transformation.translation_rate", "meters per year"),
+    ("datum_transformation.rotation_rate", "radians per year"),
+    ("datum_transformation.scale_rate", "per year"),
+    ("datum_transformation.reference_epoch", "decimal year"),
+    (
+        "constraints",
+        "Variables to fix or bound during the adjustment",
+    ),
+    ("override", "Config values for the points in a time window"),
+    ("override.start_time", "gps time, seconds"),
+    ("override.end_time", "gps time, seconds"),
+];
+
+/// Config values that replace the rest of the config for the points in a time window.
+///
+/// Long missions are sometimes reconfigured mid-flight, e.g. with a different
+/// pulse rate or scanner settings. The values have the same layout as the
+/// config, and tables are merged, so only the changed values are needed. If
+/// windows overlap, the first override wins. See [Config::at_time].
+///
+/// # Examples
+///
+/// ```toml
+/// [[override]]
+/// start_time = 400825.5
+/// end_time = 400826.0
+/// pulse_rate = 200000
+///
+/// [override.boresight]
+/// roll = 0.001
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Override {
+    /// The gps time of the first point this override applies to.
+    pub start_time: f64,
+
+    /// The gps time of the last point this override applies to.
+    pub end_time: f64,
+
+    /// The config values to replace.
+    #[serde(flatten)]
+    pub values: toml::Table,
+}
+
+/// A config with its overrides merged ahead of time, so that many points' configs can be looked up cheaply.
+#[derive(Debug)]
+pub(crate) struct ResolvedConfig {
+    base: Config,
+    overrides: Vec<(f64, f64, Config)>,
+}
+
+/// Constraints on the variables that can be adjusted.
+///
+/// # Examples
+///
+/// ```toml
+/// [constraints.boresight_yaw]
+/// fixed = true
+///
+/// [constraints.lever_arm_x]
+/// min = -0.05
+/// max = 0.05
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct Constraints {
+    #[serde(skip_serializing_if = "Constraint::is_empty")]
+    pub boresight_roll: Constraint,
+    #[serde(skip_serializing_if = "Constraint::is_empty")]
+    pub boresight_pitch: Constraint,
+    #[serde(skip_serializing_if = "Constraint::is_empty")]
+    pub boresight_yaw: Constraint,
+    #[serde(skip_serializing_if = "Constraint::is_empty")]
+    pub lever_arm_x: Constraint,
+    #[serde(skip_serializing_if = "Constraint::is_empty")]
+    pub lever_arm_y: Constraint,
+    #[serde(skip_serializing_if = "Constraint::is_empty")]
+    pub lever_arm_z: Constraint,
+    #[serde(skip_serializing_if = "Constraint::is_empty")]
+    pub scan_angle_offset: Constraint,
+    #[serde(skip_serializing_if = "Constraint::is_empty")]
+    pub scan_angle_scale: Constraint,
+}
+
+/// A linear correction to las scan angles, `scale * scan_angle + offset`.
+///
+/// A misaligned mirror encoder shows up as a scan angle scale error. The
+/// offset is in radians.
+///
+/// # Examples
+///
+/// ```toml
+/// [scan_angle_calibration]
+/// offset = 0.0001
+/// scale = 1.002
+/// ```
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct ScanAngleCalibration {
+    /// Added to the scaled scan angle, in radians.
+    pub offset: f64,
+
+    /// Multiplies the las scan angle.
+    pub scale: f64,
+}
+
+/// A constraint on a single variable.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct Constraint {
+    /// Keep this variable at its configured value.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub fixed: bool,
+
+    /// The smallest value this variable can be adjusted to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+
+    /// The largest value this variable can be adjusted to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// Configuration for uncertainty config.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Uncertainty {
     pub gnss_x: f64,
     pub gnss_y: f64,
@@ -30,7 +369,289 @@ pub struct Uncertainty {
     pub lever_arm_y: f64,
     pub lever_arm_z: f64,
     pub range: f64,
-    pub scan_angle: f64,
+    pub scan_angle: ScanAngleUncertainty,
+
+    /// The timing uncertainty between the lidar and the trajectory, in seconds.
+    ///
+    /// Propagated through the platform's velocity and attitude rates.
+    #[serde(default)]
+    pub time: f64,
+
+    /// The frame that the gnss uncertainties are given in.
+    #[serde(default)]
+    pub gnss_frame: GnssFrame,
+}
+
+/// The scanner's angular encoder uncertainty, in radians.
+///
+/// For oscillating mirrors the uncertainty isn't constant across the swath,
+/// so it can vary with the measurement's scan angle (in radians).
+///
+/// # Examples
+///
+/// ```toml
+/// [uncertainty]
+/// # The same everywhere
+/// scan_angle = 1.745329e-5
+/// # Or c0 + c1 * θ + c2 * θ² + ...
+/// scan_angle = { polynomial = [1.745329e-5, 0, 1e-4] }
+/// # Or linearly interpolated between (θ, σ) pairs, clamped at the ends
+/// scan_angle = { table = [[-0.35, 3e-5], [0, 1.745329e-5], [0.35, 3e-5]] }
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ScanAngleUncertainty {
+    /// The same uncertainty across the whole swath.
+    Constant(f64),
+
+    /// A polynomial of the scan angle, lowest order coefficient first.
+    Polynomial { polynomial: Vec<f64> },
+
+    /// (scan angle, uncertainty) pairs, sorted by scan angle.
+    Table { table: Vec<(f64, f64)> },
+}
+
+/// The reference frame of the gnss uncertainties.
+///
+/// The uncertainty covariance is rotated into the navigation frame before propagation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GnssFrame {
+    /// The navigation frame, i.e. north, east, and down.
+    #[default]
+    Navigation,
+
+    /// The local level frame, i.e. east, north, and up.
+    Enu,
+
+    /// Earth-centered, earth-fixed.
+    Ecef,
+
+    /// Projected (UTM) easting, northing, and up, which differs from [GnssFrame::Enu] by the grid convergence.
+    Projected,
+}
+
+/// The axis convention of the navigation frame, and with it the body frame.
+///
+/// The trajectory's roll, pitch, and yaw are always relative to north, east,
+/// and down. In the east, north, up convention the body frame axes are right,
+/// forward, and up, i.e. the north, east, down body frame with its x and y axes
+/// swapped and its z axis flipped.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NavigationFrame {
+    /// North, east, and down, with a forward, right, and down body frame.
+    #[default]
+    Ned,
+
+    /// East, north, and up, with a right, forward, and up body frame.
+    Enu,
+}
+
+impl NavigationFrame {
+    /// Returns the matrix that converts north, east, down coordinates into this convention.
+    ///
+    /// The matrix is its own inverse, so it also converts coordinates in this
+    /// convention back to north, east, down.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{NavigationFrame, Point};
+    /// let ned = Point::new(1., 2., 3.);
+    /// assert_eq!(ned, NavigationFrame::Ned.matrix() * ned);
+    /// assert_eq!(Point::new(2., 1., -3.), NavigationFrame::Enu.matrix() * ned);
+    /// ```
+    pub fn matrix(&self) -> Matrix3 {
+        match self {
+            NavigationFrame::Ned => Matrix3::identity(),
+            NavigationFrame::Enu => Matrix3::new(0., 1., 0., 1., 0., 0., 0., 0., -1.),
+        }
+    }
+}
+
+/// The reference ellipsoid of the points' coordinates.
+///
+/// # Examples
+///
+/// ```toml
+/// ellipsoid = "grs80"
+/// ```
+///
+/// ```toml
+/// [ellipsoid.custom]
+/// a = 6378206.4
+/// f = 0.0033900753
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceEllipsoid {
+    /// The WGS84 ellipsoid.
+    #[default]
+    Wgs84,
+
+    /// The GRS80 ellipsoid, used by NAD83 and ETRS89.
+    Grs80,
+
+    /// An ellipsoid with a semi-major axis `a`, in meters, and a flattening `f`.
+    Custom { a: f64, f: f64 },
+}
+
+impl ReferenceEllipsoid {
+    /// Returns this reference ellipsoid's parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert, ReferenceEllipsoid};
+    /// assert_eq!(convert::GRS_80, ReferenceEllipsoid::Grs80.ellipsoid());
+    /// ```
+    pub fn ellipsoid(&self) -> Ellipsoid {
+        match *self {
+            ReferenceEllipsoid::Wgs84 => WGS_84,
+            ReferenceEllipsoid::Grs80 => GRS_80,
+            ReferenceEllipsoid::Custom { a, f } => Ellipsoid::new(a, f),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if let ReferenceEllipsoid::Custom { a, f } = *self {
+            if a.is_nan() || a <= 0. || !(0. ..1.).contains(&f) {
+                return Err(Error::Config(format!(
+                    "invalid ellipsoid, a must be positive and f between zero and one: a={}, f={}",
+                    a, f
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The frame the total propagated uncertainty is propagated through.
+///
+/// The lidar equation is linearized in the platform's navigation frame. Its
+/// down axis is the plumb line under the platform, not at the point, so the
+/// horizontal and vertical uncertainties of an off-nadir point are mixed by
+/// the earth's curvature, about 0.16 mrad per kilometer of horizontal
+/// distance. The `ecef` propagation linearizes the lidar equation in ECEF
+/// instead, and reports the uncertainty in the local level frame at the
+/// point's own geodetic position. Its gnss and timing terms also account for
+/// the platform's navigation frame turning as the platform moves over the
+/// ellipsoid.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Propagation {
+    /// Report the uncertainty in the platform's north, east, down frame.
+    #[default]
+    Navigation,
+
+    /// Report the uncertainty in the north, east, down frame at the point, via ECEF.
+    Ecef,
+}
+
+/// The frame, and sign, in which the lever arm is given.
+///
+/// Internally, the lever arm is the vector from the scanner's origin to the
+/// IMU in the body frame, and it is subtracted from the boresight-rotated scan
+/// frame point. The other variants are converted to that convention.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LeverArmFrame {
+    /// From the scanner to the IMU, in the configured body frame.
+    #[default]
+    Body,
+
+    /// From the IMU to the scanner, in the IMU's forward, right, and down axes.
+    ///
+    /// The IMU axes are always north, east, down, whatever the configured navigation frame.
+    Imu,
+
+    /// From the scanner to the IMU, in the scanner's own axes.
+    ///
+    /// The lever arm is rotated into the body frame by the boresight.
+    Sensor,
+}
+
+impl LeverArmFrame {
+    /// Returns the matrix that converts a lever arm in this frame into the body frame convention.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{LeverArmFrame, Matrix3, NavigationFrame, Point};
+    /// let boresight = Matrix3::identity();
+    /// let lever_arm = Point::new(1., 2., 3.);
+    /// let matrix = LeverArmFrame::Imu.matrix(boresight, NavigationFrame::Ned);
+    /// assert_eq!(-lever_arm, matrix * lever_arm);
+    /// let matrix = LeverArmFrame::Imu.matrix(boresight, NavigationFrame::Enu);
+    /// assert_eq!(Point::new(-2., -1., 3.), matrix * lever_arm);
+    /// ```
+    pub fn matrix(&self, boresight: Matrix3, navigation_frame: NavigationFrame) -> Matrix3 {
+        match self {
+            LeverArmFrame::Body => Matrix3::identity(),
+            LeverArmFrame::Imu => -navigation_frame.matrix(),
+            LeverArmFrame::Sensor => boresight,
+        }
+    }
+}
+
+impl ScanAngleUncertainty {
+    /// Returns the uncertainty at a scan angle, both in radians.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::ScanAngleUncertainty;
+    /// assert_eq!(1e-5, ScanAngleUncertainty::Constant(1e-5).at(0.3));
+    /// let polynomial = ScanAngleUncertainty::Polynomial { polynomial: vec![1e-5, 0., 1e-4] };
+    /// assert_eq!(1e-5 + 1e-4 * 0.25, polynomial.at(-0.5));
+    /// let table = ScanAngleUncertainty::Table { table: vec![(0., 1e-5), (0.5, 2e-5)] };
+    /// assert!((table.at(0.25) - 1.5e-5).abs() < 1e-12);
+    /// assert_eq!(2e-5, table.at(0.7));
+    /// ```
+    pub fn at(&self, scan_angle: f64) -> f64 {
+        match self {
+            ScanAngleUncertainty::Constant(uncertainty) => *uncertainty,
+            ScanAngleUncertainty::Polynomial { polynomial } => polynomial
+                .iter()
+                .rev()
+                .fold(0., |value, coefficient| value * scan_angle + coefficient),
+            ScanAngleUncertainty::Table { table } => {
+                let i = table.partition_point(|&(angle, _)| angle < scan_angle);
+                match (i.checked_sub(1).and_then(|i| table.get(i)), table.get(i)) {
+                    (Some(&(a0, u0)), Some(&(a1, u1))) => {
+                        u0 + (u1 - u0) * (scan_angle - a0) / (a1 - a0)
+                    }
+                    (Some(&(_, uncertainty)), None) | (None, Some(&(_, uncertainty))) => {
+                        uncertainty
+                    }
+                    (None, None) => 0.,
+                }
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if let ScanAngleUncertainty::Table { table } = self {
+            if table.is_empty() {
+                return Err(Error::Config(
+                    "the scan angle uncertainty table is empty".to_string(),
+                ));
+            }
+            if table.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                return Err(Error::Config(
+                    "the scan angle uncertainty table must be sorted by scan angle".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ScanAngleUncertainty {
+    fn default() -> ScanAngleUncertainty {
+        ScanAngleUncertainty::Constant(0.)
+    }
 }
 
 impl Config {
@@ -42,10 +663,187 @@ impl Config {
     /// # use leeward::Config;
     /// let config = Config::from_path("data/config.toml").unwrap();
     /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %path.as_ref().display()))
+    )]
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+        Config::from_reader(File::open(path)?)
+    }
+
+    /// Reads a new configuration from anything that implements `Read`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Config;
+    /// let file = std::fs::File::open("data/config.toml").unwrap();
+    /// let config = Config::from_reader(file).unwrap();
+    /// ```
+    pub fn from_reader<R: Read>(mut read: R) -> Result<Config, Error> {
         let mut string = String::new();
-        File::open(path).and_then(|mut f| f.read_to_string(&mut string))?;
-        toml::from_str(&string).map_err(Error::from)
+        read.read_to_string(&mut string)?;
+        string.parse()
+    }
+
+    /// Returns this config as TOML, with comments that describe its values and their units.
+    ///
+    /// Angles also have a comment with their value in degrees. The string
+    /// parses back into the same config.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Config;
+    /// let config = Config::from_path("data/config.toml").unwrap();
+    /// let toml = config.to_toml_string_documented().unwrap();
+    /// assert!(toml.contains("beam_divergence = 0.00025 # beam divergence, radians"));
+    /// assert_eq!(config, toml.parse().unwrap());
+    /// ```
+    pub fn to_toml_string_documented(&self) -> Result<String, Error> {
+        let doc = |path: &str| {
+            let lookup = |path: &str| DOCS.iter().find(|(p, _)| *p == path).map(|(_, d)| *d);
+            lookup(path).or_else(|| path.strip_prefix("override.").and_then(lookup))
+        };
+        let mut documented = String::new();
+        let mut table = String::new();
+        for line in toml::to_string(self)?.lines() {
+            if line.starts_with('[') {
+                table = line.trim_matches(|c| c == '[' || c == ']').to_string();
+                if let Some(doc) = doc(&table) {
+                    documented.push_str(&format!("# {}\n", doc));
+                }
+                documented.push_str(line);
+            } else if let Some((key, value)) = line.split_once(" = ") {
+                documented.push_str(line);
+                let path = if table.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", table, key)
+                };
+                let degrees = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|_| units::is_angle(key))
+                    .map(|radians| {
+                        let degrees = format!("{:.6}", radians.to_degrees());
+                        format!("{}°", degrees.trim_end_matches('0').trim_end_matches('.'))
+                    });
+                match (doc(&path), degrees) {
+                    (Some(doc), Some(degrees)) => {
+                        documented.push_str(&format!(" # {}, {}", doc, degrees))
+                    }
+                    (Some(doc), None) => documented.push_str(&format!(" # {}", doc)),
+                    (None, Some(degrees)) => documented.push_str(&format!(" # {}", degrees)),
+                    (None, None) => {}
+                }
+            } else {
+                documented.push_str(line);
+            }
+            documented.push('\n');
+        }
+        Ok(documented)
+    }
+
+    /// Returns the config for a point at a gps time, with any override for that time merged in.
+    ///
+    /// The returned config doesn't have any overrides of its own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Config;
+    /// let toml = std::fs::read_to_string("data/config.toml").unwrap();
+    /// let config: Config = format!(
+    ///     "{}\n[[override]]\nstart_time = 10\nend_time = 20\nlever_arm = [1, 2, 3]\n",
+    ///     toml
+    /// )
+    /// .parse()
+    /// .unwrap();
+    /// assert_eq!(2., config.at_time(15.).unwrap().lever_arm.y);
+    /// assert_eq!(0., config.at_time(25.).unwrap().lever_arm.y);
+    /// assert!(config.at_time(25.).unwrap().overrides.is_empty());
+    /// ```
+    pub fn at_time(&self, time: f64) -> Result<Config, Error> {
+        match self.overrides.iter().find(|o| o.contains(time)) {
+            Some(o) => self.with_override(o),
+            None => Ok(self.without_overrides()),
+        }
+    }
+
+    /// Merges every override ahead of time.
+    pub(crate) fn resolve(&self) -> Result<ResolvedConfig, Error> {
+        Ok(ResolvedConfig {
+            base: self.without_overrides(),
+            overrides: self
+                .overrides
+                .iter()
+                .map(|o| Ok((o.start_time, o.end_time, self.with_override(o)?)))
+                .collect::<Result<_, Error>>()?,
+        })
+    }
+
+    fn with_override(&self, o: &Override) -> Result<Config, Error> {
+        let mut table = toml::Table::try_from(self.without_overrides())?;
+        merge(&mut table, &o.values);
+        let config: Config = table.try_into()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub(crate) fn without_overrides(&self) -> Config {
+        Config {
+            overrides: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        self.uncertainty.scan_angle.validate()?;
+        self.ellipsoid.validate()?;
+        self.returns.validate()?;
+        if let Some(datum_transformation) = &self.datum_transformation {
+            datum_transformation.validate(self.epoch)?;
+        }
+        for o in &self.overrides {
+            if o.start_time > o.end_time {
+                return Err(Error::Config(format!(
+                    "override starts after it ends: {} > {}",
+                    o.start_time, o.end_time
+                )));
+            }
+            if o.values.contains_key("override") {
+                return Err(Error::Config("overrides can't be nested".to_string()));
+            }
+            let _ = self.with_override(o)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the constraint for a variable.
+    ///
+    /// Variables that can't be adjusted have no constraints.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Variable, Config};
+    /// let config = Config::from_path("data/config.toml").unwrap();
+    /// assert!(!config.constraint(Variable::BoresightRoll).fixed);
+    /// ```
+    pub fn constraint(&self, variable: Variable) -> Constraint {
+        let constraints = &self.constraints;
+        match variable {
+            Variable::BoresightRoll => constraints.boresight_roll,
+            Variable::BoresightPitch => constraints.boresight_pitch,
+            Variable::BoresightYaw => constraints.boresight_yaw,
+            Variable::LeverArmX => constraints.lever_arm_x,
+            Variable::LeverArmY => constraints.lever_arm_y,
+            Variable::LeverArmZ => constraints.lever_arm_z,
+            Variable::ScanAngleOffset => constraints.scan_angle_offset,
+            Variable::ScanAngleScale => constraints.scan_angle_scale,
+            _ => Constraint::default(),
+        }
     }
 
     /// Returns a vector of values as specified by the provided variables.
@@ -68,7 +866,14 @@ impl Config {
                 Variable::LeverArmX => self.lever_arm.x,
                 Variable::LeverArmY => self.lever_arm.y,
                 Variable::LeverArmZ => self.lever_arm.z,
-                _ => return Err(anyhow!("cannot get value for variable: {:?}", variable)),
+                Variable::ScanAngleOffset => self.scan_angle_calibration.offset,
+                Variable::ScanAngleScale => self.scan_angle_calibration.scale,
+                _ => {
+                    return Err(Error::Config(format!(
+                        "cannot get value for variable: {:?}",
+                        variable
+                    )))
+                }
             };
         }
         Ok(values)
@@ -76,6 +881,9 @@ impl Config {
 
     /// Returns a new configuration with the provided variables set to the provided values.
     ///
+    /// Boresight angles are wrapped into `[-π, π)`, so an adjustment step across
+    /// ±π doesn't leave the boresight a full turn away from where it started.
+    ///
     /// # Examples
     ///
     /// ```
@@ -88,7 +896,7 @@ impl Config {
     /// assert_eq!(0.2, config.boresight.pitch);
     /// ```
     pub fn with_values(&self, variables: &[Variable], values: &[f64]) -> Result<Config, Error> {
-        let mut config = *self;
+        let mut config = self.clone();
         for (variable, value) in variables.iter().zip(values) {
             let target = match variable {
                 Variable::BoresightRoll => &mut config.boresight.roll,
@@ -97,10 +905,284 @@ impl Config {
                 Variable::LeverArmX => &mut config.lever_arm.x,
                 Variable::LeverArmY => &mut config.lever_arm.y,
                 Variable::LeverArmZ => &mut config.lever_arm.z,
-                _ => return Err(anyhow!("cannot set variable: {:?}", variable)),
+                Variable::ScanAngleOffset => &mut config.scan_angle_calibration.offset,
+                Variable::ScanAngleScale => &mut config.scan_angle_calibration.scale,
+                _ => {
+                    return Err(Error::Config(format!(
+                        "cannot set variable: {:?}",
+                        variable
+                    )))
+                }
             };
             *target = *value;
         }
+        config.boresight = config.boresight.normalized();
+        Ok(config)
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    /// Parses a configuration from a toml string.
+    ///
+    /// Angles and lengths can have units, e.g. `roll = "0.5 deg"`, and are
+    /// converted to radians and meters. If the string names a [Preset], its
+    /// values are used for anything that isn't in the string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Config;
+    /// let toml = std::fs::read_to_string("data/config.toml").unwrap();
+    /// let config: Config = toml.parse().unwrap();
+    /// assert_eq!(config, Config::from_path("data/config.toml").unwrap());
+    /// ```
+    fn from_str(s: &str) -> Result<Config, Error> {
+        let mut table: toml::Table = toml::from_str(s)?;
+        units::convert(&mut table)?;
+        if let Some(preset) = table.get("preset") {
+            let preset = Preset::deserialize(preset.clone())?;
+            let values = std::mem::replace(&mut table, preset.table());
+            merge(&mut table, &values);
+        }
+        let config: Config = table.try_into()?;
+        config.validate()?;
         Ok(config)
     }
 }
+
+impl Override {
+    fn contains(&self, time: f64) -> bool {
+        time >= self.start_time && time <= self.end_time
+    }
+}
+
+impl ResolvedConfig {
+    /// Returns the config for a point at a gps time, or the base config for points without a time.
+    pub(crate) fn at_time(&self, time: Option<f64>) -> &Config {
+        time.and_then(|time| {
+            self.overrides
+                .iter()
+                .find(|(start, end, _)| time >= *start && time <= *end)
+        })
+        .map(|(_, _, config)| config)
+        .unwrap_or(&self.base)
+    }
+}
+
+/// Merges the values into a table, recursing into tables that are in both.
+fn merge(table: &mut toml::Table, values: &toml::Table) {
+    for (key, value) in values {
+        match (table.get_mut(key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(values)) => merge(table, values),
+            _ => {
+                let _ = table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+impl Constraints {
+    fn is_empty(&self) -> bool {
+        *self == Constraints::default()
+    }
+}
+
+impl Constraint {
+    fn is_empty(&self) -> bool {
+        *self == Constraint::default()
+    }
+}
+
+impl ScanAngleCalibration {
+    /// Applies this calibration to a las scan angle, in radians.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::ScanAngleCalibration;
+    /// let calibration = ScanAngleCalibration { offset: 0.1, scale: 2. };
+    /// assert_eq!(0.5, calibration.apply(0.2));
+    /// assert_eq!(0.2, ScanAngleCalibration::default().apply(0.2));
+    /// ```
+    pub fn apply(&self, scan_angle: f64) -> f64 {
+        self.scale * scan_angle + self.offset
+    }
+
+    fn is_identity(&self) -> bool {
+        *self == ScanAngleCalibration::default()
+    }
+}
+
+impl Default for ScanAngleCalibration {
+    fn default() -> ScanAngleCalibration {
+        ScanAngleCalibration {
+            offset: 0.,
+            scale: 1.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ScanAngleUncertainty};
+
+    #[test]
+    fn scan_angle_uncertainty() {
+        let toml = std::fs::read_to_string("data/config.toml").unwrap();
+        let config: Config = toml.parse().unwrap();
+        assert_eq!(
+            ScanAngleUncertainty::Constant(1.745329e-5),
+            config.uncertainty.scan_angle
+        );
+        let polynomial = toml.replace(
+            "scan_angle = 1.745329e-5",
+            "scan_angle = { polynomial = [1e-5, 0, 1e-4] }",
+        );
+        let config: Config = polynomial.parse().unwrap();
+        assert_eq!(
+            ScanAngleUncertainty::Polynomial {
+                polynomial: vec![1e-5, 0., 1e-4]
+            },
+            config.uncertainty.scan_angle
+        );
+        let table = toml.replace(
+            "scan_angle = 1.745329e-5",
+            "scan_angle = { table = [[-0.3, 2e-5], [0, 1e-5], [0.3, 2e-5]] }",
+        );
+        let config: Config = table.parse().unwrap();
+        assert_eq!(1e-5, config.uncertainty.scan_angle.at(0.));
+        let unsorted = toml.replace(
+            "scan_angle = 1.745329e-5",
+            "scan_angle = { table = [[0.3, 2e-5], [0, 1e-5]] }",
+        );
+        assert!(unsorted.parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn preset_values_are_overridable() {
+        use crate::{Preset, ScannerModel};
+        let config: Config = "preset = \"optech-titan\"\nutm_zone = 11\nlever_arm = [0, 0, 0]\n\
+            boresight = { roll = 0, pitch = 0, yaw = 0 }\nbeam_divergence = 0.5e-3\n\
+            [uncertainty]\ngnss_z = 0.15\n"
+            .parse()
+            .unwrap();
+        assert_eq!(Some(Preset::OptechTitan), config.preset);
+        assert_eq!(0.5e-3, config.beam_divergence);
+        assert_eq!(0.15, config.uncertainty.gnss_z);
+        assert_eq!(0.05, config.uncertainty.gnss_x);
+        assert_eq!(0.008, config.uncertainty.range);
+        assert_eq!(ScannerModel::OscillatingMirror, config.scanner);
+        assert!("preset = \"leica\"".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn documented_round_trip() {
+        let toml = std::fs::read_to_string("data/config.toml").unwrap();
+        let config: Config = format!(
+            "{}\n[scanner]\ntype = \"palmer\"\nmirror_tilt = 0.1\n\
+             [[override]]\nstart_time = 10\nend_time = 20\npulse_rate = 1000\n\
+             [override.boresight]\nroll = 0.01\n",
+            toml
+        )
+        .parse()
+        .unwrap();
+        let documented = config.to_toml_string_documented().unwrap();
+        assert!(documented.contains("roll = -1.54 # radians, -88.2355°"));
+        assert!(documented.contains("mirror_tilt = 0.1 # radians, 5.729578°"));
+        assert!(documented.contains("pulse_rate = 1000 # pulses per second"));
+        assert!(documented.contains("# Misalignment between the scanner and the IMU\n[boresight]"));
+        assert_eq!(config, documented.parse().unwrap());
+    }
+
+    #[test]
+    fn boresight_wraps() {
+        use crate::Variable;
+        use std::f64::consts::PI;
+        let config = Config::from_path("data/config.toml").unwrap();
+        let config = config
+            .with_values(&[Variable::BoresightYaw], &[PI - 0.01])
+            .unwrap();
+        let values = config.values(&[Variable::BoresightYaw]).unwrap();
+        let config = config
+            .with_values(&[Variable::BoresightYaw], &[values[0] + 0.02])
+            .unwrap();
+        assert!((config.boresight.yaw - (-PI + 0.01)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ellipsoid() {
+        use super::ReferenceEllipsoid;
+        use crate::convert::{Ellipsoid, WGS_84};
+        let toml = std::fs::read_to_string("data/config.toml").unwrap();
+        let config: Config = toml.parse().unwrap();
+        assert_eq!(WGS_84, config.ellipsoid.ellipsoid());
+        let grs80: Config = format!("ellipsoid = \"grs80\"\n{}", toml).parse().unwrap();
+        assert_eq!(ReferenceEllipsoid::Grs80, grs80.ellipsoid);
+        let custom: Config = format!(
+            "{}\n[ellipsoid.custom]\na = 6378206.4\nf = 0.0033900753\n",
+            toml
+        )
+        .parse()
+        .unwrap();
+        assert_eq!(
+            Ellipsoid::new(6378206.4, 0.0033900753),
+            custom.ellipsoid.ellipsoid()
+        );
+        let invalid = format!("{}\n[ellipsoid.custom]\na = 6378206.4\nf = 1.5\n", toml);
+        assert!(invalid.parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn datum_transformation() {
+        use std::f64::consts::PI;
+        let toml = std::fs::read_to_string("data/config.toml").unwrap();
+        let transformation =
+            "[datum_transformation]\ntranslation = [1.0053, -1.9021, \"-54.16 cm\"]\n\
+            rotation = [\"26.7814 mas\", 0, \"0.01 arcsec\"]\nscale = 0.37e-9\n\
+            translation_rate = [0.0008, \"-0.6 mm\", -0.0014]\nreference_epoch = 2010.0\n";
+        assert!(format!("{}\n{}", toml, transformation)
+            .parse::<Config>()
+            .is_err());
+        let config: Config = format!("epoch = 2019.5\n{}\n{}", toml, transformation)
+            .parse()
+            .unwrap();
+        let helmert = config.datum_transformation.unwrap();
+        assert!((helmert.translation.z + 0.5416).abs() < 1e-12);
+        assert!((helmert.rotation.x - 26.7814e-3 / 3600. * PI / 180.).abs() < 1e-15);
+        assert!((helmert.rotation.z - 0.01 / 3600. * PI / 180.).abs() < 1e-15);
+        assert!((helmert.translation_rate.y + 0.0006).abs() < 1e-12);
+        assert_eq!(Some(2019.5), config.epoch);
+        let round_trip: Config = config.to_toml_string_documented().unwrap().parse().unwrap();
+        assert_eq!(config, round_trip);
+    }
+
+    #[test]
+    fn overrides() {
+        let toml = std::fs::read_to_string("data/config.toml").unwrap();
+        let config: Config = format!(
+            "{}\n[[override]]\nstart_time = 400825.5\nend_time = 400826\n[override.boresight]\nroll = 0.001\n",
+            toml
+        )
+        .parse()
+        .unwrap();
+        let overridden = config.at_time(400825.6).unwrap();
+        assert_eq!(0.001, overridden.boresight.roll);
+        assert_eq!(config.boresight.pitch, overridden.boresight.pitch);
+        let serialized = toml::to_string(&config).unwrap();
+        assert_eq!(config, serialized.parse().unwrap());
+        assert_eq!(
+            config.boresight,
+            config.at_time(400825.4).unwrap().boresight
+        );
+
+        let invalid = format!("{}\n[[override]]\nstart_time = 1\nend_time = 0\n", toml);
+        assert!(invalid.parse::<Config>().is_err());
+        let invalid = format!(
+            "{}\n[[override]]\nstart_time = 0\nend_time = 1\nbeam_divergence = \"wide\"\n",
+            toml
+        );
+        assert!(invalid.parse::<Config>().is_err());
+    }
+}
@@ -1,7 +1,14 @@
 //! Utilities for coordinate conversion.
+//!
+//! The free functions use the [WGS_84] ellipsoid. Each has an [Ellipsoid]
+//! method of the same name for other ellipsoids, e.g. [GRS_80]. A [Helmert]
+//! transformation moves ECEF points between datums, e.g. from the ITRF of a
+//! trajectory to the NAD83 of a point cloud.
 
-use crate::{Matrix3, Point, RollPitchYaw};
+use crate::{Config, Error, Matrix3, NavigationFrame, Point, RollPitchYaw, RotationOrder};
+use serde::{Deserialize, Serialize};
 
+/// The WGS84 ellipsoid, used by the trajectory.
 pub const WGS_84: Ellipsoid = Ellipsoid {
     a: 6378137.,
     a2: 6378137. * 6378137.,
@@ -10,24 +17,110 @@ pub const WGS_84: Ellipsoid = Ellipsoid {
     b2: 6356752.3142 * 6356752.3142,
 };
 
+/// The GRS80 ellipsoid, used by NAD83 and ETRS89.
+pub const GRS_80: Ellipsoid = Ellipsoid::new(6378137., 1. / 298.257222101);
+
 /// Converts a projected (UTM) point to body frame.
 ///
 /// A convenience method to chain together some other functions in this mod.
+/// The body frame axes follow the navigation frame convention.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::{convert, NavigationFrame, Point, RollPitchYaw};
+/// let point = Point::new(320000.34, 4181319.35, 2687.59);
+/// let platform = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+/// let rpy = RollPitchYaw::new(0., 0., 0.4);
+/// let body = convert::projected_to_body(point, platform, rpy, 11, NavigationFrame::Ned);
+/// let enu = convert::projected_to_body(point, platform, rpy, 11, NavigationFrame::Enu);
+/// assert_eq!(body.x, enu.y);
+/// ```
+pub fn projected_to_body(
+    point: Point,
+    platform: Point,
+    rpy: RollPitchYaw,
+    utm_zone: u8,
+    frame: NavigationFrame,
+) -> Point {
+    WGS_84.projected_to_body(point, platform, rpy, utm_zone, frame)
+}
+
+/// Converts many projected (UTM) points to the body frame of a single pose.
+///
+/// The same as calling [projected_to_body] on each point, but the platform's
+/// ECEF position and the rotation matrices are only computed once.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::{convert, NavigationFrame, Point, RollPitchYaw};
+/// let points = [Point::new(320000.34, 4181319.35, 2687.59), Point::new(320001., 4181320., 2688.)];
+/// let platform = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+/// let rpy = RollPitchYaw::new(0., 0., 0.4);
+/// let body = convert::projected_to_body_batch(&points, platform, rpy, 11, NavigationFrame::Ned);
+/// assert_eq!(convert::projected_to_body(points[1], platform, rpy, 11, NavigationFrame::Ned), body[1]);
+/// ```
+pub fn projected_to_body_batch(
+    points: &[Point],
+    platform: Point,
+    rpy: RollPitchYaw,
+    utm_zone: u8,
+    frame: NavigationFrame,
+) -> Vec<Point> {
+    WGS_84.projected_to_body_batch(points, platform, rpy, utm_zone, frame)
+}
+
+/// Converts a body frame point to projected (UTM) coordinates.
+///
+/// The inverse of [projected_to_body], chaining together the inverse functions in this mod.
 ///
 /// # Examples
 ///
 /// ```
-/// # use leeward::{convert, Point, RollPitchYaw};
+/// # use leeward::{convert, NavigationFrame, Point, RollPitchYaw};
 /// let point = Point::new(320000.34, 4181319.35, 2687.59);
 /// let platform = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
 /// let rpy = RollPitchYaw::new(0., 0., 0.4);
-/// let body = convert::projected_to_body(point, platform, rpy, 11);
+/// let frame = NavigationFrame::Enu;
+/// let body = convert::projected_to_body(point, platform, rpy, 11, frame);
+/// let projected = convert::body_to_projected(body, platform, rpy, 11, frame);
+/// assert!((projected - point).norm() < 1e-3);
 /// ```
-pub fn projected_to_body(point: Point, platform: Point, rpy: RollPitchYaw, utm_zone: u8) -> Point {
-    let geodetic = projected_to_geodetic(point, utm_zone);
-    let geocentric = geodetic_to_ecef(geodetic);
-    let navigation = ecef_to_navigation(geocentric, platform);
-    navigation_to_body(navigation, rpy)
+pub fn body_to_projected(
+    point: Point,
+    platform: Point,
+    rpy: RollPitchYaw,
+    utm_zone: u8,
+    frame: NavigationFrame,
+) -> Point {
+    WGS_84.body_to_projected(point, platform, rpy, utm_zone, frame)
+}
+
+/// Converts many body frame points of a single pose to projected (UTM) coordinates.
+///
+/// The inverse of [projected_to_body_batch].
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::{convert, NavigationFrame, Point, RollPitchYaw};
+/// let points = [Point::new(320000.34, 4181319.35, 2687.59), Point::new(320001., 4181320., 2688.)];
+/// let platform = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+/// let rpy = RollPitchYaw::new(0., 0., 0.4);
+/// let frame = NavigationFrame::Enu;
+/// let body = convert::projected_to_body_batch(&points, platform, rpy, 11, frame);
+/// let projected = convert::body_to_projected_batch(&body, platform, rpy, 11, frame);
+/// assert!((projected[1] - points[1]).norm() < 1e-3);
+/// ```
+pub fn body_to_projected_batch(
+    points: &[Point],
+    platform: Point,
+    rpy: RollPitchYaw,
+    utm_zone: u8,
+    frame: NavigationFrame,
+) -> Vec<Point> {
+    WGS_84.body_to_projected_batch(points, platform, rpy, utm_zone, frame)
 }
 
 /// Converts from projected (UTM) coordinates into geodetic coordinates.
@@ -42,31 +135,41 @@ pub fn projected_to_body(point: Point, platform: Point, rpy: RollPitchYaw, utm_z
 /// let geodetic = convert::projected_to_geodetic(projected, 11); // 11 is the UTM zone
 /// ```
 pub fn projected_to_geodetic(point: Point, utm_zone: u8) -> Point {
-    let ellipsoid = WGS_84;
-    let n = ellipsoid.f / (2. - ellipsoid.f);
-    let a = ellipsoid.a / (1. + n) * (1. + n.powi(2) / 4. + n.powi(4) / 64.);
-    let k0 = 0.9996;
-    let xi = point.y / (k0 * a);
-    let nu = (point.x - 500e3) / (k0 * a);
-    let b1 = 0.5 * n - (2. / 3.) * n.powi(2) - (37. / 96.) * n.powi(3);
-    let b2 = (1. / 48.) * n.powi(2) - (1. / 15.) * n.powi(3);
-    let b3 = (17. / 480.) * n.powi(3);
-    let d1 = 2. * n - (2. / 3.) * n.powi(2) - 2. * n.powi(3);
-    let d2 = (7. / 3.) * n.powi(2) - (8. / 5.) * n.powi(3);
-    let d3 = (56. / 15.) * n.powi(3);
-    let xi_prime = xi
-        - (b1 * (2. * xi).sin() * (2. * nu).cosh()
-            + b2 * (4. * xi).sin() * (4. * nu).cosh()
-            + b3 * (6. * xi).sin() * (6. * nu).cosh());
-    let nu_prime = nu
-        - (b1 * (2. * xi).cos() * (2. * nu).sinh()
-            + b2 * (4. * xi).cos() * (4. * nu).sinh()
-            + b3 * (6. * xi).cos() * (6. * nu).sinh());
-    let chi = (xi_prime.sin() / nu_prime.cosh()).asin();
-    let latitude = chi + d1 * (2. * chi).sin() + d2 * (4. * chi).sin() + d3 * (6. * chi).sin();
-    let reference_meridian = f64::from(utm_zone) * 6f64.to_radians() - 183f64.to_radians();
-    let longitude = reference_meridian + (nu_prime.sinh() / xi_prime.cos()).atan();
-    Point::new(longitude, latitude, point.z)
+    WGS_84.projected_to_geodetic(point, utm_zone)
+}
+
+/// Converts from geodetic coordinates into projected (UTM) coordinates.
+///
+/// The geodetic coordinates are in radians. Like [projected_to_geodetic], this
+/// uses the Krüger series and assumes the northern hemisphere.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{convert, Point};
+/// let geodetic = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+/// let projected = convert::geodetic_to_projected(geodetic, 11);
+/// ```
+pub fn geodetic_to_projected(point: Point, utm_zone: u8) -> Point {
+    WGS_84.geodetic_to_projected(point, utm_zone)
+}
+
+/// Returns the UTM zone that contains a longitude, in radians.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::convert;
+/// assert_eq!(11, convert::utm_zone(-119.0434f64.to_radians()));
+/// assert_eq!(1, convert::utm_zone(-180f64.to_radians()));
+/// assert_eq!(60, convert::utm_zone(179.9f64.to_radians()));
+/// ```
+pub fn utm_zone(longitude: f64) -> u8 {
+    // The zone can't be more than 60, even at exactly 180°.
+    ((longitude.to_degrees() + 180.) / 6.)
+        .floor()
+        .clamp(0., 59.) as u8
+        + 1
 }
 
 /// Converts a geodetic point to ECEF.
@@ -81,14 +184,25 @@ pub fn projected_to_geodetic(point: Point, utm_zone: u8) -> Point {
 /// let geocentric = convert::geodetic_to_ecef(geodetic);
 /// ```
 pub fn geodetic_to_ecef(point: Point) -> Point {
-    let n = WGS_84.n(point.y);
-    let x = (n + point.z) * point.y.cos() * point.x.cos();
-    let y = (n + point.z) * point.y.cos() * point.x.sin();
-    let z = (WGS_84.b2 / WGS_84.a2 * n + point.z) * point.y.sin();
-    Point::new(x, y, z)
+    WGS_84.geodetic_to_ecef(point)
+}
+
+/// Converts an ECEF point to geodetic coordinates.
+///
+/// Uses the WGS84 ellipsoid and Bowring's method. The geodetic coordinates are in radians.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{convert, Point};
+/// let ecef = Point::new(-2452031., -4415678., 3886195.);
+/// let geodetic = convert::ecef_to_geodetic(ecef);
+/// ```
+pub fn ecef_to_geodetic(point: Point) -> Point {
+    WGS_84.ecef_to_geodetic(point)
 }
 
-/// Converts an ECEF point to navigation frame.
+/// Converts an ECEF point to the north, east, down navigation frame.
 ///
 /// # Examples
 ///
@@ -99,9 +213,34 @@ pub fn geodetic_to_ecef(point: Point) -> Point {
 /// let navigation = convert::ecef_to_navigation(ecef, platform);
 /// ```
 pub fn ecef_to_navigation(point: Point, platform: Point) -> Point {
-    let platform_ecef = geodetic_to_ecef(platform);
-    let matrix = ecef_to_navigation_matrix(platform);
-    matrix * (point - platform_ecef)
+    WGS_84.ecef_to_navigation(point, platform)
+}
+
+/// Converts a north, east, down navigation frame point to ECEF.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{convert, Point};
+/// let navigation = Point::new(-1000., -200., 4000.);
+/// let platform = Point::new(-119.0434f64.to_radians(), 37.7615f64.to_radians(), 2687.59);
+/// let ecef = convert::navigation_to_ecef(navigation, platform);
+/// ```
+pub fn navigation_to_ecef(point: Point, platform: Point) -> Point {
+    WGS_84.navigation_to_ecef(point, platform)
+}
+
+/// Converts a body frame point to navigation frame.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{convert, Point, RollPitchYaw};
+/// let body = Point::new(-1000., -200., 4000.);
+/// let navigation = convert::body_to_navigation(body, RollPitchYaw::new(0.0, 0.0, 0.4));
+/// ```
+pub fn body_to_navigation(point: Point, rpy: RollPitchYaw) -> Point {
+    RotationOrder::Zyx.matrix(rpy) * point
 }
 
 /// Converts a navigation frame point to body frame.
@@ -114,32 +253,549 @@ pub fn ecef_to_navigation(point: Point, platform: Point) -> Point {
 /// let body = convert::navigation_to_body(navigation, RollPitchYaw::new(0.0, 0.0, 0.4));
 /// ```
 pub fn navigation_to_body(point: Point, rpy: RollPitchYaw) -> Point {
-    let matrix = rpy.as_matrix();
+    let matrix = RotationOrder::Zyx.matrix(rpy);
     matrix.transpose() * point
 }
 
+/// Returns a trajectory point's geodetic position, in the points' datum.
+///
+/// The point is longitude, latitude (both in radians), and height. If the
+/// config has a [datum transformation](Config::datum_transformation), the
+/// WGS84 position is transformed at the config's epoch onto the config's
+/// ellipsoid.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{convert, Config};
+/// let config = Config::from_path("data/config.toml").unwrap();
+/// let sbet = sbet::Point { latitude: 0.6, longitude: -2.1, altitude: 3000., ..Default::default() };
+/// assert_eq!(3000., convert::platform(&sbet, &config).z);
+/// ```
+pub fn platform(sbet: &sbet::Point, config: &Config) -> Point {
+    let platform = Point::new(sbet.longitude, sbet.latitude, sbet.altitude);
+    match &config.datum_transformation {
+        Some(transformation) => {
+            let epoch = config.epoch.unwrap_or(transformation.reference_epoch);
+            let ecef = transformation.transform(WGS_84.geodetic_to_ecef(platform), epoch);
+            config.ellipsoid.ellipsoid().ecef_to_geodetic(ecef)
+        }
+        None => platform,
+    }
+}
+
 /// An ellipsoid.
 ///
 /// Some of the fields are derived, but required to minimise computations when using the ellipsoid.
 ///
 /// cbindgen:ignore
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ellipsoid {
     a: f64,
     a2: f64,
     f: f64,
-    #[allow(dead_code)]
     b: f64,
     b2: f64,
 }
 
 impl Ellipsoid {
+    /// Creates a new ellipsoid from its semi-major axis, in meters, and its flattening.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::convert::{Ellipsoid, GRS_80};
+    /// let ellipsoid = Ellipsoid::new(6378137., 1. / 298.257222101);
+    /// assert_eq!(GRS_80.semi_minor_axis(), ellipsoid.semi_minor_axis());
+    /// ```
+    pub const fn new(a: f64, f: f64) -> Ellipsoid {
+        let b = a * (1. - f);
+        Ellipsoid {
+            a,
+            a2: a * a,
+            f,
+            b,
+            b2: b * b,
+        }
+    }
+
+    /// Returns this ellipsoid's semi-major axis, in meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// assert_eq!(6378137., leeward::convert::WGS_84.semi_major_axis());
+    /// ```
+    pub fn semi_major_axis(&self) -> f64 {
+        self.a
+    }
+
+    /// Returns this ellipsoid's semi-minor axis, in meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// assert!(leeward::convert::WGS_84.semi_minor_axis() < 6378137.);
+    /// ```
+    pub fn semi_minor_axis(&self) -> f64 {
+        self.b
+    }
+
+    /// Returns this ellipsoid's flattening.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// assert_eq!(1. / 298.257223563, leeward::convert::WGS_84.flattening());
+    /// ```
+    pub fn flattening(&self) -> f64 {
+        self.f
+    }
+
+    /// Converts a projected (UTM) point on this ellipsoid to body frame, like [projected_to_body].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, NavigationFrame, Point, RollPitchYaw};
+    /// let point = Point::new(320000.34, 4181319.35, 2687.59);
+    /// let platform = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+    /// let rpy = RollPitchYaw::new(0., 0., 0.4);
+    /// let body = GRS_80.projected_to_body(point, platform, rpy, 11, NavigationFrame::Ned);
+    /// ```
+    pub fn projected_to_body(
+        &self,
+        point: Point,
+        platform: Point,
+        rpy: RollPitchYaw,
+        utm_zone: u8,
+        frame: NavigationFrame,
+    ) -> Point {
+        let geodetic = self.projected_to_geodetic(point, utm_zone);
+        let geocentric = self.geodetic_to_ecef(geodetic);
+        let navigation = self.ecef_to_navigation(geocentric, platform);
+        frame.matrix() * navigation_to_body(navigation, rpy)
+    }
+
+    /// Converts many projected (UTM) points on this ellipsoid to body frame, like [projected_to_body_batch].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, NavigationFrame, Point, RollPitchYaw};
+    /// let points = [Point::new(320000.34, 4181319.35, 2687.59)];
+    /// let platform = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+    /// let rpy = RollPitchYaw::new(0., 0., 0.4);
+    /// let body = GRS_80.projected_to_body_batch(&points, platform, rpy, 11, NavigationFrame::Ned);
+    /// ```
+    pub fn projected_to_body_batch(
+        &self,
+        points: &[Point],
+        platform: Point,
+        rpy: RollPitchYaw,
+        utm_zone: u8,
+        frame: NavigationFrame,
+    ) -> Vec<Point> {
+        let platform_ecef = self.geodetic_to_ecef(platform);
+        let ecef_to_navigation = ecef_to_navigation_matrix(platform);
+        let navigation_to_body = RotationOrder::Zyx.matrix(rpy).transpose();
+        let frame = frame.matrix();
+        points
+            .iter()
+            .map(|&point| {
+                let geocentric = self.geodetic_to_ecef(self.projected_to_geodetic(point, utm_zone));
+                frame * (navigation_to_body * (ecef_to_navigation * (geocentric - platform_ecef)))
+            })
+            .collect()
+    }
+
+    /// Converts a body frame point to projected (UTM) coordinates on this ellipsoid, like [body_to_projected].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, NavigationFrame, Point, RollPitchYaw};
+    /// let body = Point::new(-1000., -200., 4000.);
+    /// let platform = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+    /// let rpy = RollPitchYaw::new(0., 0., 0.4);
+    /// let projected = GRS_80.body_to_projected(body, platform, rpy, 11, NavigationFrame::Ned);
+    /// ```
+    pub fn body_to_projected(
+        &self,
+        point: Point,
+        platform: Point,
+        rpy: RollPitchYaw,
+        utm_zone: u8,
+        frame: NavigationFrame,
+    ) -> Point {
+        let navigation = body_to_navigation(frame.matrix() * point, rpy);
+        let geocentric = self.navigation_to_ecef(navigation, platform);
+        let geodetic = self.ecef_to_geodetic(geocentric);
+        self.geodetic_to_projected(geodetic, utm_zone)
+    }
+
+    /// Converts many body frame points to projected (UTM) coordinates on this ellipsoid, like [body_to_projected_batch].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, NavigationFrame, Point, RollPitchYaw};
+    /// let points = [Point::new(-1000., -200., 4000.)];
+    /// let platform = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+    /// let rpy = RollPitchYaw::new(0., 0., 0.4);
+    /// let projected = GRS_80.body_to_projected_batch(&points, platform, rpy, 11, NavigationFrame::Ned);
+    /// ```
+    pub fn body_to_projected_batch(
+        &self,
+        points: &[Point],
+        platform: Point,
+        rpy: RollPitchYaw,
+        utm_zone: u8,
+        frame: NavigationFrame,
+    ) -> Vec<Point> {
+        let platform_ecef = self.geodetic_to_ecef(platform);
+        let navigation_to_ecef = ecef_to_navigation_matrix(platform).transpose();
+        let body_to_navigation = RotationOrder::Zyx.matrix(rpy);
+        let frame = frame.matrix();
+        points
+            .iter()
+            .map(|&point| {
+                let navigation = body_to_navigation * (frame * point);
+                let geocentric = navigation_to_ecef * navigation + platform_ecef;
+                self.geodetic_to_projected(self.ecef_to_geodetic(geocentric), utm_zone)
+            })
+            .collect()
+    }
+
+    /// Converts from projected (UTM) coordinates into geodetic coordinates on this ellipsoid, like [projected_to_geodetic].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, Point};
+    /// let projected = Point::new(320000.34, 4181319.35, 2687.59);
+    /// let geodetic = GRS_80.projected_to_geodetic(projected, 11);
+    /// ```
+    pub fn projected_to_geodetic(&self, point: Point, utm_zone: u8) -> Point {
+        let n = self.f / (2. - self.f);
+        let a = self.a / (1. + n) * (1. + n.powi(2) / 4. + n.powi(4) / 64.);
+        let k0 = 0.9996;
+        let xi = point.y / (k0 * a);
+        let nu = (point.x - 500e3) / (k0 * a);
+        let b1 = 0.5 * n - (2. / 3.) * n.powi(2) + (37. / 96.) * n.powi(3);
+        let b2 = (1. / 48.) * n.powi(2) + (1. / 15.) * n.powi(3);
+        let b3 = (17. / 480.) * n.powi(3);
+        let d1 = 2. * n - (2. / 3.) * n.powi(2) - 2. * n.powi(3);
+        let d2 = (7. / 3.) * n.powi(2) - (8. / 5.) * n.powi(3);
+        let d3 = (56. / 15.) * n.powi(3);
+        let xi_prime = xi
+            - (b1 * (2. * xi).sin() * (2. * nu).cosh()
+                + b2 * (4. * xi).sin() * (4. * nu).cosh()
+                + b3 * (6. * xi).sin() * (6. * nu).cosh());
+        let nu_prime = nu
+            - (b1 * (2. * xi).cos() * (2. * nu).sinh()
+                + b2 * (4. * xi).cos() * (4. * nu).sinh()
+                + b3 * (6. * xi).cos() * (6. * nu).sinh());
+        let chi = (xi_prime.sin() / nu_prime.cosh()).asin();
+        let latitude = chi + d1 * (2. * chi).sin() + d2 * (4. * chi).sin() + d3 * (6. * chi).sin();
+        let reference_meridian = f64::from(utm_zone) * 6f64.to_radians() - 183f64.to_radians();
+        let longitude = reference_meridian + (nu_prime.sinh() / xi_prime.cos()).atan();
+        Point::new(longitude, latitude, point.z)
+    }
+
+    /// Converts from geodetic coordinates on this ellipsoid into projected (UTM) coordinates, like [geodetic_to_projected].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, Point};
+    /// let geodetic = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+    /// let projected = GRS_80.geodetic_to_projected(geodetic, 11);
+    /// ```
+    pub fn geodetic_to_projected(&self, point: Point, utm_zone: u8) -> Point {
+        let n = self.f / (2. - self.f);
+        let a = self.a / (1. + n) * (1. + n.powi(2) / 4. + n.powi(4) / 64.);
+        let k0 = 0.9996;
+        let a1 = 0.5 * n - (2. / 3.) * n.powi(2) + (5. / 16.) * n.powi(3);
+        let a2 = (13. / 48.) * n.powi(2) - (3. / 5.) * n.powi(3);
+        let a3 = (61. / 240.) * n.powi(3);
+        let reference_meridian = f64::from(utm_zone) * 6f64.to_radians() - 183f64.to_radians();
+        let longitude = point.x - reference_meridian;
+        let latitude = point.y;
+        let c = 2. * n.sqrt() / (1. + n);
+        let t = (latitude.sin().atanh() - c * (c * latitude.sin()).atanh()).sinh();
+        let xi_prime = t.atan2(longitude.cos());
+        let nu_prime = (longitude.sin() / (1. + t.powi(2)).sqrt()).atanh();
+        let x = 500e3
+            + k0 * a
+                * (nu_prime
+                    + a1 * (2. * xi_prime).cos() * (2. * nu_prime).sinh()
+                    + a2 * (4. * xi_prime).cos() * (4. * nu_prime).sinh()
+                    + a3 * (6. * xi_prime).cos() * (6. * nu_prime).sinh());
+        let y = k0
+            * a
+            * (xi_prime
+                + a1 * (2. * xi_prime).sin() * (2. * nu_prime).cosh()
+                + a2 * (4. * xi_prime).sin() * (4. * nu_prime).cosh()
+                + a3 * (6. * xi_prime).sin() * (6. * nu_prime).cosh());
+        Point::new(x, y, point.z)
+    }
+
+    /// Converts a geodetic point on this ellipsoid to ECEF, like [geodetic_to_ecef].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, Point};
+    /// let geodetic = Point::new(-119.0434f64.to_radians(), 37.7614978f64.to_radians(), 2687.59);
+    /// let geocentric = GRS_80.geodetic_to_ecef(geodetic);
+    /// ```
+    pub fn geodetic_to_ecef(&self, point: Point) -> Point {
+        let n = self.n(point.y);
+        let x = (n + point.z) * point.y.cos() * point.x.cos();
+        let y = (n + point.z) * point.y.cos() * point.x.sin();
+        let z = (self.b2 / self.a2 * n + point.z) * point.y.sin();
+        Point::new(x, y, z)
+    }
+
+    /// Converts an ECEF point to geodetic coordinates on this ellipsoid, like [ecef_to_geodetic].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, Point};
+    /// let ecef = Point::new(-2452031., -4415678., 3886195.);
+    /// let geodetic = GRS_80.ecef_to_geodetic(ecef);
+    /// ```
+    pub fn ecef_to_geodetic(&self, point: Point) -> Point {
+        let e2 = 1. - self.b2 / self.a2;
+        let ep2 = self.a2 / self.b2 - 1.;
+        let p = (point.x.powi(2) + point.y.powi(2)).sqrt();
+        let theta = (point.z * self.a).atan2(p * self.b);
+        let latitude = (point.z + ep2 * self.b * theta.sin().powi(3))
+            .atan2(p - e2 * self.a * theta.cos().powi(3));
+        let longitude = point.y.atan2(point.x);
+        let height = p / latitude.cos() - self.n(latitude);
+        Point::new(longitude, latitude, height)
+    }
+
+    /// Converts an ECEF point to the navigation frame of a platform on this ellipsoid, like [ecef_to_navigation].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, Point};
+    /// let ecef = Point::new(-2452031., -4415678., 3886195.);
+    /// let platform = Point::new(-119.0434f64.to_radians(), 37.7615f64.to_radians(), 2687.59);
+    /// let navigation = GRS_80.ecef_to_navigation(ecef, platform);
+    /// ```
+    pub fn ecef_to_navigation(&self, point: Point, platform: Point) -> Point {
+        let platform_ecef = self.geodetic_to_ecef(platform);
+        let matrix = ecef_to_navigation_matrix(platform);
+        matrix * (point - platform_ecef)
+    }
+
+    /// Converts a navigation frame point of a platform on this ellipsoid to ECEF, like [navigation_to_ecef].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::GRS_80, Point};
+    /// let navigation = Point::new(-1000., -200., 4000.);
+    /// let platform = Point::new(-119.0434f64.to_radians(), 37.7615f64.to_radians(), 2687.59);
+    /// let ecef = GRS_80.navigation_to_ecef(navigation, platform);
+    /// ```
+    pub fn navigation_to_ecef(&self, point: Point, platform: Point) -> Point {
+        let platform_ecef = self.geodetic_to_ecef(platform);
+        let matrix = ecef_to_navigation_matrix(platform);
+        matrix.transpose() * point + platform_ecef
+    }
+
+    /// Returns the meridian and prime vertical radii of curvature at a latitude.
+    pub(crate) fn radii(&self, latitude: f64) -> (f64, f64) {
+        let n = self.n(latitude);
+        let w = 1. - (1. - self.b2 / self.a2) * latitude.sin().powi(2);
+        (n * self.b2 / self.a2 / w, n)
+    }
+
     fn n(&self, latitude: f64) -> f64 {
         self.a2 / (self.a2 * latitude.cos().powi(2) + self.b2 * latitude.sin().powi(2)).sqrt()
     }
 }
 
-fn ecef_to_navigation_matrix(point: Point) -> Matrix3 {
+/// A 14-parameter Helmert transformation between two ECEF datums.
+///
+/// Seven parameters (three translations, three rotations, and a scale) are
+/// given at a reference epoch, and each changes linearly with time at its
+/// rate, which accounts for plate motion. Rotations use the position vector
+/// convention, as published by the IERS; negate the rotations of parameters
+/// published in the coordinate frame convention.
+///
+/// Epochs are decimal years, e.g. `2010.0`.
+///
+/// # Examples
+///
+/// ```toml
+/// epoch = 2019.5
+///
+/// [datum_transformation]
+/// translation = [1.0053, -1.9021, -0.5416]
+/// rotation = ["26.7814 mas", "-0.4203 mas", "10.9321 mas"]
+/// scale = 0.37e-9
+/// translation_rate = [0.0008, -0.0006, -0.0014]
+/// rotation_rate = ["0.0667 mas", "-0.7574 mas", "-0.0513 mas"]
+/// scale_rate = -0.07e-9
+/// reference_epoch = 2010.0
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Helmert {
+    /// The translation, in meters.
+    pub translation: Point,
+
+    /// The rotations about the x, y, and z axes, in radians.
+    pub rotation: Point,
+
+    /// The scale difference, unitless, e.g. `1e-9` for one part per billion.
+    #[serde(default)]
+    pub scale: f64,
+
+    /// The change in the translation, in meters per year.
+    #[serde(default)]
+    pub translation_rate: Point,
+
+    /// The change in the rotations, in radians per year.
+    #[serde(default)]
+    pub rotation_rate: Point,
+
+    /// The change in the scale difference, per year.
+    #[serde(default)]
+    pub scale_rate: f64,
+
+    /// The epoch of the translation, rotation, and scale, as a decimal year.
+    pub reference_epoch: f64,
+}
+
+impl Helmert {
+    /// Returns true if any of this transformation's parameters change with time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::Helmert, Point};
+    /// let mut helmert = Helmert {
+    ///     translation: Point::new(1., 0., 0.),
+    ///     rotation: Point::zeros(),
+    ///     scale: 0.,
+    ///     translation_rate: Point::zeros(),
+    ///     rotation_rate: Point::zeros(),
+    ///     scale_rate: 0.,
+    ///     reference_epoch: 2010.,
+    /// };
+    /// assert!(!helmert.has_rates());
+    /// helmert.scale_rate = 1e-10;
+    /// assert!(helmert.has_rates());
+    /// ```
+    pub fn has_rates(&self) -> bool {
+        self.translation_rate != Point::zeros()
+            || self.rotation_rate != Point::zeros()
+            || self.scale_rate != 0.
+    }
+
+    /// Transforms an ECEF point from the first datum to the second at an epoch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::Helmert, Point};
+    /// let helmert = Helmert {
+    ///     translation: Point::new(1., 0., 0.),
+    ///     rotation: Point::zeros(),
+    ///     scale: 0.,
+    ///     translation_rate: Point::new(0.01, 0., 0.),
+    ///     rotation_rate: Point::zeros(),
+    ///     scale_rate: 0.,
+    ///     reference_epoch: 2010.,
+    /// };
+    /// let point = Point::new(-2452031., -4415678., 3886195.);
+    /// let transformed = helmert.transform(point, 2020.);
+    /// assert!((transformed.x - point.x - 1.1).abs() < 1e-9);
+    /// ```
+    pub fn transform(&self, point: Point, epoch: f64) -> Point {
+        let (translation, matrix) = self.at(epoch);
+        translation + matrix * point
+    }
+
+    /// Transforms an ECEF point from the second datum back to the first at an epoch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{convert::Helmert, Point};
+    /// let helmert = Helmert {
+    ///     translation: Point::new(1., -2., -0.5),
+    ///     rotation: Point::new(1.3e-7, -2e-9, 5.3e-8),
+    ///     scale: 0.37e-9,
+    ///     translation_rate: Point::zeros(),
+    ///     rotation_rate: Point::zeros(),
+    ///     scale_rate: 0.,
+    ///     reference_epoch: 2010.,
+    /// };
+    /// let point = Point::new(-2452031., -4415678., 3886195.);
+    /// let roundtrip = helmert.inverse_transform(helmert.transform(point, 2020.), 2020.);
+    /// assert!((roundtrip - point).norm() < 1e-6);
+    /// ```
+    pub fn inverse_transform(&self, point: Point, epoch: f64) -> Point {
+        let (translation, matrix) = self.at(epoch);
+        let inverse = matrix
+            .try_inverse()
+            .expect("a small rotation and scale is invertible");
+        inverse * (point - translation)
+    }
+
+    /// Returns the translation and the scaled rotation matrix at an epoch.
+    fn at(&self, epoch: f64) -> (Point, Matrix3) {
+        let dt = epoch - self.reference_epoch;
+        let translation = self.translation + self.translation_rate * dt;
+        let r = self.rotation + self.rotation_rate * dt;
+        let scale = 1. + self.scale + self.scale_rate * dt;
+        let matrix = Matrix3::new(scale, -r.z, r.y, r.z, scale, -r.x, -r.y, r.x, scale);
+        (translation, matrix)
+    }
+
+    pub(crate) fn validate(&self, epoch: Option<f64>) -> Result<(), Error> {
+        let finite = self
+            .translation
+            .iter()
+            .chain(self.rotation.iter())
+            .chain(self.translation_rate.iter())
+            .chain(self.rotation_rate.iter())
+            .chain([self.scale, self.scale_rate, self.reference_epoch].iter())
+            .all(|value| value.is_finite());
+        if !finite {
+            return Err(Error::Config(
+                "the datum transformation's parameters must be finite".to_string(),
+            ));
+        }
+        if self.has_rates() && epoch.is_none() {
+            return Err(Error::Config(
+                "the datum transformation changes with time, so the config needs an epoch"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the rotation matrix from ECEF to the navigation frame at a geodetic point.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{convert, Point};
+/// let platform = Point::new(-119.0434f64.to_radians(), 37.7615f64.to_radians(), 2687.59);
+/// let matrix = convert::ecef_to_navigation_matrix(platform);
+/// ```
+pub fn ecef_to_navigation_matrix(point: Point) -> Matrix3 {
     let latitude = point.y;
     let longitude = point.x;
     Matrix3::new(
@@ -155,6 +811,36 @@ fn ecef_to_navigation_matrix(point: Point) -> Matrix3 {
     )
 }
 
+/// Returns the derivatives of [ecef_to_navigation_matrix] with respect to longitude and latitude.
+pub(crate) fn ecef_to_navigation_matrix_derivatives(point: Point) -> [Matrix3; 2] {
+    let (sin_latitude, cos_latitude) = point.y.sin_cos();
+    let (sin_longitude, cos_longitude) = point.x.sin_cos();
+    [
+        Matrix3::new(
+            sin_latitude * sin_longitude,
+            -sin_latitude * cos_longitude,
+            0.,
+            -cos_longitude,
+            -sin_longitude,
+            0.,
+            cos_latitude * sin_longitude,
+            -cos_latitude * cos_longitude,
+            0.,
+        ),
+        Matrix3::new(
+            -cos_latitude * cos_longitude,
+            -cos_latitude * sin_longitude,
+            -sin_latitude,
+            0.,
+            0.,
+            0.,
+            sin_latitude * cos_longitude,
+            sin_latitude * sin_longitude,
+            -cos_latitude,
+        ),
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Point;
@@ -192,6 +878,84 @@ mod tests {
         assert_relative_eq!(geocentric.z, 3886.195e3, max_relative = 1.0);
     }
 
+    #[test]
+    fn roundtrip() {
+        let mut reader = Reader::from_path("data/points.las").unwrap();
+        for point in reader.points().step_by(100) {
+            let point = point.unwrap();
+            let projected = Point::new(point.x, point.y, point.z);
+            let geodetic = super::projected_to_geodetic(projected, 11);
+            assert_relative_eq!(
+                projected,
+                super::geodetic_to_projected(geodetic, 11),
+                epsilon = 1e-3
+            );
+            let ecef = super::geodetic_to_ecef(geodetic);
+            let roundtrip = super::ecef_to_geodetic(ecef);
+            assert_relative_eq!(geodetic.x, roundtrip.x, epsilon = 1e-12);
+            assert_relative_eq!(geodetic.y, roundtrip.y, epsilon = 1e-12);
+            assert_relative_eq!(geodetic.z, roundtrip.z, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn helmert() {
+        use super::Helmert;
+        let helmert = Helmert {
+            translation: Point::new(1., -2., -0.5),
+            rotation: Point::new(0., 0., 1e-6),
+            scale: 1e-6,
+            translation_rate: Point::new(0.1, 0., 0.),
+            rotation_rate: Point::zeros(),
+            scale_rate: -1e-7,
+            reference_epoch: 2010.,
+        };
+        let point = Point::new(6_378_137., 0., 0.);
+        let transformed = helmert.transform(point, 2010.);
+        assert_relative_eq!(point.x * (1. + 1e-6) + 1., transformed.x, epsilon = 1e-6);
+        assert_relative_eq!(point.x * 1e-6 - 2., transformed.y, epsilon = 1e-6);
+        let transformed = helmert.transform(point, 2020.);
+        assert_relative_eq!(2., transformed.x - point.x, epsilon = 1e-6);
+        assert_relative_eq!(
+            point,
+            helmert.inverse_transform(transformed, 2020.),
+            epsilon = 1e-6
+        );
+        assert!(helmert.validate(None).is_err());
+        assert!(helmert.validate(Some(2020.)).is_ok());
+    }
+
+    #[test]
+    fn navigation_matrix_derivatives() {
+        use super::{ecef_to_navigation_matrix, ecef_to_navigation_matrix_derivatives, GRS_80};
+        let point = Point::new(-119.0434f64.to_radians(), 37.7615f64.to_radians(), 2687.59);
+        let [longitude, latitude] = ecef_to_navigation_matrix_derivatives(point);
+        let step = 1e-7;
+        let numeric = |delta: Point| {
+            (ecef_to_navigation_matrix(point + delta) - ecef_to_navigation_matrix(point - delta))
+                / (2. * step)
+        };
+        assert_relative_eq!(longitude, numeric(Point::new(step, 0., 0.)), epsilon = 1e-8);
+        assert_relative_eq!(latitude, numeric(Point::new(0., step, 0.)), epsilon = 1e-8);
+
+        // A step along a meridian or a parallel is the radius of curvature times the angle.
+        let (meridian, prime_vertical) = GRS_80.radii(point.y);
+        let north = GRS_80.geodetic_to_ecef(point + Point::new(0., step, 0.))
+            - GRS_80.geodetic_to_ecef(point - Point::new(0., step, 0.));
+        let east = GRS_80.geodetic_to_ecef(point + Point::new(step, 0., 0.))
+            - GRS_80.geodetic_to_ecef(point - Point::new(step, 0., 0.));
+        assert_relative_eq!(
+            (meridian + point.z) * 2. * step,
+            north.norm(),
+            max_relative = 1e-6
+        );
+        assert_relative_eq!(
+            (prime_vertical + point.z) * point.y.cos() * 2. * step,
+            east.norm(),
+            max_relative = 1e-6
+        );
+    }
+
     #[test]
     fn compare_to_pdal() {
         let mut original = Reader::from_path("data/points.las").unwrap();
@@ -0,0 +1,167 @@
+//! Flightline coverage, e.g. for reviewing on a web map.
+//!
+//! A flightline is the measurements with the same point source id. Its
+//! footprint is the convex hull of its points, and its trajectory is the
+//! platform's path between its first and last measurements. Both are
+//! geographic, with longitude and latitude in degrees, as web maps expect.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{coverage, Trajectory};
+//! let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+//! let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+//! let flightlines = coverage::flightlines(&measurements, &trajectory, 0.1).unwrap();
+//! assert_eq!(1, flightlines.len());
+//! let footprint = &flightlines[0].footprint;
+//! assert_eq!(footprint[0], footprint[footprint.len() - 1]);
+//! ```
+
+use crate::{Error, Measurement, Point, PointSource, Trajectory};
+use std::collections::BTreeMap;
+
+/// The coverage of one flightline.
+#[derive(Clone, Debug)]
+pub struct Flightline {
+    /// The point source id shared by the flightline's points, if they have one.
+    pub point_source_id: Option<u16>,
+
+    /// The number of points in the flightline.
+    pub count: usize,
+
+    /// The gps time of the first point.
+    pub start_time: f64,
+
+    /// The gps time of the last point.
+    pub end_time: f64,
+
+    /// The platform's path as longitude, latitude (in degrees), and height.
+    pub trajectory: Vec<Point>,
+
+    /// The closed convex hull of the points as longitude, latitude (in degrees), and height.
+    ///
+    /// The first and last vertices are the same, and the hull is counterclockwise.
+    pub footprint: Vec<Point>,
+}
+
+/// Returns the coverage of each flightline, ordered by point source id.
+///
+/// The trajectory is sampled every `interval` seconds, see [Trajectory::decimate].
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{coverage, Trajectory};
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+/// let flightline = &coverage::flightlines(&measurements, &trajectory, 0.).unwrap()[0];
+/// assert_eq!(Some(36), flightline.point_source_id);
+/// assert!(flightline.trajectory.len() > 100);
+/// ```
+pub fn flightlines<L: PointSource>(
+    measurements: &[Measurement<L>],
+    trajectory: &Trajectory,
+    interval: f64,
+) -> Result<Vec<Flightline>, Error> {
+    let mut groups = BTreeMap::<Option<u16>, Vec<&Measurement<L>>>::new();
+    for measurement in measurements {
+        groups
+            .entry(measurement.lasish().point_source_id())
+            .or_default()
+            .push(measurement);
+    }
+    let mut flightlines = Vec::with_capacity(groups.len());
+    for (point_source_id, measurements) in groups {
+        let config = measurements[0].config();
+        let utm_zone = config
+            .utm_zone
+            .or_else(|| trajectory.utm_zone())
+            .ok_or_else(|| Error::Invalid("the trajectory is empty".to_string()))?;
+        let ellipsoid = config.ellipsoid.ellipsoid();
+        let (start_time, end_time) = measurements
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(start, end), m| {
+                (start.min(m.time()), end.max(m.time()))
+            });
+        let points: Vec<Point> = measurements.iter().map(|m| m.lasish().point()).collect();
+        let footprint = convex_hull(&points)
+            .into_iter()
+            .map(|point| degrees(ellipsoid.projected_to_geodetic(point, utm_zone)))
+            .collect();
+        let trajectory = trajectory
+            .subset(start_time, end_time)
+            .decimate(interval)
+            .into_iter()
+            .map(|point| degrees(Point::new(point.longitude, point.latitude, point.altitude)))
+            .collect();
+        flightlines.push(Flightline {
+            point_source_id,
+            count: measurements.len(),
+            start_time,
+            end_time,
+            trajectory,
+            footprint,
+        });
+    }
+    Ok(flightlines)
+}
+
+/// Returns the closed, counterclockwise convex hull of the points' x and y coordinates.
+///
+/// Collinear points on the hull's edges are dropped. Returns an empty vector
+/// if there are no points.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{coverage, Point};
+/// let points = [
+///     Point::new(0., 0., 0.),
+///     Point::new(1., 0., 0.),
+///     Point::new(0.5, 0.5, 0.),
+///     Point::new(1., 1., 0.),
+///     Point::new(0., 1., 0.),
+/// ];
+/// let hull = coverage::convex_hull(&points);
+/// assert_eq!(5, hull.len());
+/// assert_eq!(hull[0], hull[4]);
+/// ```
+pub fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup_by(|a, b| a.x == b.x && a.y == b.y);
+    if points.len() < 3 {
+        if let Some(&first) = points.first() {
+            points.push(first);
+        }
+        return points;
+    }
+    // Andrew's monotone chain.
+    let cross =
+        |o: &Point, a: &Point, b: &Point| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+    let mut hull: Vec<Point> = Vec::with_capacity(2 * points.len());
+    for pass in 0..2 {
+        let start = hull.len();
+        let chain: Box<dyn Iterator<Item = &Point>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for &point in chain {
+            while hull.len() >= start + 2
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], &point) <= 0.
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        // The last point of each chain is the first of the next.
+        hull.pop();
+    }
+    hull.push(hull[0]);
+    hull
+}
+
+fn degrees(point: Point) -> Point {
+    Point::new(point.x.to_degrees(), point.y.to_degrees(), point.z)
+}
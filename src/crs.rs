@@ -0,0 +1,433 @@
+//! Coordinate reference system detection from las headers.
+//!
+//! Las files carry their CRS in `LASF_Projection` vlrs, either as GeoTIFF keys
+//! or as well-known text. Only enough is parsed to tell which UTM zone and
+//! datum the points are in, so a config that doesn't match the points can be
+//! caught before it produces absurd body frame coordinates.
+//!
+//! # Examples
+//!
+//! ```
+//! let reader = las::Reader::from_path("data/points.las").unwrap();
+//! let zone = leeward::crs::utm_zone(las::Read::header(&reader));
+//! assert_eq!(Some(11), zone);
+//! ```
+
+use crate::{Config, Error};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+const PROJECTION_USER_ID: &str = "LASF_Projection";
+const WKT_RECORD_ID: u16 = 2112;
+const GEO_KEY_DIRECTORY_RECORD_ID: u16 = 34735;
+const GEO_ASCII_PARAMS_RECORD_ID: u16 = 34737;
+const MODEL_TYPE_GEO_KEY: u16 = 1024;
+const GEOGRAPHIC_TYPE_GEO_KEY: u16 = 2048;
+const GEODETIC_DATUM_GEO_KEY: u16 = 2050;
+const PROJECTED_CS_TYPE_GEO_KEY: u16 = 3072;
+const PROJECTION_GEO_KEY: u16 = 3074;
+const MODEL_TYPE_PROJECTED: u16 = 1;
+const MODEL_TYPE_GEOGRAPHIC: u16 = 2;
+
+/// What a las header says about its coordinate reference system.
+///
+/// Fields are `None` if the header doesn't say, or says something that isn't recognized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Crs {
+    /// How the points are projected.
+    pub projection: Option<Projection>,
+
+    /// The horizontal datum.
+    pub datum: Option<Datum>,
+}
+
+/// How a CRS projects its points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    /// A UTM zone.
+    Utm(u8),
+
+    /// Geographic coordinates, i.e. longitude and latitude.
+    Geographic,
+
+    /// Some other projection, e.g. a state plane.
+    Other,
+}
+
+/// A horizontal datum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Datum {
+    /// World Geodetic System 1984.
+    Wgs84,
+
+    /// North American Datum of 1983.
+    Nad83,
+
+    /// North American Datum of 1927.
+    Nad27,
+
+    /// European Terrestrial Reference System 1989.
+    Etrs89,
+}
+
+/// A disagreement between a las header's CRS and the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrsConflict {
+    /// The header's UTM zone isn't the configured one.
+    UtmZone { configured: u8, header: u8 },
+
+    /// The header's datum isn't the configured one.
+    Datum { configured: Datum, header: Datum },
+
+    /// The header's CRS isn't UTM, e.g. it's geographic or a state plane.
+    NotUtm(Projection),
+}
+
+/// What to do when a las header's CRS conflicts with the config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CrsMismatch {
+    /// Report the conflict in the [ReadSummary](crate::ReadSummary) and use the config.
+    #[default]
+    Warn,
+
+    /// Return an error.
+    Fail,
+
+    /// Don't check the header at all.
+    Ignore,
+}
+
+/// Reads what a las header says about its CRS, or `None` if there are no CRS records.
+///
+/// Well-known text takes precedence over GeoTIFF keys, which take precedence
+/// over the GeoTIFF citation strings.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::crs::{self, Datum, Projection};
+/// let reader = las::Reader::from_path("data/points.las").unwrap();
+/// let crs = crs::read(las::Read::header(&reader)).unwrap();
+/// assert_eq!(Some(Projection::Utm(11)), crs.projection);
+/// assert_eq!(Some(Datum::Wgs84), crs.datum);
+/// assert_eq!(None, crs::read(&las::Header::default()));
+/// ```
+pub fn read(header: &las::Header) -> Option<Crs> {
+    let vlrs: Vec<_> = header
+        .all_vlrs()
+        .filter(|vlr| vlr.user_id == PROJECTION_USER_ID)
+        .collect();
+    if vlrs.is_empty() {
+        return None;
+    }
+    let find = |record_id: u16, parse: fn(&[u8]) -> Crs| {
+        vlrs.iter()
+            .filter(|vlr| vlr.record_id == record_id)
+            .map(|vlr| parse(&vlr.data))
+            .fold(Crs::default(), Crs::or)
+    };
+    Some(
+        find(WKT_RECORD_ID, from_wkt)
+            .or(find(GEO_KEY_DIRECTORY_RECORD_ID, from_geo_keys))
+            .or(find(GEO_ASCII_PARAMS_RECORD_ID, from_citation)),
+    )
+}
+
+/// Returns the UTM zone of a las header's CRS, or `None` if there's no CRS or it isn't UTM.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(None, leeward::crs::utm_zone(&las::Header::default()));
+/// ```
+pub fn utm_zone(header: &las::Header) -> Option<u8> {
+    match read(header)?.projection? {
+        Projection::Utm(zone) => Some(zone),
+        Projection::Geographic | Projection::Other => None,
+    }
+}
+
+/// Returns what an EPSG code says about its CRS, e.g. 32611 for WGS 84 / UTM zone 11N.
+///
+/// Only UTM projected CRSs and the supported datums are recognized.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::crs::{self, Datum, Projection};
+/// let crs = crs::from_epsg(26913);
+/// assert_eq!(Some(Projection::Utm(13)), crs.projection);
+/// assert_eq!(Some(Datum::Nad83), crs.datum);
+/// assert_eq!(None, crs::from_epsg(2232).projection);
+/// ```
+pub fn from_epsg(code: u16) -> Crs {
+    Crs {
+        projection: utm_zone_from_epsg(code).map(Projection::Utm),
+        datum: datum_from_epsg(code),
+    }
+}
+
+impl Crs {
+    /// Returns the first conflict between this CRS and a config.
+    ///
+    /// A config without a utm zone or datum doesn't conflict with any zone or datum.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{crs::{Crs, CrsConflict, Projection}, Config};
+    /// let config = Config::from_path("data/config.toml").unwrap();
+    /// let crs = Crs { projection: Some(Projection::Utm(12)), datum: None };
+    /// assert_eq!(
+    ///     Some(CrsConflict::UtmZone { configured: 11, header: 12 }),
+    ///     crs.conflict(&config)
+    /// );
+    /// ```
+    pub fn conflict(&self, config: &Config) -> Option<CrsConflict> {
+        match (self.projection, config.utm_zone) {
+            (Some(Projection::Utm(header)), Some(configured)) if header != configured => {
+                return Some(CrsConflict::UtmZone { configured, header })
+            }
+            (Some(projection @ Projection::Geographic), _)
+            | (Some(projection @ Projection::Other), _) => {
+                return Some(CrsConflict::NotUtm(projection))
+            }
+            _ => {}
+        }
+        match (self.datum, config.datum) {
+            (Some(header), Some(configured)) if header != configured => {
+                Some(CrsConflict::Datum { configured, header })
+            }
+            _ => None,
+        }
+    }
+
+    fn or(self, other: Crs) -> Crs {
+        Crs {
+            projection: self.projection.or(other.projection),
+            datum: self.datum.or(other.datum),
+        }
+    }
+}
+
+/// Checks a las header against the config, filling in a missing utm zone from the header.
+///
+/// Returns the conflict, if there is one and it's only a warning.
+pub(crate) fn reconcile(
+    config: &mut Config,
+    header: &las::Header,
+    mismatch: CrsMismatch,
+) -> Result<Option<CrsConflict>, Error> {
+    if mismatch == CrsMismatch::Ignore {
+        return Ok(None);
+    }
+    let crs = match read(header) {
+        Some(crs) => crs,
+        None => return Ok(None),
+    };
+    if config.utm_zone.is_none() {
+        if let Some(Projection::Utm(zone)) = crs.projection {
+            config.utm_zone = Some(zone);
+        }
+    }
+    match crs.conflict(config) {
+        Some(conflict) if mismatch == CrsMismatch::Fail => Err(Error::CrsConflict(conflict)),
+        conflict => Ok(conflict),
+    }
+}
+
+/// Returns the UTM zone for an EPSG projected CRS or projection code.
+fn utm_zone_from_epsg(code: u16) -> Option<u8> {
+    let zone = match code {
+        // WGS 84, north and south
+        32601..=32660 => code - 32600,
+        32701..=32760 => code - 32700,
+        // NAD83 and NAD27
+        26901..=26923 => code - 26900,
+        26703..=26722 => code - 26700,
+        // ETRS89
+        25828..=25838 => code - 25800,
+        // UTM projections, for user-defined projected CRSs
+        16001..=16060 => code - 16000,
+        16101..=16160 => code - 16100,
+        _ => return None,
+    };
+    // All zones are 60 or less.
+    Some(zone as u8)
+}
+
+/// Returns the datum for an EPSG projected CRS, geographic CRS, or datum code.
+fn datum_from_epsg(code: u16) -> Option<Datum> {
+    match code {
+        32601..=32660 | 32701..=32760 | 4326 | 6326 => Some(Datum::Wgs84),
+        26901..=26923 | 4269 | 6269 => Some(Datum::Nad83),
+        26703..=26722 | 4267 | 6267 => Some(Datum::Nad27),
+        25828..=25838 | 4258 | 6258 => Some(Datum::Etrs89),
+        _ => None,
+    }
+}
+
+/// Parses a GeoTIFF key directory.
+fn from_geo_keys(data: &[u8]) -> Crs {
+    let shorts: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+        .collect();
+    let count = shorts.get(3).map(|&count| usize::from(count)).unwrap_or(0);
+    let keys: Vec<_> = shorts
+        .get(4..)
+        .unwrap_or_default()
+        .chunks_exact(4)
+        .take(count)
+        .collect();
+    // Only values stored directly in the key (location zero) are codes.
+    let value = |id: u16| {
+        keys.iter()
+            .find(|key| key[0] == id && key[1] == 0)
+            .map(|key| key[3])
+    };
+    let zone = value(PROJECTED_CS_TYPE_GEO_KEY)
+        .and_then(utm_zone_from_epsg)
+        .or_else(|| value(PROJECTION_GEO_KEY).and_then(utm_zone_from_epsg));
+    let projection = match (zone, value(MODEL_TYPE_GEO_KEY)) {
+        (Some(zone), _) => Some(Projection::Utm(zone)),
+        (None, Some(MODEL_TYPE_PROJECTED)) => Some(Projection::Other),
+        (None, Some(MODEL_TYPE_GEOGRAPHIC)) => Some(Projection::Geographic),
+        (None, _) => None,
+    };
+    let datum = [
+        PROJECTED_CS_TYPE_GEO_KEY,
+        GEOGRAPHIC_TYPE_GEO_KEY,
+        GEODETIC_DATUM_GEO_KEY,
+    ]
+    .iter()
+    .find_map(|&id| value(id).and_then(datum_from_epsg));
+    Crs { projection, datum }
+}
+
+/// Parses well-known text.
+fn from_wkt(data: &[u8]) -> Crs {
+    let text = String::from_utf8_lossy(data).to_lowercase();
+    let mut crs = from_citation(data);
+    if crs.projection.is_none() {
+        if text.contains("projcs[") || text.contains("projcrs[") {
+            crs.projection = Some(Projection::Other);
+        } else if ["geogcs[", "geogcrs[", "geodcrs["]
+            .iter()
+            .any(|keyword| text.starts_with(keyword))
+        {
+            crs.projection = Some(Projection::Geographic);
+        }
+    }
+    crs
+}
+
+/// Parses a citation string (or well-known text) for a UTM zone and datum, e.g. "WGS 84 / UTM zone 11N".
+fn from_citation(data: &[u8]) -> Crs {
+    let text = String::from_utf8_lossy(data).to_lowercase();
+    let compact: String = text.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    let datum = [
+        ("wgs84", Datum::Wgs84),
+        ("nad83", Datum::Nad83),
+        ("northamericandatum1983", Datum::Nad83),
+        ("nad27", Datum::Nad27),
+        ("northamericandatum1927", Datum::Nad27),
+        ("etrs89", Datum::Etrs89),
+    ]
+    .iter()
+    .filter_map(|(name, datum)| compact.find(name).map(|index| (index, *datum)))
+    .min_by_key(|(index, _)| *index)
+    .map(|(_, datum)| datum);
+    Crs {
+        projection: utm_zone_from_text(&text).map(Projection::Utm),
+        datum,
+    }
+}
+
+fn utm_zone_from_text(text: &str) -> Option<u8> {
+    let (_, rest) = text.split_once("utm zone")?;
+    let digits: String = rest
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok().filter(|zone| (1..=60).contains(zone))
+}
+
+impl fmt::Display for CrsConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrsConflict::UtmZone { configured, header } => write!(
+                f,
+                "the config's utm zone ({}) doesn't match the las header's ({})",
+                configured, header
+            ),
+            CrsConflict::Datum { configured, header } => write!(
+                f,
+                "the config's datum ({:?}) doesn't match the las header's ({:?})",
+                configured, header
+            ),
+            CrsConflict::NotUtm(projection) => {
+                write!(f, "the las header's crs isn't utm ({:?})", projection)
+            }
+        }
+    }
+}
+
+impl FromStr for CrsMismatch {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<CrsMismatch, Error> {
+        match s {
+            "warn" => Ok(CrsMismatch::Warn),
+            "fail" => Ok(CrsMismatch::Fail),
+            "ignore" => Ok(CrsMismatch::Ignore),
+            _ => Err(Error::Parse(format!("unknown crs mismatch policy: {}", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Crs, Datum, Projection};
+
+    #[test]
+    fn wkt() {
+        let wkt = br#"PROJCS["NAD83(2011) / UTM zone 13N",GEOGCS["NAD83(2011)"]]"#;
+        assert_eq!(
+            Crs {
+                projection: Some(Projection::Utm(13)),
+                datum: Some(Datum::Nad83)
+            },
+            super::from_wkt(wkt)
+        );
+        let state_plane = br#"PROJCS["NAD83 / Colorado North (ftUS)",GEOGCS["NAD83"]]"#;
+        assert_eq!(
+            Some(Projection::Other),
+            super::from_wkt(state_plane).projection
+        );
+        let geographic = br#"GEOGCS["WGS 84",DATUM["WGS_1984"]]"#;
+        assert_eq!(
+            Crs {
+                projection: Some(Projection::Geographic),
+                datum: Some(Datum::Wgs84)
+            },
+            super::from_wkt(geographic)
+        );
+        assert_eq!(None, super::utm_zone_from_text("utm zone 61n"));
+    }
+
+    #[test]
+    fn geo_keys() {
+        let shorts: [u16; 12] = [1, 1, 0, 2, 1024, 0, 1, 1, 3072, 0, 1, 32612];
+        let data: Vec<u8> = shorts.iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert_eq!(
+            Crs {
+                projection: Some(Projection::Utm(12)),
+                datum: Some(Datum::Wgs84)
+            },
+            super::from_geo_keys(&data)
+        );
+        assert_eq!(Crs::default(), super::from_geo_keys(&data[..6]));
+    }
+}
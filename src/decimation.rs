@@ -0,0 +1,397 @@
+use crate::{rng::Rng, Error, PointSource, DEFAULT_SEED};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+/// A strategy for choosing a subsample of points, e.g. for calibration.
+///
+/// The built-in strategies are the variants of [Decimation]. Measurements are
+/// [PointSource] too, so a sampler can also thin out measurements that have already
+/// been matched to the trajectory, e.g. with [crate::Adjust::with_sampler].
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{Decimation, Sampler};
+/// let points = leeward::tabular::read_csv("data/points.csv").unwrap();
+/// assert_eq!(5, Decimation::Every(2).sample(points).unwrap().len());
+/// ```
+pub trait Sampler {
+    /// Returns the points that are kept, in their original order.
+    fn sample<L: PointSource>(&self, points: Vec<L>) -> Result<Vec<L>, Error>;
+}
+
+/// How to thin out points before creating measurements.
+///
+/// Keeping every nth point oversamples wherever the scanner dwells, so the
+/// spatial modes can be used to get a more balanced sample for calibration.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::Decimation;
+/// assert_eq!(Decimation::Every(10), "10".parse().unwrap());
+/// assert_eq!(Decimation::Voxel(2.5), "voxel:2.5".parse().unwrap());
+/// assert_eq!(
+///     Decimation::Random { fraction: 0.1, seed: leeward::DEFAULT_SEED },
+///     "random:0.1".parse().unwrap()
+/// );
+/// assert_eq!(Decimation::Random { fraction: 0.1, seed: 42 }, "random:0.1:42".parse().unwrap());
+/// assert_eq!(Decimation::ScanLine(4), "scan-line:4".parse().unwrap());
+/// assert_eq!(
+///     Decimation::TimeStratified { interval: 0.5, count: 100 },
+///     "time:0.5:100".parse().unwrap()
+/// );
+/// assert_eq!(
+///     Decimation::ScanAngleStratified { width: 2., count: 100 },
+///     "scan-angle:2:100".parse().unwrap()
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decimation {
+    /// Keep every nth point.
+    Every(usize),
+
+    /// Keep the first point in each cube of this size, in meters.
+    Voxel(f64),
+
+    /// Keep this fraction of the points, chosen at random.
+    ///
+    /// The same seed keeps the same points every time, on every platform.
+    Random { fraction: f64, seed: u64 },
+
+    /// Keep every point of every nth scan line.
+    ///
+    /// Scan lines are split where the scan angle changes direction.
+    ScanLine(usize),
+
+    /// Keep up to `count` points in each `interval` seconds of gps time.
+    ///
+    /// The kept points are spread evenly through each interval. Points without
+    /// a time are dropped.
+    TimeStratified { interval: f64, count: usize },
+
+    /// Keep up to `count` points in each `width` degrees of scan angle.
+    ///
+    /// The kept points are spread evenly through each bin, so the edges of the
+    /// swath are as well represented as the nadir.
+    ScanAngleStratified { width: f64, count: usize },
+}
+
+impl Decimation {
+    /// Returns an error if this decimation's value is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::Decimation;
+    /// assert!(Decimation::Every(1).validate().is_ok());
+    /// assert!(Decimation::Every(0).validate().is_err());
+    /// assert!(Decimation::Random { fraction: 1.5, seed: 0 }.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        match *self {
+            Decimation::Every(0) => Err(Error::Invalid("cannot decimate by zero".to_string())),
+            Decimation::ScanLine(0) => Err(Error::Invalid(
+                "cannot decimate scan lines by zero".to_string(),
+            )),
+            Decimation::Voxel(size) if size.is_nan() || size <= 0. => Err(Error::Invalid(format!(
+                "voxel size must be positive: {}",
+                size
+            ))),
+            Decimation::Random { fraction, .. }
+                if !(0. ..=1.).contains(&fraction) || fraction == 0. =>
+            {
+                Err(Error::Invalid(format!(
+                    "random fraction must be in (0, 1]: {}",
+                    fraction
+                )))
+            }
+            Decimation::TimeStratified { interval, .. } if interval.is_nan() || interval <= 0. => {
+                Err(Error::Invalid(format!(
+                    "time interval must be positive: {}",
+                    interval
+                )))
+            }
+            Decimation::ScanAngleStratified { width, .. } if width.is_nan() || width <= 0. => Err(
+                Error::Invalid(format!("scan angle width must be positive: {}", width)),
+            ),
+            Decimation::TimeStratified { count: 0, .. }
+            | Decimation::ScanAngleStratified { count: 0, .. } => Err(Error::Invalid(
+                "cannot keep zero points per stratum".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns this decimation with the given random seed, if it's random.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::Decimation;
+    /// let decimation: Decimation = "random:0.1".parse().unwrap();
+    /// assert_eq!(Decimation::Random { fraction: 0.1, seed: 42 }, decimation.with_seed(42));
+    /// assert_eq!(Decimation::Every(10), Decimation::Every(10).with_seed(42));
+    /// ```
+    pub fn with_seed(self, seed: u64) -> Decimation {
+        match self {
+            Decimation::Random { fraction, .. } => Decimation::Random { fraction, seed },
+            decimation => decimation,
+        }
+    }
+
+    /// Decimates points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::Decimation;
+    /// let points = leeward::tabular::read_csv("data/points.csv").unwrap();
+    /// assert_eq!(5, Decimation::Every(2).decimate(points.clone()).unwrap().len());
+    /// assert_eq!(10, Decimation::Voxel(0.01).decimate(points).unwrap().len());
+    /// ```
+    pub fn decimate<L: PointSource>(&self, points: Vec<L>) -> Result<Vec<L>, Error> {
+        self.validate()?;
+        let points = match *self {
+            Decimation::Every(n) => points.into_iter().step_by(n).collect(),
+            Decimation::Voxel(size) => {
+                let mut voxels = HashSet::new();
+                points
+                    .into_iter()
+                    .filter(|point| {
+                        voxels.insert((
+                            (point.x() / size).floor() as i64,
+                            (point.y() / size).floor() as i64,
+                            (point.z() / size).floor() as i64,
+                        ))
+                    })
+                    .collect()
+            }
+            Decimation::Random { fraction, seed } => {
+                let mut rng = Rng::new(seed);
+                points
+                    .into_iter()
+                    .filter(|_| rng.next_f64() < fraction)
+                    .collect()
+            }
+            Decimation::ScanLine(n) => {
+                let lines = scan_lines(&points);
+                points
+                    .into_iter()
+                    .zip(lines)
+                    .filter(|(_, line)| line % n == 0)
+                    .map(|(point, _)| point)
+                    .collect()
+            }
+            Decimation::TimeStratified { interval, count } => stratify(points, count, |point| {
+                point.time().map(|time| (time / interval).floor() as i64)
+            }),
+            Decimation::ScanAngleStratified { width, count } => stratify(points, count, |point| {
+                Some((point.scan_angle() / width).floor() as i64)
+            }),
+        };
+        Ok(points)
+    }
+}
+
+impl Sampler for Decimation {
+    fn sample<L: PointSource>(&self, points: Vec<L>) -> Result<Vec<L>, Error> {
+        self.decimate(points)
+    }
+}
+
+/// Keeps up to `count` evenly spread points from each stratum, dropping points without one.
+fn stratify<L: PointSource>(
+    points: Vec<L>,
+    count: usize,
+    stratum: impl Fn(&L) -> Option<i64>,
+) -> Vec<L> {
+    let mut strata: HashMap<i64, Vec<usize>> = HashMap::new();
+    for (i, point) in points.iter().enumerate() {
+        if let Some(stratum) = stratum(point) {
+            strata.entry(stratum).or_default().push(i);
+        }
+    }
+    let mut keep = vec![false; points.len()];
+    for indices in strata.values() {
+        let n = indices.len().min(count);
+        for j in 0..n {
+            keep[indices[j * indices.len() / n]] = true;
+        }
+    }
+    points
+        .into_iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(point, _)| point)
+        .collect()
+}
+
+/// Numbers each point's scan line.
+///
+/// A new line starts when the scan angle changes direction, except after a
+/// single-point run. This handles both oscillating scanners, which reverse
+/// direction at each edge of the swath, and rotating scanners, which jump back
+/// to the start of the swath for each line.
+pub(crate) fn scan_lines<L: PointSource>(points: &[L]) -> Vec<usize> {
+    let mut lines = Vec::with_capacity(points.len());
+    let (mut line, mut direction, mut run) = (0, 0., 0);
+    let mut previous: Option<f64> = None;
+    for point in points {
+        let scan_angle = point.scan_angle();
+        if let Some(previous) = previous {
+            let delta = scan_angle - previous;
+            if delta != 0. {
+                if direction != 0. && delta.signum() != direction {
+                    if run > 1 {
+                        line += 1;
+                    }
+                    run = 0;
+                }
+                direction = delta.signum();
+                run += 1;
+            }
+        }
+        previous = Some(scan_angle);
+        lines.push(line);
+    }
+    lines
+}
+
+impl Default for Decimation {
+    fn default() -> Decimation {
+        Decimation::Every(1)
+    }
+}
+
+impl From<usize> for Decimation {
+    fn from(n: usize) -> Decimation {
+        Decimation::Every(n)
+    }
+}
+
+impl FromStr for Decimation {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Decimation, Error> {
+        let parse_error = || Error::Parse(format!("invalid decimation: {}", s));
+        let decimation = match s.split_once(':') {
+            None => Decimation::Every(s.parse().map_err(|_| parse_error())?),
+            Some(("every", value)) => Decimation::Every(value.parse().map_err(|_| parse_error())?),
+            Some(("voxel", value)) => Decimation::Voxel(value.parse().map_err(|_| parse_error())?),
+            Some(("random", value)) => {
+                let (fraction, seed) = match value.split_once(':') {
+                    Some((fraction, seed)) => (fraction, seed.parse().map_err(|_| parse_error())?),
+                    None => (value, DEFAULT_SEED),
+                };
+                Decimation::Random {
+                    fraction: fraction.parse().map_err(|_| parse_error())?,
+                    seed,
+                }
+            }
+            Some(("scan-line", value)) => {
+                Decimation::ScanLine(value.parse().map_err(|_| parse_error())?)
+            }
+            Some(("time", value)) => {
+                let (interval, count) = value.split_once(':').ok_or_else(parse_error)?;
+                Decimation::TimeStratified {
+                    interval: interval.parse().map_err(|_| parse_error())?,
+                    count: count.parse().map_err(|_| parse_error())?,
+                }
+            }
+            Some(("scan-angle", value)) => {
+                let (width, count) = value.split_once(':').ok_or_else(parse_error)?;
+                Decimation::ScanAngleStratified {
+                    width: width.parse().map_err(|_| parse_error())?,
+                    count: count.parse().map_err(|_| parse_error())?,
+                }
+            }
+            Some(_) => return Err(parse_error()),
+        };
+        decimation.validate()?;
+        Ok(decimation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decimation;
+    use crate::tabular::TabularPoint;
+
+    fn point(scan_angle: f64) -> TabularPoint {
+        TabularPoint {
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            time: None,
+            scan_angle,
+        }
+    }
+
+    #[test]
+    fn scan_lines() {
+        // Oscillating, then rotating.
+        let oscillating: Vec<_> = [-2., -1., 1., 2., 1., -1., -2., -1., 1.]
+            .iter()
+            .map(|&a| point(a))
+            .collect();
+        assert_eq!(
+            vec![0, 0, 0, 0, 1, 1, 1, 2, 2],
+            super::scan_lines(&oscillating)
+        );
+        let rotating: Vec<_> = [-2., -1., 1., 2., -2., -1., 1., 2., -2.]
+            .iter()
+            .map(|&a| point(a))
+            .collect();
+        assert_eq!(
+            vec![0, 0, 0, 0, 1, 1, 1, 1, 2],
+            super::scan_lines(&rotating)
+        );
+        assert_eq!(5, Decimation::ScanLine(2).decimate(rotating).unwrap().len());
+    }
+
+    #[test]
+    fn random() {
+        let points: Vec<_> = (0..1000).map(|i| point(i as f64)).collect();
+        let random = |seed| Decimation::Random {
+            fraction: 0.25,
+            seed,
+        };
+        let kept = random(1).decimate(points.clone()).unwrap();
+        assert!(kept.len() > 200 && kept.len() < 300, "{}", kept.len());
+        assert_eq!(kept, random(1).decimate(points.clone()).unwrap());
+        assert_ne!(kept, random(2).decimate(points).unwrap());
+    }
+
+    #[test]
+    fn stratified() {
+        // Most points are near nadir, but each bin keeps the same number.
+        let points: Vec<_> = (0..1000)
+            .map(|i| point(if i % 10 == 0 { 15. } else { 0.5 }))
+            .collect();
+        let kept = Decimation::ScanAngleStratified {
+            width: 1.,
+            count: 50,
+        }
+        .decimate(points)
+        .unwrap();
+        assert_eq!(100, kept.len());
+        assert_eq!(50, kept.iter().filter(|p| p.scan_angle == 15.).count());
+        let points: Vec<_> = (0..100)
+            .map(|i| TabularPoint {
+                time: Some(f64::from(i) * 0.01),
+                ..point(0.)
+            })
+            .chain(std::iter::once(point(0.)))
+            .collect();
+        let kept = Decimation::TimeStratified {
+            interval: 0.25,
+            count: 5,
+        }
+        .decimate(points)
+        .unwrap();
+        assert_eq!(20, kept.len());
+        assert!(kept.iter().all(|p| p.time.is_some()));
+    }
+}
@@ -0,0 +1,222 @@
+//! Digital elevation models, used to sample surface normals for incidence angles.
+//!
+//! The DEM must be in the same (projected) coordinate system as the lidar points.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::Dem;
+//! let dem = Dem::from_path("data/dem.tif").unwrap();
+//! let normal = dem.normal(320000.34, 4181319.35).unwrap();
+//! ```
+
+use crate::{Error, Point};
+use std::{fs::File, io::BufReader, path::Path};
+use tiff::{
+    decoder::{Decoder, DecodingResult},
+    tags::Tag,
+};
+
+/// A north-up, regularly-gridded elevation model.
+#[derive(Debug, Clone)]
+pub struct Dem {
+    origin_x: f64,
+    origin_y: f64,
+    pixel_width: f64,
+    pixel_height: f64,
+    width: usize,
+    height: usize,
+    data: Vec<f64>,
+    nodata: Option<f64>,
+}
+
+impl Dem {
+    /// Creates a new DEM from a row-major grid of elevations.
+    ///
+    /// `origin_x` and `origin_y` are the coordinates of the upper left corner
+    /// of the upper left cell, and rows go south.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Dem;
+    /// let dem = Dem::new(0., 2., 1., 1., 2, vec![0., 1., 0., 1.]).unwrap();
+    /// assert_eq!(0.5, dem.elevation(1., 1.).unwrap());
+    /// ```
+    pub fn new(
+        origin_x: f64,
+        origin_y: f64,
+        pixel_width: f64,
+        pixel_height: f64,
+        width: usize,
+        data: Vec<f64>,
+    ) -> Result<Dem, Error> {
+        if width == 0 || !data.len().is_multiple_of(width) {
+            return Err(Error::Dem(format!(
+                "data length ({}) is not a multiple of the width ({})",
+                data.len(),
+                width
+            )));
+        }
+        if pixel_width <= 0. || pixel_height <= 0. {
+            return Err(Error::Dem("pixel sizes must be positive".to_string()));
+        }
+        Ok(Dem {
+            origin_x,
+            origin_y,
+            pixel_width,
+            pixel_height,
+            width,
+            height: data.len() / width,
+            data,
+            nodata: None,
+        })
+    }
+
+    /// Reads a DEM from a single-band GeoTIFF.
+    ///
+    /// The geotransform is read from the `ModelPixelScale` and `ModelTiepoint`
+    /// tags, and GDAL's nodata tag is respected if present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Dem;
+    /// let dem = Dem::from_path("data/dem.tif").unwrap();
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %path.as_ref().display()))
+    )]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Dem, Error> {
+        let mut decoder = Decoder::new(BufReader::new(File::open(path)?))?;
+        let (width, _) = decoder.dimensions()?;
+        let scale = decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag)?;
+        let tiepoint = decoder.get_tag_f64_vec(Tag::ModelTiepointTag)?;
+        if scale.len() < 2 || tiepoint.len() < 6 {
+            return Err(Error::Dem(
+                "invalid geotiff georeferencing tags".to_string(),
+            ));
+        }
+        let nodata = match decoder.find_tag(Tag::GdalNodata)? {
+            Some(value) => {
+                let value = value.into_string()?;
+                let value = value.trim_end_matches('\0');
+                Some(
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| Error::Dem(format!("invalid nodata value: {}", value)))?,
+                )
+            }
+            None => None,
+        };
+        let data: Vec<f64> = match decoder.read_image()? {
+            DecodingResult::U8(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::U16(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::U32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I8(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I16(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::F32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::F64(v) => v,
+            _ => return Err(Error::Dem("unsupported sample format".to_string())),
+        };
+        let mut dem = Dem::new(
+            tiepoint[3] - tiepoint[0] * scale[0],
+            tiepoint[4] + tiepoint[1] * scale[1],
+            scale[0],
+            scale[1],
+            width as usize,
+            data,
+        )?;
+        dem.nodata = nodata;
+        Ok(dem)
+    }
+
+    /// Returns the bilinearly-interpolated elevation at the given location.
+    ///
+    /// Returns `None` if the location is outside of the DEM or near nodata cells.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Dem;
+    /// let dem = Dem::from_path("data/dem.tif").unwrap();
+    /// let elevation = dem.elevation(320000.34, 4181319.35).unwrap();
+    /// ```
+    pub fn elevation(&self, x: f64, y: f64) -> Option<f64> {
+        let column = (x - self.origin_x) / self.pixel_width - 0.5;
+        let row = (self.origin_y - y) / self.pixel_height - 0.5;
+        if column < 0. || row < 0. {
+            return None;
+        }
+        let (c0, r0) = (column.floor() as usize, row.floor() as usize);
+        let (c1, r1) = ((c0 + 1).min(self.width - 1), (r0 + 1).min(self.height - 1));
+        if c0 >= self.width || r0 >= self.height {
+            return None;
+        }
+        let (fc, fr) = (column - column.floor(), row - row.floor());
+        let top = self.get(c0, r0)? * (1. - fc) + self.get(c1, r0)? * fc;
+        let bottom = self.get(c0, r1)? * (1. - fc) + self.get(c1, r1)? * fc;
+        Some(top * (1. - fr) + bottom * fr)
+    }
+
+    /// Returns the upward-pointing unit surface normal at the given location.
+    ///
+    /// The normal is computed from central differences of the elevation, one
+    /// cell in each direction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Dem;
+    /// let dem = Dem::from_path("data/dem.tif").unwrap();
+    /// let normal = dem.normal(320000.34, 4181319.35).unwrap();
+    /// assert!(normal.z > 0.);
+    /// ```
+    pub fn normal(&self, x: f64, y: f64) -> Option<Point> {
+        let dx = self.pixel_width;
+        let dy = self.pixel_height;
+        let dzdx = (self.elevation(x + dx, y)? - self.elevation(x - dx, y)?) / (2. * dx);
+        let dzdy = (self.elevation(x, y + dy)? - self.elevation(x, y - dy)?) / (2. * dy);
+        Some(Point::new(-dzdx, -dzdy, 1.).normalize())
+    }
+
+    fn get(&self, column: usize, row: usize) -> Option<f64> {
+        let value = self.data[row * self.width + column];
+        if value.is_nan() || self.nodata == Some(value) {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dem;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn tilted_plane() {
+        // z = x / 2
+        let data = (0..100).map(|i| f64::from(i % 10) / 2. + 0.25).collect();
+        let dem = Dem::new(0., 10., 1., 1., 10, data).unwrap();
+        assert_relative_eq!(2.5, dem.elevation(5., 5.).unwrap());
+        let normal = dem.normal(5., 5.).unwrap();
+        assert_relative_eq!(-0.5 / 1.25f64.sqrt(), normal.x);
+        assert_relative_eq!(0., normal.y);
+        assert_relative_eq!(1. / 1.25f64.sqrt(), normal.z);
+        assert!(dem.elevation(-1., 5.).is_none());
+        assert!(dem.elevation(5., 11.).is_none());
+        assert!(dem.normal(9.9, 5.).is_none());
+    }
+
+    #[test]
+    fn nodata() {
+        let mut dem = Dem::new(0., 2., 1., 1., 2, vec![-9999., 1., 1., 1.]).unwrap();
+        assert!(dem.elevation(1., 1.).is_some());
+        dem.nodata = Some(-9999.);
+        assert!(dem.elevation(1., 1.).is_none());
+    }
+}
@@ -0,0 +1,92 @@
+//! Leeward's error type.
+//!
+//! Library functions return [Error], so callers can tell (e.g.) a bad config
+//! from a missing trajectory point. It implements `std::error::Error`, so it
+//! converts into `anyhow::Error` and friends.
+
+use thiserror::Error as ThisError;
+
+/// Crate-specific error enum.
+#[derive(Debug, ThisError)]
+pub enum Error {
+    /// The configuration is invalid or inconsistent.
+    #[error("invalid config: {0}")]
+    Config(String),
+
+    /// The configuration file could not be parsed.
+    #[error(transparent)]
+    ConfigParse(#[from] toml::de::Error),
+
+    /// A configuration or checkpoint could not be written as TOML.
+    #[error(transparent)]
+    TomlSerialize(#[from] toml::ser::Error),
+
+    /// A lidar point has no time, so it can't be matched to the trajectory.
+    #[error("missing time on point")]
+    MissingTime,
+
+    /// There is no trajectory point for a lidar point's time.
+    #[error("could not find sbet point for time: {0}")]
+    TrajectoryLookup(f64),
+
+    /// A las header's CRS conflicts with the config.
+    #[error("crs conflict: {0}")]
+    CrsConflict(crate::crs::CrsConflict),
+
+    /// A DEM is invalid, or can't be sampled at a location.
+    #[error("dem: {0}")]
+    Dem(String),
+
+    /// A tabular point source is missing data or has unsupported values.
+    #[error("tabular points: {0}")]
+    Tabular(String),
+
+    /// A string could not be parsed into an option value.
+    #[error("{0}")]
+    Parse(String),
+
+    /// An argument or input is invalid.
+    #[error("{0}")]
+    Invalid(String),
+
+    /// The least-squares normal matrix is singular.
+    #[error("no inverse found")]
+    SingularMatrix,
+
+    /// A record can't be written to the requested output.
+    #[error("output: {0}")]
+    Output(String),
+
+    /// Leeward was built without a feature that is required.
+    #[error("leeward was built without {0} support")]
+    FeatureDisabled(&'static str),
+
+    /// [std::io::Error]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// [las::Error]
+    #[error(transparent)]
+    Las(#[from] las::Error),
+
+    /// [sbet::Error]
+    #[error(transparent)]
+    Sbet(#[from] sbet::Error),
+
+    /// [csv::Error]
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    /// [tiff::TiffError]
+    #[error(transparent)]
+    Tiff(#[from] tiff::TiffError),
+
+    /// [parquet::errors::ParquetError]
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    /// [serde_json::Error]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
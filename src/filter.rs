@@ -0,0 +1,208 @@
+use crate::{Measurement, PointSource};
+
+/// Excludes measurements, e.g. at the edge of the swath or on vegetation, while they're read.
+///
+/// Filters are built up from the methods below. A new filter keeps everything.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{MeasurementFilter, ReadOptions};
+/// let filter = MeasurementFilter::new()
+///     .scan_angle(-15., 15.)
+///     .range(0., 2000.)
+///     .classifications(&[2])
+///     .return_number(1);
+/// let options = ReadOptions {
+///     filter,
+///     ..Default::default()
+/// };
+/// let (measurements, summary) = leeward::read_measurements(
+///     &["data/sbet.out"],
+///     "data/points.las",
+///     "data/config.toml",
+///     &options,
+/// ).unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeasurementFilter {
+    min_scan_angle: Option<f64>,
+    max_scan_angle: Option<f64>,
+    min_range: Option<f64>,
+    max_range: Option<f64>,
+    classifications: Option<Vec<u8>>,
+    return_number: Option<u8>,
+}
+
+impl MeasurementFilter {
+    /// Creates a new filter that keeps every measurement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new();
+    /// ```
+    pub fn new() -> MeasurementFilter {
+        MeasurementFilter::default()
+    }
+
+    /// Keeps measurements with a modeled scan angle between `min` and `max`, in degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new().scan_angle(-20., 20.);
+    /// ```
+    pub fn scan_angle(self, min: f64, max: f64) -> MeasurementFilter {
+        self.min_scan_angle(min).max_scan_angle(max)
+    }
+
+    /// Keeps measurements with a modeled scan angle of at least `min` degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new().min_scan_angle(-20.);
+    /// ```
+    pub fn min_scan_angle(mut self, min: f64) -> MeasurementFilter {
+        self.min_scan_angle = Some(min);
+        self
+    }
+
+    /// Keeps measurements with a modeled scan angle of at most `max` degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new().max_scan_angle(20.);
+    /// ```
+    pub fn max_scan_angle(mut self, max: f64) -> MeasurementFilter {
+        self.max_scan_angle = Some(max);
+        self
+    }
+
+    /// Keeps measurements with a range between `min` and `max`, in meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new().range(100., 2000.);
+    /// ```
+    pub fn range(self, min: f64, max: f64) -> MeasurementFilter {
+        self.min_range(min).max_range(max)
+    }
+
+    /// Keeps measurements with a range of at least `min` meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new().min_range(100.);
+    /// ```
+    pub fn min_range(mut self, min: f64) -> MeasurementFilter {
+        self.min_range = Some(min);
+        self
+    }
+
+    /// Keeps measurements with a range of at most `max` meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new().max_range(2000.);
+    /// ```
+    pub fn max_range(mut self, max: f64) -> MeasurementFilter {
+        self.max_range = Some(max);
+        self
+    }
+
+    /// Keeps measurements whose points have one of these classifications.
+    ///
+    /// Points without a classification, e.g. from tabular sources, are excluded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new().classifications(&[2, 9]);
+    /// ```
+    pub fn classifications(mut self, classifications: &[u8]) -> MeasurementFilter {
+        self.classifications = Some(classifications.to_vec());
+        self
+    }
+
+    /// Keeps measurements whose points have this return number.
+    ///
+    /// Points without a return number, e.g. from tabular sources, are excluded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let filter = MeasurementFilter::new().return_number(1);
+    /// ```
+    pub fn return_number(mut self, return_number: u8) -> MeasurementFilter {
+        self.return_number = Some(return_number);
+        self
+    }
+
+    /// Returns true if this filter keeps everything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// assert!(MeasurementFilter::new().is_empty());
+    /// assert!(!MeasurementFilter::new().return_number(1).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        *self == MeasurementFilter::default()
+    }
+
+    /// Returns true if this filter keeps the measurement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::MeasurementFilter;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// assert!(MeasurementFilter::new().matches(&measurements[0]));
+    /// assert!(!MeasurementFilter::new().max_range(1.).matches(&measurements[0]));
+    /// ```
+    pub fn matches<L: PointSource>(&self, measurement: &Measurement<L>) -> bool {
+        if self.min_scan_angle.is_some() || self.max_scan_angle.is_some() {
+            let scan_angle = measurement.scan_angle().to_degrees();
+            if !within(scan_angle, self.min_scan_angle, self.max_scan_angle) {
+                return false;
+            }
+        }
+        if (self.min_range.is_some() || self.max_range.is_some())
+            && !within(measurement.range(), self.min_range, self.max_range)
+        {
+            return false;
+        }
+        if let Some(classifications) = &self.classifications {
+            match measurement.lasish().classification() {
+                Some(classification) if classifications.contains(&classification) => {}
+                _ => return false,
+            }
+        }
+        if let Some(return_number) = self.return_number {
+            if measurement.lasish().return_number() != Some(return_number) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn within(value: f64, min: Option<f64>, max: Option<f64>) -> bool {
+    min.map(|min| value >= min).unwrap_or(true) && max.map(|max| value <= max).unwrap_or(true)
+}
@@ -0,0 +1,199 @@
+//! Comparison of lidar measurements against surveyed ground control points.
+//!
+//! Control points are read from a CSV file with `id`, `x`, `y`, and `z`
+//! columns, in the same projected coordinate system as the lidar points. The
+//! lidar surface elevation at each control point is interpolated with inverse
+//! distance weighting of the lidar points within a search radius.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::gcp::{self, Gcp, Options};
+//! let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+//! let gcps = vec![Gcp { id: "a".to_string(), x: 320000.34, y: 4181319.35, z: 2687.5 }];
+//! let comparisons = gcp::compare(&gcps, &measurements, &Options::default()).unwrap();
+//! assert_eq!(1, comparisons.len());
+//! ```
+
+use crate::{spatial::KdTree, Error, Measurement, Point, PointSource};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A surveyed ground control point.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Gcp {
+    pub id: String,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Options for comparing control points to lidar.
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    /// The horizontal search radius for lidar points, in meters.
+    pub radius: f64,
+
+    /// A control point is flagged if its vertical difference exceeds this many sigmas.
+    pub k: f64,
+}
+
+/// The comparison between one control point and the lidar.
+#[derive(Clone, Debug, Serialize)]
+pub struct Comparison {
+    pub id: String,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+
+    /// The number of lidar points within the search radius.
+    pub count: usize,
+
+    /// The interpolated lidar elevation.
+    pub lidar_z: f64,
+
+    /// The lidar elevation minus the control point elevation.
+    pub dz: f64,
+
+    /// The x offset from the control point to the nearest lidar point.
+    pub dx: f64,
+
+    /// The y offset from the control point to the nearest lidar point.
+    pub dy: f64,
+
+    /// The interpolated vertical propagated uncertainty of the lidar.
+    pub sigma_vertical: f64,
+
+    /// The interpolated horizontal propagated uncertainty of the lidar.
+    pub sigma_horizontal: f64,
+
+    /// True if `|dz| > k * sigma_vertical`.
+    pub exceeds: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { radius: 2., k: 3. }
+    }
+}
+
+/// Reads control points from a CSV file with a header row.
+///
+/// # Examples
+///
+/// ```
+/// let gcps = leeward::gcp::read_csv("data/gcps.csv").unwrap();
+/// ```
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn read_csv<P: AsRef<Path>>(path: P) -> Result<Vec<Gcp>, Error> {
+    csv::Reader::from_path(path)?
+        .deserialize()
+        .map(|r| r.map_err(Error::from))
+        .collect()
+}
+
+/// Compares control points to the lidar measurements.
+///
+/// Control points without any lidar points within the search radius are skipped.
+/// Uncertainties are computed assuming a horizontal surface.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::gcp::{self, Options};
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let gcps = gcp::read_csv("data/gcps.csv").unwrap();
+/// let comparisons = gcp::compare(&gcps, &measurements, &Options::default()).unwrap();
+/// ```
+pub fn compare<L: PointSource>(
+    gcps: &[Gcp],
+    measurements: &[Measurement<L>],
+    options: &Options,
+) -> Result<Vec<Comparison>, Error> {
+    let tree = KdTree::new_xy(measurements.iter().map(|m| m.lasish().point()).collect());
+    let mut comparisons = Vec::new();
+    for gcp in gcps {
+        let location = Point::new(gcp.x, gcp.y, gcp.z);
+        let neighbors = tree.within(location, options.radius);
+        if neighbors.is_empty() {
+            continue;
+        }
+        let (mut weights, mut z, mut vertical, mut horizontal) = (0., 0., 0., 0.);
+        for &(index, distance) in &neighbors {
+            let measurement = &measurements[index];
+            let tpu = measurement.tpu(Point::new(0., 0., 1.))?;
+            let weight = if distance > 0. {
+                distance.powi(-2)
+            } else {
+                f64::INFINITY
+            };
+            if weight.is_infinite() {
+                // A lidar point right on the control point wins outright.
+                weights = 1.;
+                z = measurement.z();
+                vertical = tpu.vertical;
+                horizontal = tpu.horizontal;
+                break;
+            }
+            weights += weight;
+            z += weight * measurement.z();
+            vertical += weight * tpu.vertical;
+            horizontal += weight * tpu.horizontal;
+        }
+        let (z, vertical, horizontal) = (z / weights, vertical / weights, horizontal / weights);
+        let nearest = tree.point(neighbors[0].0);
+        let dz = z - gcp.z;
+        comparisons.push(Comparison {
+            id: gcp.id.clone(),
+            x: gcp.x,
+            y: gcp.y,
+            z: gcp.z,
+            count: neighbors.len(),
+            lidar_z: z,
+            dz,
+            dx: nearest.x - gcp.x,
+            dy: nearest.y - gcp.y,
+            sigma_vertical: vertical,
+            sigma_horizontal: horizontal,
+            exceeds: dz.abs() > options.k * vertical,
+        });
+    }
+    Ok(comparisons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gcp, Options};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn compare() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let point = &measurements[0];
+        let gcps = vec![
+            Gcp {
+                id: "on".to_string(),
+                x: point.x(),
+                y: point.y(),
+                z: point.z() - 10.,
+            },
+            Gcp {
+                id: "away".to_string(),
+                x: 0.,
+                y: 0.,
+                z: 0.,
+            },
+        ];
+        let comparisons = super::compare(&gcps, &measurements, &Options::default()).unwrap();
+        assert_eq!(1, comparisons.len());
+        let comparison = &comparisons[0];
+        assert_eq!("on", comparison.id);
+        assert_relative_eq!(10., comparison.dz);
+        assert_eq!(0., comparison.dx);
+        assert!(comparison.exceeds);
+    }
+}
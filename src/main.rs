@@ -1,24 +1,142 @@
-use anyhow::Error;
-use clap::{Parser, Subcommand};
+use anyhow::{anyhow, Error};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand, ValueEnum};
 use csv::Writer;
-use leeward::{utils, Adjust, Config, Lasish, Measurement, Point};
+use leeward::{
+    adjust::Checkpoint,
+    agl, compare, coverage,
+    crs::{self, CrsMismatch, Datum},
+    gcp,
+    sink::{CsvSink, JsonlSink, LasSink, OutputSink},
+    strip,
+    tabular::{self, Format},
+    utils, Adjust, Config, Decimation, Dem, Dimension, IntensityRangeBias, Measurement,
+    MeasurementFilter, MissingPose, Point, PointSource, Preset, Propagation, RangeError,
+    ReadOptions, ReadSummary, TimeReconstruction, TpuQuality, Trajectory, Variable,
+};
 use serde::Serialize;
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
 #[derive(Debug, Parser)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
     /// The SBET file
-    sbet: PathBuf,
+    #[arg(required = true)]
+    sbet: Option<PathBuf>,
 
-    /// The LAS file holding the points
-    las: PathBuf,
+    /// Another SBET file to merge with the first
+    ///
+    /// Provide more than once to merge a trajectory delivered as several segments.
+    #[arg(long = "sbet-segment", value_name = "SBET")]
+    sbet_segments: Vec<PathBuf>,
+
+    /// The file(s) holding the points
+    ///
+    /// Glob patterns in the file name, e.g. "strips/*.las", are expanded. All
+    /// files are processed against the same trajectory and config.
+    #[arg(required = true, num_args = 1..)]
+    las: Vec<String>,
 
     /// The config TOML file
-    config: PathBuf,
+    #[arg(required = true)]
+    config: Option<PathBuf>,
 
-    /// The amount to decimate the incoming points
+    /// How to decimate the incoming points
+    ///
+    /// A number n keeps every nth point. For spatially balanced samples, use
+    /// voxel:SIZE to keep one point per cube of SIZE meters, random:FRACTION
+    /// to keep a random fraction of the points (see --seed), or scan-line:N to keep every
+    /// nth scan line. To stratify, use time:SECONDS:COUNT to keep COUNT points
+    /// per SECONDS of gps time, or scan-angle:DEGREES:COUNT to keep COUNT
+    /// points per DEGREES of scan angle.
     #[arg(short, long, default_value = "1")]
-    decimation: usize,
+    decimation: Decimation,
+
+    /// The seed for random sampling, so results are reproducible
+    ///
+    /// The same seed gives the same sample on every run and platform.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// The format of the points file (las, csv, or parquet)
+    #[arg(short, long, default_value = "las")]
+    format: Format,
+
+    /// Exclude points with a modeled scan angle less than this, in degrees
+    #[arg(long, allow_negative_numbers = true)]
+    min_scan_angle: Option<f64>,
+
+    /// Exclude points with a modeled scan angle greater than this, in degrees
+    #[arg(long, allow_negative_numbers = true)]
+    max_scan_angle: Option<f64>,
+
+    /// Exclude points with a range less than this, in meters
+    #[arg(long)]
+    min_range: Option<f64>,
+
+    /// Exclude points with a range greater than this, in meters
+    #[arg(long)]
+    max_range: Option<f64>,
+
+    /// Only include points with these classifications, e.g. 2,9
+    #[arg(long, value_delimiter = ',')]
+    classification: Vec<u8>,
+
+    /// Only include points with this return number
+    #[arg(long)]
+    return_number: Option<u8>,
+
+    /// What to do with points that don't have a matching trajectory point (fail, skip, or nearest)
+    #[arg(long, default_value = "fail")]
+    on_missing_pose: MissingPose,
+
+    /// How to assign times to las points without them (off, interpolate, or start:TIME)
+    ///
+    /// interpolate interpolates by point order between time-tagged points in
+    /// the same scan line. start:TIME counts pulses from TIME at the config's
+    /// pulse_rate, e.g. for point format 0 files. The times are approximate.
+    #[arg(long, default_value = "off")]
+    time_reconstruction: TimeReconstruction,
+
+    /// What to do if a las header's CRS conflicts with the config (warn, fail, or ignore)
+    #[arg(long, default_value = "warn")]
+    on_crs_mismatch: CrsMismatch,
+
+    /// Smooth the trajectory's roll, pitch, and yaw with a moving average over this many seconds
+    ///
+    /// Noisy attitude at high sbet rates aliases into the tpu. The window is
+    /// centered on each trajectory point.
+    #[arg(long, value_name = "SECONDS")]
+    attitude_smoothing: Option<f64>,
+
+    /// A CSV file of intensity-dependent range biases, with intensity and bias (meters) columns
+    ///
+    /// The bias, interpolated at each point's intensity, is subtracted from
+    /// its range before computing anything else.
+    #[arg(long, value_name = "CSV")]
+    intensity_range_bias: Option<PathBuf>,
+
+    /// The format of per-point output records
+    ///
+    /// jsonl writes one JSON object per line. las output requires an outfile
+    /// and reuses the header (and CRS) of a las input. Records with x, y, and
+    /// z fields are written as points with their other numeric fields as extra
+    /// bytes, except for backconvert and scan-angle, which write the point
+    /// cloud itself.
+    #[arg(long, value_enum, default_value = "csv")]
+    output_format: OutputFormat,
+
+    /// A DEM (GeoTIFF) used to compute surface normals for incidence angles
+    ///
+    /// If not provided, the surface is assumed to be horizontal.
+    #[arg(long)]
+    dem: Option<PathBuf>,
 
     /// The output file.
     ///
@@ -26,71 +144,1609 @@ struct Args {
     #[arg(short, long)]
     outfile: Option<PathBuf>,
 
+    /// Write one output file per input file, instead of concatenating the inputs
+    ///
+    /// {stem} is replaced with each input's file name without its extension,
+    /// e.g. "{stem}-tpu.csv".
+    #[arg(long, conflicts_with = "outfile")]
+    output_template: Option<String>,
+
+    /// Log progress, e.g. each adjustment iteration, to standard error
+    ///
+    /// Once for info, twice for debug, e.g. each variable's change per iteration.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
     #[command(subcommand)]
     command: Command,
 }
 
-#[derive(Debug, Subcommand)]
+#[derive(Clone, Debug, clap::Args)]
+struct InitConfig {
+    /// Where to write the config
+    config: PathBuf,
+
+    /// A las file, to pre-fill the utm zone and datum from its header
+    ///
+    /// Its time range is written into the config as a comment.
+    #[arg(long)]
+    las: Option<PathBuf>,
+
+    /// An sbet file, to pre-fill the utm zone from the trajectory if the las doesn't have one
+    #[arg(long)]
+    sbet: Option<PathBuf>,
+
+    /// Overwrite the config if it already exists
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum OutputFormat {
+    Csv,
+    Jsonl,
+    Parquet,
+    Las,
+    Geojson,
+    Kml,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ScanAngleField {
+    /// The las 1.4 extended scan angle, with a resolution of 0.006 degrees
+    Extended,
+    /// A double extra bytes dimension named "scan_angle"
+    ExtraBytes,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum HistoryFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum TrajectoryCrs {
+    Utm(Option<u8>),
+    Geographic,
+}
+
+struct Output {
+    outfile: Option<PathBuf>,
+    format: OutputFormat,
+    header: Option<las::Header>,
+    template: Option<String>,
+}
+
+#[derive(Clone, Debug, Subcommand)]
 enum Command {
     /// Computes the boresight adjustment.
     Adjust {
         /// The file to write the history information.
+        ///
+        /// Each iteration has its rmse, plus the value and standard deviation
+        /// of each adjusted variable.
         history: Option<PathBuf>,
+
+        /// The format of the history file
+        #[arg(long, value_enum, default_value = "csv")]
+        history_format: HistoryFormat,
+
+        /// Only use points with these classifications in the adjustment, e.g. 2
+        #[arg(long, value_delimiter = ',')]
+        classes: Vec<u8>,
+
+        /// Adjust these variables instead of the boresight, e.g. boresight_roll,lever_arm_z
+        #[arg(long, value_delimiter = ',', conflicts_with = "lever_arm")]
+        variables: Vec<Variable>,
+
+        /// Adjust the lever arm instead of the boresight
+        #[arg(long)]
+        lever_arm: bool,
+
+        /// Keep these variables at their configured values, e.g. boresight_yaw
+        #[arg(long, value_delimiter = ',')]
+        fix: Vec<Variable>,
+
+        /// Bound a variable, as name:min:max, e.g. lever_arm_x:-0.05:0.05
+        #[arg(long)]
+        bound: Vec<Bound>,
+
+        /// Start from this config's boresight and lever arm, e.g. a previous survey's solution
+        #[arg(long)]
+        initial: Option<PathBuf>,
+
+        /// Resume the adjustment from a checkpoint, using the same points
+        #[arg(long, conflicts_with = "initial")]
+        resume: Option<PathBuf>,
+
+        /// Write a checkpoint of the finished adjustment, for resuming it later
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+
+        /// Run a k-fold cross-validation and report each fold's training and validation rmse
+        ///
+        /// The points are split into k contiguous folds. A validation rmse much
+        /// larger than the training rmse means the solution is overfit.
+        #[arg(long, value_name = "K")]
+        cross_validate: Option<usize>,
+
+        /// Use the las scan angles, and also adjust their calibration's offset and scale
+        #[arg(long)]
+        adjust_scan_angle: bool,
+
+        /// After each iteration, drop points whose residual is more than this many rmses
+        #[arg(long, value_name = "N_SIGMA")]
+        outlier_rejection: Option<f64>,
+
+        /// Also estimate a roll, pitch, and yaw correction for each strip (point source id)
+        ///
+        /// The first strip is the reference. Use with --adjust-scan-angle, or
+        /// another way of using las scan angles, since a computed scan angle
+        /// absorbs the strips' roll.
+        #[arg(long)]
+        strips: bool,
+    },
+
+    /// Computes the platform's height above ground level along the trajectory
+    ///
+    /// Writes a time-tagged profile of the platform altitude, the ground
+    /// elevation under the platform, and the difference between them. The
+    /// ground is the median elevation of the points near the platform's nadir,
+    /// or the DEM if one is provided.
+    Agl {
+        /// The time between trajectory samples, in seconds
+        #[arg(long, default_value = "1")]
+        interval: f64,
+
+        /// The horizontal search radius for points around the platform's nadir, in meters
+        #[arg(long, default_value = "5")]
+        radius: f64,
     },
 
+    /// Recomputes each point's map coordinates from the trajectory and config
+    ///
+    /// Writes the original and recomputed coordinates and the misfit between them,
+    /// for validating a config against a delivered point cloud. With las output,
+    /// writes the recomputed point cloud instead, e.g. to apply an adjusted config.
+    Backconvert,
+
     /// Computes the best fit plane for the points in the body frame of the platform
+    ///
+    /// Writes the plane's normal, centroid, and the rms distance of the points
+    /// from it, then each point in plane coordinates: x and y along the plane
+    /// from the centroid, and z the distance from the plane. With csv output, the
+    /// plane is written as comment lines starting with "#" before the records,
+    /// otherwise it's written to standard error.
     BestFitPlane,
 
     /// Computes the points in the body frame of the aircraft
-    BodyFrame,
+    ///
+    /// Each record also has the point's incidence angle, in radians, to the
+    /// DEM's surface or, if there's no DEM, to a horizontal surface.
+    BodyFrame {
+        /// Estimate each point's surface from a plane fit to this many nearest neighbors
+        ///
+        /// Overrides the DEM for the incidence angles.
+        #[arg(long, value_name = "K")]
+        neighbors: Option<usize>,
+    },
+
+    /// Compares the residuals and uncertainties of the config to another config
+    ///
+    /// Writes each statistic for both configs and the change between them, e.g.
+    /// to check whether a new boresight solution is an improvement.
+    CompareConfigs {
+        /// The config TOML file to compare against
+        other: PathBuf,
+    },
+
+    /// Writes each flightline's trajectory and swath footprint for review on a web map
+    ///
+    /// The footprint is the convex hull of the flightline's points.
+    /// Flightlines are grouped by point source id. Requires geojson or kml
+    /// output, which are longitude and latitude in degrees.
+    Coverage {
+        /// The time between trajectory vertices, in seconds (0 keeps every point)
+        #[arg(long, default_value = "1")]
+        interval: f64,
+    },
+
+    /// Compares the points to surveyed ground control points
+    ///
+    /// Writes the vertical difference between each control point and the
+    /// interpolated lidar surface, along with the propagated uncertainty of the
+    /// lidar, flagging control points whose difference exceeds k sigma.
+    Gcp {
+        /// The CSV file of control points, with id, x, y, and z columns
+        gcps: PathBuf,
+
+        /// The horizontal search radius for lidar points, in meters
+        #[arg(long, default_value = "2")]
+        radius: f64,
+
+        /// The number of sigmas beyond which a control point is flagged
+        #[arg(short, default_value = "3")]
+        k: f64,
+    },
+
+    /// Reports the quick sanity numbers for each flightline
+    ///
+    /// Writes each flightline's swath width, mean point density, scan angle
+    /// and range statistics, height above the points, and speed. Flightlines
+    /// are grouped by point source id. The swath width is the spread of the
+    /// points' cross-track offsets from the platform, between their 1st and
+    /// 99th percentiles, and the density is the point count over the swath
+    /// width times the distance flown. Whether the las scan angles look
+    /// roll-compensated is detected by comparing them to the computed scan
+    /// angles.
+    Info,
+
+    /// Prints everything about a single measurement as TOML
+    ///
+    /// Includes the las point, the trajectory pose, the body frame and modeled
+    /// coordinates, the scan angles, the residuals, the body frame partial
+    /// derivatives, and the total propagated uncertainty. Useful for debugging
+    /// a config.
+    Inspect {
+        /// The index of the measurement, after decimation and filtering
+        #[arg(long)]
+        index: usize,
+    },
+
+    /// Summarizes total propagated uncertainty in bins of scan angle and range
+    ///
+    /// Writes the mean and 95th percentile of the horizontal, vertical, and
+    /// total uncertainties for each non-empty bin.
+    Profile {
+        /// The width of the scan angle bins, in degrees
+        #[arg(long, default_value = "1")]
+        scan_angle_bin: f64,
+
+        /// The width of the range bins, in meters
+        #[arg(long, default_value = "100")]
+        range_bin: f64,
+    },
+
+    /// Writes each point's misfit between its modeled and measured coordinates
+    ///
+    /// The residuals are written in the body frame and in projected
+    /// coordinates, along with the misfit and its z-score across all points for
+    /// coloring. Misconfigured lever arms and boresights show up as spatial
+    /// patterns, e.g. a tilt across the swath, before running a full adjustment.
+    Residuals,
+
+    /// Writes the points with their computed scan angles
+    ///
+    /// Las scan angles are quantized to whole degrees in legacy point formats,
+    /// but leeward's scan angle is continuous. The computed angle, in degrees,
+    /// is written either to the extended scan angle field, which upgrades the
+    /// point format to las 1.4's extended formats, or to a double extra bytes
+    /// dimension named "scan_angle". Requires las output.
+    ScanAngle {
+        /// Where to write the computed scan angle
+        #[arg(long, value_enum, default_value = "extended")]
+        field: ScanAngleField,
+    },
+
+    /// Reports how far the points move when each variable is off by its uncertainty
+    ///
+    /// Each variable in the lidar equation is perturbed by plus and minus its
+    /// (one sigma, by default) uncertainty from the config, one at a time, and
+    /// the resulting horizontal, vertical, and total displacements of the
+    /// modeled points are summarized across all points. This is an empirical
+    /// version of the tpu's partial derivatives. Variables without any
+    /// uncertainty are skipped.
+    Sensitivity {
+        /// The number of sigmas to perturb each variable by
+        #[arg(long, default_value = "1")]
+        sigma: f64,
+    },
+
+    /// Compares the points to an overlapping flightline
+    ///
+    /// Writes interswath statistics for the points on smooth surfaces in the
+    /// overlap, along with the roll, pitch, and yaw signatures of the
+    /// vertical differences. The other flightline is read in the same format
+    /// as the points.
+    StripDiff {
+        /// The file holding the points of the overlapping flightline
+        reference: PathBuf,
+
+        /// The horizontal search radius for reference points, in meters
+        #[arg(long, default_value = "1")]
+        radius: f64,
+
+        /// The minimum number of reference points needed to fit a surface
+        #[arg(long, default_value = "5")]
+        min_points: usize,
+
+        /// The maximum rms residual of a smooth surface, in meters
+        #[arg(long, default_value = "0.1")]
+        max_roughness: f64,
+
+        /// The file to write each sample
+        #[arg(long)]
+        samples: Option<PathBuf>,
+    },
+
+    /// Exports the trajectory for QC in a GIS
+    ///
+    /// Writes the platform's position, roll, pitch, and yaw (in degrees), and
+    /// speed (in meters per second), decimated in time. The points aren't
+    /// read, but a las file's CRS sets the UTM zone if the config doesn't.
+    /// Las output only has the positions and times, and requires a utm crs.
+    Trajectory {
+        /// The time between exported trajectory points, in seconds (0 keeps every point)
+        #[arg(long, default_value = "1")]
+        interval: f64,
+
+        /// The CRS of the exported positions (utm, utm:ZONE, or geographic)
+        ///
+        /// geographic positions are longitude and latitude in degrees and
+        /// ellipsoidal height in meters.
+        #[arg(long, default_value = "utm")]
+        crs: TrajectoryCrs,
+    },
 
     /// Computes total propagated uncertainty
-    Tpu,
+    ///
+    /// Each record also has the platform's horizontal speed (in meters per
+    /// second) and attitude rates (in radians per second), from the
+    /// neighboring trajectory points, and its height above the point, so
+    /// uncertainty spikes can be correlated with aggressive turns. The
+    /// point's return number and one sigma range uncertainty show how later
+    /// returns of multi-return pulses are modeled, see the config's [returns].
+    Tpu {
+        /// The confidence level of the reported uncertainties, e.g. 0.95
+        ///
+        /// If not provided, uncertainties are one sigma.
+        #[arg(long)]
+        confidence: Option<f64>,
+
+        /// Write aggregate statistics as JSON instead of per-point records
+        ///
+        /// The median and 95th percentile are interpolated from the histogram,
+        /// so they're accurate to the bucket width.
+        #[arg(long)]
+        summary: bool,
+
+        /// The width of the summary histogram buckets, in meters
+        #[arg(long, default_value = "0.01")]
+        bucket_width: f64,
+
+        /// Add each point's local roughness, from the points within this radius, to each record
+        ///
+        /// The roughness is the rms distance of the points from their best fit
+        /// plane, in meters. It's empty for points with fewer than three points
+        /// within the radius.
+        #[arg(long, value_name = "METERS")]
+        roughness_radius: Option<f64>,
+
+        /// Add the difference between the ecef and navigation propagations to each record
+        ///
+        /// The differences are the ecef uncertainties minus the navigation
+        /// uncertainties, whichever propagation the config selects.
+        #[arg(long)]
+        compare_propagation: bool,
+    },
+
+    /// Writes a starter config, asking for the values it can't work out
+    ///
+    /// Run as `leeward init-config`, without the sbet, las, and config
+    /// arguments that the other subcommands need. The sensor preset fills in
+    /// the beam divergence, scanner, and uncertainties; edit the config to
+    /// change them.
+    InitConfig(InitConfig),
 }
 
 fn main() -> Result<(), Error> {
     let args = Args::parse();
-    let measurements =
-        leeward::decimated_measurements(args.sbet, args.las, args.config, args.decimation)?;
-    let mut write: Box<dyn Write> = if let Some(outfile) = args.outfile {
-        Box::new(File::create(outfile)?)
+    let level = match args.verbose {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::INFO,
+        _ => tracing::Level::DEBUG,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .init();
+    if let Command::InitConfig(init) = &args.command {
+        return init_config(init.clone());
+    }
+    let (sbet, config) = match (args.sbet, args.config) {
+        (Some(sbet), Some(config)) if !args.las.is_empty() => (sbet, config),
+        _ => Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the sbet, las, and config arguments are required for this command",
+            )
+            .exit(),
+    };
+    let mut filter = MeasurementFilter::new();
+    if let Some(min) = args.min_scan_angle {
+        filter = filter.min_scan_angle(min);
+    }
+    if let Some(max) = args.max_scan_angle {
+        filter = filter.max_scan_angle(max);
+    }
+    if let Some(min) = args.min_range {
+        filter = filter.min_range(min);
+    }
+    if let Some(max) = args.max_range {
+        filter = filter.max_range(max);
+    }
+    if !args.classification.is_empty() {
+        filter = filter.classifications(&args.classification);
+    }
+    if let Some(return_number) = args.return_number {
+        filter = filter.return_number(return_number);
+    }
+    let decimation = match args.seed {
+        Some(seed) => args.decimation.with_seed(seed),
+        None => args.decimation,
+    };
+    let options = ReadOptions {
+        decimation,
+        missing_pose: args.on_missing_pose,
+        filter,
+        time_reconstruction: args.time_reconstruction,
+        crs_mismatch: args.on_crs_mismatch,
+        attitude_smoothing: args.attitude_smoothing,
+        range_correction: match &args.intensity_range_bias {
+            Some(path) => Some(Arc::new(IntensityRangeBias::from_path(path)?)),
+            None => None,
+        },
+    };
+    if let Some(template) = &args.output_template {
+        if !template.contains("{stem}") {
+            return Err(anyhow!("the output template must contain {{stem}}"));
+        }
+    }
+    let paths = expand(&args.las)?;
+    let sbets: Vec<PathBuf> = std::iter::once(sbet).chain(args.sbet_segments).collect();
+    let output = Output {
+        outfile: args.outfile,
+        format: args.output_format,
+        header: None,
+        template: args.output_template,
+    };
+    if let Command::Trajectory { interval, crs } = args.command {
+        return export_trajectory(&sbets, &config, &paths, args.format, output, interval, crs);
+    }
+    let dem = args.dem.map(Dem::from_path).transpose()?;
+    let (sbets, config, format) = (&sbets, &config, args.format);
+    match format {
+        Format::Las => process(
+            &paths,
+            |path| leeward::read_measurements(sbets, path, config, &options),
+            args.command,
+            format,
+            output,
+            dem.as_ref(),
+            sbets,
+        ),
+        Format::Csv => process(
+            &paths,
+            |path| {
+                let points = tabular::read_csv(path)?;
+                leeward::measurements_from_points(sbets, points, config, &options)
+            },
+            args.command,
+            format,
+            output,
+            dem.as_ref(),
+            sbets,
+        ),
+        Format::Parquet => process(
+            &paths,
+            |path| {
+                let points = tabular::read_parquet(path)?;
+                leeward::measurements_from_points(sbets, points, config, &options)
+            },
+            args.command,
+            format,
+            output,
+            dem.as_ref(),
+            sbets,
+        ),
+    }
+}
+
+/// Expands glob patterns in the file name of each path.
+///
+/// Supports `*` and `?`. Paths without a pattern are passed through as-is.
+fn expand(patterns: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        if !name.contains(['*', '?']) {
+            paths.push(path.to_path_buf());
+            continue;
+        }
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut matches = Vec::new();
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            if let Some(file_name) = entry.file_name().to_str() {
+                if entry.file_type()?.is_file() && wildcard(name.as_bytes(), file_name.as_bytes()) {
+                    matches.push(path.with_file_name(file_name));
+                }
+            }
+        }
+        if matches.is_empty() {
+            return Err(anyhow!("no files match {}", pattern));
+        }
+        matches.sort();
+        paths.extend(matches);
+    }
+    Ok(paths)
+}
+
+fn wildcard(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            wildcard(&pattern[1..], name) || (!name.is_empty() && wildcard(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => wildcard(&pattern[1..], &name[1..]),
+        (Some(a), Some(b)) if a == b => wildcard(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// Reads each file and runs the command, either once on all the measurements
+/// or once per file if there's an output template.
+fn process<L, F>(
+    paths: &[PathBuf],
+    read: F,
+    command: Command,
+    format: Format,
+    output: Output,
+    dem: Option<&Dem>,
+    sbets: &[PathBuf],
+) -> Result<(), Error>
+where
+    L: PointSource,
+    F: Fn(&Path) -> Result<(Vec<Measurement<L>>, ReadSummary), leeward::Error>,
+{
+    let name = |path| if paths.len() > 1 { Some(path) } else { None };
+    if output.template.is_some() {
+        for path in paths {
+            let (measurements, summary) = read(path)?;
+            report(name(path), measurements.len(), summary);
+            let output = output.for_input(path, format)?;
+            run(command.clone(), measurements, format, output, dem, sbets)?;
+        }
+        Ok(())
+    } else {
+        let mut all = Vec::new();
+        for path in paths {
+            let (measurements, summary) = read(path)?;
+            report(name(path), measurements.len(), summary);
+            all.extend(measurements);
+        }
+        // Las output reuses the header of the first input.
+        let output = output.for_input(&paths[0], format)?;
+        run(command, all, format, output, dem, sbets)
+    }
+}
+
+/// Asks for the config values on standard error and reads the answers from standard input.
+fn init_config(args: InitConfig) -> Result<(), Error> {
+    if args.config.exists() && !args.force {
+        return Err(anyhow!(
+            "{} already exists, use --force to overwrite it",
+            args.config.display()
+        ));
+    }
+    let mut crs = crs::Crs::default();
+    let mut time_range = None;
+    if let Some(path) = &args.las {
+        let mut reader = las::Reader::from_path(path)?;
+        crs = crs::read(las::Read::header(&reader)).unwrap_or_default();
+        time_range =
+            las::Read::points(&mut reader).try_fold(None, |range: Option<(f64, f64)>, point| {
+                Ok::<_, las::Error>(match (range, point?.gps_time) {
+                    (Some((start, end)), Some(time)) => Some((start.min(time), end.max(time))),
+                    (None, Some(time)) => Some((time, time)),
+                    (range, None) => range,
+                })
+            })?;
+    }
+    let mut utm_zone = match crs.projection {
+        Some(crs::Projection::Utm(zone)) => Some(zone),
+        _ => None,
+    };
+    if let (None, Some(path)) = (utm_zone, &args.sbet) {
+        let trajectory = match time_range {
+            Some((start, end)) => Trajectory::from_path_with_range(path, start, end)?,
+            None => Trajectory::from_path(path)?,
+        };
+        utm_zone = trajectory.utm_zone();
+    }
+
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut ask = |question: &str, default: Option<String>| -> Result<String, Error> {
+        match &default {
+            Some(default) => eprint!("{} [{}]: ", question, default),
+            None => eprint!("{}: ", question),
+        }
+        let mut answer = String::new();
+        if std::io::BufRead::read_line(&mut input, &mut answer)? == 0 {
+            eprintln!();
+        }
+        match (answer.trim(), default) {
+            ("", Some(default)) => Ok(default),
+            ("", None) => Err(anyhow!("no answer for: {}", question)),
+            (answer, _) => Ok(answer.to_string()),
+        }
+    };
+    let crs_answer = ask(
+        "UTM zone or EPSG code, e.g. 11 or 32611",
+        utm_zone.map(|zone| zone.to_string()),
+    )?;
+    let (utm_zone, datum) = match crs_answer.trim_start_matches("EPSG:").parse::<u16>()? {
+        zone @ 1..=60 => (zone as u8, crs.datum),
+        code => match crs::from_epsg(code) {
+            crs::Crs {
+                projection: Some(crs::Projection::Utm(zone)),
+                datum,
+            } => (zone, datum.or(crs.datum)),
+            _ => return Err(anyhow!("EPSG:{} isn't a utm crs", code)),
+        },
+    };
+    let presets: Vec<String> = Preset::ALL.iter().map(|p| p.to_string()).collect();
+    let preset: Preset = ask(
+        &format!("Sensor preset ({})", presets.join(", ")),
+        Some(presets[0].clone()),
+    )?
+    .parse()?;
+    let triple = |answer: String| -> Result<[f64; 3], Error> {
+        let values = answer
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()?;
+        match values[..] {
+            [x, y, z] => Ok([x, y, z]),
+            _ => Err(anyhow!("expected three values: {}", answer)),
+        }
+    };
+    let lever_arm = triple(ask(
+        "Lever arm from the scanner to the IMU (x, y, z) in meters",
+        Some("0, 0, 0".to_string()),
+    )?)?;
+    let boresight = triple(ask(
+        "Boresight (roll, pitch, yaw) in degrees",
+        Some("0, 0, 0".to_string()),
+    )?)?;
+
+    let mut toml = String::new();
+    if let Some((start, end)) = time_range {
+        toml.push_str(&format!(
+            "# The las points span gps times {} to {}\n",
+            start, end
+        ));
+    }
+    toml.push_str(&format!("preset = \"{}\"\n", preset));
+    toml.push_str(&format!("utm_zone = {}\n", utm_zone));
+    if let Some(datum) = datum {
+        toml.push_str(&format!("datum = {}\n", toml::Value::try_from(datum)?));
+    }
+    toml.push_str(&format!(
+        "lever_arm = [{:?}, {:?}, {:?}]\n",
+        lever_arm[0], lever_arm[1], lever_arm[2]
+    ));
+    toml.push_str("\n[boresight]\n");
+    for (name, degrees) in ["roll", "pitch", "yaw"].iter().zip(boresight) {
+        toml.push_str(&format!(
+            "{} = {:?} # {}°\n",
+            name,
+            degrees.to_radians(),
+            degrees
+        ));
+    }
+    let _: Config = toml.parse()?;
+    std::fs::write(&args.config, toml)?;
+    eprintln!("Wrote {}", args.config.display());
+    Ok(())
+}
+
+/// Writes the decimated trajectory, without reading any points.
+fn export_trajectory(
+    sbets: &[PathBuf],
+    config: &Path,
+    paths: &[PathBuf],
+    format: Format,
+    output: Output,
+    interval: f64,
+    crs: TrajectoryCrs,
+) -> Result<(), Error> {
+    if interval.is_nan() || interval < 0. {
+        return Err(anyhow!("the interval can't be negative, got {}", interval));
+    }
+    let trajectory = Trajectory::from_paths(sbets)?;
+    let config = Config::from_path(config)?;
+    let utm_zone = match crs {
+        TrajectoryCrs::Utm(zone) => Some(
+            zone.or(config.utm_zone)
+                .or_else(|| match format {
+                    Format::Las => las::Reader::from_path(&paths[0])
+                        .ok()
+                        .and_then(|reader| crs::utm_zone(las::Read::header(&reader))),
+                    Format::Csv | Format::Parquet => None,
+                })
+                .or_else(|| trajectory.utm_zone())
+                .ok_or(anyhow!("the trajectory is empty"))?,
+        ),
+        TrajectoryCrs::Geographic => None,
+    };
+    let ellipsoid = config.ellipsoid.ellipsoid();
+    let records = trajectory.decimate(interval).into_iter().map(|point| {
+        let geodetic = Point::new(point.longitude, point.latitude, point.altitude);
+        let position = match utm_zone {
+            Some(utm_zone) => ellipsoid.geodetic_to_projected(geodetic, utm_zone),
+            None => Point::new(
+                point.longitude.to_degrees(),
+                point.latitude.to_degrees(),
+                point.altitude,
+            ),
+        };
+        TrajectoryPoint {
+            time: point.time,
+            x: position.x,
+            y: position.y,
+            z: position.z,
+            roll: point.roll.to_degrees(),
+            pitch: point.pitch.to_degrees(),
+            yaw: point.yaw.to_degrees(),
+            speed: Point::new(point.x_velocity, point.y_velocity, point.z_velocity).norm(),
+        }
+    });
+    match output.format {
+        OutputFormat::Kml => {
+            return Err(anyhow!(
+                "kml output is only supported by the coverage subcommand"
+            ))
+        }
+        OutputFormat::Geojson => {
+            let features = records
+                .map(|record| {
+                    serde_json::json!({
+                        "type": "Feature",
+                        "geometry": {
+                            "type": "Point",
+                            "coordinates": [record.x, record.y, record.z],
+                        },
+                        "properties": record,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut collection = serde_json::json!({
+                "type": "FeatureCollection",
+                "features": features,
+            });
+            // GeoJSON is longitude and latitude unless told otherwise, which
+            // only has a well-known EPSG code for WGS84 UTM zones.
+            if let (Some(utm_zone), None | Some(Datum::Wgs84)) = (utm_zone, config.datum) {
+                collection["crs"] = serde_json::json!({
+                    "type": "name",
+                    "properties": {"name": format!("urn:ogc:def:crs:EPSG::326{:02}", utm_zone)},
+                });
+            }
+            let mut write = output.write()?;
+            serde_json::to_writer(&mut write, &collection)?;
+            writeln!(write)?;
+        }
+        OutputFormat::Las => {
+            if utm_zone.is_none() {
+                return Err(anyhow!("las trajectory output requires a utm crs"));
+            }
+            let mut output = match format {
+                Format::Las => output.for_input(&paths[0], format)?,
+                Format::Csv | Format::Parquet => output,
+            };
+            // Keep the input's transforms and CRS, but only write positions and times.
+            if let Some(header) = output.header.take() {
+                let mut builder = las::Builder::from(header);
+                builder.point_format = las::point::Format::new(1)?;
+                output.header = Some(builder.into_header()?);
+            }
+            let mut writer = output.las()?;
+            for record in records {
+                let point = las::Point {
+                    x: record.x,
+                    y: record.y,
+                    z: record.z,
+                    gps_time: Some(record.time),
+                    ..Default::default()
+                };
+                las::Write::write(&mut writer, point)?;
+            }
+            writer.close()?;
+        }
+        OutputFormat::Csv | OutputFormat::Jsonl | OutputFormat::Parquet => {
+            let mut writer = output.records()?;
+            for record in records {
+                writer.write(record)?;
+            }
+            writer.finish()?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the flightlines as a GeoJSON feature collection, with a line and a polygon for each.
+fn coverage_geojson(flightlines: &[coverage::Flightline]) -> serde_json::Value {
+    let coordinates = |points: &[Point]| points.iter().map(|p| [p.x, p.y, p.z]).collect::<Vec<_>>();
+    let features = flightlines
+        .iter()
+        .flat_map(|flightline| {
+            let properties = |kind: &str| {
+                serde_json::json!({
+                    "kind": kind,
+                    "point_source_id": flightline.point_source_id,
+                    "count": flightline.count,
+                    "start_time": flightline.start_time,
+                    "end_time": flightline.end_time,
+                })
+            };
+            [
+                serde_json::json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "LineString",
+                        "coordinates": coordinates(&flightline.trajectory),
+                    },
+                    "properties": properties("trajectory"),
+                }),
+                serde_json::json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [coordinates(&flightline.footprint)],
+                    },
+                    "properties": properties("footprint"),
+                }),
+            ]
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+/// Writes the flightlines as a KML document, with a line and a polygon placemark for each.
+fn write_coverage_kml(
+    write: &mut dyn Write,
+    flightlines: &[coverage::Flightline],
+) -> Result<(), Error> {
+    let coordinates = |points: &[Point]| {
+        points
+            .iter()
+            .map(|p| format!("{},{},{}", p.x, p.y, p.z))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    writeln!(write, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(write, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(write, "<Document>")?;
+    for flightline in flightlines {
+        let name = match flightline.point_source_id {
+            Some(id) => format!("flightline {}", id),
+            None => "flightline".to_string(),
+        };
+        writeln!(write, "<Placemark><name>{} trajectory</name>", name)?;
+        writeln!(
+            write,
+            "<LineString><altitudeMode>absolute</altitudeMode><coordinates>{}</coordinates></LineString>",
+            coordinates(&flightline.trajectory)
+        )?;
+        writeln!(write, "</Placemark>")?;
+        writeln!(write, "<Placemark><name>{} footprint</name>", name)?;
+        writeln!(
+            write,
+            "<Polygon><outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs></Polygon>",
+            coordinates(&flightline.footprint)
+        )?;
+        writeln!(write, "</Placemark>")?;
+    }
+    writeln!(write, "</Document>")?;
+    writeln!(write, "</kml>")?;
+    Ok(())
+}
+
+/// Writes the adjustment history, one row or object per iteration.
+fn write_history(
+    write: File,
+    history: &[leeward::adjust::Record],
+    format: HistoryFormat,
+) -> Result<(), Error> {
+    match format {
+        HistoryFormat::Csv => {
+            let mut writer = Writer::from_writer(write);
+            if let Some(record) = history.first() {
+                writer.write_field("iteration")?;
+                writer.write_record(record.columns())?;
+            }
+            for (iteration, record) in history.iter().enumerate() {
+                writer.write_field(iteration.to_string())?;
+                writer.write_record(record.row().iter().map(f64::to_string))?;
+            }
+            writer.flush()?;
+        }
+        HistoryFormat::Json => {
+            let iterations = history
+                .iter()
+                .enumerate()
+                .map(|(iteration, record)| {
+                    let mut object = serde_json::Map::new();
+                    object.insert("iteration".to_string(), iteration.into());
+                    if let serde_json::Value::Object(record) = serde_json::to_value(record)? {
+                        object.extend(record);
+                    }
+                    Ok(serde_json::Value::Object(object))
+                })
+                .collect::<Result<Vec<_>, serde_json::Error>>()?;
+            let mut write = BufWriter::new(write);
+            serde_json::to_writer_pretty(&mut write, &iterations)?;
+            writeln!(write)?;
+        }
+    }
+    Ok(())
+}
+
+fn report(path: Option<&PathBuf>, count: usize, summary: ReadSummary) {
+    let prefix = if let Some(path) = path {
+        eprintln!("{}: read {} measurements", path.display(), count);
+        format!("{}: ", path.display())
     } else {
-        Box::new(std::io::stdout())
+        String::new()
     };
-    match args.command {
-        Command::Adjust { history } => {
-            let adjust = Adjust::new(measurements)?.adjust()?;
-            writeln!(write, "{}", toml::to_string_pretty(&adjust.config())?)?;
+    if let Some(conflict) = summary.crs_conflict {
+        eprintln!("{}warning: {}, using the config", prefix, conflict);
+    }
+    if summary.reconstructed > 0 {
+        eprintln!(
+            "{}reconstructed approximate times for {} points",
+            prefix, summary.reconstructed
+        );
+    }
+    if summary.skipped > 0 {
+        eprintln!(
+            "{}skipped {} points without a matching trajectory point",
+            prefix, summary.skipped
+        );
+    }
+    if summary.nearest > 0 {
+        eprintln!(
+            "{}used the nearest trajectory point for {} points",
+            prefix, summary.nearest
+        );
+    }
+    if summary.filtered > 0 {
+        eprintln!("{}filtered out {} points", prefix, summary.filtered);
+    }
+}
+
+fn run<L: PointSource>(
+    command: Command,
+    measurements: Vec<Measurement<L>>,
+    format: Format,
+    output: Output,
+    dem: Option<&Dem>,
+    sbets: &[PathBuf],
+) -> Result<(), Error> {
+    match command {
+        Command::Adjust {
+            history,
+            history_format,
+            classes,
+            variables,
+            lever_arm,
+            fix,
+            bound,
+            initial,
+            resume,
+            checkpoint,
+            cross_validate,
+            outlier_rejection,
+            adjust_scan_angle,
+            strips,
+        } => {
+            let mut measurements = measurements;
+            if adjust_scan_angle {
+                for measurement in &mut measurements {
+                    measurement.use_las_scan_angle(true);
+                }
+            }
+            let mut adjust = if let Some(resume) = resume {
+                Adjust::from_checkpoint(measurements, Checkpoint::from_path(resume)?)?
+            } else {
+                Adjust::new(measurements)?
+            };
+            if let Some(initial) = initial {
+                adjust = adjust.with_initial(&Config::from_path(initial)?)?;
+            }
+            if !classes.is_empty() {
+                adjust = adjust.with_classes(&classes)?;
+            }
+            if lever_arm {
+                adjust.adjust_lever_arm(true);
+            } else if !variables.is_empty() {
+                adjust.adjust_variables(&variables)?;
+            }
+            // Don't drop scan angle variables that were asked for by name.
+            if adjust_scan_angle || variables.is_empty() {
+                adjust.adjust_scan_angle(adjust_scan_angle);
+            }
+            for variable in fix {
+                adjust.fix(variable);
+            }
+            for bound in bound {
+                adjust.bound(bound.variable, bound.min, bound.max)?;
+            }
+            if let Some(n_sigma) = outlier_rejection {
+                adjust.outlier_rejection(n_sigma)?;
+            }
+            if strips {
+                adjust.adjust_strips(true);
+            }
+            let folds = cross_validate
+                .map(|k| adjust.cross_validate(k))
+                .transpose()?;
+            let adjust = adjust.adjust()?;
+            if let Some(checkpoint) = checkpoint {
+                adjust.checkpoint().to_path(checkpoint)?;
+            }
+            let mut write = output.write()?;
+            writeln!(write, "{}", adjust.config().to_toml_string_documented()?)?;
+            if let Some(record) = adjust.history().last() {
+                // Comments, so the output is still a valid config.
+                writeln!(write, "# Standard deviations of the adjusted variables:")?;
+                for (&variable, sigma) in record.variables.iter().zip(&record.sigmas) {
+                    writeln!(write, "# {} = {}", variable, sigma)?;
+                }
+            }
+            if !adjust.strips().is_empty() {
+                writeln!(
+                    write,
+                    "# Strip roll, pitch, and yaw corrections, with their standard deviations:"
+                )?;
+                for strip in adjust.strips() {
+                    let [roll, pitch, yaw] = strip.correction;
+                    let [sigma_roll, sigma_pitch, sigma_yaw] = strip.sigmas;
+                    writeln!(
+                        write,
+                        "# {} = {} ± {}, {} ± {}, {} ± {}",
+                        strip.name(),
+                        roll,
+                        sigma_roll,
+                        pitch,
+                        sigma_pitch,
+                        yaw,
+                        sigma_yaw
+                    )?;
+                }
+            }
+            if outlier_rejection.is_some() {
+                let rejected: usize = adjust.history().iter().map(|r| r.rejected).sum();
+                writeln!(write, "# Rejected {} outliers", rejected)?;
+            }
+            if let Some(folds) = folds {
+                writeln!(write, "# Cross-validation rmse (training, validation):")?;
+                for fold in folds {
+                    writeln!(
+                        write,
+                        "# fold {} = {}, {}",
+                        fold.fold, fold.training_rmse, fold.validation_rmse
+                    )?;
+                }
+            }
             if let Some(history) = history {
-                let mut writer = File::create(history).map(Writer::from_writer)?;
-                for (iteration, record) in adjust.history().iter().enumerate() {
-                    writer.serialize(Record::new(iteration, record))?;
+                write_history(File::create(history)?, adjust.history(), history_format)?;
+            }
+        }
+        Command::Agl { interval, radius } => {
+            let trajectory = Trajectory::from_paths(sbets)?;
+            let options = agl::Options { interval, radius };
+            let config = match measurements.first() {
+                Some(measurement) => measurement.config(),
+                None => return Err(anyhow!("no measurements to compute the ground from")),
+            };
+            let profile = if let Some(dem) = dem {
+                agl::from_dem(&trajectory, dem, &config, &options)?
+            } else {
+                let points = measurements
+                    .iter()
+                    .map(|m| Point::new(m.x(), m.y(), m.z()))
+                    .collect();
+                agl::from_points(&trajectory, points, &config, &options)?
+            };
+            let mut writer = output.records()?;
+            for agl in profile {
+                writer.write(agl)?;
+            }
+            writer.finish()?;
+        }
+        Command::Backconvert => {
+            if let OutputFormat::Las = output.format {
+                let mut writer = output.las()?;
+                for measurement in &measurements {
+                    let backconvert = Backconvert::new(measurement);
+                    let mut point = measurement.lasish().to_las();
+                    point.x = backconvert.modeled_x;
+                    point.y = backconvert.modeled_y;
+                    point.z = backconvert.modeled_z;
+                    las::Write::write(&mut writer, point)?;
+                }
+                writer.close()?;
+            } else {
+                let mut writer = output.records()?;
+                for measurement in &measurements {
+                    writer.write(Backconvert::new(measurement))?;
                 }
+                writer.finish()?;
             }
         }
-        Command::BestFitPlane {} => {
-            let mut writer = Writer::from_writer(write);
-            for result in measurements.into_iter().map(|m| BodyFrame::new(&m)) {
-                let body_frame = result?;
-                writer.serialize(body_frame)?;
+        Command::BestFitPlane => {
+            let plane = utils::fit_to_plane_in_body_frame(&measurements)?;
+            let header = [
+                format!(
+                    "normal = [{}, {}, {}]",
+                    plane.normal.x, plane.normal.y, plane.normal.z
+                ),
+                format!(
+                    "centroid = [{}, {}, {}]",
+                    plane.centroid.x, plane.centroid.y, plane.centroid.z
+                ),
+                format!("rms = {}", plane.rms),
+            ];
+            let mut writer: Box<dyn OutputSink<PlanePoint>> =
+                if let OutputFormat::Csv = output.format {
+                    let mut write = output.write()?;
+                    for line in &header {
+                        writeln!(write, "# {}", line)?;
+                    }
+                    Box::new(CsvSink::new(write))
+                } else {
+                    for line in &header {
+                        eprintln!("{}", line);
+                    }
+                    output.records()?
+                };
+            for point in plane.points {
+                writer.write(PlanePoint::from(point))?;
             }
+            writer.finish()?;
         }
-        Command::BodyFrame {} => {
-            let mut writer = Writer::from_writer(write);
-            for point in utils::fit_to_plane_in_body_frame(&measurements) {
-                writer.serialize(point)?;
+        Command::BodyFrame { neighbors } => {
+            let normals = match neighbors {
+                Some(k) => leeward::normals_from_neighbors(&measurements, k)?,
+                None => measurements.iter().map(|m| normal(m, dem)).collect(),
+            };
+            let mut writer = output.records()?;
+            for (measurement, normal) in measurements.iter().zip(normals) {
+                writer.write(BodyFrame::new(measurement, normal)?)?;
             }
+            writer.finish()?;
         }
-        Command::Tpu {} => {
-            let mut writer = Writer::from_writer(write);
-            for tpu in measurements.into_iter().flat_map(Tpu::new) {
-                writer.serialize(tpu)?;
+        Command::CompareConfigs { other } => {
+            let first = measurements
+                .first()
+                .map(|measurement| measurement.config())
+                .ok_or_else(|| anyhow!("no measurements to compare"))?;
+            let second = Config::from_path(other)?;
+            let mut writer = output.records()?;
+            for difference in compare::compare(&measurements, first, second, dem)? {
+                writer.write(difference)?;
+            }
+            writer.finish()?;
+        }
+        Command::Coverage { interval } => {
+            if interval.is_nan() || interval < 0. {
+                return Err(anyhow!("the interval can't be negative, got {}", interval));
+            }
+            let trajectory = Trajectory::from_paths(sbets)?;
+            let flightlines = coverage::flightlines(&measurements, &trajectory, interval)?;
+            let mut write = output.write()?;
+            match output.format {
+                OutputFormat::Geojson => {
+                    serde_json::to_writer(&mut write, &coverage_geojson(&flightlines))?;
+                    writeln!(write)?;
+                }
+                OutputFormat::Kml => write_coverage_kml(&mut write, &flightlines)?,
+                _ => return Err(anyhow!("coverage output must be geojson or kml")),
+            }
+        }
+        Command::Gcp { gcps, radius, k } => {
+            let gcps = gcp::read_csv(gcps)?;
+            let options = gcp::Options { radius, k };
+            let mut writer = output.records()?;
+            for comparison in gcp::compare(&gcps, &measurements, &options)? {
+                writer.write(comparison)?;
+            }
+            writer.finish()?;
+        }
+        Command::Info => {
+            let mut flightlines = BTreeMap::<Option<u16>, Vec<&Measurement<L>>>::new();
+            for measurement in &measurements {
+                flightlines
+                    .entry(measurement.lasish().point_source_id())
+                    .or_default()
+                    .push(measurement);
+            }
+            let mut writer = output.records()?;
+            for (point_source_id, measurements) in flightlines {
+                writer.write(Info::new(point_source_id, &measurements))?;
+            }
+            writer.finish()?;
+        }
+        Command::Inspect { index } => {
+            let measurement = measurements.get(index).ok_or_else(|| {
+                anyhow!(
+                    "index {} is out of range for {} measurements",
+                    index,
+                    measurements.len()
+                )
+            })?;
+            let inspect = Inspect::new(index, measurement, dem)?;
+            if let OutputFormat::Jsonl = output.format {
+                let mut writer = output.records()?;
+                writer.write(inspect)?;
+                writer.finish()?;
+            } else {
+                write!(output.write()?, "{}", toml::to_string_pretty(&inspect)?)?;
+            }
+        }
+        Command::Profile {
+            scan_angle_bin,
+            range_bin,
+        } => {
+            if scan_angle_bin <= 0. || range_bin <= 0. {
+                return Err(anyhow!("bin widths must be positive"));
+            }
+            let mut bins: BTreeMap<(i64, i64), Vec<leeward::Tpu>> = BTreeMap::new();
+            for measurement in &measurements {
+                let tpu = tpu(measurement, dem)?;
+                let key = (
+                    (measurement.scan_angle().to_degrees() / scan_angle_bin).floor() as i64,
+                    (measurement.range() / range_bin).floor() as i64,
+                );
+                bins.entry(key).or_default().push(tpu);
+            }
+            let mut writer = output.records()?;
+            for ((scan_angle, range), tpus) in bins {
+                writer.write(Profile::new(
+                    scan_angle as f64 * scan_angle_bin,
+                    scan_angle_bin,
+                    range as f64 * range_bin,
+                    range_bin,
+                    &tpus,
+                ))?;
+            }
+            writer.finish()?;
+        }
+        Command::Residuals => {
+            let residuals: Vec<Residual> = measurements.iter().map(Residual::new).collect();
+            let count = residuals.len() as f64;
+            let mean = residuals.iter().map(|r| r.misfit).sum::<f64>() / count;
+            let std = (residuals
+                .iter()
+                .map(|r| (r.misfit - mean).powi(2))
+                .sum::<f64>()
+                / (count - 1.))
+                .sqrt();
+            let mut writer = output.records()?;
+            for mut residual in residuals {
+                if std > 0. {
+                    residual.misfit_z_score = (residual.misfit - mean) / std;
+                }
+                writer.write(residual)?;
+            }
+            writer.finish()?;
+        }
+        Command::ScanAngle { field } => {
+            if !matches!(output.format, OutputFormat::Las) {
+                return Err(anyhow!("scan angles can only be written to las"));
+            }
+            let mut builder = output.las_builder()?;
+            match field {
+                ScanAngleField::Extended => {
+                    builder.point_format.is_extended = true;
+                    builder.point_format.has_gps_time = true;
+                }
+                ScanAngleField::ExtraBytes => {
+                    leeward::sink::add_extra_bytes(&mut builder, "scan_angle")
+                }
+            }
+            let mut writer = las::Writer::from_path(output.outfile()?, builder.into_header()?)?;
+            for measurement in &measurements {
+                let mut point = measurement.lasish().to_las();
+                let scan_angle = measurement.scan_angle().to_degrees();
+                match field {
+                    ScanAngleField::Extended => point.scan_angle = scan_angle as f32,
+                    ScanAngleField::ExtraBytes => {
+                        point.extra_bytes.extend(scan_angle.to_le_bytes())
+                    }
+                }
+                las::Write::write(&mut writer, point)?;
+            }
+            writer.close()?;
+        }
+        Command::Sensitivity { sigma } => {
+            let mut writer = output.records()?;
+            for variable in Variable::iter() {
+                let sigmas: Vec<f64> = measurements
+                    .iter()
+                    .map(|m| sigma * m.sigma(variable, normal(m, dem)))
+                    .collect();
+                if sigmas.iter().all(|&sigma| sigma == 0.) {
+                    continue;
+                }
+                for (direction, sign) in [("+", 1.), ("-", -1.)] {
+                    let displacements: Vec<Point> = measurements
+                        .iter()
+                        .zip(&sigmas)
+                        .map(|(m, &sigma)| {
+                            m.adjusted(variable, sign * sigma).modeled_point() - m.modeled_point()
+                        })
+                        .collect();
+                    writer.write(Sensitivity::new(
+                        variable,
+                        direction,
+                        &sigmas,
+                        &displacements,
+                    ))?;
+                }
+            }
+            writer.finish()?;
+        }
+        Command::StripDiff {
+            reference,
+            radius,
+            min_points,
+            max_roughness,
+            samples,
+        } => {
+            let reference = tabular::read_points(reference, format)?;
+            let options = strip::Options {
+                radius,
+                min_points,
+                max_roughness,
+            };
+            let strip_samples = strip::samples(&measurements, &reference, &options);
+            if let Some(samples) = samples {
+                let mut writer = File::create(samples).map(Writer::from_writer)?;
+                for sample in &strip_samples {
+                    writer.serialize(sample)?;
+                }
+            }
+            let mut writer = output.records()?;
+            writer.write(strip::summarize(&strip_samples)?)?;
+            writer.finish()?;
+        }
+        Command::Trajectory { .. } => {
+            unreachable!("the trajectory is exported before any points are read")
+        }
+        Command::InitConfig(_) => {
+            unreachable!("the config is written before any points are read")
+        }
+        Command::Tpu {
+            confidence,
+            summary: true,
+            bucket_width,
+            ..
+        } => {
+            if bucket_width.is_nan() || bucket_width <= 0. {
+                return Err(anyhow!("bucket width must be positive"));
             }
+            check_confidence(confidence)?;
+            let mut horizontal = Histogram::new(bucket_width);
+            let mut vertical = Histogram::new(bucket_width);
+            let mut total = Histogram::new(bucket_width);
+            for measurement in measurements {
+                let tpu = Tpu::new(measurement, dem, confidence, false, None)?;
+                horizontal.add(tpu.horizontal);
+                vertical.add(tpu.vertical);
+                total.add(tpu.total);
+            }
+            let summary = TpuSummary {
+                count: total.count,
+                horizontal: horizontal.statistics(),
+                vertical: vertical.statistics(),
+                total: total.statistics(),
+            };
+            let mut write = output.write()?;
+            serde_json::to_writer_pretty(&mut write, &summary)?;
+            writeln!(write)?;
+        }
+        Command::Tpu {
+            confidence,
+            compare_propagation,
+            roughness_radius,
+            ..
+        } => {
+            check_confidence(confidence)?;
+            let roughness = match roughness_radius {
+                Some(radius) => utils::roughness(&measurements, radius)
+                    .into_iter()
+                    .map(Some)
+                    .collect(),
+                None => vec![None; measurements.len()],
+            };
+            let mut writer = output.records()?;
+            for (measurement, roughness) in measurements.into_iter().zip(roughness) {
+                let tpu = Tpu::new(measurement, dem, confidence, compare_propagation, roughness)?;
+                writer.write(tpu)?;
+            }
+            writer.finish()?;
         }
     }
     Ok(())
 }
 
+impl Output {
+    /// Returns the output for an input file, with its templated outfile and its las header.
+    fn for_input(&self, path: &Path, format: Format) -> Result<Output, Error> {
+        let outfile = if let Some(template) = &self.template {
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy())
+                .unwrap_or_default();
+            Some(PathBuf::from(template.replace("{stem}", &stem)))
+        } else {
+            self.outfile.clone()
+        };
+        let header = if let Format::Las = format {
+            Some(las::Read::header(&las::Reader::from_path(path)?).clone())
+        } else {
+            None
+        };
+        Ok(Output {
+            outfile,
+            format: self.format,
+            header,
+            template: None,
+        })
+    }
+
+    fn write(&self) -> Result<Box<dyn Write + Send>, Error> {
+        if let Some(outfile) = &self.outfile {
+            Ok(Box::new(File::create(outfile)?))
+        } else {
+            Ok(Box::new(std::io::stdout()))
+        }
+    }
+
+    fn records<T: Serialize + 'static>(&self) -> Result<Box<dyn OutputSink<T>>, Error> {
+        match self.format {
+            OutputFormat::Csv => Ok(Box::new(CsvSink::new(self.write()?))),
+            OutputFormat::Jsonl => Ok(Box::new(JsonlSink::new(self.write()?))),
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => Ok(Box::new(leeward::sink::ParquetSink::new(self.write()?))),
+            #[cfg(not(feature = "parquet"))]
+            OutputFormat::Parquet => Err(anyhow!("leeward was built without parquet support")),
+            OutputFormat::Las => Ok(Box::new(LasSink::new(
+                BufWriter::new(File::create(self.outfile()?)?),
+                self.las_builder()?,
+            ))),
+            OutputFormat::Geojson | OutputFormat::Kml => Err(anyhow!(
+                "geojson and kml output are only supported by the coverage and trajectory subcommands"
+            )),
+        }
+    }
+
+    fn las(&self) -> Result<las::Writer<BufWriter<File>>, Error> {
+        let header = self.las_builder()?.into_header()?;
+        las::Writer::from_path(self.outfile()?, header).map_err(Error::from)
+    }
+
+    fn outfile(&self) -> Result<&PathBuf, Error> {
+        self.outfile
+            .as_ref()
+            .ok_or(anyhow!("las output requires an outfile"))
+    }
+
+    fn las_builder(&self) -> Result<las::Builder, Error> {
+        let mut builder = if let Some(header) = &self.header {
+            las::Builder::from(header.clone())
+        } else {
+            let mut builder = las::Builder::from((1, 4));
+            builder.point_format = las::point::Format::new(1)?;
+            // Centimeter precision covers any UTM coordinate without an offset.
+            let transform = las::Transform {
+                scale: 0.01,
+                offset: 0.,
+            };
+            builder.transforms = las::Vector {
+                x: transform,
+                y: transform,
+                z: transform,
+            };
+            builder
+        };
+        // Las 1.4 is the most permissive about return numbers, and legacy
+        // point formats (and their CRS vlrs) are still valid.
+        builder.version = las::Version::new(1, 4);
+        Ok(builder)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TpuSummary {
+    count: usize,
+    horizontal: Option<Statistics>,
+    vertical: Option<Statistics>,
+    total: Option<Statistics>,
+}
+
+#[derive(Debug, Serialize)]
+struct Sensitivity {
+    variable: Variable,
+    direction: &'static str,
+    sigma_mean: f64,
+    count: usize,
+    horizontal_mean: f64,
+    horizontal_p95: f64,
+    vertical_mean: f64,
+    vertical_p95: f64,
+    total_mean: f64,
+    total_p95: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Statistics {
+    min: f64,
+    mean: f64,
+    median: f64,
+    p95: f64,
+    max: f64,
+    histogram: Vec<Bucket>,
+}
+
+#[derive(Debug, Serialize)]
+struct Bucket {
+    min: f64,
+    max: f64,
+    count: usize,
+}
+
+/// Accumulates values in a single pass, without holding on to them.
+struct Histogram {
+    width: f64,
+    count: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+    buckets: BTreeMap<i64, usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct Backconvert {
+    time: f64,
+    x: f64,
+    y: f64,
+    z: f64,
+    modeled_x: f64,
+    modeled_y: f64,
+    modeled_z: f64,
+    misfit_x: f64,
+    misfit_y: f64,
+    misfit_z: f64,
+    misfit: f64,
+}
+
 #[derive(Debug, Serialize)]
 struct BodyFrame {
     time: f64,
@@ -112,13 +1768,128 @@ struct BodyFrame {
     range: f64,
     scan_angle_computed: f64,
     scan_angle_las: f64,
+    incidence_angle: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Info {
+    point_source_id: Option<u16>,
+    count: usize,
+    start_time: f64,
+    end_time: f64,
+    swath_width: f64,
+    length: f64,
+    density: f64,
+    scan_angle_min: f64,
+    scan_angle_max: f64,
+    range_min: f64,
+    range_mean: f64,
+    range_max: f64,
+    agl: f64,
+    speed: f64,
+    roll_compensated: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct Inspect {
+    index: usize,
+    range: f64,
+    scan_angle_computed: f64,
+    scan_angle_las: f64,
+    point: InspectPoint,
+    pose: Pose,
+    body_frame: PlanePoint,
+    modeled_body_frame: PlanePoint,
+    modeled_point: PlanePoint,
+    residuals: PlanePoint,
+    jacobian: BTreeMap<String, PlanePoint>,
+    tpu: Tpu,
+    range_error: RangeError,
+}
+
+#[derive(Debug, Serialize)]
+struct InspectPoint {
+    x: f64,
+    y: f64,
+    z: f64,
+    time: Option<f64>,
+    scan_angle: f64,
+    intensity: Option<u16>,
+    classification: Option<u8>,
+    return_number: Option<u8>,
+    number_of_returns: Option<u8>,
+    point_source_id: Option<u16>,
+    user_data: Option<u8>,
+}
+
+#[derive(Debug, Serialize)]
+struct Pose {
+    time: f64,
+    longitude: f64,
+    latitude: f64,
+    altitude: f64,
+    roll: f64,
+    pitch: f64,
+    yaw: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct PlanePoint {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bound {
+    variable: Variable,
+    min: f64,
+    max: f64,
 }
 
 #[derive(Debug, Serialize)]
-struct Record {
-    iteration: usize,
-    rmse: f64,
-    config: Config,
+struct Profile {
+    scan_angle_min: f64,
+    scan_angle_max: f64,
+    range_min: f64,
+    range_max: f64,
+    count: usize,
+    horizontal_mean: f64,
+    horizontal_p95: f64,
+    vertical_mean: f64,
+    vertical_p95: f64,
+    total_mean: f64,
+    total_p95: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Residual {
+    time: f64,
+    x: f64,
+    y: f64,
+    z: f64,
+    range: f64,
+    scan_angle: f64,
+    body_frame_x: f64,
+    body_frame_y: f64,
+    body_frame_z: f64,
+    projected_x: f64,
+    projected_y: f64,
+    projected_z: f64,
+    misfit: f64,
+    misfit_z_score: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct TrajectoryPoint {
+    time: f64,
+    x: f64,
+    y: f64,
+    z: f64,
+    roll: f64,
+    pitch: f64,
+    yaw: f64,
+    speed: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -131,11 +1902,54 @@ struct Tpu {
     vertical: f64,
     total: f64,
     incidence_angle: f64,
+    footprint_major: Option<f64>,
+    footprint_minor: Option<f64>,
+    sigma_along: f64,
+    sigma_cross: f64,
+    quality: TpuQuality,
+    speed: Option<f64>,
+    roll_rate: Option<f64>,
+    pitch_rate: Option<f64>,
+    yaw_rate: Option<f64>,
+    agl: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    propagation_horizontal_difference: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    propagation_vertical_difference: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    roughness: Option<Option<f64>>,
+    return_number: Option<u8>,
+    number_of_returns: Option<u8>,
+    range_uncertainty: f64,
+}
+
+impl Backconvert {
+    fn new<L: PointSource>(measurement: &Measurement<L>) -> Backconvert {
+        let modeled = measurement.modeled_point();
+        let misfit = modeled - Point::new(measurement.x(), measurement.y(), measurement.z());
+        Backconvert {
+            time: measurement.time(),
+            x: measurement.x(),
+            y: measurement.y(),
+            z: measurement.z(),
+            modeled_x: modeled.x,
+            modeled_y: modeled.y,
+            modeled_z: modeled.z,
+            misfit_x: misfit.x,
+            misfit_y: misfit.y,
+            misfit_z: misfit.z,
+            misfit: misfit.norm(),
+        }
+    }
 }
 
 impl BodyFrame {
-    fn new<L: Lasish>(measurement: &Measurement<L>) -> Result<BodyFrame, Error> {
+    fn new<L: PointSource>(
+        measurement: &Measurement<L>,
+        normal: Point,
+    ) -> Result<BodyFrame, Error> {
         let body_frame = measurement.body_frame();
+        let incidence_angle = measurement.incidence_angle(normal);
         let modeled_body_frame_computed = measurement.modeled_body_frame();
         let scan_angle_computed = measurement.scan_angle();
         let mut measurement = measurement.clone();
@@ -162,23 +1976,374 @@ impl BodyFrame {
             range: measurement.range(),
             scan_angle_computed,
             scan_angle_las,
+            incidence_angle,
+        })
+    }
+}
+
+impl Info {
+    fn new<L: PointSource>(point_source_id: Option<u16>, measurements: &[&Measurement<L>]) -> Info {
+        let count = measurements.len();
+        let mean = |values: &mut dyn Iterator<Item = f64>| {
+            let (sum, n) = values.fold((0., 0), |(sum, n), value| (sum + value, n + 1));
+            sum / n as f64
+        };
+        let (start_time, end_time) = measurements
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(start, end), m| {
+                (start.min(m.time()), end.max(m.time()))
+            });
+        let mut offsets: Vec<f64> = measurements
+            .iter()
+            .map(|m| {
+                let navigation = m.navigation_frame();
+                navigation.y * m.yaw().cos() - navigation.x * m.yaw().sin()
+            })
+            .collect();
+        offsets.sort_by(f64::total_cmp);
+        let percentile = |p: f64| offsets[(p * (count - 1) as f64).round() as usize];
+        let swath_width = percentile(0.99) - percentile(0.01);
+        let speed = mean(
+            &mut measurements
+                .iter()
+                .filter_map(|m| m.rates())
+                .map(|rates| rates.north.hypot(rates.east)),
+        );
+        let length = speed * (end_time - start_time);
+        let scan_angles = measurements.iter().map(|m| m.scan_angle().to_degrees());
+        let ranges = measurements.iter().map(|m| m.range());
+        Info {
+            point_source_id,
+            count,
+            start_time,
+            end_time,
+            swath_width,
+            length,
+            density: count as f64 / (swath_width * length),
+            scan_angle_min: scan_angles.clone().fold(f64::INFINITY, f64::min),
+            scan_angle_max: scan_angles.fold(f64::NEG_INFINITY, f64::max),
+            range_min: ranges.clone().fold(f64::INFINITY, f64::min),
+            range_mean: mean(&mut ranges.clone()),
+            range_max: ranges.fold(f64::NEG_INFINITY, f64::max),
+            agl: mean(&mut measurements.iter().map(|m| m.platform().z - m.z())),
+            speed,
+            roll_compensated: leeward::detect_roll_compensation(measurements.iter().copied()),
+        }
+    }
+}
+
+impl Inspect {
+    fn new<L: PointSource>(
+        index: usize,
+        measurement: &Measurement<L>,
+        dem: Option<&Dem>,
+    ) -> Result<Inspect, Error> {
+        let point = measurement.lasish();
+        let platform = measurement.platform();
+        let mut jacobian = BTreeMap::new();
+        for variable in Variable::iter() {
+            let partials: Vec<f64> = Dimension::iter()
+                .map(|dimension| measurement.partial_derivative_in_body_frame(dimension, variable))
+                .collect();
+            jacobian.insert(
+                variable.to_string(),
+                PlanePoint::from(Point::from_vec(partials)),
+            );
+        }
+        let mut las_scan_angle = measurement.clone();
+        las_scan_angle.use_las_scan_angle(true);
+        Ok(Inspect {
+            index,
+            range: measurement.range(),
+            scan_angle_computed: measurement.scan_angle(),
+            scan_angle_las: las_scan_angle.scan_angle(),
+            point: InspectPoint {
+                x: point.x(),
+                y: point.y(),
+                z: point.z(),
+                time: point.time(),
+                scan_angle: point.scan_angle(),
+                intensity: point.intensity(),
+                classification: point.classification(),
+                return_number: point.return_number(),
+                number_of_returns: point.number_of_returns(),
+                point_source_id: point.point_source_id(),
+                user_data: point.user_data(),
+            },
+            pose: Pose {
+                time: measurement.time(),
+                longitude: platform.x.to_degrees(),
+                latitude: platform.y.to_degrees(),
+                altitude: platform.z,
+                roll: measurement.roll(),
+                pitch: measurement.pitch(),
+                yaw: measurement.yaw(),
+            },
+            body_frame: measurement.body_frame().into(),
+            modeled_body_frame: measurement.modeled_body_frame().into(),
+            modeled_point: measurement.modeled_point().into(),
+            residuals: measurement.residuals().into(),
+            jacobian,
+            tpu: Tpu::new(measurement.clone(), dem, None, false, None)?,
+            range_error: measurement.range_error(normal(measurement, dem)),
         })
     }
 }
 
-impl Record {
-    fn new(iteration: usize, record: &leeward::adjust::Record) -> Record {
-        Record {
-            iteration,
-            rmse: record.rmse,
-            config: record.config,
+impl From<Point> for PlanePoint {
+    fn from(point: Point) -> PlanePoint {
+        PlanePoint {
+            x: point.x,
+            y: point.y,
+            z: point.z,
         }
     }
 }
 
+impl FromStr for Bound {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Bound, Error> {
+        let mut parts = s.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(variable), Some(min), Some(max)) => Ok(Bound {
+                variable: variable.parse()?,
+                min: min.parse()?,
+                max: max.parse()?,
+            }),
+            _ => Err(anyhow!("bounds must be name:min:max, got {}", s)),
+        }
+    }
+}
+
+impl FromStr for TrajectoryCrs {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<TrajectoryCrs, Error> {
+        match s.split_once(':') {
+            None if s == "utm" => Ok(TrajectoryCrs::Utm(None)),
+            None if s == "geographic" => Ok(TrajectoryCrs::Geographic),
+            Some(("utm", zone)) => match zone.parse()? {
+                zone @ 1..=60 => Ok(TrajectoryCrs::Utm(Some(zone))),
+                zone => Err(anyhow!("utm zones are between 1 and 60, got {}", zone)),
+            },
+            _ => Err(anyhow!(
+                "the crs must be utm, utm:ZONE, or geographic, got {}",
+                s
+            )),
+        }
+    }
+}
+
+impl Profile {
+    fn new(
+        scan_angle_min: f64,
+        scan_angle_bin: f64,
+        range_min: f64,
+        range_bin: f64,
+        tpus: &[leeward::Tpu],
+    ) -> Profile {
+        let (horizontal_mean, horizontal_p95) = summarize(tpus.iter().map(|t| t.horizontal));
+        let (vertical_mean, vertical_p95) = summarize(tpus.iter().map(|t| t.vertical));
+        let (total_mean, total_p95) = summarize(tpus.iter().map(|t| t.total));
+        Profile {
+            scan_angle_min,
+            scan_angle_max: scan_angle_min + scan_angle_bin,
+            range_min,
+            range_max: range_min + range_bin,
+            count: tpus.len(),
+            horizontal_mean,
+            horizontal_p95,
+            vertical_mean,
+            vertical_p95,
+            total_mean,
+            total_p95,
+        }
+    }
+}
+
+impl Residual {
+    fn new<L: PointSource>(measurement: &Measurement<L>) -> Residual {
+        let body_frame = measurement.residuals();
+        let projected = measurement.modeled_point()
+            - Point::new(measurement.x(), measurement.y(), measurement.z());
+        Residual {
+            time: measurement.time(),
+            x: measurement.x(),
+            y: measurement.y(),
+            z: measurement.z(),
+            range: measurement.range(),
+            scan_angle: measurement.scan_angle(),
+            body_frame_x: body_frame.x,
+            body_frame_y: body_frame.y,
+            body_frame_z: body_frame.z,
+            projected_x: projected.x,
+            projected_y: projected.y,
+            projected_z: projected.z,
+            misfit: projected.norm(),
+            misfit_z_score: 0.,
+        }
+    }
+}
+
+impl Histogram {
+    fn new(width: f64) -> Histogram {
+        Histogram {
+            width,
+            count: 0,
+            sum: 0.,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        *self
+            .buckets
+            .entry((value / self.width).floor() as i64)
+            .or_default() += 1;
+    }
+
+    /// Interpolates a quantile within its bucket, clamped to the observed range.
+    fn quantile(&self, quantile: f64) -> f64 {
+        let target = quantile * self.count as f64;
+        let mut seen = 0.;
+        for (&bucket, &count) in &self.buckets {
+            let count = count as f64;
+            if seen + count >= target {
+                let value = (bucket as f64 + (target - seen) / count) * self.width;
+                return value.clamp(self.min, self.max);
+            }
+            seen += count;
+        }
+        self.max
+    }
+
+    fn statistics(&self) -> Option<Statistics> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(Statistics {
+            min: self.min,
+            mean: self.sum / self.count as f64,
+            median: self.quantile(0.5),
+            p95: self.quantile(0.95),
+            max: self.max,
+            histogram: self
+                .buckets
+                .iter()
+                .map(|(&bucket, &count)| Bucket {
+                    min: bucket as f64 * self.width,
+                    max: (bucket + 1) as f64 * self.width,
+                    count,
+                })
+                .collect(),
+        })
+    }
+}
+
+impl Sensitivity {
+    fn new(
+        variable: Variable,
+        direction: &'static str,
+        sigmas: &[f64],
+        displacements: &[Point],
+    ) -> Sensitivity {
+        let (horizontal_mean, horizontal_p95) =
+            summarize(displacements.iter().map(|d| d.x.hypot(d.y)));
+        let (vertical_mean, vertical_p95) = summarize(displacements.iter().map(|d| d.z.abs()));
+        let (total_mean, total_p95) = summarize(displacements.iter().map(|d| d.norm()));
+        Sensitivity {
+            variable,
+            direction,
+            sigma_mean: sigmas.iter().sum::<f64>() / sigmas.len() as f64,
+            count: displacements.len(),
+            horizontal_mean,
+            horizontal_p95,
+            vertical_mean,
+            vertical_p95,
+            total_mean,
+            total_p95,
+        }
+    }
+}
+
+/// Returns the mean and the (nearest-rank) 95th percentile.
+fn summarize(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let rank = (0.95 * values.len() as f64).ceil() as usize;
+    (mean, values[rank.max(1) - 1])
+}
+
+/// Returns the surface normal from the DEM, or a horizontal surface's if there's no DEM or it doesn't cover the point.
+fn normal<L: PointSource>(measurement: &Measurement<L>, dem: Option<&Dem>) -> Point {
+    dem.and_then(|dem| dem.normal(measurement.x(), measurement.y()))
+        .unwrap_or_else(|| Point::new(0., 0., 1.))
+}
+
+/// Checks the confidence level once, so a bad one fails the command instead of every point.
+fn check_confidence(confidence: Option<f64>) -> Result<(), Error> {
+    match confidence {
+        Some(confidence) if confidence.is_nan() || confidence <= 0. || confidence >= 1. => {
+            Err(anyhow!(
+                "confidence must be between zero and one, got {}",
+                confidence
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Computes the total propagated uncertainty, flagging points that the DEM doesn't cover.
+fn tpu<L: PointSource>(
+    measurement: &Measurement<L>,
+    dem: Option<&Dem>,
+) -> Result<leeward::Tpu, leeward::Error> {
+    if let Some(dem) = dem {
+        measurement.tpu_with_dem(dem)
+    } else {
+        measurement.tpu(normal(measurement, None))
+    }
+}
+
 impl Tpu {
-    fn new<L: Lasish>(measurement: Measurement<L>) -> Result<Tpu, Error> {
-        let tpu = measurement.tpu(Point::new(0., 0., 1.))?;
+    fn new<L: PointSource>(
+        measurement: Measurement<L>,
+        dem: Option<&Dem>,
+        confidence: Option<f64>,
+        compare_propagation: bool,
+        roughness: Option<Option<f64>>,
+    ) -> Result<Tpu, Error> {
+        let normal = normal(&measurement, dem);
+        let with_confidence = |tpu: leeward::Tpu| -> Result<leeward::Tpu, Error> {
+            if let Some(confidence) = confidence {
+                Ok(tpu.with_confidence(confidence)?)
+            } else {
+                Ok(tpu)
+            }
+        };
+        let tpu = with_confidence(tpu(&measurement, dem)?)?;
+        let difference = if compare_propagation {
+            let mut config = measurement.config();
+            config.propagation = Propagation::Navigation;
+            let navigation =
+                with_confidence(self::tpu(&measurement.with_config(config.clone()), dem)?)?;
+            config.propagation = Propagation::Ecef;
+            let ecef = with_confidence(self::tpu(&measurement.with_config(config), dem)?)?;
+            Some((
+                ecef.horizontal - navigation.horizontal,
+                ecef.vertical - navigation.vertical,
+            ))
+        } else {
+            None
+        };
+        let footprint = measurement.footprint(normal);
+        let rates = measurement.rates();
         Ok(Tpu {
             x: measurement.x(),
             y: measurement.y(),
@@ -188,6 +2353,22 @@ impl Tpu {
             vertical: tpu.vertical,
             total: tpu.total,
             incidence_angle: tpu.incidence_angle,
+            footprint_major: footprint.map(|footprint| footprint.major),
+            footprint_minor: footprint.map(|footprint| footprint.minor),
+            sigma_along: tpu.sigma_along,
+            sigma_cross: tpu.sigma_cross,
+            quality: tpu.quality,
+            speed: rates.map(|rates| rates.north.hypot(rates.east)),
+            roll_rate: rates.map(|rates| rates.roll),
+            pitch_rate: rates.map(|rates| rates.pitch),
+            yaw_rate: rates.map(|rates| rates.yaw),
+            agl: measurement.platform().z - measurement.z(),
+            propagation_horizontal_difference: difference.map(|(horizontal, _)| horizontal),
+            propagation_vertical_difference: difference.map(|(_, vertical)| vertical),
+            roughness,
+            return_number: measurement.return_number(),
+            number_of_returns: measurement.number_of_returns(),
+            range_uncertainty: measurement.range_error(normal).range(),
         })
     }
 }
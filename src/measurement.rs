@@ -1,7 +1,21 @@
-use crate::{convert, Config, Dimension, Matrix3, Point, RollPitchYaw, Trajectory, Variable};
-use anyhow::{anyhow, Error};
-use nalgebra::SMatrix;
-use std::path::Path;
+use crate::{
+    convert::{self, Ellipsoid},
+    crs::{self, CrsConflict, CrsMismatch},
+    spatial::KdTree,
+    stats, utils, Config, Decimation, Dem, Dimension, Error, GnssFrame, LeverArmFrame, Matrix3,
+    MeasurementFilter, Point, Propagation, RangeCorrection, RangeError, Rates, RollPitchYaw,
+    RotationOrder, TimeReconstruction, Trajectory, Variable,
+};
+use nalgebra::{SMatrix, SVector};
+use serde::Serialize;
+use std::{
+    cell::OnceCell,
+    fmt::Debug,
+    io::{Read, Seek},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
 
 /// Reads in a vector of measurements from files.
 ///
@@ -24,49 +38,742 @@ pub fn measurements<P0: AsRef<Path>, P1: AsRef<Path>, P2: AsRef<Path>>(
 
 /// Reads in a vector of measurements from files with the provided decimation.
 ///
+/// A plain number keeps every nth point.
+///
 /// # Examples
 ///
 /// ```
+/// use leeward::Decimation;
 /// let measurements = leeward::decimated_measurements(
 ///     "data/sbet.out",
 ///     "data/points.las",
 ///     "data/config.toml",
 ///     100
 /// ).unwrap();
+/// let measurements = leeward::decimated_measurements(
+///     "data/sbet.out",
+///     "data/points.las",
+///     "data/config.toml",
+///     Decimation::Voxel(50.),
+/// ).unwrap();
 /// ```
-pub fn decimated_measurements<P0: AsRef<Path>, P1: AsRef<Path>, P2: AsRef<Path>>(
+pub fn decimated_measurements<
+    P0: AsRef<Path>,
+    P1: AsRef<Path>,
+    P2: AsRef<Path>,
+    D: Into<Decimation>,
+>(
     sbet: P0,
     las: P1,
     config: P2,
-    decimation: usize,
+    decimation: D,
 ) -> Result<Vec<Measurement<las::Point>>, Error> {
+    let options = ReadOptions {
+        decimation: decimation.into(),
+        ..Default::default()
+    };
+    read_measurements(&[sbet], las, config, &options).map(|(measurements, _)| measurements)
+}
+
+/// Reads in a vector of measurements from one or more sbet files, a las file, and a config file.
+///
+/// The sbet files are merged into a single trajectory. Returns the measurements
+/// and a summary of how many points couldn't be matched to the trajectory.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{MissingPose, ReadOptions};
+/// let options = ReadOptions {
+///     decimation: 100.into(),
+///     missing_pose: MissingPose::Skip,
+///     ..Default::default()
+/// };
+/// let (measurements, summary) = leeward::read_measurements(
+///     &["data/sbet.out"],
+///     "data/points.las",
+///     "data/config.toml",
+///     &options,
+/// ).unwrap();
+/// assert_eq!(0, summary.skipped);
+/// ```
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(las = %las.as_ref().display()))
+)]
+pub fn read_measurements<P0: AsRef<Path>, P1: AsRef<Path>, P2: AsRef<Path>>(
+    sbets: &[P0],
+    las: P1,
+    config: P2,
+    options: &ReadOptions,
+) -> Result<(Vec<Measurement<las::Point>>, ReadSummary), Error> {
+    let mut config = Config::from_path(config)?;
+    let reader = las::Reader::from_path(las)?;
+    let crs_conflict = crs::reconcile(
+        &mut config,
+        las::Read::header(&reader),
+        options.crs_mismatch,
+    )?;
+    let (points, reconstructed) = read_las_points(reader, options, config.pulse_rate)?;
+    let (measurements, mut summary) = from_decimated_points(sbets, points, config, options)?;
+    summary.reconstructed = reconstructed;
+    summary.crs_conflict = crs_conflict;
+    Ok((measurements, summary))
+}
+
+/// Reads in a vector of measurements from readers, e.g. in-memory buffers or network streams.
+///
+/// Unlike [read_measurements], the whole trajectory is read.
+///
+/// # Examples
+///
+/// ```
+/// use std::{fs::File, io::Cursor};
+/// let las = Cursor::new(std::fs::read("data/points.las").unwrap());
+/// let (measurements, summary) = leeward::measurements_from_readers(
+///     File::open("data/sbet.out").unwrap(),
+///     las,
+///     File::open("data/config.toml").unwrap(),
+///     &Default::default(),
+/// ).unwrap();
+/// assert_eq!(1325, measurements.len());
+/// ```
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn measurements_from_readers<R0, R1, R2>(
+    sbet: R0,
+    las: R1,
+    config: R2,
+    options: &ReadOptions,
+) -> Result<(Vec<Measurement<las::Point>>, ReadSummary), Error>
+where
+    R0: Read,
+    R1: Read + Seek + Send + Debug,
+    R2: Read,
+{
+    let mut config = Config::from_reader(config)?;
+    let reader = las::Reader::new(las)?;
+    let crs_conflict = crs::reconcile(
+        &mut config,
+        las::Read::header(&reader),
+        options.crs_mismatch,
+    )?;
+    let (points, reconstructed) = read_las_points(reader, options, config.pulse_rate)?;
+    let trajectory = Trajectory::from_reader(sbet)?;
+    let (measurements, mut summary) = match_and_filter(&trajectory, points, config, options)?;
+    summary.reconstructed = reconstructed;
+    summary.crs_conflict = crs_conflict;
+    Ok((measurements, summary))
+}
+
+/// Reads, reconstructs times for, and decimates las points, returning the number of reconstructed times.
+fn read_las_points(
+    mut reader: las::Reader<'_>,
+    options: &ReadOptions,
+    pulse_rate: Option<f64>,
+) -> Result<(Vec<las::Point>, usize), Error> {
     use las::Read;
-    if decimation == 0 {
-        return Err(anyhow!("cannot decimate by zero"));
-    }
-    let trajectory = Trajectory::from_path(sbet)?;
-    let config = Config::from_path(config)?;
-    las::Reader::from_path(las)?
-        .points()
-        .step_by(decimation)
-        .map(|r| {
-            r.map_err(Error::from)
-                .and_then(|p| Measurement::new(&trajectory, p, config))
+    let decimation = options.decimation;
+    decimation.validate()?;
+    match (decimation, options.time_reconstruction) {
+        (Decimation::Every(n), TimeReconstruction::Off) => {
+            // Skip the points as they're read, rather than holding them all in memory.
+            let points = reader.points().step_by(n).collect::<Result<Vec<_>, _>>()?;
+            Ok((points, 0))
+        }
+        (decimation, time_reconstruction) => {
+            // Times are reconstructed from the point order, so all points are needed.
+            let mut points = reader.points().collect::<Result<Vec<_>, _>>()?;
+            let reconstructed = time_reconstruction.reconstruct(&mut points, pulse_rate)?;
+            Ok((decimation.decimate(points)?, reconstructed))
+        }
+    }
+}
+
+/// Creates measurements from points that have already been read, e.g. from a CSV file.
+///
+/// # Examples
+///
+/// ```
+/// let points = leeward::tabular::read_csv("data/points.csv").unwrap();
+/// let (measurements, summary) = leeward::measurements_from_points(
+///     &["data/sbet.out"],
+///     points,
+///     "data/config.toml",
+///     &Default::default(),
+/// ).unwrap();
+/// assert_eq!(10, measurements.len());
+/// ```
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(points = points.len())))]
+pub fn measurements_from_points<L: PointSource, P0: AsRef<Path>, P1: AsRef<Path>>(
+    sbets: &[P0],
+    points: Vec<L>,
+    config: P1,
+    options: &ReadOptions,
+) -> Result<(Vec<Measurement<L>>, ReadSummary), Error> {
+    let points = options.decimation.decimate(points)?;
+    from_decimated_points(sbets, points, Config::from_path(config)?, options)
+}
+
+fn from_decimated_points<L: PointSource, P0: AsRef<Path>>(
+    sbets: &[P0],
+    points: Vec<L>,
+    config: Config,
+    options: &ReadOptions,
+) -> Result<(Vec<Measurement<L>>, ReadSummary), Error> {
+    let trajectory = if let Some((start, end)) = time_range(&points) {
+        Trajectory::from_paths_with_range(
+            sbets,
+            start - TRAJECTORY_TIME_PADDING,
+            end + TRAJECTORY_TIME_PADDING,
+        )?
+    } else {
+        Trajectory::from_paths(sbets)?
+    };
+    match_and_filter(&trajectory, points, config, options)
+}
+
+fn match_and_filter<L: PointSource>(
+    trajectory: &Trajectory,
+    points: Vec<L>,
+    mut config: Config,
+    options: &ReadOptions,
+) -> Result<(Vec<Measurement<L>>, ReadSummary), Error> {
+    let smoothed;
+    let trajectory = if let Some(window) = options.attitude_smoothing {
+        smoothed = trajectory.smooth(window);
+        &smoothed
+    } else {
+        trajectory
+    };
+    if config.utm_zone.is_none() {
+        config.utm_zone = trajectory.utm_zone();
+    }
+    let per_pulse = config.returns.per_pulse;
+    let (mut measurements, mut summary) =
+        match_points(trajectory, points, config, options.missing_pose)?;
+    if let Some(correction) = &options.range_correction {
+        for measurement in &mut measurements {
+            measurement.set_range_correction(correction.clone());
+        }
+    }
+    if per_pulse {
+        model_per_pulse(&mut measurements);
+    }
+    if options.filter.is_empty() {
+        return Ok((measurements, summary));
+    }
+    let count = measurements.len();
+    let measurements: Vec<_> = measurements
+        .into_iter()
+        .filter(|measurement| options.filter.matches(measurement))
+        .collect();
+    summary.filtered = count - measurements.len();
+    Ok((measurements, summary))
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(points = points.len()))
+)]
+fn match_points<L: PointSource>(
+    trajectory: &Trajectory,
+    points: Vec<L>,
+    config: Config,
+    missing_pose: MissingPose,
+) -> Result<(Vec<Measurement<L>>, ReadSummary), Error> {
+    let mut summary = ReadSummary::default();
+    let mut measurements = Vec::with_capacity(points.len());
+    let configs = config.resolve()?;
+    let source = if config.overrides.is_empty() {
+        None
+    } else {
+        Some(Arc::new(config))
+    };
+    for point in points {
+        let time = point.time();
+        let config = configs.at_time(time);
+        match time.and_then(|time| trajectory.get(time)) {
+            Some(sbet) => {
+                let mut measurement = Measurement::from_parts(*sbet, point, config.clone());
+                measurement.rates = time.and_then(|time| trajectory.rates(time));
+                measurement.source = source.clone();
+                measurements.push(measurement);
+            }
+            None => match missing_pose {
+                MissingPose::Fail => {
+                    return Err(match time {
+                        Some(time) => Error::TrajectoryLookup(time),
+                        None => Error::MissingTime,
+                    })
+                }
+                MissingPose::Skip => summary.skipped += 1,
+                MissingPose::Nearest => match time.and_then(|time| trajectory.nearest(time)) {
+                    Some(sbet) => {
+                        let mut measurement = Measurement::from_parts(*sbet, point, config.clone());
+                        measurement.extrapolated = true;
+                        measurements.push(measurement);
+                        summary.nearest += 1;
+                    }
+                    None => summary.skipped += 1,
+                },
+            },
+        }
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        measurements = measurements.len(),
+        skipped = summary.skipped,
+        nearest = summary.nearest,
+        "matched points to the trajectory"
+    );
+    Ok((measurements, summary))
+}
+
+/// What to do with a lidar point that doesn't have a matching trajectory point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissingPose {
+    /// Return an error.
+    #[default]
+    Fail,
+
+    /// Drop the point.
+    Skip,
+
+    /// Use the closest trajectory point in time, no matter how far away it is.
+    ///
+    /// Points without any time are still dropped.
+    Nearest,
+}
+
+/// Options for reading measurements from files.
+#[derive(Clone, Debug)]
+pub struct ReadOptions {
+    /// How to thin out the points.
+    pub decimation: Decimation,
+
+    /// What to do with points that don't match the trajectory.
+    pub missing_pose: MissingPose,
+
+    /// Which measurements to keep.
+    pub filter: MeasurementFilter,
+
+    /// How to assign times to las points that don't have them.
+    ///
+    /// Reconstruction happens before decimation, since it depends on the point
+    /// order. Points that have already been read, e.g. by
+    /// [measurements_from_points], aren't reconstructed.
+    pub time_reconstruction: TimeReconstruction,
+
+    /// What to do if a las header's CRS conflicts with the config.
+    pub crs_mismatch: CrsMismatch,
+
+    /// The window (in seconds) to smooth the trajectory's attitude over before matching points, see [Trajectory::smooth].
+    pub attitude_smoothing: Option<f64>,
+
+    /// A correction applied to every measurement's range, see [RangeCorrection].
+    pub range_correction: Option<Arc<dyn RangeCorrection>>,
+}
+
+/// Bookkeeping from reading measurements.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReadSummary {
+    /// The number of points that were dropped because they didn't match the trajectory.
+    pub skipped: usize,
+
+    /// The number of points that were matched to their nearest trajectory point.
+    pub nearest: usize,
+
+    /// The number of measurements that were dropped by the filter.
+    pub filtered: usize,
+
+    /// The number of points whose times were reconstructed.
+    pub reconstructed: usize,
+
+    /// How the las header's CRS conflicts with the config, if it does.
+    pub crs_conflict: Option<CrsConflict>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> ReadOptions {
+        ReadOptions {
+            decimation: Decimation::default(),
+            missing_pose: MissingPose::Fail,
+            filter: MeasurementFilter::default(),
+            time_reconstruction: TimeReconstruction::default(),
+            crs_mismatch: CrsMismatch::default(),
+            attitude_smoothing: None,
+            range_correction: None,
+        }
+    }
+}
+
+impl FromStr for MissingPose {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<MissingPose, Error> {
+        match s {
+            "fail" => Ok(MissingPose::Fail),
+            "skip" => Ok(MissingPose::Skip),
+            "nearest" => Ok(MissingPose::Nearest),
+            _ => Err(Error::Parse(format!("unknown missing pose policy: {}", s))),
+        }
+    }
+}
+
+/// Padding (in seconds) applied to the lidar time span when subsetting the trajectory.
+const TRAJECTORY_TIME_PADDING: f64 = 1.;
+
+/// Computes the total propagated uncertainty of many measurements on surfaces with the same normal.
+///
+/// Gives the same results as calling [Measurement::tpu] on each measurement,
+/// but is faster for large datasets. The uncertainties that only depend on the
+/// config are computed once for each run of measurements that share a config,
+/// and each point's covariance is accumulated from the rows of its jacobian in
+/// a fixed-size 3x3 matrix instead of via the full 16x16 matrix product.
+/// Nothing is allocated per point, unless the measurements have a range
+/// correction that needs their range recomputed for this normal.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::Point;
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let tpus = leeward::tpu_batch(&measurements, Point::new(0., 0., 1.)).unwrap();
+/// assert_eq!(measurements.len(), tpus.len());
+/// ```
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(measurements = measurements.len()))
+)]
+pub fn tpu_batch<L: PointSource>(
+    measurements: &[Measurement<L>],
+    normal: Point,
+) -> Result<Vec<Tpu>, Error> {
+    let gnss = Variable::iter()
+        .position(|variable| variable == Variable::GnssX)
+        .expect("gnss x is a variable");
+    let range = Variable::iter()
+        .position(|variable| variable == Variable::Range)
+        .expect("range is a variable");
+    let scan_angle = Variable::iter()
+        .position(|variable| variable == Variable::ScanAngle)
+        .expect("scan angle is a variable");
+    let mut tpus = Vec::with_capacity(measurements.len());
+    let mut constants: Option<(Config, SVector<f64, 16>, Option<Matrix3>)> = None;
+    for measurement in measurements {
+        let corrected = measurement.with_range_correction_normal(normal);
+        let measurement = corrected.as_ref().unwrap_or(measurement);
+        let config = &measurement.config;
+        let (mut variances, gnss_covariance) = match &constants {
+            Some((constants_config, variances, gnss_covariance)) if constants_config == config => {
+                (*variances, *gnss_covariance)
+            }
+            _ => {
+                let variances = SVector::from_iterator(
+                    Variable::iter().map(|variable| measurement.uncertainty(variable, 0.).powi(2)),
+                );
+                // Only the ecef and projected gnss frames vary with the platform's position.
+                let gnss_covariance = match config.uncertainty.gnss_frame {
+                    GnssFrame::Navigation | GnssFrame::Enu => Some(measurement.gnss_covariance()),
+                    GnssFrame::Ecef | GnssFrame::Projected => None,
+                };
+                constants = Some((config.clone(), variances, gnss_covariance));
+                (variances, gnss_covariance)
+            }
+        };
+        let (rotation, displacement) = measurement.propagation_matrices();
+        let jacobian = measurement.jacobian(&rotation, &displacement);
+        let incidence_angle = measurement.incidence_angle(normal);
+        let range_error =
+            measurement.range_error_at(measurement.capped_incidence_angle(incidence_angle));
+        variances[range] = range_error.range().powi(2);
+        variances[scan_angle] = range_error.scan_angle().powi(2);
+        // The gnss rows of the jacobian are the platform displacement matrix.
+        let mut covariance = displacement
+            * gnss_covariance.unwrap_or_else(|| measurement.gnss_covariance())
+            * displacement.transpose();
+        for (row, &variance) in variances.iter().enumerate() {
+            if row >= gnss && row < gnss + 3 {
+                continue;
+            }
+            let partials = jacobian.row(row).transpose();
+            covariance += variance * partials * partials.transpose();
+        }
+        covariance += measurement.time_covariance(&rotation, &displacement);
+        tpus.push(measurement.tpu_from_covariance(covariance, incidence_angle));
+    }
+    Ok(tpus)
+}
+
+/// Computes the total propagated uncertainty of each measurement on a plane fit to its neighbors.
+///
+/// Instead of a supplied normal, each measurement's surface is the
+/// least-squares plane through its `k` nearest neighbors (including itself).
+/// The plane's normal sets the incidence angle, and the local roughness, the
+/// rms distance of the neighbors from the plane, is added to the range
+/// uncertainty in quadrature.
+///
+/// # Examples
+///
+/// ```
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let tpus = leeward::tpu_from_neighbors(&measurements, 10).unwrap();
+/// assert_eq!(measurements.len(), tpus.len());
+/// assert!(leeward::tpu_from_neighbors(&measurements, 2).is_err());
+/// ```
+pub fn tpu_from_neighbors<L: PointSource>(
+    measurements: &[Measurement<L>],
+    k: usize,
+) -> Result<Vec<Tpu>, Error> {
+    measurements
+        .iter()
+        .zip(neighbor_planes(measurements, k)?)
+        .map(|(measurement, (normal, roughness))| measurement.tpu_with_roughness(normal, roughness))
+        .collect()
+}
+
+/// Estimates each measurement's surface normal from a plane fit to its neighbors.
+///
+/// Each measurement's surface is the least-squares plane through its `k`
+/// nearest neighbors (including itself), as for [tpu_from_neighbors]. The
+/// normals point up, e.g. for [Measurement::incidence_angle].
+///
+/// # Examples
+///
+/// ```
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let normals = leeward::normals_from_neighbors(&measurements, 10).unwrap();
+/// let incidence_angle = measurements[0].incidence_angle(normals[0]);
+/// ```
+pub fn normals_from_neighbors<L: PointSource>(
+    measurements: &[Measurement<L>],
+    k: usize,
+) -> Result<Vec<Point>, Error> {
+    Ok(neighbor_planes(measurements, k)?
+        .into_iter()
+        .map(|(normal, _)| normal)
+        .collect())
+}
+
+/// Returns the normal and roughness of the plane through each measurement's `k` nearest neighbors.
+fn neighbor_planes<L: PointSource>(
+    measurements: &[Measurement<L>],
+    k: usize,
+) -> Result<Vec<(Point, f64)>, Error> {
+    if k < 3 || measurements.len() < 3 {
+        return Err(Error::Invalid(format!(
+            "need at least three neighbors to fit a plane, got {}",
+            k.min(measurements.len())
+        )));
+    }
+    let tree = KdTree::from_measurements(measurements);
+    measurements
+        .iter()
+        .map(|measurement| {
+            let neighbors: Vec<Point> = tree
+                .nearest(measurement.lasish().point(), k)
+                .into_iter()
+                .map(|(index, _)| tree.point(index))
+                .collect();
+            utils::fit_plane(&neighbors).map(|plane| (plane.normal, plane.rms))
         })
         .collect()
 }
 
+/// Detects whether las scan angles are roll-compensated by comparing them to the computed scan angles.
+///
+/// Roll-compensated las scan angles differ from the computed scan angles by
+/// the negative of the platform's roll, while uncompensated ones don't depend
+/// on roll at all. The difference is regressed on roll, along with an
+/// intercept and the las scan angle to absorb scan angle calibration errors,
+/// and the interpretation closest to the roll coefficient is picked. Returns
+/// `None` if there are too few measurements or if the roll doesn't vary enough
+/// to tell the two apart, e.g. for a short, level flightline with whole-degree
+/// las scan angles.
+///
+/// # Examples
+///
+/// ```
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// // The sample flightline is too level to tell.
+/// assert_eq!(None, leeward::detect_roll_compensation(&measurements));
+/// ```
+pub fn detect_roll_compensation<'a, L, I>(measurements: I) -> Option<bool>
+where
+    L: PointSource + 'a,
+    I: IntoIterator<Item = &'a Measurement<L>>,
+{
+    let mut normal = Matrix3::zeros();
+    let mut rhs = Point::zeros();
+    let mut sum_of_squares = 0.;
+    let mut count = 0;
+    for measurement in measurements {
+        let precomputed = measurement.precomputed();
+        let computed =
+            measurement.computed_scan_angle(precomputed.body_frame, &precomputed.boresight);
+        let las = measurement
+            .config
+            .scan_angle_calibration
+            .apply(measurement.las.scan_angle().to_radians());
+        let row = Point::new(1., las, measurement.sbet.roll);
+        let difference = utils::angle_difference(las, computed);
+        normal += row * row.transpose();
+        rhs += row * difference;
+        sum_of_squares += difference * difference;
+        count += 1;
+    }
+    if count <= 3 {
+        return None;
+    }
+    let inverse = normal.try_inverse()?;
+    let coefficients = inverse * rhs;
+    let residual_variance = (sum_of_squares - coefficients.dot(&rhs)).max(0.) / (count - 3) as f64;
+    let roll = coefficients.z;
+    let standard_error = (residual_variance * inverse[(2, 2)]).sqrt();
+    // The two interpretations' roll coefficients are one apart, so they can
+    // only be told apart if the coefficient is known to better than a quarter.
+    if standard_error.is_nan() || standard_error > 0.25 {
+        None
+    } else {
+        Some(roll < -0.5)
+    }
+}
+
+fn component(point: Point, dimension: Dimension) -> f64 {
+    match dimension {
+        Dimension::X => point.x,
+        Dimension::Y => point.y,
+        Dimension::Z => point.z,
+    }
+}
+
+/// Groups points into the pulses they were returned from.
+///
+/// The returns of a pulse share its gps time, so each pulse is the indices of
+/// the points with the same time, ordered by return number. Points without a
+/// time are each their own pulse. Pulses are ordered by time.
+///
+/// # Examples
+///
+/// ```
+/// let mut points = vec![las::Point::default(); 3];
+/// for (point, (time, return_number)) in points.iter_mut().zip([(1., 2), (1., 1), (2., 1)].iter()) {
+///     point.gps_time = Some(*time);
+///     point.return_number = *return_number;
+/// }
+/// assert_eq!(vec![vec![1, 0], vec![2]], leeward::pulses(&points));
+/// ```
+pub fn pulses<L: PointSource>(points: &[L]) -> Vec<Vec<usize>> {
+    let mut indices: Vec<usize> = (0..points.len()).collect();
+    indices.sort_by(|&a, &b| {
+        let key = |i: usize| (points[i].time(), points[i].return_number());
+        key(a)
+            .partial_cmp(&key(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mut pulses: Vec<Vec<usize>> = Vec::new();
+    for index in indices {
+        let time = points[index].time();
+        match pulses.last_mut() {
+            Some(pulse) if time.is_some() && points[pulse[0]].time() == time => pulse.push(index),
+            _ => pulses.push(vec![index]),
+        }
+    }
+    pulses
+}
+
+/// Models each pulse's returns along a single shared beam.
+///
+/// The returns of a pulse left the scanner at the same scan angle, but each
+/// return's computed scan angle picks up its own noise. This sets the scan
+/// angle of every return in a multi-return pulse to their mean, so the
+/// returns only differ in range. Like las scan angles, the shared scan angle
+/// is observed, so it isn't recomputed when the boresight changes.
+///
+/// [measurements](crate::measurements) calls this when the config's
+/// [returns](crate::Config::returns) are modeled per pulse.
+///
+/// # Examples
+///
+/// ```
+/// let mut measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// leeward::model_per_pulse(&mut measurements);
+/// ```
+pub fn model_per_pulse<L: PointSource>(measurements: &mut [Measurement<L>]) {
+    for pulse in pulses(measurements) {
+        if pulse.len() < 2 {
+            continue;
+        }
+        let scan_angle = pulse
+            .iter()
+            .map(|&i| measurements[i].scan_angle())
+            .sum::<f64>()
+            / pulse.len() as f64;
+        for i in pulse {
+            let measurement = &mut measurements[i];
+            measurement.overrides.scan_angle = Some(scan_angle);
+            measurement.precomputed = OnceCell::new();
+        }
+    }
+}
+
+fn time_range<L: PointSource>(points: &[L]) -> Option<(f64, f64)> {
+    points
+        .iter()
+        .filter_map(|p| p.time())
+        .fold(None, |range, time| match range {
+            Some((start, end)) => Some((time.min(start), time.max(end))),
+            None => Some((time, time)),
+        })
+}
+
 /// A measurement combines trajectory information with the lidar point.
 #[derive(Debug, Clone)]
-pub struct Measurement<L: Lasish> {
+pub struct Measurement<L: PointSource> {
     las: L,
     sbet: sbet::Point,
     config: Config,
+    /// The config with overrides that `config` was resolved from, if there were any.
+    source: Option<Arc<Config>>,
     use_las_scan_angle: bool,
+    extrapolated: bool,
+    rates: Option<Rates>,
+    overrides: Overrides,
+    range_correction: Option<Arc<dyn RangeCorrection>>,
+    /// The surface normal for the range correction's incidence angle, in projected coordinates.
+    normal: Option<Point>,
+    precomputed: OnceCell<Precomputed>,
+}
+
+/// Variables that have been set on a measurement, instead of derived from its point and pose.
+#[derive(Clone, Copy, Debug, Default)]
+struct Overrides {
+    range: Option<f64>,
+    scan_angle: Option<f64>,
+    /// The platform's north, east, down offset from the trajectory, in meters.
+    gnss: Point,
+}
+
+/// Terms of the lidar equation that are shared by the residuals, partial derivatives, and uncertainties.
+///
+/// These are computed once per measurement, the first time any of them are
+/// needed, instead of once per (variable, dimension) pair.
+#[derive(Clone, Copy, Debug)]
+struct Precomputed {
+    body_frame: Point,
+    range: f64,
+    scan_angle: f64,
+    direction: Point,
+    direction_derivative: Point,
+    rotation: Matrix3,
+    rotation_derivatives: [Matrix3; 3],
+    boresight: Matrix3,
+    boresight_derivatives: [Matrix3; 3],
 }
 
 /// The total propagated uncertainty for a measurement.
-#[derive(Debug)]
+///
+/// Uncertainties are one sigma unless scaled with [Tpu::with_confidence].
+#[derive(Clone, Copy, Debug, Serialize)]
 pub struct Tpu {
     pub x: f64,
     pub y: f64,
@@ -74,10 +781,149 @@ pub struct Tpu {
     pub vertical: f64,
     pub total: f64,
     pub incidence_angle: f64,
+
+    /// The horizontal uncertainty along the platform's heading.
+    pub sigma_along: f64,
+
+    /// The horizontal uncertainty perpendicular to the platform's heading.
+    pub sigma_cross: f64,
+
+    /// Whether these uncertainties can be trusted.
+    pub quality: TpuQuality,
+}
+
+/// Flags total propagated uncertainties that shouldn't be trusted.
+///
+/// If more than one applies, the first in this list wins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TpuQuality {
+    /// Nothing is wrong.
+    #[default]
+    Ok,
+
+    /// The trajectory didn't cover the point's time, so the nearest pose was used.
+    Extrapolated,
+
+    /// The DEM couldn't provide a surface normal, so the surface was assumed to be horizontal.
+    NoNormal,
+
+    /// The incidence angle was larger than the config's maximum, so it was capped.
+    HighIncidence,
+}
+
+impl Tpu {
+    /// Scales the uncertainties to the given confidence level, e.g. 0.95.
+    ///
+    /// The one dimensional values (`x`, `y`, `vertical`, `sigma_along`, and
+    /// `sigma_cross`) are scaled by the two-sided normal quantile. `horizontal`
+    /// and `total` are root-sum-squares, so they are scaled to the radius of the
+    /// circle (2D chi distribution) or sphere (3D chi distribution) holding
+    /// `confidence` of the probability, assuming equal per-axis uncertainties.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Point;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let tpu = measurements[0].tpu(Point::new(0., 0., 1.)).unwrap();
+    /// let tpu95 = tpu.with_confidence(0.95).unwrap();
+    /// assert!((tpu95.vertical / tpu.vertical - 1.96).abs() < 1e-3);
+    /// assert!((tpu95.horizontal / tpu.horizontal - 1.7308).abs() < 1e-3);
+    /// ```
+    pub fn with_confidence(&self, confidence: f64) -> Result<Tpu, Error> {
+        if !(confidence > 0. && confidence < 1.) {
+            return Err(Error::Invalid(format!(
+                "confidence must be between zero and one, got {}",
+                confidence
+            )));
+        }
+        let one = stats::chi_quantile(stats::Dimensions::One, confidence);
+        let two = stats::chi_quantile(stats::Dimensions::Two, confidence) / 2f64.sqrt();
+        let three = stats::chi_quantile(stats::Dimensions::Three, confidence) / 3f64.sqrt();
+        Ok(Tpu {
+            x: self.x * one,
+            y: self.y * one,
+            horizontal: self.horizontal * two,
+            vertical: self.vertical * one,
+            total: self.total * three,
+            incidence_angle: self.incidence_angle,
+            sigma_along: self.sigma_along * one,
+            sigma_cross: self.sigma_cross * one,
+            quality: self.quality,
+        })
+    }
+}
+
+/// The laser footprint on the target surface, approximated as an ellipse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Footprint {
+    /// The full length of the major axis, in meters.
+    pub major: f64,
+
+    /// The full length of the minor axis, in meters.
+    pub minor: f64,
+
+    /// The direction of the major axis, in radians counter-clockwise from the projected x axis.
+    ///
+    /// At nadir incidence the footprint is a circle and the orientation is meaningless.
+    pub orientation: f64,
 }
 
 /// A trait implemented by 3D points with ancillary lidar information, e.g. `las::Point`.
-pub trait Lasish: Clone {
+///
+/// This is everything leeward needs from a sensor's points, so points from formats other than
+/// las can be turned into measurements by implementing it. Only [time](PointSource::time) and
+/// the coordinates are required; the scan angle and the ancillary attributes have defaults for
+/// sensors that don't record them. Anything else the format carries per point can ride along
+/// as the [Extra](PointSource::Extra) type, which stays with the point through measurement and
+/// adjustment.
+///
+/// # Examples
+///
+/// A point from a binary format with just coordinates and time:
+///
+/// ```
+/// use leeward::PointSource;
+///
+/// #[derive(Clone)]
+/// struct Xyzt([f64; 4]);
+///
+/// impl PointSource for Xyzt {
+///     type Extra = ();
+///
+///     fn time(&self) -> Option<f64> {
+///         Some(self.0[3])
+///     }
+///
+///     fn x(&self) -> f64 {
+///         self.0[0]
+///     }
+///
+///     fn y(&self) -> f64 {
+///         self.0[1]
+///     }
+///
+///     fn z(&self) -> f64 {
+///         self.0[2]
+///     }
+/// }
+///
+/// let points = vec![Xyzt([320000.34, 4181319.35, 2687.59, 400825.80571932])];
+/// let (measurements, _) = leeward::measurements_from_points(
+///     &["data/sbet.out"],
+///     points,
+///     "data/config.toml",
+///     &Default::default(),
+/// ).unwrap();
+/// assert_eq!(1, measurements.len());
+/// ```
+///
+/// See `examples/xyzt.rs` for a reader for a whole file of these points.
+pub trait PointSource: Clone {
+    /// Format-specific attributes carried with each point, or `()` if there are none.
+    type Extra;
+
     /// Returns the gps time from this point, or `None` if it is not defined.
     ///
     /// We can't really do anything useful without time, but since las points can
@@ -98,11 +944,84 @@ pub trait Lasish: Clone {
         Point::new(self.x(), self.y(), self.z())
     }
 
-    /// Returns the scan angle of this point.
-    fn scan_angle(&self) -> f64;
+    /// Returns this point as a las point, e.g. to write it out.
+    ///
+    /// The default implementation copies the coordinates, time, scan angle, and
+    /// any ancillary attributes that are defined.
+    fn to_las(&self) -> las::Point {
+        let classification = self.classification();
+        las::Point {
+            x: self.x(),
+            y: self.y(),
+            z: self.z(),
+            gps_time: self.time(),
+            scan_angle: self.scan_angle() as f32,
+            intensity: self.intensity().unwrap_or_default(),
+            return_number: self.return_number().unwrap_or_default(),
+            number_of_returns: self.number_of_returns().unwrap_or_default(),
+            classification: classification
+                .and_then(|n| las::point::Classification::new(n).ok())
+                .unwrap_or_default(),
+            is_overlap: classification == Some(12),
+            point_source_id: self.point_source_id().unwrap_or_default(),
+            user_data: self.user_data().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the scan angle of this point, in degrees.
+    ///
+    /// Measurements compute their own scan angle, and only use the recorded one for scan angle
+    /// calibration or when asked to, so sensors that don't record it can leave this at zero.
+    fn scan_angle(&self) -> f64 {
+        0.
+    }
+
+    /// Returns the classification of this point, or `None` if it is not defined.
+    fn classification(&self) -> Option<u8> {
+        None
+    }
+
+    /// Returns the return number of this point, or `None` if it is not defined.
+    fn return_number(&self) -> Option<u8> {
+        None
+    }
+
+    /// Returns the intensity of this point, or `None` if it is not defined.
+    fn intensity(&self) -> Option<u16> {
+        None
+    }
+
+    /// Returns the number of returns of this point's pulse, or `None` if it is not defined.
+    fn number_of_returns(&self) -> Option<u8> {
+        None
+    }
+
+    /// Returns the point source id (usually the flightline) of this point, or `None` if it is not defined.
+    fn point_source_id(&self) -> Option<u16> {
+        None
+    }
+
+    /// Returns the user data of this point, or `None` if it is not defined.
+    fn user_data(&self) -> Option<u8> {
+        None
+    }
+
+    /// Returns this point's format-specific attributes, or `None` if it doesn't have any.
+    fn extra(&self) -> Option<&Self::Extra> {
+        None
+    }
+
+    /// Returns the range the scanner recorded for this point, in meters, or `None` if it is not defined.
+    ///
+    /// This is the raw range, computed with the vacuum speed of light, before
+    /// any atmospheric correction.
+    fn range(&self) -> Option<f64> {
+        None
+    }
 }
 
-impl<L: Lasish> Measurement<L> {
+impl<L: PointSource> Measurement<L> {
     /// Creates a new measurement.
     ///
     /// # Examples
@@ -125,30 +1044,159 @@ impl<L: Lasish> Measurement<L> {
         lasish: L,
         config: Config,
     ) -> Result<Measurement<L>, Error> {
-        let time = lasish.time().ok_or(anyhow!("missing time on point"))?;
-        let sbet = trajectory
-            .get(time)
-            .ok_or(anyhow!("could not find sbet point for time: {}", time))?;
-        Ok(Measurement {
-            las: lasish,
-            sbet: *sbet,
-            config,
-            use_las_scan_angle: false,
-        })
+        let time = lasish.time().ok_or(Error::MissingTime)?;
+        let sbet = trajectory.get(time).ok_or(Error::TrajectoryLookup(time))?;
+        let resolved = config.at_time(time)?;
+        let mut measurement = Measurement::from_parts(*sbet, lasish, resolved);
+        measurement.rates = trajectory.rates(time);
+        if !config.overrides.is_empty() {
+            measurement.source = Some(Arc::new(config));
+        }
+        Ok(measurement)
     }
 
-    /// Sets whether this measurement uses the scan angle from the las point, or calculates it itself.
+    /// Creates a new measurement from a trajectory point, without looking it up by time.
+    ///
+    /// Useful for unit tests and simulations, where the trajectory point and
+    /// lidar point are made up rather than read from files. There's no
+    /// trajectory to take rates from, so the timing uncertainty is ignored
+    /// unless rates are set with [Measurement::set_rates].
     ///
     /// # Examples
     ///
     /// ```
-    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
-    /// let mut measurement = measurements[0].clone();
-    /// measurement.use_las_scan_angle(true);
-    /// assert_eq!(measurement.scan_angle().to_degrees(), measurement.scan_angle().to_degrees().round());
+    /// # use leeward::{Config, Measurement, tabular::TabularPoint};
+    /// let sbet = sbet::Point {
+    ///     latitude: 37.8f64.to_radians(),
+    ///     longitude: -117f64.to_radians(),
+    ///     altitude: 3000.,
+    ///     ..Default::default()
+    /// };
+    /// let point = TabularPoint { x: 500000., y: 4183000., z: 1000., time: None, scan_angle: 0. };
+    /// let config = Config::from_path("data/config.toml").unwrap();
+    /// let measurement = Measurement::from_parts(sbet, point, config);
+    /// assert_eq!(3000., measurement.platform().z);
     /// ```
-    pub fn use_las_scan_angle(&mut self, use_las_scan_angle: bool) {
-        self.use_las_scan_angle = use_las_scan_angle;
+    pub fn from_parts(sbet: sbet::Point, lasish: L, config: Config) -> Measurement<L> {
+        Measurement {
+            las: lasish,
+            sbet,
+            config,
+            source: None,
+            use_las_scan_angle: false,
+            extrapolated: false,
+            rates: None,
+            overrides: Overrides::default(),
+            range_correction: None,
+            normal: None,
+            precomputed: OnceCell::new(),
+        }
+    }
+
+    /// Sets a correction that's applied to this measurement's range, see [RangeCorrection].
+    ///
+    /// Ranges that have been set with [Measurement::with_variable] are observed values, so
+    /// they aren't corrected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::IntensityRangeBias;
+    /// # use std::sync::Arc;
+    /// let mut measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let range = measurements[0].range();
+    /// let bias = IntensityRangeBias::new(vec![(0., 0.1)]).unwrap();
+    /// measurements[0].set_range_correction(Arc::new(bias));
+    /// assert!((range - 0.1 - measurements[0].range()).abs() < 1e-9);
+    /// ```
+    pub fn set_range_correction(&mut self, correction: Arc<dyn RangeCorrection>) {
+        self.range_correction = Some(correction);
+        self.precomputed = OnceCell::new();
+    }
+
+    /// Sets the surface normal, in projected coordinates, for the range correction's incidence angle.
+    ///
+    /// Without a normal, the range correction's incidence angle is to a
+    /// horizontal surface, and so are this measurement's range, residuals, and
+    /// modeled points. [Measurement::tpu], [Measurement::tpu_with_dem], and
+    /// [tpu_batch] correct the range with the normal they're given instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Point, RangeCorrection};
+    /// # use std::sync::Arc;
+    /// #[derive(Debug)]
+    /// struct Incidence;
+    /// impl RangeCorrection for Incidence {
+    ///     fn correct(&self, _: Option<u16>, range: f64, incidence_angle: f64) -> f64 {
+    ///         range - incidence_angle
+    ///     }
+    /// }
+    /// let mut measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// measurements[0].set_range_correction(Arc::new(Incidence));
+    /// let horizontal = measurements[0].range();
+    /// measurements[0].set_normal(Point::new(0., 1., 1.).normalize());
+    /// assert_ne!(horizontal, measurements[0].range());
+    /// ```
+    pub fn set_normal(&mut self, normal: Point) {
+        self.normal = Some(normal);
+        self.precomputed = OnceCell::new();
+    }
+
+    /// Returns a copy of this measurement whose range is corrected with this normal, if that changes its range.
+    fn with_range_correction_normal(&self, normal: Point) -> Option<Measurement<L>> {
+        if self.range_correction.is_some() && self.normal != Some(normal) {
+            let mut measurement = self.clone();
+            measurement.set_normal(normal);
+            Some(measurement)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the platform's rates at this measurement's time, if they're known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let rates = measurements[0].rates().unwrap();
+    /// ```
+    pub fn rates(&self) -> Option<Rates> {
+        self.rates
+    }
+
+    /// Sets the platform's rates, which propagate the timing uncertainty into the tpu.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Config, Measurement, Rates, tabular::TabularPoint};
+    /// let sbet = sbet::Point { altitude: 3000., ..Default::default() };
+    /// let point = TabularPoint { x: 500000., y: 0., z: 1000., time: None, scan_angle: 0. };
+    /// let config = Config::from_path("data/config.toml").unwrap();
+    /// let mut measurement = Measurement::from_parts(sbet, point, config);
+    /// measurement.set_rates(Rates { north: 100., ..Default::default() });
+    /// assert_eq!(100., measurement.rates().unwrap().north);
+    /// ```
+    pub fn set_rates(&mut self, rates: Rates) {
+        self.rates = Some(rates);
+    }
+
+    /// Sets whether this measurement uses the scan angle from the las point, or calculates it itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let mut measurement = measurements[0].clone();
+    /// measurement.use_las_scan_angle(true);
+    /// assert_eq!(measurement.scan_angle().to_degrees(), measurement.scan_angle().to_degrees().round());
+    /// ```
+    pub fn use_las_scan_angle(&mut self, use_las_scan_angle: bool) {
+        self.use_las_scan_angle = use_las_scan_angle;
+        self.precomputed = OnceCell::new();
     }
 
     /// Returns the x coordinate of this measurement, from the lidar point.
@@ -253,6 +1301,100 @@ impl<L: Lasish> Measurement<L> {
         self.sbet.yaw
     }
 
+    /// Returns the platform's heading, its yaw wrapped to [0, 2π) radians clockwise from north.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let heading = measurements[0].heading();
+    /// assert!((0.0..2. * std::f64::consts::PI).contains(&heading));
+    /// ```
+    pub fn heading(&self) -> f64 {
+        self.sbet.yaw.rem_euclid(2. * std::f64::consts::PI)
+    }
+
+    /// Returns the platform's latitude in radians.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// assert!((measurements[0].latitude().to_degrees() - 37.8).abs() < 0.1);
+    /// ```
+    pub fn latitude(&self) -> f64 {
+        self.platform().y
+    }
+
+    /// Returns the platform's longitude in radians.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let longitude = measurements[0].longitude();
+    /// ```
+    pub fn longitude(&self) -> f64 {
+        self.platform().x
+    }
+
+    /// Returns the platform's height above the ellipsoid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let altitude = measurements[0].altitude();
+    /// ```
+    pub fn altitude(&self) -> f64 {
+        self.platform().z
+    }
+
+    /// Returns the platform's velocity from the sbet, in meters per second.
+    ///
+    /// Sbet velocities are in the wander frame, with x along the wander
+    /// azimuth, y west of it, and z up. For north, east, down velocities, see
+    /// [Measurement::rates].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let speed = measurements[0].velocity().norm();
+    /// ```
+    pub fn velocity(&self) -> Point {
+        Point::new(
+            self.sbet.x_velocity,
+            self.sbet.y_velocity,
+            self.sbet.z_velocity,
+        )
+    }
+
+    /// Returns the trajectory point that this measurement was matched to.
+    ///
+    /// This is the trajectory point at the measurement's time slot, not
+    /// interpolated between trajectory points. Variables set with
+    /// [Measurement::with_variable] are included, so its position is the
+    /// [platform](Measurement::platform).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let point = measurements[0].trajectory_point();
+    /// assert_eq!(measurements[0].roll(), point.roll);
+    /// assert_eq!(measurements[0].altitude(), point.altitude);
+    /// ```
+    pub fn trajectory_point(&self) -> sbet::Point {
+        let platform = self.platform();
+        sbet::Point {
+            longitude: platform.x,
+            latitude: platform.y,
+            altitude: platform.z,
+            ..self.sbet
+        }
+    }
+
     /// Returns the time of this measurement, from the las point.
     ///
     /// Although not all las points have gps time, we know ours does because we check during measurement creation.
@@ -279,6 +1421,19 @@ impl<L: Lasish> Measurement<L> {
             .expect("time should be something because we check when creating the measurement")
     }
 
+    /// Returns the lidar point underlying this measurement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let point = measurements[0].lasish();
+    /// assert_eq!(320000.34, point.x);
+    /// ```
+    pub fn lasish(&self) -> &L {
+        &self.las
+    }
+
     /// Returns this measurement in the body frame of the aircraft.
     ///
     /// # Examples
@@ -298,12 +1453,48 @@ impl<L: Lasish> Measurement<L> {
     /// let body_frame = measurement.body_frame();
     /// ```
     pub fn body_frame(&self) -> Point {
-        let projected = self.las.point();
-        convert::projected_to_body(projected, self.platform(), self.rpy(), self.utm_zone())
+        self.precomputed().body_frame
+    }
+
+    /// Returns this measurement in the north, east, down navigation frame, relative to the platform.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let navigation = measurements[0].navigation_frame();
+    /// assert!(navigation.z > 0.);
+    /// assert!((navigation.norm() - measurements[0].body_frame().norm()).abs() < 1e-6);
+    /// ```
+    pub fn navigation_frame(&self) -> Point {
+        let precomputed = self.precomputed();
+        precomputed.rotation * self.frame_matrix() * precomputed.body_frame
+    }
+
+    /// Returns the platform's geodetic position from the trajectory.
+    ///
+    /// The point is longitude, latitude (both in radians), and height. If the
+    /// config has a [datum transformation](Config::datum_transformation), the
+    /// position is in the points' datum.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let platform = measurements[0].platform();
+    /// ```
+    pub fn platform(&self) -> Point {
+        let platform = convert::platform(&self.sbet, &self.config);
+        if self.overrides.gnss == Point::zeros() {
+            platform
+        } else {
+            let ellipsoid = self.ellipsoid();
+            ellipsoid.ecef_to_geodetic(ellipsoid.navigation_to_ecef(self.overrides.gnss, platform))
+        }
     }
 
-    fn platform(&self) -> Point {
-        Point::new(self.sbet.longitude, self.sbet.latitude, self.sbet.altitude)
+    fn ellipsoid(&self) -> Ellipsoid {
+        self.config.ellipsoid.ellipsoid()
     }
 
     fn rpy(&self) -> RollPitchYaw {
@@ -311,7 +1502,111 @@ impl<L: Lasish> Measurement<L> {
     }
 
     fn utm_zone(&self) -> u8 {
-        self.config.utm_zone
+        self.config
+            .utm_zone
+            .unwrap_or_else(|| convert::utm_zone(self.sbet.longitude))
+    }
+
+    fn precomputed(&self) -> &Precomputed {
+        self.precomputed.get_or_init(|| self.precompute())
+    }
+
+    fn precompute(&self) -> Precomputed {
+        let rpy = self.rpy();
+        let body_frame = self.ellipsoid().projected_to_body(
+            self.las.point(),
+            self.platform(),
+            rpy,
+            self.utm_zone(),
+            self.config.navigation_frame,
+        );
+        let boresight_rpy = self.boresight_rpy();
+        let order = self.config.rotation_order;
+        let boresight = order.matrix(boresight_rpy);
+        let range = if let Some(range) = self.overrides.range {
+            range
+        } else {
+            // A range from the delivered coordinates already has the processor's atmospheric correction.
+            let (range, atmosphere) = match self.las.range() {
+                Some(range) => (range, self.config.atmosphere),
+                None => ((body_frame + self.lever_arm()).norm(), None),
+            };
+            let range = if let Some(correction) = &self.range_correction {
+                let normal = self.normal.unwrap_or_else(|| Point::new(0., 0., 1.));
+                let incidence_angle = self.incidence_angle_at(body_frame, normal);
+                correction.correct(self.las.intensity(), range, incidence_angle)
+            } else {
+                range
+            };
+            if let Some(atmosphere) = atmosphere {
+                atmosphere.correct(range)
+            } else {
+                range
+            }
+        };
+        let scan_angle = if let Some(scan_angle) = self.overrides.scan_angle {
+            scan_angle
+        } else if self.use_las_scan_angle {
+            self.las_scan_angle()
+        } else {
+            self.computed_scan_angle(body_frame, &boresight)
+        };
+        Precomputed {
+            body_frame,
+            range,
+            scan_angle,
+            direction: self.frame_matrix() * self.config.scanner.direction(scan_angle),
+            direction_derivative: self.frame_matrix()
+                * self.config.scanner.direction_derivative(scan_angle),
+            rotation: RotationOrder::Zyx.matrix(rpy),
+            rotation_derivatives: [
+                RotationOrder::Zyx.roll_derivative(rpy),
+                RotationOrder::Zyx.pitch_derivative(rpy),
+                RotationOrder::Zyx.yaw_derivative(rpy),
+            ],
+            boresight,
+            boresight_derivatives: [
+                order.roll_derivative(boresight_rpy),
+                order.pitch_derivative(boresight_rpy),
+                order.yaw_derivative(boresight_rpy),
+            ],
+        }
+    }
+
+    /// Returns the calibrated las scan angle in the platform's frame, in radians.
+    ///
+    /// Roll-compensated scan angles are relative to vertical, so the
+    /// platform's roll is added back.
+    fn las_scan_angle(&self) -> f64 {
+        let scan_angle = self
+            .config
+            .scan_angle_calibration
+            .apply(self.las.scan_angle().to_radians());
+        if self.config.scan_angle_is_roll_compensated {
+            scan_angle + self.sbet.roll
+        } else {
+            scan_angle
+        }
+    }
+
+    fn computed_scan_angle(&self, body_frame: Point, boresight: &Matrix3) -> f64 {
+        if !self.config.scanner.is_planar() {
+            let scan_frame = boresight.transpose() * (body_frame + self.lever_arm());
+            self.config
+                .scanner
+                .scan_angle(self.frame_matrix() * scan_frame)
+        } else {
+            let body_frame = self.frame_matrix() * body_frame;
+            body_frame.y.signum()
+                * (body_frame.x.powi(2) + body_frame.y.powi(2))
+                    .sqrt()
+                    .atan2(body_frame.z)
+        }
+    }
+
+    /// Converts between the configured body frame and the north, east, down body frame.
+    fn frame_matrix(&self) -> Matrix3 {
+        self.config.navigation_frame.matrix()
     }
 
     /// Calculates body frame coordinates using the lidar equation and this measurement's configuration.
@@ -326,9 +1621,31 @@ impl<L: Lasish> Measurement<L> {
         self.boresight() * self.modeled_scan_frame() - self.lever_arm()
     }
 
+    /// Calculates projected coordinates using the lidar equation and this measurement's configuration.
+    ///
+    /// This is the modeled body frame point carried through the platform's
+    /// attitude and position, so it can be compared directly to the las point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let point = measurements[0].modeled_point();
+    /// ```
+    pub fn modeled_point(&self) -> Point {
+        self.ellipsoid().body_to_projected(
+            self.modeled_body_frame(),
+            self.platform(),
+            self.rpy(),
+            self.utm_zone(),
+            self.config.navigation_frame,
+        )
+    }
+
     /// Returns this measurement's point in the scanner reference frame.
     ///
     /// This is calculated from the las point's scan angle and the computed range from the scanner origin to the target point.
+    /// The scan angle is mapped to a beam direction by the configured [ScannerModel](crate::ScannerModel).
     ///
     /// # Examples
     ///
@@ -337,14 +1654,18 @@ impl<L: Lasish> Measurement<L> {
     /// let scanner = measurements[0].modeled_scan_frame();
     /// ```
     pub fn modeled_scan_frame(&self) -> Point {
-        let range = self.range();
-        let scan_angle = self.scan_angle();
-        Point::new(range * scan_angle.cos(), 0., range * scan_angle.sin())
+        let precomputed = self.precomputed();
+        precomputed.range * precomputed.direction
     }
 
     /// Returns this measurement's scan range.
     ///
-    /// This is the vector distance from the scanner origin to the measured point.
+    /// This is the vector distance from the scanner origin to the measured
+    /// point. The point's coordinates were computed by the processor, so they
+    /// already include its atmospheric correction, and the config's atmosphere
+    /// only adds to the range uncertainty. If the point has a raw range, see
+    /// [PointSource::range], that's used instead, and it's corrected for the
+    /// group refractive index of the config's atmosphere.
     ///
     /// # Examples
     ///
@@ -353,8 +1674,7 @@ impl<L: Lasish> Measurement<L> {
     /// let range = measurements[0].range();
     /// ```
     pub fn range(&self) -> f64 {
-        let body_frame = self.body_frame();
-        (body_frame - (Point::new(0., 0., 0.) - self.lever_arm())).norm()
+        self.precomputed().range
     }
 
     /// Returns this measurement's scan angle in radians.
@@ -366,15 +1686,7 @@ impl<L: Lasish> Measurement<L> {
     /// let scan_angle = measurements[0].scan_angle();
     /// ```
     pub fn scan_angle(&self) -> f64 {
-        if self.use_las_scan_angle {
-            self.las.scan_angle().to_radians()
-        } else {
-            let body_frame = self.body_frame();
-            body_frame.y.signum()
-                * (body_frame.x.powi(2) + body_frame.y.powi(2))
-                    .sqrt()
-                    .atan2(body_frame.z)
-        }
+        self.precomputed().scan_angle
     }
 
     /// Returns this measurement's boresight angles as a rotation matrix.
@@ -386,15 +1698,20 @@ impl<L: Lasish> Measurement<L> {
     /// let boresight = measurements[0].boresight();
     /// ```
     pub fn boresight(&self) -> Matrix3 {
+        self.precomputed().boresight
+    }
+
+    fn boresight_rpy(&self) -> RollPitchYaw {
         RollPitchYaw::new(
             self.config.boresight.roll,
             self.config.boresight.pitch,
             self.config.boresight.yaw,
         )
-        .as_matrix()
     }
 
-    /// Returns this measurement's lever arm.
+    /// Returns this measurement's lever arm, from the scanner to the IMU in the body frame.
+    ///
+    /// The configured lever arm is converted from its [LeverArmFrame].
     ///
     /// # Examples
     ///
@@ -403,11 +1720,33 @@ impl<L: Lasish> Measurement<L> {
     /// let lever_arm = measurements[0].lever_arm();
     /// ```
     pub fn lever_arm(&self) -> Point {
-        self.config.lever_arm
+        self.lever_arm_matrix() * self.config.lever_arm
+    }
+
+    /// Converts the configured lever arm into the body frame convention, see [LeverArmFrame].
+    fn lever_arm_matrix(&self) -> Matrix3 {
+        let boresight = self.config.rotation_order.matrix(self.boresight_rpy());
+        self.config
+            .lever_arm_frame
+            .matrix(boresight, self.config.navigation_frame)
+    }
+
+    /// Returns the part of the body frame point that is rotated by the boresight.
+    ///
+    /// A lever arm in the scanner's axes is rotated along with the scan frame point.
+    fn boresight_rotated(&self, scan_frame: Point) -> Point {
+        match self.config.lever_arm_frame {
+            LeverArmFrame::Sensor => scan_frame - self.config.lever_arm,
+            LeverArmFrame::Body | LeverArmFrame::Imu => scan_frame,
+        }
     }
 
     /// Returns the partial derivative in the body frame for the given dimension and variable.
     ///
+    /// Roll, pitch, and yaw rotate the las point into the body frame instead
+    /// of the modeled point, so theirs are the partial derivatives of the
+    /// [residuals](Measurement::residuals).
+    ///
     /// # Examples
     ///
     /// ```
@@ -420,51 +1759,52 @@ impl<L: Lasish> Measurement<L> {
         dimension: Dimension,
         variable: Variable,
     ) -> f64 {
-        let range = self.range();
-        let scan_angle = self.scan_angle();
-        let sa = scan_angle.sin();
-        let ca = scan_angle.cos();
-        let sr = self.config.boresight.roll.sin();
-        let cr = self.config.boresight.roll.cos();
-        let sp = self.config.boresight.pitch.sin();
-        let cp = self.config.boresight.pitch.cos();
-        let sy = self.config.boresight.yaw.sin();
-        let cy = self.config.boresight.yaw.cos();
-        match variable {
-            Variable::BoresightRoll => match dimension {
-                Dimension::X => range * sa * (cr * sy - cy * sp * sr),
-                Dimension::Y => range * sa * (-cr * cy - sp * sr * sy),
-                Dimension::Z => -cp * range * sa * sr,
-            },
-            Variable::BoresightPitch => match dimension {
-                Dimension::X => -ca * cy * range * sp + cp * cr * cy * range * sa,
-                Dimension::Y => -ca * range * sp * sy + cp * cr * range * sa * sy,
-                Dimension::Z => -ca * cp * range - cr * range * sa * sp,
-            },
-            Variable::BoresightYaw => match dimension {
-                Dimension::X => -ca * cp * range * sy + range * sa * (-cr * sp * sy + cy * sr),
-                Dimension::Y => ca * cp * cy * range + range * sa * (cr * cy * sp + sr * sy),
-                Dimension::Z => 0.,
-            },
-            Variable::LeverArmX => match dimension {
-                Dimension::X => -1.,
-                Dimension::Y => 0.,
-                Dimension::Z => 0.,
-            },
-            Variable::LeverArmY => match dimension {
-                Dimension::X => 0.,
-                Dimension::Y => -1.,
-                Dimension::Z => 0.,
-            },
-            Variable::LeverArmZ => match dimension {
-                Dimension::X => 0.,
-                Dimension::Y => 0.,
-                Dimension::Z => -1.,
-            },
-            Variable::Range => unimplemented!(),
-            Variable::ScanAngle => unimplemented!(),
-            _ => 0.,
-        }
+        let precomputed = self.precomputed();
+        let [roll, pitch, yaw] = precomputed.boresight_derivatives;
+        let scan_frame = precomputed.range * precomputed.direction;
+        let rotated = self.boresight_rotated(scan_frame);
+        let lever_arm = self.lever_arm_matrix();
+        let partials = match variable {
+            Variable::BoresightRoll => roll * rotated,
+            Variable::BoresightPitch => pitch * rotated,
+            Variable::BoresightYaw => yaw * rotated,
+            Variable::LeverArmX => -lever_arm.column(0),
+            Variable::LeverArmY => -lever_arm.column(1),
+            Variable::LeverArmZ => -lever_arm.column(2),
+            Variable::Range => precomputed.boresight * precomputed.direction,
+            Variable::ScanAngle => {
+                precomputed.boresight * (precomputed.range * precomputed.direction_derivative)
+            }
+            Variable::ScanAngleOffset | Variable::ScanAngleScale => {
+                self.scan_angle_calibration_derivative(variable)
+                    * (precomputed.boresight
+                        * (precomputed.range * precomputed.direction_derivative))
+            }
+            Variable::Roll | Variable::Pitch | Variable::Yaw => {
+                let [roll, pitch, yaw] = precomputed.rotation_derivatives;
+                let derivative = match variable {
+                    Variable::Roll => roll,
+                    Variable::Pitch => pitch,
+                    _ => yaw,
+                };
+                -(self.frame_matrix() * derivative.transpose() * self.navigation_frame())
+            }
+            _ => Point::zeros(),
+        };
+        component(partials, dimension)
+    }
+
+    /// Creates a new measurement with the trajectory's roll, pitch, and yaw changed by these offsets, in radians.
+    ///
+    /// Unlike [Measurement::with_variable], the range and scan angle are still
+    /// derived from the point and the new pose.
+    pub(crate) fn with_attitude_offset(&self, offset: [f64; 3]) -> Measurement<L> {
+        let mut measurement = self.clone();
+        measurement.sbet.roll = utils::normalize_angle(self.sbet.roll + offset[0]);
+        measurement.sbet.pitch = utils::normalize_angle(self.sbet.pitch + offset[1]);
+        measurement.sbet.yaw = utils::normalize_angle(self.sbet.yaw + offset[2]);
+        measurement.precomputed = OnceCell::new();
+        measurement
     }
 
     /// Returns this measurement's boresight roll.
@@ -548,11 +1888,21 @@ impl<L: Lasish> Measurement<L> {
     /// let config = measurements[0].config();
     /// ```
     pub fn config(&self) -> Config {
-        self.config
+        self.config.clone()
+    }
+
+    /// Returns the config this measurement's config was resolved from, with its overrides.
+    pub(crate) fn source_config(&self) -> &Config {
+        self.source.as_deref().unwrap_or(&self.config)
     }
 
     /// Creates a new measurement with the provided config.
     ///
+    /// If the config has overrides, the one for this measurement's time is
+    /// merged in, see [Config::at_time]. Overrides are checked when a config is
+    /// parsed, so merging only fails for configs that are built in code, and
+    /// then the measurement gets the values without the override.
+    ///
     /// # Examples
     ///
     /// ```
@@ -563,286 +1913,579 @@ impl<L: Lasish> Measurement<L> {
     /// let measurement = measurements[0].with_config(config);
     /// ```
     pub fn with_config(&self, config: Config) -> Measurement<L> {
+        if config.overrides.is_empty() {
+            self.with_resolved_config(config, None)
+        } else {
+            let resolved = self
+                .las
+                .time()
+                .and_then(|time| config.at_time(time).ok())
+                .unwrap_or_else(|| config.without_overrides());
+            self.with_resolved_config(resolved, Some(Arc::new(config)))
+        }
+    }
+
+    /// Creates a new measurement with a config that's already been resolved for its time.
+    pub(crate) fn with_resolved_config(
+        &self,
+        config: Config,
+        source: Option<Arc<Config>>,
+    ) -> Measurement<L> {
         Measurement {
             las: self.las.clone(),
             sbet: self.sbet,
             config,
+            source,
             use_las_scan_angle: self.use_las_scan_angle,
+            extrapolated: self.extrapolated,
+            rates: self.rates,
+            overrides: self.overrides,
+            range_correction: self.range_correction.clone(),
+            normal: self.normal,
+            precomputed: OnceCell::new(),
         }
     }
 
-    /// Returns this measurement's residuals.
+    /// Returns the value of a variable in this measurement's lidar equation.
     ///
-    /// Residuals are computed in the body frame of the aircraft, and are the
-    /// difference between the computed body coordinates and the las body
-    /// coordinates.
+    /// The gnss variables are the platform's north, east, and down offsets
+    /// from the trajectory, in meters, so they're zero unless they've been set
+    /// with [Measurement::with_variable].
     ///
     /// # Examples
     ///
     /// ```
+    /// # use leeward::Variable;
     /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
-    /// let residuals = measurements[0].residuals();
+    /// assert_eq!(measurements[0].roll(), measurements[0].value(Variable::Roll));
+    /// assert_eq!(0., measurements[0].value(Variable::GnssX));
     /// ```
-    pub fn residuals(&self) -> Point {
-        self.modeled_body_frame() - self.body_frame()
+    pub fn value(&self, variable: Variable) -> f64 {
+        match variable {
+            Variable::Range => self.range(),
+            Variable::ScanAngle => self.scan_angle(),
+            Variable::Roll => self.sbet.roll,
+            Variable::Pitch => self.sbet.pitch,
+            Variable::Yaw => self.sbet.yaw,
+            Variable::GnssX => self.overrides.gnss.x,
+            Variable::GnssY => self.overrides.gnss.y,
+            Variable::GnssZ => self.overrides.gnss.z,
+            _ => self
+                .config
+                .values(&[variable])
+                .expect("every other variable is in the config")[0],
+        }
     }
 
-    /// Returns this measurement's total propagated uncertainty.
+    /// Creates a new measurement with a variable in its lidar equation set to a value.
+    ///
+    /// The new measurement keeps this measurement's range and computed scan
+    /// angle as observed values, so its modeled point moves with the variable
+    /// while its las point stays put, e.g. for sensitivity studies. Las scan
+    /// angles are already observed, so their calibration and roll compensation
+    /// still apply unless the scan angle itself is set. Roll, pitch, and yaw are
+    /// wrapped into `[-π, π)`.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use leeward::Point;
+    /// # use leeward::Variable;
     /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
-    /// let uncertainty = measurements[0].tpu(Point::new(0., 0., 1.)).unwrap();
+    /// let measurement = measurements[0].with_variable(Variable::Roll, 0.01);
+    /// assert_eq!(0.01, measurement.roll());
+    /// assert_eq!(measurements[0].range(), measurement.range());
+    /// assert!((measurement.modeled_point() - measurements[0].modeled_point()).norm() > 1.);
     /// ```
-    pub fn tpu(&self, normal: Point) -> Result<Tpu, Error> {
-        let jacobian = self.jacobian();
-        let incidence_angle = self.incidence_angle(normal);
-        let covariance =
-            jacobian.transpose() * self.uncertainty_covariance(incidence_angle) * jacobian;
-        let x = covariance[(0, 0)].sqrt();
-        let y = covariance[(1, 1)].sqrt();
-        let z = covariance[(2, 2)].sqrt();
-        Ok(Tpu {
-            x,
-            y,
-            horizontal: (x.powi(2) + y.powi(2)).sqrt(),
-            vertical: z,
-            total: (x.powi(2) + y.powi(2) + z.powi(2)).sqrt(),
-            incidence_angle,
-        })
-    }
-
-    fn jacobian(&self) -> SMatrix<f64, 14, 3> {
-        let mut jacobian = SMatrix::zeros();
-        for (row, variable) in Variable::iter().enumerate() {
-            for (col, dimension) in Dimension::iter().enumerate() {
-                jacobian[(row, col)] = self.partial_derivative(variable, dimension);
+    pub fn with_variable(&self, variable: Variable, value: f64) -> Measurement<L> {
+        let mut measurement = self.clone();
+        let overrides = &mut measurement.overrides;
+        if overrides.range.is_none() {
+            overrides.range = Some(self.range());
+        }
+        if overrides.scan_angle.is_none() && !self.use_las_scan_angle {
+            overrides.scan_angle = Some(self.scan_angle());
+        }
+        match variable {
+            Variable::Range => overrides.range = Some(value),
+            Variable::ScanAngle => overrides.scan_angle = Some(value),
+            Variable::Roll => measurement.sbet.roll = utils::normalize_angle(value),
+            Variable::Pitch => measurement.sbet.pitch = utils::normalize_angle(value),
+            Variable::Yaw => measurement.sbet.yaw = utils::normalize_angle(value),
+            Variable::GnssX => overrides.gnss.x = value,
+            Variable::GnssY => overrides.gnss.y = value,
+            Variable::GnssZ => overrides.gnss.z = value,
+            _ => {
+                measurement.config = self
+                    .config
+                    .with_values(&[variable], &[value])
+                    .expect("every other variable is in the config")
             }
         }
-        jacobian
+        measurement.precomputed = OnceCell::new();
+        measurement
     }
 
-    fn partial_derivative(&self, variable: Variable, dimension: Dimension) -> f64 {
-        let cr = self.roll().cos();
-        let sr = self.roll().sin();
-        let cp = self.pitch().cos();
-        let sp = self.pitch().sin();
-        let cy = self.yaw().cos();
-        let sy = self.yaw().sin();
-        let cbr = self.boresight_roll().cos();
-        let sbr = self.boresight_roll().sin();
-        let cbp = self.boresight_pitch().cos();
-        let sbp = self.boresight_pitch().sin();
-        let cby = self.boresight_yaw().cos();
-        let sby = self.boresight_yaw().sin();
-        let lx = self.lever_arm_x();
-        let ly = self.lever_arm_y();
-        let lz = self.lever_arm_z();
-        let ca = self.scan_angle().cos();
-        let sa = self.scan_angle().sin();
-        let r = self.range();
-        match variable {
-            Variable::GnssX => match dimension {
-                Dimension::X => 1.,
-                Dimension::Y => 0.,
-                Dimension::Z => 0.,
-            },
-            Variable::GnssY => match dimension {
-                Dimension::X => 0.,
-                Dimension::Y => 1.,
-                Dimension::Z => 0.,
-            },
-            Variable::GnssZ => match dimension {
-                Dimension::X => 0.,
-                Dimension::Y => 0.,
-                Dimension::Z => 1.,
-            },
-            Variable::Roll => match dimension {
-                Dimension::X => {
-                    (cr * sy - cy * sp * sr) * (-ca * r * sbp + cbp * cbr * r * sa - lz)
-                        + (cr * cy * sp + sr * sy)
-                            * (ca * cbp * r * sby - ly + r * sa * (cbr * sbp * sby - cby * sbr))
-                }
-                Dimension::Y => {
-                    (-cr * cy - sp * sr * sy) * (-ca * r * sbp + cbp * cbr * r * sa - lz)
-                        + (cr * sp * sy - cy * sr)
-                            * (ca * cbp * r * sby - ly + r * sa * (cbr * sbp * sby - cby * sbr))
-                }
-                Dimension::Z => {
-                    cp * cr * (ca * cbp * r * sby - ly + r * sa * (cbr * sbp * sby - cby * sbr))
-                        - cp * sr * (-ca * r * sbp + cbp * cbr * r * sa - lz)
-                }
-            },
-            Variable::Pitch => match dimension {
-                Dimension::X => {
-                    cp * cr * cy * (-ca * r * sbp + cbp * cbr * r * sa - lz)
-                        + cp * cy
-                            * sr
-                            * (ca * cbp * r * sby - ly + r * sa * (cbr * sbp * sby - cby * sbr))
-                        - cy * sp
-                            * (ca * cbp * cby * r - lx + r * sa * (cbr * cby * sbp + sbr * sby))
-                }
-                Dimension::Y => {
-                    cp * cr * sy * (-ca * r * sbp + cbp * cbr * r * sa - lz)
-                        + cp * sr
-                            * sy
-                            * (ca * cbp * r * sby - ly + r * sa * (cbr * sbp * sby - cby * sbr))
-                        - sp * sy
-                            * (ca * cbp * cby * r - lx + r * sa * (cbr * cby * sbp + sbr * sby))
-                }
-                Dimension::Z => {
-                    -cp * (ca * cbp * cby * r - lx + r * sa * (cbr * cby * sbp + sbr * sby))
-                        - cr * sp * (-ca * r * sbp + cbp * cbr * r * sa - lz)
-                        - sp * sr
-                            * (ca * cbp * r * sby - ly + r * sa * (cbr * sbp * sby - cby * sbr))
-                }
-            },
-            Variable::Yaw => match dimension {
-                Dimension::X => {
-                    -cp * sy * (ca * cbp * cby * r - lx + r * sa * (cbr * cby * sbp + sbr * sby))
-                        + (-cr * cy - sp * sr * sy)
-                            * (ca * cbp * r * sby - ly + r * sa * (cbr * sbp * sby - cby * sbr))
-                        + (-cr * sp * sy + cy * sr) * (-ca * r * sbp + cbp * cbr * r * sa - lz)
-                }
-                Dimension::Y => {
-                    cp * cy * (ca * cbp * cby * r - lx + r * sa * (cbr * cby * sbp + sbr * sby))
-                        + (-cr * sy + cy * sp * sr)
-                            * (ca * cbp * r * sby - ly + r * sa * (cbr * sbp * sby - cby * sbr))
-                        + (cr * cy * sp + sr * sy) * (-ca * r * sbp + cbp * cbr * r * sa - lz)
-                }
-                Dimension::Z => 0.,
-            },
-            Variable::BoresightRoll => match dimension {
-                Dimension::X => {
-                    -cbp * r * sa * sbr * (cr * cy * sp + sr * sy)
-                        + cp * cy * r * sa * (cbr * sby - cby * sbp * sbr)
-                        + r * sa * (-cbr * cby - sbp * sbr * sby) * (-cr * sy + cy * sp * sr)
-                }
-                Dimension::Y => {
-                    -cbp * r * sa * sbr * (cr * sp * sy - cy * sr)
-                        + cp * r * sa * sy * (cbr * sby - cby * sbp * sbr)
-                        + r * sa * (-cbr * cby - sbp * sbr * sby) * (cr * cy + sp * sr * sy)
-                }
-                Dimension::Z => {
-                    -cbp * cp * cr * r * sa * sbr
-                        + cp * r * sa * sr * (-cbr * cby - sbp * sbr * sby)
-                        - r * sa * sp * (cbr * sby - cby * sbp * sbr)
-                }
-            },
-            Variable::BoresightPitch => match dimension {
-                Dimension::X => {
-                    cp * cy * (-ca * cby * r * sbp + cbp * cbr * cby * r * sa)
-                        + (-cr * sy + cy * sp * sr)
-                            * (-ca * r * sbp * sby + cbp * cbr * r * sa * sby)
-                        + (-ca * cbp * r - cbr * r * sa * sbp) * (cr * cy * sp + sr * sy)
-                }
-                Dimension::Y => {
-                    cp * sy * (-ca * cby * r * sbp + cbp * cbr * cby * r * sa)
-                        + (cr * cy + sp * sr * sy)
-                            * (-ca * r * sbp * sby + cbp * cbr * r * sa * sby)
-                        + (-ca * cbp * r - cbr * r * sa * sbp) * (cr * sp * sy - cy * sr)
-                }
-                Dimension::Z => {
-                    cp * cr * (-ca * cbp * r - cbr * r * sa * sbp)
-                        + cp * sr * (-ca * r * sbp * sby + cbp * cbr * r * sa * sby)
-                        - sp * (-ca * cby * r * sbp + cbp * cbr * cby * r * sa)
-                }
-            },
-            Variable::BoresightYaw => match dimension {
-                Dimension::X => {
-                    cp * cy * (-ca * cbp * r * sby + r * sa * (-cbr * sbp * sby + cby * sbr))
-                        + (-cr * sy + cy * sp * sr)
-                            * (ca * cbp * cby * r + r * sa * (cbr * cby * sbp + sbr * sby))
-                }
-                Dimension::Y => {
-                    cp * sy * (-ca * cbp * r * sby + r * sa * (-cbr * sbp * sby + cby * sbr))
-                        + (cr * cy + sp * sr * sy)
-                            * (ca * cbp * cby * r + r * sa * (cbr * cby * sbp + sbr * sby))
-                }
-                Dimension::Z => {
-                    cp * sr * (ca * cbp * cby * r + r * sa * (cbr * cby * sbp + sbr * sby))
-                        - sp * (-ca * cbp * r * sby + r * sa * (-cbr * sbp * sby + cby * sbr))
-                }
-            },
-            Variable::Range => match dimension {
-                Dimension::X => {
-                    cp * cy * (ca * cbp * cby + sa * (cbr * cby * sbp + sbr * sby))
-                        + (-ca * sbp + cbp * cbr * sa) * (cr * cy * sp + sr * sy)
-                        + (-cr * sy + cy * sp * sr)
-                            * (ca * cbp * sby + sa * (cbr * sbp * sby - cby * sbr))
-                }
-                Dimension::Y => {
-                    cp * sy * (ca * cbp * cby + sa * (cbr * cby * sbp + sbr * sby))
-                        + (-ca * sbp + cbp * cbr * sa) * (cr * sp * sy - cy * sr)
-                        + (cr * cy + sp * sr * sy)
-                            * (ca * cbp * sby + sa * (cbr * sbp * sby - cby * sbr))
-                }
-                Dimension::Z => {
-                    cp * cr * (-ca * sbp + cbp * cbr * sa)
-                        + cp * sr * (ca * cbp * sby + sa * (cbr * sbp * sby - cby * sbr))
-                        - sp * (ca * cbp * cby + sa * (cbr * cby * sbp + sbr * sby))
-                }
+    /// Creates a new measurement with a variable in its lidar equation changed by a delta.
+    ///
+    /// See [Measurement::with_variable].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Variable;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let measurement = measurements[0].adjusted(Variable::Range, 1.);
+    /// assert!((measurements[0].range() + 1. - measurement.range()).abs() < 1e-9);
+    /// ```
+    pub fn adjusted(&self, variable: Variable, delta: f64) -> Measurement<L> {
+        self.with_variable(variable, self.value(variable) + delta)
+    }
+
+    /// Returns the one sigma uncertainty of a variable in this measurement's lidar equation.
+    ///
+    /// The range and scan angle uncertainties depend on the incidence angle
+    /// with the surface's normal, as for [Measurement::tpu].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Point, Variable};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let sigma = measurements[0].sigma(Variable::Roll, Point::new(0., 0., 1.));
+    /// assert_eq!(measurements[0].config().uncertainty.roll, sigma);
+    /// ```
+    pub fn sigma(&self, variable: Variable, normal: Point) -> f64 {
+        let incidence_angle = self.capped_incidence_angle(self.incidence_angle(normal));
+        self.uncertainty(variable, incidence_angle)
+    }
+
+    /// Returns this measurement's residuals.
+    ///
+    /// Residuals are computed in the body frame of the aircraft, and are the
+    /// difference between the computed body coordinates and the las body
+    /// coordinates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let residuals = measurements[0].residuals();
+    /// ```
+    pub fn residuals(&self) -> Point {
+        self.modeled_body_frame() - self.body_frame()
+    }
+
+    /// Returns this measurement's total propagated uncertainty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Point;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let uncertainty = measurements[0].tpu(Point::new(0., 0., 1.)).unwrap();
+    /// ```
+    pub fn tpu(&self, normal: Point) -> Result<Tpu, Error> {
+        self.tpu_with_roughness(normal, 0.)
+    }
+
+    /// Returns the total propagated uncertainty with an extra range uncertainty from surface roughness.
+    fn tpu_with_roughness(&self, normal: Point, roughness: f64) -> Result<Tpu, Error> {
+        if let Some(measurement) = self.with_range_correction_normal(normal) {
+            return measurement.tpu_with_roughness(normal, roughness);
+        }
+        let (rotation, displacement) = self.propagation_matrices();
+        let jacobian = self.jacobian(&rotation, &displacement);
+        let incidence_angle = self.incidence_angle(normal);
+        let range = rotation * self.partial_derivatives(Variable::Range);
+        let covariance = jacobian.transpose()
+            * self.uncertainty_covariance(self.capped_incidence_angle(incidence_angle))
+            * jacobian
+            + self.time_covariance(&rotation, &displacement)
+            + roughness.powi(2) * range * range.transpose();
+        Ok(self.tpu_from_covariance(covariance, incidence_angle))
+    }
+
+    /// Returns the covariance from the timing uncertainty, in the propagation frame.
+    ///
+    /// A timing error moves the platform along its trajectory, so the point
+    /// moves with the platform's velocity and with the attitude rates times the
+    /// attitude partial derivatives. See [Measurement::propagation_matrices].
+    fn time_covariance(&self, rotation: &Matrix3, displacement: &Matrix3) -> Matrix3 {
+        let sigma = self.config.uncertainty.time;
+        match self.rates {
+            Some(rates) if sigma > 0. => {
+                let partials = displacement * Point::new(rates.north, rates.east, rates.down)
+                    + rotation
+                        * (rates.roll * self.partial_derivatives(Variable::Roll)
+                            + rates.pitch * self.partial_derivatives(Variable::Pitch)
+                            + rates.yaw * self.partial_derivatives(Variable::Yaw));
+                sigma.powi(2) * partials * partials.transpose()
+            }
+            _ => Matrix3::zeros(),
+        }
+    }
+
+    fn tpu_from_covariance(&self, covariance: Matrix3, incidence_angle: f64) -> Tpu {
+        let x = covariance[(0, 0)].sqrt();
+        let y = covariance[(1, 1)].sqrt();
+        let z = covariance[(2, 2)].sqrt();
+        // The navigation frame is north, east, down, and yaw is the heading from north.
+        let horizontal = covariance.fixed_view::<2, 2>(0, 0);
+        let along = nalgebra::Vector2::new(self.yaw().cos(), self.yaw().sin());
+        let cross = nalgebra::Vector2::new(-self.yaw().sin(), self.yaw().cos());
+        Tpu {
+            x,
+            y,
+            horizontal: (x.powi(2) + y.powi(2)).sqrt(),
+            vertical: z,
+            total: (x.powi(2) + y.powi(2) + z.powi(2)).sqrt(),
+            incidence_angle,
+            sigma_along: along.dot(&(horizontal * along)).sqrt(),
+            sigma_cross: cross.dot(&(horizontal * cross)).sqrt(),
+            quality: if self.extrapolated {
+                TpuQuality::Extrapolated
+            } else if self.capped_incidence_angle(incidence_angle) < incidence_angle {
+                TpuQuality::HighIncidence
+            } else {
+                TpuQuality::Ok
             },
-            Variable::ScanAngle => match dimension {
-                Dimension::X => {
-                    cp * cy * (ca * r * (cbr * cby * sbp + sbr * sby) - cbp * cby * r * sa)
-                        + (-cr * sy + cy * sp * sr)
-                            * (ca * r * (cbr * sbp * sby - cby * sbr) - cbp * r * sa * sby)
-                        + (cr * cy * sp + sr * sy) * (ca * cbp * cbr * r + r * sa * sbp)
-                }
-                Dimension::Y => {
-                    cp * sy * (ca * r * (cbr * cby * sbp + sbr * sby) - cbp * cby * r * sa)
-                        + (cr * cy + sp * sr * sy)
-                            * (ca * r * (cbr * sbp * sby - cby * sbr) - cbp * r * sa * sby)
-                        + (cr * sp * sy - cy * sr) * (ca * cbp * cbr * r + r * sa * sbp)
+        }
+    }
+
+    /// Returns the matrices that carry navigation frame partial derivatives, and platform displacements, into the propagation frame.
+    ///
+    /// For [Propagation::Navigation] both are the identity. For
+    /// [Propagation::Ecef] the point is `ecef(platform) + Cᵀ(platform) * v`,
+    /// where `C` is the rotation from ECEF to a navigation frame and `v` is the
+    /// laser vector in the platform's navigation frame, and the propagation
+    /// frame is north, east, down at the point. A partial derivative of `v`
+    /// goes through `Cᵀ(platform)` and then `C(point)`. A platform displacement
+    /// also moves the platform's latitude and longitude, which turns `C` and
+    /// carries `v` with it, so its matrix has an extra curvature term.
+    fn propagation_matrices(&self) -> (Matrix3, Matrix3) {
+        match self.config.propagation {
+            Propagation::Navigation => (Matrix3::identity(), Matrix3::identity()),
+            Propagation::Ecef => {
+                let ellipsoid = self.ellipsoid();
+                let platform = self.platform();
+                let point = ellipsoid.projected_to_geodetic(self.las.point(), self.utm_zone());
+                let to_local = convert::ecef_to_navigation_matrix(point);
+                let to_ecef = convert::ecef_to_navigation_matrix(platform).transpose();
+                let laser =
+                    self.precomputed().rotation * self.frame_matrix() * self.modeled_body_frame();
+                let [longitude, latitude] =
+                    convert::ecef_to_navigation_matrix_derivatives(platform);
+                let (meridian, prime_vertical) = ellipsoid.radii(platform.y);
+                let mut curvature = Matrix3::zeros();
+                curvature.set_column(0, &(latitude.transpose() * laser / (meridian + platform.z)));
+                curvature.set_column(
+                    1,
+                    &(longitude.transpose() * laser
+                        / ((prime_vertical + platform.z) * platform.y.cos())),
+                );
+                (to_local * to_ecef, to_local * (to_ecef + curvature))
+            }
+        }
+    }
+
+    fn capped_incidence_angle(&self, incidence_angle: f64) -> f64 {
+        self.config
+            .max_incidence_angle
+            .map(|max| incidence_angle.min(max))
+            .unwrap_or(incidence_angle)
+    }
+
+    /// Returns this measurement's total propagated uncertainty, using the DEM's surface normal at the point location.
+    ///
+    /// If the DEM can't provide a normal, e.g. because the point is outside of
+    /// it, the surface is assumed to be horizontal and the uncertainty is
+    /// flagged as [TpuQuality::NoNormal].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Dem, TpuQuality};
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let dem = Dem::from_path("data/dem.tif").unwrap();
+    /// let uncertainty = measurements[0].tpu_with_dem(&dem).unwrap();
+    /// assert_eq!(TpuQuality::Ok, uncertainty.quality);
+    /// ```
+    pub fn tpu_with_dem(&self, dem: &Dem) -> Result<Tpu, Error> {
+        if let Some(normal) = dem.normal(self.x(), self.y()) {
+            self.tpu(normal)
+        } else {
+            let mut tpu = self.tpu(Point::new(0., 0., 1.))?;
+            if tpu.quality != TpuQuality::Extrapolated {
+                tpu.quality = TpuQuality::NoNormal;
+            }
+            Ok(tpu)
+        }
+    }
+
+    /// Returns the laser footprint on a surface with the given normal.
+    ///
+    /// The beam divergence is treated as the full cone angle. The minor axis is
+    /// the footprint diameter perpendicular to the beam, and the major axis is
+    /// stretched along the surface by the incidence angle, which is capped at
+    /// the config's maximum as for [Measurement::tpu].
+    ///
+    /// Returns `None` if the far edge of the beam is at or past grazing
+    /// incidence, since the footprint is unbounded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Point;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let footprint = measurements[0].footprint(Point::new(0., 0., 1.)).unwrap();
+    /// assert!(footprint.major > footprint.minor);
+    /// ```
+    pub fn footprint(&self, normal: Point) -> Option<Footprint> {
+        let range = self.range();
+        let half_divergence = self.config.beam_divergence / 2.;
+        let incidence_angle = self.capped_incidence_angle(self.incidence_angle(normal));
+        if incidence_angle.is_nan()
+            || incidence_angle + half_divergence >= std::f64::consts::FRAC_PI_2
+        {
+            return None;
+        }
+        let minor = 2. * range * half_divergence.tan();
+        let major = range
+            * half_divergence.sin()
+            * (1. / (incidence_angle + half_divergence).cos()
+                + 1. / (incidence_angle - half_divergence).cos());
+        let normal = normal.normalize();
+        let beam = self.beam_direction();
+        let along_surface = beam - normal * beam.dot(&normal);
+        Some(Footprint {
+            major,
+            minor,
+            orientation: along_surface.y.atan2(along_surface.x),
+        })
+    }
+
+    /// Returns the unit vector from the scanner to the point, in (approximate) projected axes.
+    ///
+    /// Grid convergence is ignored.
+    fn beam_direction(&self) -> Point {
+        let navigation = self.precomputed().rotation * self.frame_matrix() * self.body_frame();
+        // The navigation frame is north, east, down.
+        Point::new(navigation.y, navigation.x, -navigation.z).normalize()
+    }
+
+    /// Returns the partial derivatives of the point in the propagation frame, one row per variable.
+    ///
+    /// The gnss variables displace the platform, see [Measurement::propagation_matrices].
+    fn jacobian(&self, rotation: &Matrix3, displacement: &Matrix3) -> SMatrix<f64, 16, 3> {
+        let mut jacobian = SMatrix::zeros();
+        for (row, variable) in Variable::iter().enumerate() {
+            let partials = match variable {
+                Variable::GnssX | Variable::GnssY | Variable::GnssZ => {
+                    displacement * self.partial_derivatives(variable)
                 }
-                Dimension::Z => {
-                    cp * cr * (ca * cbp * cbr * r + r * sa * sbp)
-                        + cp * sr * (ca * r * (cbr * sbp * sby - cby * sbr) - cbp * r * sa * sby)
-                        - sp * (ca * r * (cbr * cby * sbp + sbr * sby) - cbp * cby * r * sa)
+                _ => rotation * self.partial_derivatives(variable),
+            };
+            for (col, dimension) in Dimension::iter().enumerate() {
+                jacobian[(row, col)] = component(partials, dimension);
+            }
+        }
+        jacobian
+    }
+
+    /// Returns the partial derivatives of the navigation frame point with respect to a variable.
+    ///
+    /// The lidar equation is `gnss + R_nav * (R_boresight * scan_frame - lever_arm)`. The
+    /// partials are always north, east, down, no matter the configured convention.
+    fn partial_derivatives(&self, variable: Variable) -> Point {
+        let precomputed = self.precomputed();
+        let from_ned = self.frame_matrix();
+        let navigation = precomputed.rotation * from_ned;
+        let [roll, pitch, yaw] = precomputed.rotation_derivatives;
+        let [boresight_roll, boresight_pitch, boresight_yaw] = precomputed.boresight_derivatives;
+        let scan_frame = precomputed.range * precomputed.direction;
+        let body_frame = precomputed.boresight * scan_frame - self.lever_arm();
+        let rotated = self.boresight_rotated(scan_frame);
+        let lever_arm = navigation * self.lever_arm_matrix();
+        match variable {
+            Variable::GnssX => Point::new(1., 0., 0.),
+            Variable::GnssY => Point::new(0., 1., 0.),
+            Variable::GnssZ => Point::new(0., 0., 1.),
+            Variable::Roll => {
+                let partials = roll * from_ned * body_frame;
+                if self.use_las_scan_angle && self.config.scan_angle_is_roll_compensated {
+                    // The beam stays put in the navigation frame as the platform rolls.
+                    partials + self.partial_derivatives(Variable::ScanAngle)
+                } else {
+                    partials
                 }
-            },
-            Variable::LeverArmX => match dimension {
-                Dimension::X => -cp * cy,
-                Dimension::Y => -cp * sy,
-                Dimension::Z => sp,
-            },
-            Variable::LeverArmY => match dimension {
-                Dimension::X => cr * sy - cy * sp * sr,
-                Dimension::Y => -cr * cy - sp * sr * sy,
-                Dimension::Z => -cp * sr,
-            },
-            Variable::LeverArmZ => match dimension {
-                Dimension::X => -cr * cy * sp - sr * sy,
-                Dimension::Y => -cr * sp * sy + cy * sr,
-                Dimension::Z => -cp * cr,
-            },
+            }
+            Variable::Pitch => pitch * from_ned * body_frame,
+            Variable::Yaw => yaw * from_ned * body_frame,
+            Variable::BoresightRoll => navigation * boresight_roll * rotated,
+            Variable::BoresightPitch => navigation * boresight_pitch * rotated,
+            Variable::BoresightYaw => navigation * boresight_yaw * rotated,
+            Variable::Range => navigation * precomputed.boresight * precomputed.direction,
+            Variable::ScanAngle => {
+                navigation
+                    * precomputed.boresight
+                    * (precomputed.range * precomputed.direction_derivative)
+            }
+            Variable::LeverArmX => -lever_arm.column(0),
+            Variable::LeverArmY => -lever_arm.column(1),
+            Variable::LeverArmZ => -lever_arm.column(2),
+            Variable::ScanAngleOffset | Variable::ScanAngleScale => {
+                self.scan_angle_calibration_derivative(variable)
+                    * self.partial_derivatives(Variable::ScanAngle)
+            }
+        }
+    }
+
+    /// Returns the derivative of the scan angle with respect to a scan angle calibration variable.
+    ///
+    /// The calibration only applies to las scan angles, so this is zero for computed scan angles.
+    fn scan_angle_calibration_derivative(&self, variable: Variable) -> f64 {
+        if !self.use_las_scan_angle {
+            return 0.;
+        }
+        match variable {
+            Variable::ScanAngleOffset => 1.,
+            Variable::ScanAngleScale => self.las.scan_angle().to_radians(),
+            _ => 0.,
         }
     }
 
-    fn incidence_angle(&self, normal: Point) -> f64 {
+    /// Returns the angle between the laser beam and a surface normal, in radians.
+    ///
+    /// The normal is in projected coordinates, e.g. from a [Dem] or from
+    /// [normals_from_neighbors]. Unlike [Measurement::tpu], the angle isn't
+    /// capped at the config's maximum. Surfaces seen from behind have incidence
+    /// angles past 90°.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Point;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let incidence_angle = measurements[0].incidence_angle(Point::new(0., 0., 1.));
+    /// let tpu = measurements[0].tpu(Point::new(0., 0., 1.)).unwrap();
+    /// assert_eq!(tpu.incidence_angle, incidence_angle);
+    /// ```
+    pub fn incidence_angle(&self, normal: Point) -> f64 {
+        self.incidence_angle_at(self.body_frame(), normal)
+    }
+
+    fn incidence_angle_at(&self, body_frame: Point, normal: Point) -> f64 {
         let projected_normal_endpoint = self.las.point() + normal;
-        let body_normal_endpoint = convert::projected_to_body(
+        let body_normal_endpoint = self.ellipsoid().projected_to_body(
             projected_normal_endpoint,
             self.platform(),
             self.rpy(),
             self.utm_zone(),
+            self.config.navigation_frame,
         );
-        let body_frame = self.body_frame();
         let normal = body_frame - body_normal_endpoint;
         (normal.dot(&body_frame) / (normal.norm() * body_frame.norm())).acos()
     }
 
-    fn uncertainty_covariance(&self, incidence_angle: f64) -> SMatrix<f64, 14, 14> {
-        let mut matrix = SMatrix::<f64, 14, 14>::zeros();
+    /// Returns the components of this measurement's range and scan angle uncertainties.
+    ///
+    /// The incidence angle is capped at the config's maximum, as for [Measurement::tpu].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Point;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let range_error = measurements[0].range_error(Point::new(0., 0., 1.));
+    /// assert!(range_error.range() > measurements[0].config().uncertainty.range);
+    /// ```
+    pub fn range_error(&self, normal: Point) -> RangeError {
+        self.range_error_at(self.capped_incidence_angle(self.incidence_angle(normal)))
+    }
+
+    fn range_error_at(&self, incidence_angle: f64) -> RangeError {
+        let mut range_error = self.config.range_error_model.range_error(
+            self.config.uncertainty.range,
+            self.config.uncertainty.scan_angle.at(self.scan_angle()),
+            self.config.beam_divergence,
+            self.range(),
+            incidence_angle,
+        );
+        range_error.range_atmosphere = self.atmosphere_range_uncertainty();
+        range_error.range_return = self
+            .config
+            .returns
+            .range_uncertainty(self.las.return_number());
+        range_error
+    }
+
+    fn atmosphere_range_uncertainty(&self) -> f64 {
+        self.config
+            .atmosphere
+            .map(|atmosphere| atmosphere.range_uncertainty(self.range()))
+            .unwrap_or(0.)
+    }
+
+    fn uncertainty_covariance(&self, incidence_angle: f64) -> SMatrix<f64, 16, 16> {
+        let mut matrix = SMatrix::<f64, 16, 16>::zeros();
         for (i, variable) in Variable::iter().enumerate() {
             matrix[(i, i)] = self.uncertainty(variable, incidence_angle).powi(2);
         }
+        let gnss = Variable::iter()
+            .position(|variable| variable == Variable::GnssX)
+            .expect("gnss x is a variable");
+        matrix
+            .fixed_view_mut::<3, 3>(gnss, gnss)
+            .copy_from(&self.gnss_covariance());
         matrix
     }
 
+    /// Returns the gnss covariance in the navigation frame.
+    fn gnss_covariance(&self) -> Matrix3 {
+        let uncertainty = &self.config.uncertainty;
+        let rotation = self.gnss_rotation();
+        let variances = Point::new(
+            uncertainty.gnss_x.powi(2),
+            uncertainty.gnss_y.powi(2),
+            uncertainty.gnss_z.powi(2),
+        );
+        rotation * Matrix3::from_diagonal(&variances) * rotation.transpose()
+    }
+
+    /// Returns the rotation from the configured gnss uncertainty frame to the navigation frame.
+    fn gnss_rotation(&self) -> Matrix3 {
+        let enu = |convergence: f64| {
+            Matrix3::new(
+                convergence.sin(),
+                convergence.cos(),
+                0.,
+                convergence.cos(),
+                -convergence.sin(),
+                0.,
+                0.,
+                0.,
+                -1.,
+            )
+        };
+        match self.config.uncertainty.gnss_frame {
+            GnssFrame::Navigation => Matrix3::identity(),
+            GnssFrame::Enu => enu(0.),
+            GnssFrame::Ecef => convert::ecef_to_navigation_matrix(self.platform()),
+            GnssFrame::Projected => {
+                // The direction of true north in grid coordinates.
+                let platform = self.platform();
+                let north = platform + Point::new(0., 1e-6, 0.);
+                let delta = self
+                    .ellipsoid()
+                    .geodetic_to_projected(north, self.utm_zone())
+                    - self
+                        .ellipsoid()
+                        .geodetic_to_projected(platform, self.utm_zone());
+                enu(delta.x.atan2(delta.y))
+            }
+        }
+    }
+
     fn uncertainty(&self, variable: Variable, incidence_angle: f64) -> f64 {
         use Variable::*;
         match variable {
@@ -858,18 +2501,18 @@ impl<L: Lasish> Measurement<L> {
             LeverArmX => self.config.uncertainty.lever_arm_x,
             LeverArmY => self.config.uncertainty.lever_arm_y,
             LeverArmZ => self.config.uncertainty.lever_arm_z,
-            Range => (self.config.uncertainty.range.powi(2)
-                + (self.range() * self.config.beam_divergence / 4.0 * incidence_angle.tan()))
-            .sqrt(),
-            ScanAngle => {
-                self.config.uncertainty.scan_angle.powi(2)
-                    + (self.config.beam_divergence / 4.0).powi(2)
-            }
+            Range => self.range_error_at(incidence_angle).range(),
+            ScanAngle => self.range_error_at(incidence_angle).scan_angle(),
+            // Calibration errors are part of the scan angle uncertainty.
+            ScanAngleOffset | ScanAngleScale => 0.,
         }
     }
 }
 
-impl Lasish for las::Point {
+impl PointSource for las::Point {
+    /// The point's extra bytes.
+    type Extra = Vec<u8>;
+
     fn time(&self) -> Option<f64> {
         self.gps_time
     }
@@ -889,11 +2532,99 @@ impl Lasish for las::Point {
     fn scan_angle(&self) -> f64 {
         f64::from(self.scan_angle)
     }
+
+    fn classification(&self) -> Option<u8> {
+        Some(u8::from(self.classification))
+    }
+
+    fn return_number(&self) -> Option<u8> {
+        Some(self.return_number)
+    }
+
+    fn intensity(&self) -> Option<u16> {
+        Some(self.intensity)
+    }
+
+    fn number_of_returns(&self) -> Option<u8> {
+        Some(self.number_of_returns)
+    }
+
+    fn point_source_id(&self) -> Option<u16> {
+        Some(self.point_source_id)
+    }
+
+    fn user_data(&self) -> Option<u8> {
+        Some(self.user_data)
+    }
+
+    fn to_las(&self) -> las::Point {
+        self.clone()
+    }
+
+    fn extra(&self) -> Option<&Vec<u8>> {
+        Some(&self.extra_bytes)
+    }
+}
+
+/// Measurements pass through their lidar point's attributes, so they can be sampled and filtered like points.
+impl<L: PointSource> PointSource for Measurement<L> {
+    type Extra = L::Extra;
+
+    fn time(&self) -> Option<f64> {
+        self.las.time()
+    }
+
+    fn x(&self) -> f64 {
+        self.las.x()
+    }
+
+    fn y(&self) -> f64 {
+        self.las.y()
+    }
+
+    fn z(&self) -> f64 {
+        self.las.z()
+    }
+
+    fn to_las(&self) -> las::Point {
+        self.las.to_las()
+    }
+
+    fn scan_angle(&self) -> f64 {
+        self.las.scan_angle()
+    }
+
+    fn classification(&self) -> Option<u8> {
+        self.las.classification()
+    }
+
+    fn return_number(&self) -> Option<u8> {
+        self.las.return_number()
+    }
+
+    fn intensity(&self) -> Option<u16> {
+        self.las.intensity()
+    }
+
+    fn number_of_returns(&self) -> Option<u8> {
+        self.las.number_of_returns()
+    }
+
+    fn point_source_id(&self) -> Option<u16> {
+        self.las.point_source_id()
+    }
+
+    fn user_data(&self) -> Option<u8> {
+        self.las.user_data()
+    }
+    fn extra(&self) -> Option<&L::Extra> {
+        self.las.extra()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Point;
+    use crate::{GnssFrame, Point};
     use approx::assert_relative_eq;
 
     #[test]
@@ -907,6 +2638,120 @@ mod tests {
         assert_eq!(2687.59, measurement.z());
     }
 
+    #[test]
+    fn ancillary_attributes() {
+        use super::PointSource;
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let point = measurements[0].lasish();
+        assert_eq!(Some(point.intensity), point.intensity());
+        assert_eq!(Some(point.return_number), point.return_number());
+        assert_eq!(Some(point.number_of_returns), point.number_of_returns());
+        let tabular = crate::tabular::read_csv("data/points.csv").unwrap()[0];
+        assert_eq!(None, tabular.intensity());
+        assert_eq!(0, tabular.to_las().intensity);
+    }
+
+    #[test]
+    fn missing_pose() {
+        use super::MissingPose;
+        use crate::{Config, Trajectory};
+        use las::Read;
+        let trajectory = Trajectory::from_path("data/sbet.out")
+            .unwrap()
+            .subset(400825.5, 400826.);
+        let config = Config::from_path("data/config.toml").unwrap();
+        let points = las::Reader::from_path("data/points.las")
+            .unwrap()
+            .points()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let count = points.len();
+        assert!(matches!(
+            super::match_points(
+                &trajectory,
+                points.clone(),
+                config.clone(),
+                MissingPose::Fail
+            ),
+            Err(crate::Error::TrajectoryLookup(_))
+        ));
+        let (measurements, summary) = super::match_points(
+            &trajectory,
+            points.clone(),
+            config.clone(),
+            MissingPose::Skip,
+        )
+        .unwrap();
+        assert!(summary.skipped > 0);
+        assert_eq!(0, summary.nearest);
+        assert_eq!(count, measurements.len() + summary.skipped);
+        let (measurements, summary) =
+            super::match_points(&trajectory, points, config.clone(), MissingPose::Nearest).unwrap();
+        assert_eq!(0, summary.skipped);
+        assert!(summary.nearest > 0);
+        assert_eq!(count, measurements.len());
+        let extrapolated = measurements
+            .iter()
+            .filter(|measurement| {
+                measurement.tpu(Point::new(0., 0., 1.)).unwrap().quality
+                    == crate::TpuQuality::Extrapolated
+            })
+            .count();
+        assert_eq!(summary.nearest, extrapolated);
+    }
+
+    #[test]
+    fn multiple_returns() {
+        use crate::{Config, Returns, Trajectory};
+        use las::Read;
+        let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+        let mut config = Config::from_path("data/config.toml").unwrap();
+        config.returns = Returns {
+            per_pulse: true,
+            range: vec![0., 0.05],
+        };
+        let first = las::Reader::from_path("data/points.las")
+            .unwrap()
+            .points()
+            .next()
+            .unwrap()
+            .unwrap();
+        let mut last = first.clone();
+        last.return_number = 2;
+        last.x += 0.3;
+        last.z -= 5.;
+        let (mut measurements, _) = super::match_points(
+            &trajectory,
+            vec![first, last],
+            config,
+            super::MissingPose::Fail,
+        )
+        .unwrap();
+        assert_eq!(vec![vec![0, 1]], super::pulses(&measurements));
+        assert_ne!(measurements[0].scan_angle(), measurements[1].scan_angle());
+        let normal = Point::new(0., 0., 1.);
+        assert_eq!(0., measurements[0].range_error(normal).range_return);
+        assert_eq!(0.05, measurements[1].range_error(normal).range_return);
+        super::model_per_pulse(&mut measurements);
+        assert_eq!(measurements[0].scan_angle(), measurements[1].scan_angle());
+        assert!(measurements[1].range() > measurements[0].range());
+    }
+
+    #[test]
+    fn time_range() {
+        use las::Read;
+        let points = las::Reader::from_path("data/points.las")
+            .unwrap()
+            .points()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let (start, end) = super::time_range(&points).unwrap();
+        assert_eq!(400825.10568986, start);
+        assert_eq!(400825.89946538, end);
+        assert!(super::time_range::<las::Point>(&[]).is_none());
+    }
+
     #[test]
     fn body_frame() {
         let measurements =
@@ -926,6 +2771,50 @@ mod tests {
         assert_relative_eq!(4660.10, range, max_relative = 1e-2);
     }
 
+    #[test]
+    fn range_with_atmosphere() {
+        use crate::{Atmosphere, Measurement, PointSource};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut config = measurements[0].config();
+        let range = measurements[0].range();
+        let mut atmosphere = Atmosphere::new(15., 1013.25, 50.);
+        config.atmosphere = Some(atmosphere);
+        let measurement = measurements[0].with_config(config.clone());
+        assert_eq!(range, measurement.range());
+        assert_eq!(range, measurement.modeled_scan_frame().norm());
+
+        #[derive(Clone)]
+        struct Ranged(las::Point, f64);
+        impl PointSource for Ranged {
+            type Extra = ();
+            fn time(&self) -> Option<f64> {
+                self.0.gps_time
+            }
+            fn x(&self) -> f64 {
+                self.0.x
+            }
+            fn y(&self) -> f64 {
+                self.0.y
+            }
+            fn z(&self) -> f64 {
+                self.0.z
+            }
+            fn range(&self) -> Option<f64> {
+                Some(self.1)
+            }
+        }
+        let ranged = Ranged(measurement.las.clone(), range + 1.);
+        let ranged = Measurement::from_parts(measurement.sbet, ranged, config.clone());
+        assert_relative_eq!((range + 1.) / atmosphere.refractive_index(), ranged.range());
+
+        let tpu = measurement.tpu(Point::new(0., 0., 1.)).unwrap();
+        atmosphere.pressure_uncertainty = 10.;
+        config.atmosphere = Some(atmosphere);
+        let measurement = measurements[0].with_config(config.clone());
+        assert!(measurement.tpu(Point::new(0., 0., 1.)).unwrap().total > tpu.total);
+    }
+
     #[test]
     fn scan_angle() {
         let measurements =
@@ -940,6 +2829,328 @@ mod tests {
         assert_eq!(22f64.to_radians(), measurement.scan_angle());
     }
 
+    #[test]
+    fn scan_angle_calibration() {
+        use crate::{Dimension, Variable};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut measurement = measurements[0].clone();
+        let mut config = measurement.config();
+        config.scan_angle_calibration.scale = 1.01;
+        config.scan_angle_calibration.offset = 0.001;
+        let calibrated = measurement.with_config(config.clone());
+        assert_eq!(measurement.scan_angle(), calibrated.scan_angle());
+        assert_eq!(
+            0.,
+            calibrated.partial_derivative_in_body_frame(Dimension::Y, Variable::ScanAngleScale)
+        );
+        measurement.use_las_scan_angle(true);
+        let calibrated = measurement.with_config(config.clone());
+        assert_relative_eq!(
+            1.01 * 22f64.to_radians() + 0.001,
+            calibrated.scan_angle(),
+            epsilon = 1e-12
+        );
+        let step = 1e-7;
+        for variable in [Variable::ScanAngleOffset, Variable::ScanAngleScale] {
+            let value = config.values(&[variable]).unwrap()[0];
+            let stepped = config.with_values(&[variable], &[value + step]).unwrap();
+            let numeric = (measurement.with_config(stepped).modeled_body_frame()
+                - calibrated.modeled_body_frame())
+                / step;
+            for (dimension, numeric) in Dimension::iter().zip(numeric.iter()) {
+                assert_relative_eq!(
+                    *numeric,
+                    calibrated.partial_derivative_in_body_frame(dimension, variable),
+                    epsilon = 1e-3
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn datum_transformation() {
+        use crate::convert::{self, Helmert};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let measurement = &measurements[0];
+        let platform = measurement.platform();
+        // One meter up, in ECEF, at the platform.
+        let up = -convert::ecef_to_navigation_matrix(platform)
+            .row(2)
+            .transpose();
+        let mut config = measurement.config();
+        config.datum_transformation = Some(Helmert {
+            translation: Point::zeros(),
+            rotation: Point::zeros(),
+            scale: 0.,
+            translation_rate: up * 0.1,
+            rotation_rate: Point::zeros(),
+            scale_rate: 0.,
+            reference_epoch: 2010.,
+        });
+        config.epoch = Some(2020.);
+        let transformed = measurement.with_config(config);
+        assert_relative_eq!(platform.z + 1., transformed.platform().z, epsilon = 1e-6);
+        assert_relative_eq!(platform.x, transformed.platform().x, epsilon = 1e-12);
+        assert_relative_eq!(
+            measurement.body_frame().z + 1.,
+            transformed.body_frame().z,
+            epsilon = 1e-2
+        );
+    }
+
+    #[test]
+    fn attitude_partial_derivatives() {
+        use crate::{Dimension, Variable};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let measurement = &measurements[0];
+        let step = 1e-7;
+        for (i, variable) in [Variable::Roll, Variable::Pitch, Variable::Yaw]
+            .iter()
+            .enumerate()
+        {
+            let mut offset = [0.; 3];
+            offset[i] = step;
+            let stepped = measurement.with_variable(*variable, measurement.value(*variable) + step);
+            let numeric = (stepped.residuals() - measurement.residuals()) / step;
+            let offset = measurement.with_attitude_offset(offset);
+            assert_relative_eq!(stepped.body_frame(), offset.body_frame(), epsilon = 1e-6);
+            for (dimension, numeric) in Dimension::iter().zip(numeric.iter()) {
+                assert_relative_eq!(
+                    *numeric,
+                    measurement.partial_derivative_in_body_frame(dimension, *variable),
+                    epsilon = 1e-2
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn roll_compensation() {
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut measurement = measurements[0].clone();
+        measurement.use_las_scan_angle(true);
+        let mut config = measurement.config();
+        config.scan_angle_is_roll_compensated = true;
+        let compensated = measurement.with_config(config);
+        assert_relative_eq!(
+            measurement.scan_angle() + measurement.roll(),
+            compensated.scan_angle(),
+            epsilon = 1e-12
+        );
+        assert_eq!(None, super::detect_roll_compensation(&measurements[..0]));
+
+        use crate::{
+            simulate::{self, Options, Terrain},
+            Measurement, Trajectory,
+        };
+        let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+        let config = measurement.config();
+        let options = Options {
+            duration: None,
+            pulse_rate: 1000.,
+            ..Default::default()
+        };
+        let points = simulate::simulate(
+            &trajectory,
+            config.clone(),
+            &Terrain::Plane(2690.),
+            &options,
+        )
+        .unwrap();
+        let mut platform = Vec::new();
+        let mut compensated = Vec::new();
+        for mut point in points {
+            let measurement = Measurement::new(&trajectory, point.clone(), config.clone()).unwrap();
+            platform.push(measurement.clone());
+            point.scan_angle -= measurement.roll().to_degrees() as f32;
+            compensated.push(Measurement::new(&trajectory, point, config.clone()).unwrap());
+        }
+        assert_eq!(Some(false), super::detect_roll_compensation(&platform));
+        assert_eq!(Some(true), super::detect_roll_compensation(&compensated));
+    }
+
+    #[test]
+    fn adjusted() {
+        use crate::{convert, Variable};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let measurement = &measurements[0];
+        let platform = measurement.platform();
+        let navigation = |measurement: &super::Measurement<las::Point>| {
+            let modeled =
+                convert::body_to_navigation(measurement.modeled_body_frame(), measurement.rpy());
+            convert::ecef_to_navigation(
+                convert::navigation_to_ecef(modeled, measurement.platform()),
+                platform,
+            )
+        };
+        let modeled = navigation(measurement);
+        for variable in Variable::iter() {
+            let step = match variable {
+                Variable::Range
+                | Variable::LeverArmX
+                | Variable::LeverArmY
+                | Variable::LeverArmZ
+                | Variable::GnssX
+                | Variable::GnssY
+                | Variable::GnssZ => 1e-3,
+                _ => 1e-7,
+            };
+            let numeric = (navigation(&measurement.adjusted(variable, step)) - modeled) / step;
+            assert_relative_eq!(
+                numeric,
+                measurement.partial_derivatives(variable),
+                epsilon = 1e-2
+            );
+        }
+    }
+
+    #[test]
+    fn yaw_across_the_wrap() {
+        use crate::Variable;
+        use std::f64::consts::PI;
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let below = measurements[0].with_variable(Variable::Yaw, PI - 1e-7);
+        let above = below.adjusted(Variable::Yaw, 2e-7);
+        assert!((above.yaw() + PI - 1e-7).abs() < 1e-9);
+        assert!((above.modeled_point() - below.modeled_point()).norm() < 1e-3);
+        assert!((above.residuals() - below.residuals()).norm() < 1e-3);
+    }
+
+    #[test]
+    fn rotation_order() {
+        use crate::{RollPitchYaw, RotationOrder};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut config = measurements[0].config();
+        config.rotation_order = RotationOrder::Xzy;
+        let measurement = measurements[0].with_config(config.clone());
+        let rpy = measurement.boresight_rpy();
+        assert_relative_eq!(
+            RotationOrder::Xzy.matrix(rpy),
+            measurement.boresight(),
+            epsilon = 1e-12
+        );
+        let step = 1e-7;
+        let order = RotationOrder::Yzx;
+        let rpy = RollPitchYaw::new(0.1, -0.2, 0.3);
+        let numeric =
+            (order.matrix(RollPitchYaw::new(0.1 + step, -0.2, 0.3)) - order.matrix(rpy)) / step;
+        assert_relative_eq!(numeric, order.roll_derivative(rpy), epsilon = 1e-6);
+        let numeric =
+            (order.matrix(RollPitchYaw::new(0.1, -0.2 + step, 0.3)) - order.matrix(rpy)) / step;
+        assert_relative_eq!(numeric, order.pitch_derivative(rpy), epsilon = 1e-6);
+        let numeric =
+            (order.matrix(RollPitchYaw::new(0.1, -0.2, 0.3 + step)) - order.matrix(rpy)) / step;
+        assert_relative_eq!(numeric, order.yaw_derivative(rpy), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn navigation_frame() {
+        use crate::{NavigationFrame, RollPitchYaw};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut config = measurements[0].config();
+        config.boresight = RollPitchYaw::new(0., 0., 0.);
+        let ned = measurements[0].with_config(config.clone());
+        let lever_arm = config.lever_arm;
+        config.navigation_frame = NavigationFrame::Enu;
+        config.lever_arm = Point::new(lever_arm.y, lever_arm.x, -lever_arm.z);
+        let enu = measurements[0].with_config(config.clone());
+        let mirror = |point: Point| Point::new(point.y, point.x, -point.z);
+        assert_relative_eq!(mirror(ned.body_frame()), enu.body_frame(), epsilon = 1e-6);
+        assert_relative_eq!(
+            mirror(ned.modeled_body_frame()),
+            enu.modeled_body_frame(),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(mirror(ned.residuals()), enu.residuals(), epsilon = 1e-6);
+        assert_relative_eq!(ned.scan_angle(), enu.scan_angle(), epsilon = 1e-9);
+        let normal = Point::new(0., 0., 1.);
+        let (ned, enu) = (ned.tpu(normal).unwrap(), enu.tpu(normal).unwrap());
+        assert_relative_eq!(ned.horizontal, enu.horizontal, epsilon = 1e-9);
+        assert_relative_eq!(ned.vertical, enu.vertical, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn conic_scanner() {
+        use crate::{Dimension, ScannerModel, Variable};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut config = measurements[0].config();
+        config.scanner = ScannerModel::Palmer { mirror_tilt: 0.2 };
+        let measurement = measurements[0].with_config(config.clone());
+        let scan_frame = measurement.modeled_scan_frame();
+        assert_relative_eq!(measurement.range(), scan_frame.norm());
+        assert_relative_eq!(0.4, (scan_frame.x / scan_frame.norm()).acos());
+        let partials: Vec<f64> = Dimension::iter()
+            .map(|dimension| {
+                measurement.partial_derivative_in_body_frame(dimension, Variable::ScanAngle)
+            })
+            .collect();
+        let beam = measurement.boresight() * scan_frame;
+        assert_relative_eq!(
+            0.,
+            Point::from_vec(partials).dot(&beam),
+            epsilon = 1e-6 * beam.norm_squared()
+        );
+        assert!(measurement.tpu(Point::new(0., 0., 1.)).unwrap().total > 0.);
+    }
+
+    #[test]
+    fn gnss_frame() {
+        use crate::GnssFrame;
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let normal = Point::new(0., 0., 1.);
+        let mut config = measurements[0].config();
+        config.uncertainty.gnss_x = 0.1;
+        config.uncertainty.gnss_y = 0.2;
+        let navigation = measurements[0]
+            .with_config(config.clone())
+            .tpu(normal)
+            .unwrap();
+        config.uncertainty.gnss_frame = GnssFrame::Enu;
+        config.uncertainty.gnss_x = 0.2;
+        config.uncertainty.gnss_y = 0.1;
+        let enu = measurements[0]
+            .with_config(config.clone())
+            .tpu(normal)
+            .unwrap();
+        assert_relative_eq!(navigation.x, enu.x);
+        assert_relative_eq!(navigation.y, enu.y);
+        assert_relative_eq!(navigation.vertical, enu.vertical);
+        config.uncertainty.gnss_frame = GnssFrame::Projected;
+        let projected = measurements[0]
+            .with_config(config.clone())
+            .tpu(normal)
+            .unwrap();
+        assert_relative_eq!(enu.x, projected.x, max_relative = 1e-3);
+        assert_relative_eq!(enu.total, projected.total);
+        config.uncertainty.gnss_frame = GnssFrame::Ecef;
+        let ecef = measurements[0]
+            .with_config(config.clone())
+            .tpu(normal)
+            .unwrap();
+        assert!(ecef.vertical > enu.vertical);
+    }
+
+    #[test]
+    fn along_and_cross_track() {
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let tpu = measurements[0].tpu(Point::new(0., 0., 1.)).unwrap();
+        assert_relative_eq!(
+            tpu.horizontal,
+            (tpu.sigma_along.powi(2) + tpu.sigma_cross.powi(2)).sqrt()
+        );
+    }
+
     #[test]
     fn uncertainty() {
         let measurements =
@@ -947,6 +3158,354 @@ mod tests {
         let _uncertainty = measurements[0].tpu(Point::new(0., 0., 1.)).unwrap();
     }
 
+    #[test]
+    fn tpu_from_neighbors() {
+        use super::PointSource;
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let tpus = super::tpu_from_neighbors(&measurements, 8).unwrap();
+        let tree = crate::spatial::KdTree::from_measurements(&measurements);
+        for (measurement, tpu) in measurements.iter().zip(&tpus).step_by(100) {
+            let neighbors: Vec<Point> = tree
+                .nearest(measurement.lasish().point(), 8)
+                .into_iter()
+                .map(|(index, _)| tree.point(index))
+                .collect();
+            let plane = crate::utils::fit_plane(&neighbors).unwrap();
+            let (normal, roughness) = (plane.normal, plane.rms);
+            let smooth = measurement.tpu(normal).unwrap();
+            assert_relative_eq!(smooth.incidence_angle, tpu.incidence_angle);
+            assert!(roughness == 0. || tpu.total > smooth.total);
+        }
+        let tilted = [
+            Point::new(0., 0., 0.),
+            Point::new(1., 0., 1.),
+            Point::new(0., 1., 0.),
+            Point::new(1., 1., 1.),
+        ];
+        let plane = crate::utils::fit_plane(&tilted).unwrap();
+        assert_relative_eq!(
+            Point::new(-1., 0., 1.).normalize(),
+            plane.normal,
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(0., plane.rms, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn crs() {
+        use super::{CrsConflict, CrsMismatch, ReadOptions};
+        use crate::crs::Datum;
+        use std::{fs::File, io::Cursor};
+        let config = std::fs::read_to_string("data/config.toml").unwrap();
+        let read = |config: String, crs_mismatch| {
+            super::measurements_from_readers(
+                File::open("data/sbet.out").unwrap(),
+                File::open("data/points.las").unwrap(),
+                Cursor::new(config),
+                &ReadOptions {
+                    crs_mismatch,
+                    ..Default::default()
+                },
+            )
+        };
+        let (measurements, summary) =
+            read(config.replace("utm_zone = 11", ""), CrsMismatch::Warn).unwrap();
+        assert_eq!(Some(11), measurements[0].config().utm_zone);
+        assert_eq!(None, summary.crs_conflict);
+        let twelve = config.replace("utm_zone = 11", "utm_zone = 12");
+        let (measurements, summary) = read(twelve.clone(), CrsMismatch::Warn).unwrap();
+        assert_eq!(Some(12), measurements[0].config().utm_zone);
+        assert_eq!(
+            Some(CrsConflict::UtmZone {
+                configured: 12,
+                header: 11
+            }),
+            summary.crs_conflict
+        );
+        assert!(read(twelve.clone(), CrsMismatch::Fail).is_err());
+        let (_, summary) = read(twelve, CrsMismatch::Ignore).unwrap();
+        assert_eq!(None, summary.crs_conflict);
+        let nad83 = format!("datum = \"nad83\"\n{}", config);
+        assert_eq!(
+            Some(CrsConflict::Datum {
+                configured: Datum::Nad83,
+                header: Datum::Wgs84
+            }),
+            read(nad83, CrsMismatch::Warn).unwrap().1.crs_conflict
+        );
+    }
+
+    #[test]
+    fn time_uncertainty() {
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let normal = Point::new(0., 0., 1.);
+        let measurement = &measurements[0];
+        let before = measurement.tpu(normal).unwrap();
+        let mut config = measurement.config();
+        config.uncertainty.time = 1e-3;
+        let measurement = measurement.with_config(config);
+        let after = measurement.tpu(normal).unwrap();
+        // The platform moves about 11 cm in a millisecond.
+        assert!(after.horizontal.powi(2) - before.horizontal.powi(2) > 0.1f64.powi(2));
+        let batch = super::tpu_batch(&[measurement], normal).unwrap();
+        assert_relative_eq!(after.total, batch[0].total, max_relative = 1e-9);
+    }
+
+    #[test]
+    fn lever_arm_frame() {
+        use crate::{Dimension, LeverArmFrame, Variable};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let measurement = &measurements[0];
+        let body = measurement.lever_arm();
+        let partial = |measurement: &super::Measurement<las::Point>| {
+            measurement.partial_derivative_in_body_frame(Dimension::X, Variable::BoresightRoll)
+        };
+        let mut config = measurement.config();
+        config.lever_arm_frame = LeverArmFrame::Imu;
+        config.lever_arm = -body;
+        let imu = measurement.with_config(config.clone());
+        assert_relative_eq!(body, imu.lever_arm());
+        assert_relative_eq!(measurement.modeled_body_frame(), imu.modeled_body_frame());
+        assert_eq!(
+            1.,
+            imu.partial_derivative_in_body_frame(Dimension::X, Variable::LeverArmX)
+        );
+        config.lever_arm_frame = LeverArmFrame::Sensor;
+        config.lever_arm = measurement.boresight().transpose() * body;
+        let sensor = measurement.with_config(config.clone());
+        assert_relative_eq!(body, sensor.lever_arm(), epsilon = 1e-12);
+        assert_relative_eq!(
+            measurement.modeled_body_frame(),
+            sensor.modeled_body_frame(),
+            epsilon = 1e-9
+        );
+        // A lever arm in the scanner's axes turns with the boresight.
+        let roll = config.rotation_order.roll_derivative(config.boresight) * config.lever_arm;
+        assert_relative_eq!(
+            partial(measurement) - roll.x,
+            partial(&sensor),
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn ecef_propagation() {
+        use crate::{convert, Lasish, Propagation, Variable};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut config = measurements[0].config();
+        config.propagation = Propagation::Ecef;
+        let measurement = measurements[0].with_config(config);
+        let ellipsoid = measurement.ellipsoid();
+        let platform = measurement.platform();
+        let laser = measurement.precomputed().rotation
+            * measurement.frame_matrix()
+            * measurement.modeled_body_frame();
+        let point =
+            ellipsoid.projected_to_geodetic(measurement.las.point(), measurement.utm_zone());
+        let to_local = convert::ecef_to_navigation_matrix(point);
+        // The point in the local level frame, with the platform moved north, east, and down, and the laser vector moved.
+        let (meridian, prime_vertical) = ellipsoid.radii(platform.y);
+        let local = |displacement: Point, delta: Point| {
+            let platform = platform
+                + Point::new(
+                    displacement.y / ((prime_vertical + platform.z) * platform.y.cos()),
+                    displacement.x / (meridian + platform.z),
+                    -displacement.z,
+                );
+            to_local * ellipsoid.navigation_to_ecef(laser + delta, platform)
+        };
+        let (rotation, displacement) = measurement.propagation_matrices();
+        let jacobian = measurement.jacobian(&rotation, &displacement);
+        for (row, variable) in Variable::iter().enumerate() {
+            let partials = measurement.partial_derivatives(variable);
+            let zero = Point::zeros();
+            let numeric = match variable {
+                Variable::GnssX | Variable::GnssY | Variable::GnssZ => {
+                    (local(partials, zero) - local(-partials, zero)) / 2.
+                }
+                _ => (local(zero, partials) - local(zero, -partials)) / 2.,
+            };
+            let analytic: Point = jacobian.row(row).transpose().into_owned();
+            assert_relative_eq!(numeric, analytic, epsilon = 1e-6 * numeric.norm().max(1.));
+        }
+        // Moving the platform turns its navigation frame, which a rotation alone misses.
+        let gnss = Variable::iter()
+            .position(|variable| variable == Variable::GnssX)
+            .unwrap();
+        let gnss = jacobian.fixed_rows::<3>(gnss).transpose();
+        assert!((gnss - rotation).abs().max() > 1e-5);
+
+        let navigation = measurements[0].tpu(Point::new(0., 0., 1.)).unwrap();
+        let tpu = measurement.tpu(Point::new(0., 0., 1.)).unwrap();
+        assert!(tpu.vertical != navigation.vertical);
+        let batch = super::tpu_batch(&[measurement], Point::new(0., 0., 1.)).unwrap();
+        assert_relative_eq!(tpu.vertical, batch[0].vertical, max_relative = 1e-9);
+    }
+
+    #[test]
+    fn range_correction_normal() {
+        use crate::RangeCorrection;
+        use std::sync::Arc;
+
+        #[derive(Debug)]
+        struct Incidence;
+        impl RangeCorrection for Incidence {
+            fn correct(&self, _: Option<u16>, range: f64, incidence_angle: f64) -> f64 {
+                range - incidence_angle
+            }
+        }
+
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let uncorrected = &measurements[0];
+        let normal = Point::new(0.3, 0.4, 1.).normalize();
+        let mut measurement = uncorrected.clone();
+        measurement.set_range_correction(Arc::new(Incidence));
+        assert_relative_eq!(
+            uncorrected.range() - uncorrected.incidence_angle(Point::new(0., 0., 1.)),
+            measurement.range(),
+            max_relative = 1e-12
+        );
+        let tpu = measurement.tpu(normal).unwrap();
+        measurement.set_normal(normal);
+        assert_relative_eq!(
+            uncorrected.range() - uncorrected.incidence_angle(normal),
+            measurement.range(),
+            max_relative = 1e-12
+        );
+        assert_eq!(tpu.total, measurement.tpu(normal).unwrap().total);
+        let batch = super::tpu_batch(&[measurement], normal).unwrap();
+        assert_relative_eq!(tpu.total, batch[0].total, max_relative = 1e-9);
+    }
+
+    #[test]
+    fn range_uncertainty() {
+        use crate::{RangeErrorModel, ScanAngleUncertainty, Variable};
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let measurement = &measurements[0];
+        let config = measurement.config();
+        let incidence_angle = 0.5;
+        let incidence =
+            measurement.range() * config.beam_divergence / 4. * f64::tan(incidence_angle);
+        assert_relative_eq!(
+            (config.uncertainty.range.powi(2) + incidence.powi(2)).sqrt(),
+            measurement.uncertainty(Variable::Range, incidence_angle)
+        );
+        assert_relative_eq!(
+            (config.uncertainty.scan_angle.at(0.).powi(2) + (config.beam_divergence / 4.).powi(2))
+                .sqrt(),
+            measurement.uncertainty(Variable::ScanAngle, incidence_angle)
+        );
+        let mut config = config;
+        config.range_error_model = RangeErrorModel::Instrument;
+        config.uncertainty.scan_angle = ScanAngleUncertainty::Polynomial {
+            polynomial: vec![1e-5, 0., 1e-4],
+        };
+        let measurement = measurement.with_config(config.clone());
+        assert_eq!(
+            config.uncertainty.range,
+            measurement.uncertainty(Variable::Range, incidence_angle)
+        );
+        assert_relative_eq!(
+            1e-5 + 1e-4 * measurement.scan_angle().powi(2),
+            measurement.uncertainty(Variable::ScanAngle, incidence_angle)
+        );
+    }
+
+    #[test]
+    fn tpu_batch() {
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let mut config = measurements[0].config();
+        config.uncertainty.gnss_frame = GnssFrame::Projected;
+        let mut measurements: Vec<_> = measurements.into_iter().take(100).collect();
+        for measurement in measurements.iter_mut().skip(50) {
+            *measurement = measurement.with_config(config.clone());
+        }
+        let normal = Point::new(0.1, 0., 1.).normalize();
+        let tpus = super::tpu_batch(&measurements, normal).unwrap();
+        for (measurement, actual) in measurements.iter().zip(tpus) {
+            let expected = measurement.tpu(normal).unwrap();
+            assert_relative_eq!(expected.total, actual.total, max_relative = 1e-9);
+            assert_relative_eq!(
+                expected.sigma_along,
+                actual.sigma_along,
+                max_relative = 1e-9
+            );
+            assert_relative_eq!(expected.incidence_angle, actual.incidence_angle);
+        }
+    }
+
+    #[test]
+    fn max_incidence_angle() {
+        use crate::TpuQuality;
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let normal = Point::new(1., 0., 0.2).normalize();
+        let tpu = measurements[0].tpu(normal).unwrap();
+        assert_eq!(TpuQuality::Ok, tpu.quality);
+        let mut config = measurements[0].config();
+        config.max_incidence_angle = Some(tpu.incidence_angle / 2.);
+        let measurement = measurements[0].with_config(config.clone());
+        let capped = measurement.tpu(normal).unwrap();
+        assert_eq!(TpuQuality::HighIncidence, capped.quality);
+        assert_eq!(tpu.incidence_angle, capped.incidence_angle);
+        assert!(capped.total < tpu.total);
+        let batch = super::tpu_batch(&[measurement], normal).unwrap();
+        assert_eq!(TpuQuality::HighIncidence, batch[0].quality);
+        assert_relative_eq!(capped.total, batch[0].total, max_relative = 1e-9);
+    }
+
+    #[test]
+    fn tpu_with_dem() {
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let dem = crate::Dem::from_path("data/dem.tif").unwrap();
+        let normal = dem
+            .normal(measurements[0].x(), measurements[0].y())
+            .unwrap();
+        let expected = measurements[0].tpu(normal).unwrap();
+        let actual = measurements[0].tpu_with_dem(&dem).unwrap();
+        assert_eq!(expected.incidence_angle, actual.incidence_angle);
+        assert_eq!(expected.total, actual.total);
+    }
+
+    #[test]
+    fn footprint() {
+        let measurements =
+            super::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let measurement = &measurements[0];
+        let footprint = measurement.footprint(Point::new(0., 0., 1.)).unwrap();
+        let range = measurement.range();
+        assert_relative_eq!(
+            range * measurement.config().beam_divergence,
+            footprint.minor,
+            max_relative = 1e-6
+        );
+        let incidence_angle = measurement.incidence_angle(Point::new(0., 0., 1.));
+        assert_relative_eq!(
+            footprint.minor / incidence_angle.cos(),
+            footprint.major,
+            max_relative = 1e-3
+        );
+        let beam = measurement.beam_direction();
+        assert!(beam.z < 0.);
+
+        // A steep slope facing away from the scanner, as a raw DEM normal can be.
+        let steep = Point::new(-beam.y, beam.x, 0.) + beam * 0.1;
+        assert!(measurement.footprint(steep).is_none());
+        let mut config = measurement.config();
+        config.max_incidence_angle = Some(80f64.to_radians());
+        let footprint = measurement.with_config(config).footprint(steep).unwrap();
+        assert!(footprint.major.is_finite());
+        assert!(footprint.major > footprint.minor);
+    }
+
     #[test]
     fn incidence_angle() {
         let measurements =
@@ -0,0 +1,307 @@
+//! Writers for leeward's per-point outputs.
+//!
+//! These mirror `csv::Writer::serialize`, so any flat `Serialize` record (a
+//! struct of numbers, booleans, strings, and options of those) can be written.
+
+use crate::Error;
+use parquet::{
+    data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{io::Write, sync::Arc};
+
+/// The number of rows buffered before they are written out as a parquet row group.
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// Writes serializable records to a parquet file.
+///
+/// Column names and types are taken from the first record: floating point
+/// numbers become `DOUBLE`, integers `INT64`, booleans `BOOLEAN`, and strings
+/// `BYTE_ARRAY` (UTF8). All columns are optional, so `None` values are written
+/// as nulls.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::output::ParquetWriter;
+/// #[derive(serde::Serialize)]
+/// struct Record {
+///     x: f64,
+///     count: u32,
+/// }
+/// let mut writer = ParquetWriter::new(Vec::new());
+/// writer.serialize(Record { x: 1.2, count: 3 }).unwrap();
+/// let bytes = writer.into_inner().unwrap();
+/// ```
+pub struct ParquetWriter<W: Write + Send> {
+    write: Option<W>,
+    writer: Option<SerializedFileWriter<W>>,
+    columns: Vec<Column>,
+    rows: Vec<Vec<Value>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColumnType {
+    Double,
+    Int64,
+    Boolean,
+    Utf8,
+}
+
+#[derive(Debug)]
+struct Column {
+    name: String,
+    column_type: ColumnType,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Creates a new parquet writer.
+    ///
+    /// Nothing is written until the first record is serialized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::output::ParquetWriter;
+    /// let writer = ParquetWriter::new(Vec::new());
+    /// ```
+    pub fn new(write: W) -> ParquetWriter<W> {
+        ParquetWriter {
+            write: Some(write),
+            writer: None,
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Serializes a single record.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::output::ParquetWriter;
+    /// let mut writer = ParquetWriter::new(Vec::new());
+    /// #[derive(serde::Serialize)]
+    /// struct Record {
+    ///     x: f64,
+    /// }
+    /// writer.serialize(Record { x: 1.2 }).unwrap();
+    /// ```
+    pub fn serialize<S: Serialize>(&mut self, record: S) -> Result<(), Error> {
+        let object = match serde_json::to_value(record)? {
+            Value::Object(object) => object,
+            value => {
+                return Err(Error::Output(format!(
+                    "can only write structs to parquet, got {}",
+                    value
+                )))
+            }
+        };
+        if self.writer.is_none() {
+            self.start(&object)?;
+        }
+        let mut row = Vec::with_capacity(self.columns.len());
+        for column in &self.columns {
+            row.push(object.get(&column.name).cloned().unwrap_or(Value::Null));
+        }
+        self.rows.push(row);
+        if self.rows.len() >= ROW_GROUP_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes any buffered rows and the parquet footer, returning the underlying writer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::output::ParquetWriter;
+    /// let writer = ParquetWriter::new(Vec::new());
+    /// let bytes = writer.into_inner().unwrap();
+    /// ```
+    pub fn into_inner(mut self) -> Result<W, Error> {
+        if self.writer.is_none() {
+            self.start(&serde_json::Map::new())?;
+        }
+        self.flush()?;
+        let writer = self.writer.take().expect("writer was just started");
+        writer.into_inner().map_err(Error::from)
+    }
+
+    fn start(&mut self, object: &serde_json::Map<String, Value>) -> Result<(), Error> {
+        let mut columns = Vec::with_capacity(object.len());
+        for (name, value) in object {
+            let column_type = match value {
+                Value::Number(number) if number.is_f64() => ColumnType::Double,
+                Value::Number(_) => ColumnType::Int64,
+                Value::Bool(_) => ColumnType::Boolean,
+                Value::String(_) => ColumnType::Utf8,
+                Value::Null => ColumnType::Double,
+                _ => {
+                    return Err(Error::Output(format!(
+                        "cannot write nested value to parquet: {}",
+                        name
+                    )))
+                }
+            };
+            columns.push(Column {
+                name: name.clone(),
+                column_type,
+            });
+        }
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                let type_name = match column.column_type {
+                    ColumnType::Double => "DOUBLE",
+                    ColumnType::Int64 => "INT64",
+                    ColumnType::Boolean => "BOOLEAN",
+                    ColumnType::Utf8 => "BYTE_ARRAY (UTF8)",
+                };
+                format!("OPTIONAL {} {};", type_name, column.name)
+            })
+            .collect();
+        let schema = parse_message_type(&format!("message leeward {{ {} }}", fields.join(" ")))?;
+        let properties = WriterProperties::builder().build();
+        let write = self
+            .write
+            .take()
+            .expect("writer should only be started once");
+        self.writer = Some(SerializedFileWriter::new(
+            write,
+            Arc::new(schema),
+            Arc::new(properties),
+        )?);
+        self.columns = columns;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let writer = self.writer.as_mut().expect("writer should be started");
+        let mut row_group = writer.next_row_group()?;
+        for (i, column) in self.columns.iter().enumerate() {
+            let mut column_writer = row_group
+                .next_column()?
+                .ok_or_else(|| Error::Output("missing parquet column writer".to_string()))?;
+            let values = self.rows.iter().map(|row| &row[i]);
+            let definition_levels: Vec<i16> = values
+                .clone()
+                .map(|value| if value.is_null() { 0 } else { 1 })
+                .collect();
+            let values = values.filter(|value| !value.is_null());
+            match column.column_type {
+                ColumnType::Double => {
+                    let values = values
+                        .map(|value| value.as_f64().ok_or_else(|| mismatch(column, value)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    column_writer.typed::<DoubleType>().write_batch(
+                        &values,
+                        Some(&definition_levels),
+                        None,
+                    )?;
+                }
+                ColumnType::Int64 => {
+                    let values = values
+                        .map(|value| value.as_i64().ok_or_else(|| mismatch(column, value)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    column_writer.typed::<Int64Type>().write_batch(
+                        &values,
+                        Some(&definition_levels),
+                        None,
+                    )?;
+                }
+                ColumnType::Boolean => {
+                    let values = values
+                        .map(|value| value.as_bool().ok_or_else(|| mismatch(column, value)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    column_writer.typed::<BoolType>().write_batch(
+                        &values,
+                        Some(&definition_levels),
+                        None,
+                    )?;
+                }
+                ColumnType::Utf8 => {
+                    let values = values
+                        .map(|value| {
+                            value
+                                .as_str()
+                                .map(ByteArray::from)
+                                .ok_or_else(|| mismatch(column, value))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    column_writer.typed::<ByteArrayType>().write_batch(
+                        &values,
+                        Some(&definition_levels),
+                        None,
+                    )?;
+                }
+            }
+            column_writer.close()?;
+        }
+        row_group.close()?;
+        self.rows.clear();
+        Ok(())
+    }
+}
+
+fn mismatch(column: &Column, value: &Value) -> Error {
+    Error::Output(format!(
+        "value {} does not match the type of parquet column {} ({:?})",
+        value, column.name, column.column_type
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParquetWriter;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Record {
+        x: f64,
+        time: Option<f64>,
+        scan_angle: i32,
+        y: f64,
+        z: f64,
+    }
+
+    #[test]
+    fn roundtrip() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("points.parquet");
+        let mut writer = ParquetWriter::new(std::fs::File::create(&path).unwrap());
+        writer
+            .serialize(Record {
+                x: 1.,
+                y: 2.,
+                z: 3.,
+                time: Some(4.),
+                scan_angle: 5,
+            })
+            .unwrap();
+        writer
+            .serialize(Record {
+                x: 6.,
+                y: 7.,
+                z: 8.,
+                time: None,
+                scan_angle: -9,
+            })
+            .unwrap();
+        writer.into_inner().unwrap();
+        let points = crate::tabular::read_parquet(&path).unwrap();
+        assert_eq!(2, points.len());
+        assert_eq!(1., points[0].x);
+        assert_eq!(Some(4.), points[0].time);
+        assert_eq!(5., points[0].scan_angle);
+        assert_eq!(None, points[1].time);
+        assert_eq!(-9., points[1].scan_angle);
+    }
+}
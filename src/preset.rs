@@ -0,0 +1,186 @@
+//! Typical config values for common sensors.
+//!
+//! A preset fills in the beam divergence, scanner model, and uncertainties
+//! from the sensor's spec sheet and the trajectory system it usually ships
+//! with. Values in the config file take precedence, tables are merged, so
+//! only the values that differ from the preset are needed.
+//!
+//! # Examples
+//!
+//! ```toml
+//! preset = "riegl-vq580"
+//! utm_zone = 11
+//! lever_arm = [0, 0, 0]
+//!
+//! [boresight]
+//! roll = 0
+//! pitch = 0
+//! yaw = 0
+//!
+//! [uncertainty]
+//! gnss_z = 0.15
+//! ```
+
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// A RIEGL VQ-580 on an Applanix POS AV 610.
+const RIEGL_VQ580: &str = r#"
+beam_divergence = 0.2e-3
+
+[scanner]
+type = "rotating_polygon"
+
+[uncertainty]
+gnss_x = 0.05
+gnss_y = 0.05
+gnss_z = 0.1
+roll = 4.36332313e-5 # 0.0025°
+pitch = 4.36332313e-5 # 0.0025°
+yaw = 8.72664626e-5 # 0.005°
+boresight_roll = 1.745329e-5 # 0.001°
+boresight_pitch = 1.745329e-5 # 0.001°
+boresight_yaw = 6.981317e-5 # 0.004°
+lever_arm_x = 0.02
+lever_arm_y = 0.02
+lever_arm_z = 0.02
+range = 0.025
+scan_angle = 1.745329e-5 # 0.001°
+"#;
+
+/// An Optech Titan on an Applanix POS AV 510.
+const OPTECH_TITAN: &str = r#"
+beam_divergence = 0.35e-3
+
+[scanner]
+type = "oscillating_mirror"
+
+[uncertainty]
+gnss_x = 0.05
+gnss_y = 0.05
+gnss_z = 0.1
+roll = 8.72664626e-5 # 0.005°
+pitch = 8.72664626e-5 # 0.005°
+yaw = 1.3962634e-4 # 0.008°
+boresight_roll = 1.745329e-5 # 0.001°
+boresight_pitch = 1.745329e-5 # 0.001°
+boresight_yaw = 6.981317e-5 # 0.004°
+lever_arm_x = 0.02
+lever_arm_y = 0.02
+lever_arm_z = 0.02
+range = 0.008
+scan_angle = 1.745329e-5 # 0.001°
+"#;
+
+/// A Velodyne VLP-16 on an Applanix APX-15, e.g. on a drone.
+const VELODYNE_VLP16: &str = r#"
+beam_divergence = 3e-3
+
+[scanner]
+type = "rotating_polygon"
+
+[uncertainty]
+gnss_x = 0.05
+gnss_y = 0.05
+gnss_z = 0.1
+roll = 4.36332313e-4 # 0.025°
+pitch = 4.36332313e-4 # 0.025°
+yaw = 1.3962634e-3 # 0.08°
+boresight_roll = 1.745329e-4 # 0.01°
+boresight_pitch = 1.745329e-4 # 0.01°
+boresight_yaw = 3.490659e-4 # 0.02°
+lever_arm_x = 0.01
+lever_arm_y = 0.01
+lever_arm_z = 0.01
+range = 0.03
+scan_angle = 5.235988e-4 # 0.03°
+"#;
+
+/// A named set of typical config values for a sensor.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// A RIEGL VQ-580 with an Applanix POS AV 610.
+    RieglVq580,
+
+    /// An Optech Titan with an Applanix POS AV 510.
+    OptechTitan,
+
+    /// A Velodyne VLP-16 with an Applanix APX-15.
+    VelodyneVlp16,
+}
+
+impl Preset {
+    /// All of the presets.
+    pub const ALL: [Preset; 3] = [
+        Preset::RieglVq580,
+        Preset::OptechTitan,
+        Preset::VelodyneVlp16,
+    ];
+
+    /// Returns this preset's values, laid out like a config file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::Preset;
+    /// let table = Preset::VelodyneVlp16.table();
+    /// assert_eq!(0.03, table["uncertainty"]["range"].as_float().unwrap());
+    /// ```
+    pub fn table(&self) -> toml::Table {
+        let toml = match self {
+            Preset::RieglVq580 => RIEGL_VQ580,
+            Preset::OptechTitan => OPTECH_TITAN,
+            Preset::VelodyneVlp16 => VELODYNE_VLP16,
+        };
+        toml.parse().expect("presets are valid toml")
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Preset::RieglVq580 => "riegl-vq580",
+            Preset::OptechTitan => "optech-titan",
+            Preset::VelodyneVlp16 => "velodyne-vlp16",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Preset {
+    type Err = Error;
+
+    /// Parses a preset from its name, e.g. `riegl-vq580`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::Preset;
+    /// assert_eq!(Preset::OptechTitan, "optech-titan".parse().unwrap());
+    /// assert_eq!("optech-titan", Preset::OptechTitan.to_string());
+    /// ```
+    fn from_str(s: &str) -> Result<Preset, Error> {
+        Preset::ALL
+            .iter()
+            .copied()
+            .find(|preset| preset.to_string() == s)
+            .ok_or_else(|| Error::Parse(format!("unknown preset: {}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Preset;
+
+    #[test]
+    fn presets_are_complete() {
+        let base =
+            "utm_zone = 11\nlever_arm = [0, 0, 0]\n[boresight]\nroll = 0\npitch = 0\nyaw = 0\n";
+        for preset in Preset::ALL {
+            let toml = format!("preset = \"{}\"\n{}", preset, base);
+            let config: crate::Config = toml.parse().unwrap();
+            assert_eq!(Some(preset), config.preset);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! A high-level interface for the command line tool's workflows.
+//!
+//! A [Processor] holds the trajectory, the las files, the config, and the
+//! read options. Each las file is read and matched to the trajectory
+//! separately, so a processor can spread its las files across threads.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{processor::Processor, Point};
+//! let processor = Processor::new("data/sbet.out", "data/config.toml")
+//!     .las("data/points.las")
+//!     .decimation(100);
+//! let tpu = processor.tpu(Point::new(0., 0., 1.)).unwrap();
+//! let body_frame = processor.body_frame().unwrap();
+//! assert_eq!(tpu.len(), body_frame.len());
+//! ```
+
+use crate::sink::OutputSink;
+use crate::{
+    Adjust, Decimation, Error, Measurement, MeasurementFilter, MissingPose, Point, ReadOptions,
+    ReadSummary, Tpu,
+};
+use std::path::{Path, PathBuf};
+
+/// Reads measurements from files and computes tpu, body frame coordinates, or adjustments.
+#[derive(Clone, Debug)]
+pub struct Processor {
+    sbets: Vec<PathBuf>,
+    las: Vec<PathBuf>,
+    config: PathBuf,
+    options: ReadOptions,
+    threads: usize,
+}
+
+impl Processor {
+    /// Creates a new processor for an sbet file and a config file.
+    ///
+    /// The processor doesn't have any las files yet, see [Processor::las].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::processor::Processor;
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml");
+    /// assert!(processor.measurements().unwrap().0.is_empty());
+    /// ```
+    pub fn new<P0: AsRef<Path>, P1: AsRef<Path>>(sbet: P0, config: P1) -> Processor {
+        Processor {
+            sbets: vec![sbet.as_ref().to_path_buf()],
+            las: Vec::new(),
+            config: config.as_ref().to_path_buf(),
+            options: ReadOptions::default(),
+            threads: 1,
+        }
+    }
+
+    /// Adds another sbet file, which is merged with the others into a single trajectory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::processor::Processor;
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml").sbet("data/sbet.out");
+    /// ```
+    pub fn sbet<P: AsRef<Path>>(mut self, sbet: P) -> Processor {
+        self.sbets.push(sbet.as_ref().to_path_buf());
+        self
+    }
+
+    /// Adds a las file.
+    ///
+    /// Measurements are returned in the order their las files were added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::processor::Processor;
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml").las("data/points.las");
+    /// assert_eq!(1325, processor.measurements().unwrap().0.len());
+    /// ```
+    pub fn las<P: AsRef<Path>>(mut self, las: P) -> Processor {
+        self.las.push(las.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the decimation, which is applied to each las file separately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::processor::Processor;
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml")
+    ///     .las("data/points.las")
+    ///     .decimation(100);
+    /// assert_eq!(14, processor.measurements().unwrap().0.len());
+    /// ```
+    pub fn decimation<D: Into<Decimation>>(mut self, decimation: D) -> Processor {
+        self.options.decimation = decimation.into();
+        self
+    }
+
+    /// Sets the measurement filter.
+    pub fn filter(mut self, filter: MeasurementFilter) -> Processor {
+        self.options.filter = filter;
+        self
+    }
+
+    /// Sets what to do with points that aren't covered by the trajectory.
+    pub fn missing_pose(mut self, missing_pose: MissingPose) -> Processor {
+        self.options.missing_pose = missing_pose;
+        self
+    }
+
+    /// Sets all of the read options at once, replacing the decimation, filter, and missing pose.
+    pub fn read_options(mut self, options: ReadOptions) -> Processor {
+        self.options = options;
+        self
+    }
+
+    /// Sets the maximum number of las files to read at once.
+    ///
+    /// Zero uses the available parallelism.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::processor::Processor;
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml")
+    ///     .las("data/points.las")
+    ///     .las("data/points.las")
+    ///     .threads(2);
+    /// assert_eq!(2650, processor.measurements().unwrap().0.len());
+    /// ```
+    pub fn threads(mut self, threads: usize) -> Processor {
+        self.threads = threads;
+        self
+    }
+
+    /// Reads the measurements from all of the las files.
+    ///
+    /// The summary's counts are summed across the las files, and its crs
+    /// conflict is the first one found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::processor::Processor;
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml").las("data/points.las");
+    /// let (measurements, summary) = processor.measurements().unwrap();
+    /// assert_eq!(0, summary.skipped);
+    /// ```
+    pub fn measurements(&self) -> Result<(Vec<Measurement<las::Point>>, ReadSummary), Error> {
+        let mut measurements = Vec::new();
+        let mut summary = ReadSummary::default();
+        for result in self.map(Ok)? {
+            let (mut other, other_summary) = result;
+            measurements.append(&mut other);
+            merge(&mut summary, other_summary);
+        }
+        Ok((measurements, summary))
+    }
+
+    /// Computes the total propagated uncertainty of every measurement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{processor::Processor, Point};
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml").las("data/points.las");
+    /// let tpu = processor.tpu(Point::new(0., 0., 1.)).unwrap();
+    /// assert_eq!(1325, tpu.len());
+    /// ```
+    pub fn tpu(&self, normal: Point) -> Result<Vec<Tpu>, Error> {
+        let mut tpu = Vec::new();
+        self.tpu_into(normal, &mut tpu)?;
+        Ok(tpu)
+    }
+
+    /// Computes the total propagated uncertainty of every measurement and writes it to a sink.
+    ///
+    /// Each las file's records are written as soon as it's done, so only
+    /// `threads` las files are held in memory at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{processor::Processor, Point};
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml").las("data/points.las");
+    /// let mut sink = leeward::sink::CsvSink::new(Vec::new());
+    /// let summary = processor.tpu_into(Point::new(0., 0., 1.), &mut sink).unwrap();
+    /// assert_eq!(0, summary.skipped);
+    /// ```
+    pub fn tpu_into<S: OutputSink<Tpu>>(
+        &self,
+        normal: Point,
+        sink: &mut S,
+    ) -> Result<ReadSummary, Error> {
+        self.write(|measurements| crate::tpu_batch(&measurements, normal), sink)
+    }
+
+    /// Computes the body frame coordinates of every measurement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::processor::Processor;
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml").las("data/points.las");
+    /// let body_frame = processor.body_frame().unwrap();
+    /// assert_eq!(1325, body_frame.len());
+    /// ```
+    pub fn body_frame(&self) -> Result<Vec<Point>, Error> {
+        let mut body_frame = Vec::new();
+        self.body_frame_into(&mut body_frame)?;
+        Ok(body_frame)
+    }
+
+    /// Computes the body frame coordinates of every measurement and writes them to a sink.
+    pub fn body_frame_into<S: OutputSink<Point>>(
+        &self,
+        sink: &mut S,
+    ) -> Result<ReadSummary, Error> {
+        self.write(
+            |measurements| Ok(measurements.iter().map(|m| m.body_frame()).collect()),
+            sink,
+        )
+    }
+
+    /// Creates an adjustment from all of the measurements and runs it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::processor::Processor;
+    /// let processor = Processor::new("data/sbet.out", "data/config.toml")
+    ///     .las("data/points.las")
+    ///     .decimation(10);
+    /// let adjust = processor.adjust().unwrap();
+    /// let config = adjust.config();
+    /// ```
+    pub fn adjust(&self) -> Result<Adjust<las::Point>, Error> {
+        let (measurements, _) = self.measurements()?;
+        Adjust::new(measurements)?.adjust()
+    }
+
+    fn write<T, F, S>(&self, f: F, sink: &mut S) -> Result<ReadSummary, Error>
+    where
+        T: Send,
+        F: Fn(Vec<Measurement<las::Point>>) -> Result<Vec<T>, Error> + Sync,
+        S: OutputSink<T>,
+    {
+        let mut summary = ReadSummary::default();
+        for chunk in self.las.chunks(self.thread_count()) {
+            for (records, other) in self.map_chunk(chunk, &f)? {
+                for record in records {
+                    sink.write(record)?;
+                }
+                merge(&mut summary, other);
+            }
+        }
+        Ok(summary)
+    }
+
+    fn map<T, F>(&self, f: F) -> Result<Vec<(T, ReadSummary)>, Error>
+    where
+        T: Send,
+        F: Fn(Vec<Measurement<las::Point>>) -> Result<T, Error> + Sync,
+    {
+        let mut results = Vec::with_capacity(self.las.len());
+        for chunk in self.las.chunks(self.thread_count()) {
+            results.extend(self.map_chunk(chunk, &f)?);
+        }
+        Ok(results)
+    }
+
+    fn map_chunk<T, F>(&self, chunk: &[PathBuf], f: &F) -> Result<Vec<(T, ReadSummary)>, Error>
+    where
+        T: Send,
+        F: Fn(Vec<Measurement<las::Point>>) -> Result<T, Error> + Sync,
+    {
+        let read = |las: &PathBuf| {
+            let (measurements, summary) =
+                crate::read_measurements(&self.sbets, las, &self.config, &self.options)?;
+            f(measurements).map(|value| (value, summary))
+        };
+        if chunk.len() == 1 {
+            return read(&chunk[0]).map(|result| vec![result]);
+        }
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|las| scope.spawn(move || read(las)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("las reading thread panicked"))
+                .collect()
+        })
+    }
+
+    fn thread_count(&self) -> usize {
+        if self.threads == 0 {
+            std::thread::available_parallelism()
+                .map(|threads| threads.get())
+                .unwrap_or(1)
+        } else {
+            self.threads
+        }
+    }
+}
+
+fn merge(summary: &mut ReadSummary, other: ReadSummary) {
+    summary.skipped += other.skipped;
+    summary.nearest += other.nearest;
+    summary.filtered += other.filtered;
+    summary.reconstructed += other.reconstructed;
+    if summary.crs_conflict.is_none() {
+        summary.crs_conflict = other.crs_conflict;
+    }
+}
@@ -0,0 +1,168 @@
+//! Corrections to the measured range, e.g. for intensity-dependent range bias.
+//!
+//! Without full waveforms, a scanner's range walk shows up as a bias that
+//! depends on the strength of the return. A [RangeCorrection] is applied to
+//! each measurement's range before anything else uses it, so the corrected
+//! range flows through both the modeled points and the tpu. Corrections are
+//! set on [ReadOptions](crate::ReadOptions) or on individual measurements with
+//! [Measurement::set_range_correction](crate::Measurement::set_range_correction).
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{IntensityRangeBias, ReadOptions};
+//! use std::sync::Arc;
+//! let bias = IntensityRangeBias::new(vec![(0., 0.05), (1000., 0.)]).unwrap();
+//! let options = ReadOptions {
+//!     range_correction: Some(Arc::new(bias)),
+//!     ..Default::default()
+//! };
+//! let (measurements, _) = leeward::read_measurements(
+//!     &["data/sbet.out"],
+//!     "data/points.las",
+//!     "data/config.toml",
+//!     &options,
+//! ).unwrap();
+//! ```
+
+use crate::Error;
+use std::{fmt::Debug, path::Path};
+
+/// A correction to a measurement's range.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::RangeCorrection;
+///
+/// /// Returns are late by a centimeter per kilometer.
+/// #[derive(Debug)]
+/// struct Scale;
+///
+/// impl RangeCorrection for Scale {
+///     fn correct(&self, _: Option<u16>, range: f64, _: f64) -> f64 {
+///         range * (1. - 1e-5)
+///     }
+/// }
+///
+/// assert_eq!(999.99, Scale.correct(Some(100), 1000., 0.));
+/// ```
+pub trait RangeCorrection: Debug + Send + Sync {
+    /// Returns the corrected range, in meters.
+    ///
+    /// The intensity is the point's, if it has one. The incidence angle, in
+    /// radians, is to the measurement's surface normal, see
+    /// [Measurement::set_normal](crate::Measurement::set_normal), or to a
+    /// horizontal surface if it doesn't have one. Total propagated uncertainty
+    /// is computed with the range corrected for its own normal.
+    fn correct(&self, intensity: Option<u16>, range: f64, incidence_angle: f64) -> f64;
+}
+
+/// A range bias that depends on intensity, linearly interpolated from a table.
+///
+/// The bias is subtracted from the range. Intensities outside the table use
+/// the bias at the nearest end, and points without an intensity aren't
+/// corrected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntensityRangeBias {
+    table: Vec<(f64, f64)>,
+}
+
+impl IntensityRangeBias {
+    /// Creates a new bias from (intensity, bias in meters) pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{IntensityRangeBias, RangeCorrection};
+    /// let bias = IntensityRangeBias::new(vec![(0., 0.05), (1000., 0.)]).unwrap();
+    /// assert_eq!(999.975, bias.correct(Some(500), 1000., 0.));
+    /// assert_eq!(1000., bias.correct(None, 1000., 0.));
+    /// assert!(IntensityRangeBias::new(Vec::new()).is_err());
+    /// ```
+    pub fn new(mut table: Vec<(f64, f64)>) -> Result<IntensityRangeBias, Error> {
+        if table.is_empty() {
+            return Err(Error::Invalid(
+                "an intensity range bias needs at least one entry".to_string(),
+            ));
+        }
+        if table
+            .iter()
+            .any(|(intensity, bias)| !intensity.is_finite() || !bias.is_finite())
+        {
+            return Err(Error::Invalid(
+                "intensity range bias entries must be finite".to_string(),
+            ));
+        }
+        table.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(IntensityRangeBias { table })
+    }
+
+    /// Reads a bias table from a CSV file with `intensity` and `bias` columns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::IntensityRangeBias;
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join("bias.csv");
+    /// std::fs::write(&path, "intensity,bias\n0,0.05\n1000,0\n").unwrap();
+    /// let bias = IntensityRangeBias::from_path(&path).unwrap();
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<IntensityRangeBias, Error> {
+        #[derive(serde::Deserialize)]
+        struct Row {
+            intensity: f64,
+            bias: f64,
+        }
+        let mut reader = csv::Reader::from_path(path)?;
+        let table = reader
+            .deserialize()
+            .map(|row| row.map(|row: Row| (row.intensity, row.bias)))
+            .collect::<Result<Vec<_>, _>>()?;
+        IntensityRangeBias::new(table)
+    }
+
+    /// Returns the bias at an intensity, in meters.
+    pub fn bias(&self, intensity: f64) -> f64 {
+        let index = self.table.partition_point(|(i, _)| *i < intensity);
+        if index == 0 {
+            self.table[0].1
+        } else if index == self.table.len() {
+            self.table[index - 1].1
+        } else {
+            let (i0, b0) = self.table[index - 1];
+            let (i1, b1) = self.table[index];
+            b0 + (b1 - b0) * (intensity - i0) / (i1 - i0)
+        }
+    }
+}
+
+impl RangeCorrection for IntensityRangeBias {
+    fn correct(&self, intensity: Option<u16>, range: f64, _: f64) -> f64 {
+        match intensity {
+            Some(intensity) => range - self.bias(f64::from(intensity)),
+            None => range,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntensityRangeBias;
+
+    #[test]
+    fn bias() {
+        let bias = IntensityRangeBias::new(vec![(100., 0.02), (0., 0.04), (200., 0.)]).unwrap();
+        assert_eq!(0.04, bias.bias(-1.));
+        assert_eq!(0.04, bias.bias(0.));
+        assert!((bias.bias(50.) - 0.03).abs() < 1e-12);
+        assert_eq!(0.02, bias.bias(100.));
+        assert!((bias.bias(150.) - 0.01).abs() < 1e-12);
+        assert_eq!(0., bias.bias(1000.));
+        let single = IntensityRangeBias::new(vec![(10., 0.01)]).unwrap();
+        assert_eq!(0.01, single.bias(0.));
+        assert_eq!(0.01, single.bias(10.));
+        assert_eq!(0.01, single.bias(20.));
+    }
+}
@@ -0,0 +1,210 @@
+//! Models of the range and scan angle uncertainties of a single pulse.
+//!
+//! The laser beam isn't infinitely thin, so the range and scan angle that a
+//! pulse reports depend on where in the beam's footprint the return came
+//! from. Following Glennie (2007), the beam divergence adds a quarter of its
+//! angle to the scan angle uncertainty, and on an inclined surface the
+//! footprint is stretched along the beam, adding `range * divergence / 4 *
+//! tan(incidence angle)` to the range uncertainty. All components are added in
+//! quadrature.
+//!
+//! The later returns of a multi-return pulse come from partial hits, so their
+//! ranges are noisier than a single return's. [Returns] adds range
+//! uncertainty by return number, and can model each pulse's returns along a
+//! single shared beam.
+//!
+//! # Examples
+//!
+//! ```toml
+//! range_error_model = "instrument"
+//!
+//! [returns]
+//! per_pulse = true
+//! range = [0, 0.01, 0.02]
+//! ```
+
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// How the range and scan angle uncertainties of a pulse are modeled.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RangeErrorModel {
+    /// The instrument's uncertainties plus the divergence- and incidence-induced components.
+    #[default]
+    Divergence,
+
+    /// Only the instrument's uncertainties, e.g. if they already include the beam's footprint.
+    Instrument,
+}
+
+/// How the returns of multi-return pulses are modeled.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Returns {
+    /// Whether each pulse's returns share one beam, see [model_per_pulse](crate::model_per_pulse).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub per_pulse: bool,
+
+    /// Additional range uncertainty by return number, in meters.
+    ///
+    /// The first value is for first returns, the second for second returns,
+    /// and so on. The last value is used for any later returns.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub range: Vec<f64>,
+}
+
+/// The components of a pulse's range and scan angle uncertainties, each one sigma.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Default)]
+pub struct RangeError {
+    /// The instrument's range uncertainty, in meters.
+    pub range_instrument: f64,
+
+    /// The range uncertainty from the beam's footprint on an inclined surface, in meters.
+    pub range_incidence: f64,
+
+    /// The range uncertainty from the atmospheric correction, in meters.
+    pub range_atmosphere: f64,
+
+    /// The range uncertainty from the return's position in a multi-return pulse, in meters.
+    pub range_return: f64,
+
+    /// The instrument's scan angle uncertainty, in radians.
+    pub scan_angle_instrument: f64,
+
+    /// The scan angle uncertainty from the beam divergence, in radians.
+    pub scan_angle_divergence: f64,
+}
+
+impl RangeErrorModel {
+    /// Returns the components of a pulse's uncertainties.
+    ///
+    /// The atmospheric component is left at zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::RangeErrorModel;
+    /// let error = RangeErrorModel::Divergence.range_error(0.02, 1e-5, 0.25e-3, 1000., 0.);
+    /// assert_eq!(0.02, error.range());
+    /// let error = RangeErrorModel::Divergence.range_error(0.02, 1e-5, 0.25e-3, 1000., 0.5);
+    /// assert!(error.range() > 0.02);
+    /// ```
+    pub fn range_error(
+        &self,
+        range_uncertainty: f64,
+        scan_angle_uncertainty: f64,
+        beam_divergence: f64,
+        range: f64,
+        incidence_angle: f64,
+    ) -> RangeError {
+        let mut error = RangeError {
+            range_instrument: range_uncertainty,
+            scan_angle_instrument: scan_angle_uncertainty,
+            ..Default::default()
+        };
+        match self {
+            RangeErrorModel::Divergence => {
+                // Surfaces seen from behind have incidence angles past 90°.
+                error.range_incidence = range * beam_divergence / 4. * incidence_angle.tan().abs();
+                error.scan_angle_divergence = beam_divergence / 4.;
+            }
+            RangeErrorModel::Instrument => {}
+        }
+        error
+    }
+}
+
+impl Returns {
+    /// Returns true if returns are modeled like any other point.
+    pub fn is_default(&self) -> bool {
+        *self == Returns::default()
+    }
+
+    /// Returns the additional range uncertainty of a return, in meters.
+    ///
+    /// Points without a return number are treated as first returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::Returns;
+    /// let returns = Returns { range: vec![0., 0.01, 0.02], ..Default::default() };
+    /// assert_eq!(0., returns.range_uncertainty(Some(1)));
+    /// assert_eq!(0.01, returns.range_uncertainty(Some(2)));
+    /// assert_eq!(0.02, returns.range_uncertainty(Some(5)));
+    /// assert_eq!(0., Returns::default().range_uncertainty(Some(2)));
+    /// ```
+    pub fn range_uncertainty(&self, return_number: Option<u8>) -> f64 {
+        let index = usize::from(return_number.unwrap_or(1).max(1)) - 1;
+        self.range
+            .get(index)
+            .or_else(|| self.range.last())
+            .copied()
+            .unwrap_or(0.)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if let Some(value) = self
+            .range
+            .iter()
+            .find(|value| value.is_nan() || **value < 0.)
+        {
+            return Err(Error::Config(format!(
+                "return range uncertainties must be non-negative, got {}",
+                value
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl RangeError {
+    /// Returns the total range uncertainty, in meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::RangeError;
+    /// let error = RangeError { range_instrument: 3., range_incidence: 4., ..Default::default() };
+    /// assert_eq!(5., error.range());
+    /// ```
+    pub fn range(&self) -> f64 {
+        (self.range_instrument.powi(2)
+            + self.range_incidence.powi(2)
+            + self.range_atmosphere.powi(2)
+            + self.range_return.powi(2))
+        .sqrt()
+    }
+
+    /// Returns the total scan angle uncertainty, in radians.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::RangeError;
+    /// let error = RangeError { scan_angle_instrument: 3., scan_angle_divergence: 4., ..Default::default() };
+    /// assert_eq!(5., error.scan_angle());
+    /// ```
+    pub fn scan_angle(&self) -> f64 {
+        (self.scan_angle_instrument.powi(2) + self.scan_angle_divergence.powi(2)).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RangeErrorModel;
+
+    #[test]
+    fn incidence() {
+        let model = RangeErrorModel::Divergence;
+        let error = model.range_error(0., 0., 1e-3, 1000., 45f64.to_radians());
+        assert!((error.range_incidence - 0.25).abs() < 1e-12);
+        let behind = model.range_error(0., 0., 1e-3, 1000., 135f64.to_radians());
+        assert!((behind.range_incidence - 0.25).abs() < 1e-12);
+        let instrument =
+            RangeErrorModel::Instrument.range_error(0.02, 0., 1e-3, 1000., 45f64.to_radians());
+        assert_eq!(0.02, instrument.range());
+        assert_eq!(0., instrument.scan_angle());
+    }
+}
@@ -0,0 +1,44 @@
+//! A small, seedable random number generator.
+//!
+//! Only integer arithmetic is used to advance the state, so the same seed gives
+//! the same sequence on every platform.
+
+/// The seed used when none is given.
+pub const DEFAULT_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+/// A linear congruential generator.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Returns a number in [0, 1).
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rng, DEFAULT_SEED};
+
+    #[test]
+    fn reproducible() {
+        let mut a = Rng::new(DEFAULT_SEED);
+        let mut b = Rng::new(DEFAULT_SEED);
+        let mut c = Rng::new(42);
+        let a: Vec<_> = (0..10).map(|_| a.next_f64()).collect();
+        assert_eq!(a, (0..10).map(|_| b.next_f64()).collect::<Vec<_>>());
+        assert_ne!(a, (0..10).map(|_| c.next_f64()).collect::<Vec<_>>());
+        assert!(a.iter().all(|&x| (0. ..1.).contains(&x)));
+    }
+}
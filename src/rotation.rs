@@ -0,0 +1,455 @@
+//! Rotations between the body, boresight, and navigation frames.
+//!
+//! Roll, pitch, and yaw don't define a rotation on their own: the order in
+//! which the elementary rotations are multiplied matters. Every conversion in
+//! this module takes an explicit [RotationOrder], so there's only one place
+//! that decides what a set of angles means. The platform attitude in an sbet
+//! is always [RotationOrder::Zyx].
+
+use crate::{utils, Matrix3};
+use nalgebra::{Rotation3, UnitQuaternion};
+use serde::{Deserialize, Serialize};
+
+/// Roll, pitch, and yaw.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RollPitchYaw {
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+}
+
+impl RollPitchYaw {
+    /// Creates a new roll, pitch, and yaw.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::RollPitchYaw;
+    /// let rpy = RollPitchYaw::new(0., 0., 0.);
+    /// ```
+    pub fn new(roll: f64, pitch: f64, yaw: f64) -> RollPitchYaw {
+        RollPitchYaw { roll, pitch, yaw }
+    }
+
+    /// Returns these angles wrapped into `[-π, π)`.
+    ///
+    /// The rotation matrix doesn't change.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::RollPitchYaw;
+    /// let rpy = RollPitchYaw::new(0.1, -0.2, 2. * std::f64::consts::PI - 0.1);
+    /// let normalized = rpy.normalized();
+    /// assert!((normalized.yaw + 0.1).abs() < 1e-12);
+    /// let order = leeward::RotationOrder::Zyx;
+    /// assert!((order.matrix(rpy) - order.matrix(normalized)).norm() < 1e-12);
+    /// ```
+    pub fn normalized(&self) -> RollPitchYaw {
+        RollPitchYaw {
+            roll: utils::normalize_angle(self.roll),
+            pitch: utils::normalize_angle(self.pitch),
+            yaw: utils::normalize_angle(self.yaw),
+        }
+    }
+
+    /// Returns a rotation matrix from a roll, pitch, and yaw.
+    ///
+    /// This is always `Rz(yaw)·Ry(pitch)·Rx(roll)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(deprecated)]
+    /// # use leeward::RollPitchYaw;
+    /// let rpy = RollPitchYaw::new(0., 0., 0.);
+    /// let matrix = rpy.as_matrix();
+    /// assert_eq!(matrix[(0, 0)], 1.);
+    /// assert_eq!(matrix[(1, 1)], 1.);
+    /// assert_eq!(matrix[(2, 2)], 1.);
+    /// ```
+    #[deprecated(note = "use `RotationOrder::Zyx.matrix`, which makes the convention explicit")]
+    pub fn as_matrix(&self) -> Matrix3 {
+        let cy = self.yaw.cos();
+        let sy = self.yaw.sin();
+        let cp = self.pitch.cos();
+        let sp = self.pitch.sin();
+        let cr = self.roll.cos();
+        let sr = self.roll.sin();
+        Matrix3::new(
+            cy * cp,
+            cy * sp * sr - sy * cr,
+            cy * sp * cr + sy * sr,
+            sy * cp,
+            sy * sp * sr + cy * cr,
+            sy * sp * cr - cy * sr,
+            -sp,
+            cp * sr,
+            cp * cr,
+        )
+    }
+
+    /// Returns the partial derivative of the rotation matrix with respect to roll.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(deprecated)]
+    /// # use leeward::RollPitchYaw;
+    /// let rpy = RollPitchYaw::new(0., 0., 0.);
+    /// let derivative = rpy.roll_derivative();
+    /// assert_eq!(derivative[(2, 1)], 1.);
+    /// ```
+    #[deprecated(
+        note = "use `RotationOrder::Zyx.roll_derivative`, which makes the convention explicit"
+    )]
+    pub fn roll_derivative(&self) -> Matrix3 {
+        let cy = self.yaw.cos();
+        let sy = self.yaw.sin();
+        let cp = self.pitch.cos();
+        let sp = self.pitch.sin();
+        let cr = self.roll.cos();
+        let sr = self.roll.sin();
+        Matrix3::new(
+            0.,
+            cy * sp * cr + sy * sr,
+            -cy * sp * sr + sy * cr,
+            0.,
+            sy * sp * cr - cy * sr,
+            -sy * sp * sr - cy * cr,
+            0.,
+            cp * cr,
+            -cp * sr,
+        )
+    }
+
+    /// Returns the partial derivative of the rotation matrix with respect to pitch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(deprecated)]
+    /// # use leeward::RollPitchYaw;
+    /// let rpy = RollPitchYaw::new(0., 0., 0.);
+    /// let derivative = rpy.pitch_derivative();
+    /// assert_eq!(derivative[(2, 0)], -1.);
+    /// ```
+    #[deprecated(
+        note = "use `RotationOrder::Zyx.pitch_derivative`, which makes the convention explicit"
+    )]
+    pub fn pitch_derivative(&self) -> Matrix3 {
+        let cy = self.yaw.cos();
+        let sy = self.yaw.sin();
+        let cp = self.pitch.cos();
+        let sp = self.pitch.sin();
+        let cr = self.roll.cos();
+        let sr = self.roll.sin();
+        Matrix3::new(
+            -cy * sp,
+            cy * cp * sr,
+            cy * cp * cr,
+            -sy * sp,
+            sy * cp * sr,
+            sy * cp * cr,
+            -cp,
+            -sp * sr,
+            -sp * cr,
+        )
+    }
+
+    /// Returns the partial derivative of the rotation matrix with respect to yaw.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(deprecated)]
+    /// # use leeward::RollPitchYaw;
+    /// let rpy = RollPitchYaw::new(0., 0., 0.);
+    /// let derivative = rpy.yaw_derivative();
+    /// assert_eq!(derivative[(1, 0)], 1.);
+    /// ```
+    #[deprecated(
+        note = "use `RotationOrder::Zyx.yaw_derivative`, which makes the convention explicit"
+    )]
+    pub fn yaw_derivative(&self) -> Matrix3 {
+        let cy = self.yaw.cos();
+        let sy = self.yaw.sin();
+        let cp = self.pitch.cos();
+        let sp = self.pitch.sin();
+        let cr = self.roll.cos();
+        let sr = self.roll.sin();
+        Matrix3::new(
+            -sy * cp,
+            -sy * sp * sr - cy * cr,
+            -sy * sp * cr + cy * sr,
+            cy * cp,
+            cy * sp * sr - sy * cr,
+            cy * sp * cr + sy * sr,
+            0.,
+            0.,
+            0.,
+        )
+    }
+}
+
+/// The order in which roll, pitch, and yaw rotations are combined into a rotation matrix.
+///
+/// The letters are the axes of the elementary rotations, multiplied left to
+/// right. The default, `zyx`, is `Rz(yaw)·Ry(pitch)·Rx(roll)`, which is yaw, then
+/// pitch, then roll as intrinsic rotations, or roll, then pitch, then yaw as
+/// extrinsic rotations. Different vendors define their boresight angles with
+/// different orders.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{RollPitchYaw, RotationOrder};
+/// let rpy = RollPitchYaw::new(0.1, 0.2, 0.3);
+/// let zyx = RotationOrder::Zyx.matrix(rpy);
+/// assert!((zyx - RotationOrder::Xyz.matrix(rpy)).norm() > 1e-3);
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationOrder {
+    /// `Rz(yaw)·Ry(pitch)·Rx(roll)`.
+    #[default]
+    Zyx,
+
+    /// `Rz(yaw)·Rx(roll)·Ry(pitch)`.
+    Zxy,
+
+    /// `Ry(pitch)·Rx(roll)·Rz(yaw)`.
+    Yxz,
+
+    /// `Ry(pitch)·Rz(yaw)·Rx(roll)`.
+    Yzx,
+
+    /// `Rx(roll)·Ry(pitch)·Rz(yaw)`.
+    Xyz,
+
+    /// `Rx(roll)·Rz(yaw)·Ry(pitch)`.
+    Xzy,
+}
+
+impl RotationOrder {
+    /// Returns the rotation matrix for a roll, pitch, and yaw in this order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{RollPitchYaw, RotationOrder};
+    /// let matrix = RotationOrder::Xyz.matrix(RollPitchYaw::new(0., 0., 0.));
+    /// assert_eq!(leeward::Matrix3::identity(), matrix);
+    /// ```
+    pub fn matrix(&self, rpy: RollPitchYaw) -> Matrix3 {
+        self.product(rpy, None)
+    }
+
+    /// Returns the partial derivative of the rotation matrix with respect to roll.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{RollPitchYaw, RotationOrder};
+    /// let rpy = RollPitchYaw::new(0., 0., 0.);
+    /// assert_eq!(1., RotationOrder::Zyx.roll_derivative(rpy)[(2, 1)]);
+    /// ```
+    pub fn roll_derivative(&self, rpy: RollPitchYaw) -> Matrix3 {
+        self.product(rpy, Some('x'))
+    }
+
+    /// Returns the partial derivative of the rotation matrix with respect to pitch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{RollPitchYaw, RotationOrder};
+    /// let rpy = RollPitchYaw::new(0., 0., 0.);
+    /// assert_eq!(-1., RotationOrder::Xzy.pitch_derivative(rpy)[(2, 0)]);
+    /// ```
+    pub fn pitch_derivative(&self, rpy: RollPitchYaw) -> Matrix3 {
+        self.product(rpy, Some('y'))
+    }
+
+    /// Returns the partial derivative of the rotation matrix with respect to yaw.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{RollPitchYaw, RotationOrder};
+    /// let rpy = RollPitchYaw::new(0., 0., 0.);
+    /// assert_eq!(1., RotationOrder::Yxz.yaw_derivative(rpy)[(1, 0)]);
+    /// ```
+    pub fn yaw_derivative(&self, rpy: RollPitchYaw) -> Matrix3 {
+        self.product(rpy, Some('z'))
+    }
+
+    /// Returns the rotation for a roll, pitch, and yaw in this order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{RollPitchYaw, RotationOrder};
+    /// let rpy = RollPitchYaw::new(0.1, 0.2, 0.3);
+    /// let rotation = RotationOrder::Zyx.rotation(rpy);
+    /// assert!((rotation.matrix() - RotationOrder::Zyx.matrix(rpy)).norm() < 1e-12);
+    /// ```
+    pub fn rotation(&self, rpy: RollPitchYaw) -> Rotation3<f64> {
+        Rotation3::from_matrix_unchecked(self.matrix(rpy))
+    }
+
+    /// Returns the unit quaternion for a roll, pitch, and yaw in this order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{RollPitchYaw, RotationOrder};
+    /// let rpy = RollPitchYaw::new(0.1, 0.2, 0.3);
+    /// let quaternion = RotationOrder::Xzy.quaternion(rpy);
+    /// let matrix = quaternion.to_rotation_matrix();
+    /// assert!((matrix.matrix() - RotationOrder::Xzy.matrix(rpy)).norm() < 1e-12);
+    /// ```
+    pub fn quaternion(&self, rpy: RollPitchYaw) -> UnitQuaternion<f64> {
+        UnitQuaternion::from_rotation_matrix(&self.rotation(rpy))
+    }
+
+    /// Decomposes a rotation into roll, pitch, and yaw in this order.
+    ///
+    /// The middle angle is in `[-π/2, π/2]` and the outer two are in `[-π, π]`.
+    /// At gimbal lock, when the middle angle is ±π/2, only the sum or
+    /// difference of the outer angles is defined, and the returned split is
+    /// arbitrary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::{RollPitchYaw, RotationOrder};
+    /// let rpy = RollPitchYaw::new(0.1, 0.2, 0.3);
+    /// let rotation = RotationOrder::Yzx.rotation(rpy);
+    /// let decomposed = RotationOrder::Yzx.rpy(&rotation);
+    /// assert!((decomposed.roll - 0.1).abs() < 1e-12);
+    /// assert!((decomposed.pitch - 0.2).abs() < 1e-12);
+    /// assert!((decomposed.yaw - 0.3).abs() < 1e-12);
+    /// ```
+    pub fn rpy(&self, rotation: &Rotation3<f64>) -> RollPitchYaw {
+        let m = rotation.matrix();
+        let [i, j, k] = self.axes().map(axis_index);
+        // Even permutations of xyz have a positive sign on the middle angle's sine.
+        let sign = if (j + 3 - i) % 3 == 1 { 1. } else { -1. };
+        let middle = (sign * m[(i, k)]).clamp(-1., 1.).asin();
+        let first = (-sign * m[(j, k)]).atan2(m[(k, k)]);
+        let last = (-sign * m[(i, j)]).atan2(m[(i, i)]);
+        let mut angles = [0.; 3];
+        angles[i] = first;
+        angles[j] = middle;
+        angles[k] = last;
+        RollPitchYaw::new(angles[0], angles[1], angles[2])
+    }
+
+    fn axes(&self) -> [char; 3] {
+        match self {
+            RotationOrder::Zyx => ['z', 'y', 'x'],
+            RotationOrder::Zxy => ['z', 'x', 'y'],
+            RotationOrder::Yxz => ['y', 'x', 'z'],
+            RotationOrder::Yzx => ['y', 'z', 'x'],
+            RotationOrder::Xyz => ['x', 'y', 'z'],
+            RotationOrder::Xzy => ['x', 'z', 'y'],
+        }
+    }
+
+    /// Multiplies the elementary rotations, differentiating the one about `derivative`, if provided.
+    fn product(&self, rpy: RollPitchYaw, derivative: Option<char>) -> Matrix3 {
+        self.axes()
+            .iter()
+            .fold(Matrix3::identity(), |matrix, &axis| {
+                let angle = match axis {
+                    'x' => rpy.roll,
+                    'y' => rpy.pitch,
+                    _ => rpy.yaw,
+                };
+                matrix * elementary_rotation(axis, angle, derivative == Some(axis))
+            })
+    }
+}
+
+fn axis_index(axis: char) -> usize {
+    match axis {
+        'x' => 0,
+        'y' => 1,
+        _ => 2,
+    }
+}
+
+/// Returns the rotation matrix about a single axis, or its derivative.
+fn elementary_rotation(axis: char, angle: f64, derivative: bool) -> Matrix3 {
+    let (s, c) = angle.sin_cos();
+    // The derivative of [c, -s; s, c] is [-s, -c; c, -s], and the fixed axis drops out.
+    let (s, c, one) = if derivative { (c, -s, 0.) } else { (s, c, 1.) };
+    match axis {
+        'x' => Matrix3::new(one, 0., 0., 0., c, -s, 0., s, c),
+        'y' => Matrix3::new(c, 0., s, 0., one, 0., -s, 0., c),
+        _ => Matrix3::new(c, -s, 0., s, c, 0., 0., 0., one),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RollPitchYaw, RotationOrder};
+    use nalgebra::Rotation3;
+
+    const ORDERS: [RotationOrder; 6] = [
+        RotationOrder::Zyx,
+        RotationOrder::Zxy,
+        RotationOrder::Yxz,
+        RotationOrder::Yzx,
+        RotationOrder::Xyz,
+        RotationOrder::Xzy,
+    ];
+
+    #[test]
+    #[allow(deprecated)]
+    fn zyx_matches_closed_form() {
+        let rpy = RollPitchYaw::new(0.1, -0.2, 2.9);
+        let order = RotationOrder::Zyx;
+        assert!((rpy.as_matrix() - order.matrix(rpy)).norm() < 1e-12);
+        assert!((rpy.roll_derivative() - order.roll_derivative(rpy)).norm() < 1e-12);
+        assert!((rpy.pitch_derivative() - order.pitch_derivative(rpy)).norm() < 1e-12);
+        assert!((rpy.yaw_derivative() - order.yaw_derivative(rpy)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn zyx_matches_nalgebra() {
+        let rpy = RollPitchYaw::new(0.1, -0.2, 2.9);
+        let rotation = Rotation3::from_euler_angles(rpy.roll, rpy.pitch, rpy.yaw);
+        assert!((rotation.matrix() - RotationOrder::Zyx.matrix(rpy)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn round_trip() {
+        let rpy = RollPitchYaw::new(-0.3, 0.4, -1.2);
+        for order in ORDERS {
+            let decomposed = order.rpy(&order.rotation(rpy));
+            assert!((decomposed.roll - rpy.roll).abs() < 1e-12, "{:?}", order);
+            assert!((decomposed.pitch - rpy.pitch).abs() < 1e-12, "{:?}", order);
+            assert!((decomposed.yaw - rpy.yaw).abs() < 1e-12, "{:?}", order);
+            let quaternion = order.quaternion(rpy);
+            let rotation = quaternion.to_rotation_matrix();
+            assert!(
+                (rotation.matrix() - order.matrix(rpy)).norm() < 1e-12,
+                "{:?}",
+                order
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_outside_middle_range() {
+        // The middle angle can't be recovered past ±π/2, but the rotation can.
+        let rpy = RollPitchYaw::new(2.8, -2.0, -2.5);
+        for order in ORDERS {
+            let decomposed = order.rpy(&order.rotation(rpy));
+            let difference = order.matrix(decomposed) - order.matrix(rpy);
+            assert!(difference.norm() < 1e-12, "{:?}", order);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Scanner models, which map a scan angle to a beam direction in the scanner frame.
+//!
+//! The scanner frame's x axis is the scanner's optical axis. Line scanners
+//! sweep the beam through the x-z plane, and conic scanners sweep it around a
+//! cone centered on the x axis.
+//!
+//! # Examples
+//!
+//! ```toml
+//! [scanner]
+//! type = "palmer"
+//! mirror_tilt = 0.17453292519943295 # 10°, for a 20° off-nadir circular scan
+//! ```
+
+use crate::Point;
+use serde::{Deserialize, Serialize};
+
+/// The geometry of a scanner's deflection unit.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScannerModel {
+    /// An oscillating (galvanometer) mirror, sweeping the beam back and forth in a line.
+    #[default]
+    OscillatingMirror,
+
+    /// A rotating polygon mirror, sweeping the beam in parallel lines.
+    ///
+    /// The per-pulse geometry is the same as an oscillating mirror.
+    RotatingPolygon,
+
+    /// A Palmer (elliptical) scanner, where a nutating mirror sweeps the beam around a cone.
+    ///
+    /// The scan angle is the azimuth of the beam around the cone.
+    Palmer {
+        /// The tilt of the mirror normal away from the rotation axis, in radians.
+        ///
+        /// The beam is deflected by twice this angle.
+        mirror_tilt: f64,
+    },
+
+    /// A rotating wedge prism, sweeping the beam around a cone.
+    ///
+    /// The scan angle is the azimuth of the beam around the cone.
+    RotatingPrism {
+        /// The deviation of the beam by the prism, in radians.
+        deviation: f64,
+    },
+}
+
+impl ScannerModel {
+    /// Returns the unit beam direction in the scanner frame for the given scan angle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::ScannerModel;
+    /// let direction = ScannerModel::OscillatingMirror.direction(0.);
+    /// assert_eq!(1., direction.x);
+    /// ```
+    pub fn direction(&self, scan_angle: f64) -> Point {
+        match self.cone_angle() {
+            None => Point::new(scan_angle.cos(), 0., scan_angle.sin()),
+            Some(cone_angle) => Point::new(
+                cone_angle.cos(),
+                -cone_angle.sin() * scan_angle.cos(),
+                cone_angle.sin() * scan_angle.sin(),
+            ),
+        }
+    }
+
+    /// Returns the derivative of the beam direction with respect to the scan angle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::ScannerModel;
+    /// let derivative = ScannerModel::OscillatingMirror.direction_derivative(0.);
+    /// assert_eq!(1., derivative.z);
+    /// ```
+    pub fn direction_derivative(&self, scan_angle: f64) -> Point {
+        match self.cone_angle() {
+            None => Point::new(-scan_angle.sin(), 0., scan_angle.cos()),
+            Some(cone_angle) => Point::new(
+                0.,
+                cone_angle.sin() * scan_angle.sin(),
+                cone_angle.sin() * scan_angle.cos(),
+            ),
+        }
+    }
+
+    /// Returns the scan angle of a direction in the scanner frame.
+    ///
+    /// This is the inverse of [ScannerModel::direction] for directions that lie
+    /// in the scan plane or on the scan cone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::ScannerModel;
+    /// let scanner = ScannerModel::Palmer { mirror_tilt: 0.2 };
+    /// let scan_angle = scanner.scan_angle(scanner.direction(0.5));
+    /// assert!((scan_angle - 0.5).abs() < 1e-12);
+    /// ```
+    pub fn scan_angle(&self, direction: Point) -> f64 {
+        if self.is_planar() {
+            direction.z.atan2(direction.x)
+        } else {
+            direction.z.atan2(-direction.y)
+        }
+    }
+
+    /// Returns true if this scanner sweeps the beam through a plane.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::ScannerModel;
+    /// assert!(ScannerModel::RotatingPolygon.is_planar());
+    /// assert!(!ScannerModel::RotatingPrism { deviation: 0.3 }.is_planar());
+    /// ```
+    pub fn is_planar(&self) -> bool {
+        self.cone_angle().is_none()
+    }
+
+    /// Returns the half angle of the scan cone, or `None` for line scanners.
+    fn cone_angle(&self) -> Option<f64> {
+        match *self {
+            ScannerModel::OscillatingMirror | ScannerModel::RotatingPolygon => None,
+            ScannerModel::Palmer { mirror_tilt } => Some(2. * mirror_tilt),
+            ScannerModel::RotatingPrism { deviation } => Some(deviation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScannerModel;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn conic_directions() {
+        let scanner = ScannerModel::Palmer {
+            mirror_tilt: 10f64.to_radians(),
+        };
+        for scan_angle in [-3., -1., 0., 1., 3.] {
+            let direction = scanner.direction(scan_angle);
+            assert_relative_eq!(1., direction.norm());
+            assert_relative_eq!(20f64.to_radians(), direction.x.acos());
+            assert_relative_eq!(scan_angle, scanner.scan_angle(direction));
+            let step = 1e-6;
+            let numeric = (scanner.direction(scan_angle + step)
+                - scanner.direction(scan_angle - step))
+                / (2. * step);
+            assert_relative_eq!(
+                numeric,
+                scanner.direction_derivative(scan_angle),
+                epsilon = 1e-8
+            );
+        }
+    }
+
+    #[test]
+    fn deserialize() {
+        #[derive(serde::Deserialize)]
+        struct Wrapper {
+            scanner: ScannerModel,
+        }
+        let wrapper: Wrapper =
+            toml::from_str("[scanner]\ntype = \"rotating_prism\"\ndeviation = 0.3").unwrap();
+        assert_eq!(
+            ScannerModel::RotatingPrism { deviation: 0.3 },
+            wrapper.scanner
+        );
+    }
+}
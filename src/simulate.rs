@@ -0,0 +1,276 @@
+//! Forward simulation of lidar points with known truth.
+//!
+//! Pulses are fired at a fixed rate along a trajectory, steered by the
+//! config's scanner model, and intersected with a synthetic terrain via the
+//! forward lidar equation. Since the resulting points exactly follow the
+//! lidar equation for the config, they can be used to validate partial
+//! derivatives and the adjustment.
+//!
+//! Each pulse uses the trajectory point that [Trajectory::get] returns for its
+//! time, the same as measurements do. The atmosphere is not simulated. The
+//! true scan angle is stored on each point, so use
+//! [Measurement::use_las_scan_angle](crate::Measurement::use_las_scan_angle)
+//! to model the simulated points with it.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{simulate::{self, Options, Terrain}, Config, Measurement, Trajectory};
+//! let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+//! let config = Config::from_path("data/config.toml").unwrap();
+//! let options = Options { duration: Some(0.01), ..Default::default() };
+//! let points = simulate::simulate(&trajectory, config.clone(), &Terrain::Plane(2690.), &options).unwrap();
+//! let mut measurement = Measurement::new(&trajectory, points[0].clone(), config).unwrap();
+//! measurement.use_las_scan_angle(true);
+//! assert!(measurement.residuals().norm() < 1e-3);
+//! ```
+
+use crate::{convert, Config, Dem, Error, Point, RollPitchYaw, ScannerModel, Trajectory};
+use std::f64::consts::PI;
+
+/// The maximum number of Newton iterations when intersecting a beam with the terrain.
+const MAX_ITERATIONS: usize = 20;
+
+/// The tolerance (in meters) for intersecting a beam with the terrain.
+const TOLERANCE: f64 = 1e-6;
+
+/// The synthetic terrain that pulses are intersected with.
+#[derive(Debug, Clone, Copy)]
+pub enum Terrain<'a> {
+    /// A horizontal plane at this elevation.
+    Plane(f64),
+
+    /// A DEM, which must be in the same projected coordinate system as the config.
+    Dem(&'a Dem),
+}
+
+/// Options for simulating points.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// The number of pulses per second.
+    pub pulse_rate: f64,
+
+    /// The number of scan lines (or, for conic scanners, rotations) per second.
+    pub scan_rate: f64,
+
+    /// The full field of view of planar scanners, in radians.
+    ///
+    /// Conic scanners always sweep the full circle.
+    pub field_of_view: f64,
+
+    /// The time of the first pulse, or the start of the trajectory if not provided.
+    pub start: Option<f64>,
+
+    /// How long to fire pulses for, in seconds, or until the end of the trajectory if not provided.
+    pub duration: Option<f64>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            pulse_rate: 100_000.,
+            scan_rate: 50.,
+            field_of_view: 40f64.to_radians(),
+            start: None,
+            duration: None,
+        }
+    }
+}
+
+impl Terrain<'_> {
+    fn elevation(&self, x: f64, y: f64) -> Option<f64> {
+        match self {
+            Terrain::Plane(elevation) => Some(*elevation),
+            Terrain::Dem(dem) => dem.elevation(x, y),
+        }
+    }
+}
+
+/// Simulates a point cloud.
+///
+/// Pulses whose time isn't covered by the trajectory, that point upwards, or
+/// that don't hit the terrain are dropped. Points have a gps time, a scan
+/// angle in degrees, and are single returns.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{simulate::{self, Options, Terrain}, Config, Dem, Trajectory};
+/// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+/// let config = Config::from_path("data/config.toml").unwrap();
+/// let dem = Dem::from_path("data/dem.tif").unwrap();
+/// let options = Options { duration: Some(0.01), ..Default::default() };
+/// let points = simulate::simulate(&trajectory, config, &Terrain::Dem(&dem), &options).unwrap();
+/// ```
+pub fn simulate(
+    trajectory: &Trajectory,
+    config: Config,
+    terrain: &Terrain<'_>,
+    options: &Options,
+) -> Result<Vec<las::Point>, Error> {
+    if options.pulse_rate <= 0. || options.scan_rate <= 0. {
+        return Err(Error::Invalid(
+            "pulse and scan rates must be positive".to_string(),
+        ));
+    }
+    let (first, last) = match (trajectory.points().first(), trajectory.points().last()) {
+        (Some(first), Some(last)) => (first.time, last.time),
+        _ => return Ok(Vec::new()),
+    };
+    let start = options.start.unwrap_or(first);
+    let end = options
+        .duration
+        .map(|duration| start + duration)
+        .unwrap_or(last)
+        .min(last);
+    let boresight = config.rotation_order.matrix(config.boresight);
+    let lever_arm = config
+        .lever_arm_frame
+        .matrix(boresight, config.navigation_frame)
+        * config.lever_arm;
+    let ellipsoid = config.ellipsoid.ellipsoid();
+    let utm_zone = match config.utm_zone.or_else(|| trajectory.utm_zone()) {
+        Some(utm_zone) => utm_zone,
+        None => return Ok(Vec::new()),
+    };
+    let mut points = Vec::new();
+    let mut pulse = 0;
+    loop {
+        let time = start + pulse as f64 / options.pulse_rate;
+        pulse += 1;
+        if time > end {
+            break;
+        }
+        let sbet = match trajectory.get(time) {
+            Some(sbet) => sbet,
+            None => continue,
+        };
+        let scan_angle = scan_angle(config.scanner, time * options.scan_rate, options);
+        let from_ned = config.navigation_frame.matrix();
+        let direction = boresight * from_ned * config.scanner.direction(scan_angle);
+        let platform = convert::platform(sbet, &config);
+        let rpy = RollPitchYaw::new(sbet.roll, sbet.pitch, sbet.yaw);
+        let project = |range: f64| {
+            ellipsoid.body_to_projected(
+                range * direction - lever_arm,
+                platform,
+                rpy,
+                utm_zone,
+                config.navigation_frame,
+            )
+        };
+        // The navigation frame is north, east, down.
+        let down = convert::body_to_navigation(from_ned * direction, rpy).z;
+        if down <= 0. {
+            continue;
+        }
+        let height = match terrain.elevation(project(0.).x, project(0.).y) {
+            Some(elevation) => platform.z - elevation,
+            None => continue,
+        };
+        if let Some(range) = intersect(&project, terrain, height / down) {
+            let point = project(range);
+            points.push(las::Point {
+                x: point.x,
+                y: point.y,
+                z: point.z,
+                gps_time: Some(time),
+                scan_angle: scan_angle.to_degrees() as f32,
+                return_number: 1,
+                number_of_returns: 1,
+                ..Default::default()
+            });
+        }
+    }
+    Ok(points)
+}
+
+/// Returns the scan angle for a number of scan lines (or rotations) since time zero.
+fn scan_angle(scanner: ScannerModel, lines: f64, options: &Options) -> f64 {
+    let phase = lines - lines.floor();
+    let half = options.field_of_view / 2.;
+    match scanner {
+        // Sweeps out and back each line.
+        ScannerModel::OscillatingMirror => {
+            if phase < 0.5 {
+                -half + 4. * half * phase
+            } else {
+                3. * half - 4. * half * phase
+            }
+        }
+        ScannerModel::RotatingPolygon => -half + 2. * half * phase,
+        ScannerModel::Palmer { .. } | ScannerModel::RotatingPrism { .. } => -PI + 2. * PI * phase,
+    }
+}
+
+/// Finds the range where the beam hits the terrain with Newton's method.
+fn intersect<F: Fn(f64) -> Point>(project: &F, terrain: &Terrain<'_>, guess: f64) -> Option<f64> {
+    let misfit = |range: f64| {
+        let point = project(range);
+        terrain
+            .elevation(point.x, point.y)
+            .map(|elevation| point.z - elevation)
+    };
+    let mut range = guess;
+    for _ in 0..MAX_ITERATIONS {
+        let value = misfit(range)?;
+        if value.abs() < TOLERANCE {
+            return if range > 0. { Some(range) } else { None };
+        }
+        let step = 1e-3;
+        let derivative = (misfit(range + step)? - value) / step;
+        if derivative == 0. {
+            return None;
+        }
+        range -= value / derivative;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options, Terrain};
+    use crate::{convert::Helmert, Config, Measurement, Point, Trajectory};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn roundtrip() {
+        let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+        let config = Config::from_path("data/config.toml").unwrap();
+        let mut transformed = config.clone();
+        transformed.datum_transformation = Some(Helmert {
+            translation: Point::new(0.5, -0.3, 2.),
+            rotation: Point::zeros(),
+            scale: 1e-6,
+            translation_rate: Point::zeros(),
+            rotation_rate: Point::zeros(),
+            scale_rate: 0.,
+            reference_epoch: 2010.,
+        });
+        let options = Options {
+            pulse_rate: 10_000.,
+            duration: Some(0.05),
+            ..Default::default()
+        };
+        for config in [config, transformed] {
+            let points = super::simulate(
+                &trajectory,
+                config.clone(),
+                &Terrain::Plane(2690.),
+                &options,
+            )
+            .unwrap();
+            assert!(points.len() > 400);
+            for point in points {
+                assert_relative_eq!(2690., point.z, epsilon = 1e-5);
+                let mut measurement =
+                    Measurement::new(&trajectory, point.clone(), config.clone()).unwrap();
+                measurement.use_las_scan_angle(true);
+                assert!(measurement.residuals().norm() < 1e-3);
+                let modeled = measurement.modeled_point();
+                assert_relative_eq!(point.x, modeled.x, epsilon = 1e-3);
+                assert_relative_eq!(point.z, modeled.z, epsilon = 1e-3);
+            }
+        }
+    }
+}
@@ -0,0 +1,435 @@
+//! Output sinks for leeward's per-point records.
+//!
+//! An [OutputSink] takes records one at a time, so the same workflow can
+//! write CSV, JSON lines, parquet, or las, collect records into a vector, or
+//! hand them to a callback. [CsvSink] is the default.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{processor::Processor, sink::{CsvSink, OutputSink}, Point};
+//! let processor = Processor::new("data/sbet.out", "data/config.toml")
+//!     .las("data/points.las")
+//!     .decimation(100);
+//! let mut sink = CsvSink::new(Vec::new());
+//! processor.tpu_into(Point::new(0., 0., 1.), &mut sink).unwrap();
+//! sink.finish().unwrap();
+//! ```
+
+use crate::Error;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    fmt::Debug,
+    io::{BufWriter, Seek, Write},
+};
+
+/// Somewhere to write records, one per measurement.
+pub trait OutputSink<T> {
+    /// Writes one record.
+    fn write(&mut self, record: T) -> Result<(), Error>;
+
+    /// Finishes writing, e.g. by flushing buffers or writing a footer.
+    ///
+    /// Nothing should be written after the sink is finished.
+    fn finish(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Writes records as CSV with a header row.
+#[derive(Debug)]
+pub struct CsvSink<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+/// Writes records as JSON objects, one per line.
+#[derive(Debug)]
+pub struct JsonlSink<W: Write> {
+    writer: BufWriter<W>,
+}
+
+/// Writes records to a parquet file.
+///
+/// See [ParquetWriter](crate::output::ParquetWriter) for how records become columns.
+#[cfg(feature = "parquet")]
+pub struct ParquetSink<W: Write + Send> {
+    writer: Option<crate::output::ParquetWriter<W>>,
+}
+
+/// Writes records as las points, with their other fields as extra bytes.
+///
+/// Records must have `x`, `y`, and `z` fields, which become the point's
+/// coordinates, and a `time` field, if present, becomes its gps time. Every
+/// other number, boolean, or missing value is written as an extra bytes
+/// double, with missing values written as NaN. Strings and nested values are
+/// skipped.
+///
+/// The header builder sets the transforms, CRS, and version. Its point format
+/// is replaced by one with gps times, without colors or waveforms, and with
+/// room for the extra bytes, and any existing extra bytes are dropped.
+pub struct LasSink<W: 'static + Write + Seek + Debug + Send> {
+    builder: Option<las::Builder>,
+    write: Option<W>,
+    writer: Option<las::Writer<W>>,
+    extra_bytes: Vec<String>,
+}
+
+/// Passes records to a function.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::sink::{Callback, OutputSink};
+/// let mut count = 0;
+/// let mut sink = Callback(|_: f64| {
+///     count += 1;
+///     Ok(())
+/// });
+/// sink.write(1.).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Callback<F>(pub F);
+
+impl<W: Write> CsvSink<W> {
+    /// Creates a new CSV sink.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::sink::{CsvSink, OutputSink};
+    /// let mut sink = CsvSink::new(Vec::new());
+    /// sink.write((1., 2.)).unwrap();
+    /// ```
+    pub fn new(write: W) -> CsvSink<W> {
+        CsvSink {
+            writer: csv::Writer::from_writer(write),
+        }
+    }
+
+    /// Flushes the underlying writer.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        self.writer.flush().map_err(Error::from)
+    }
+}
+
+impl<W: Write> JsonlSink<W> {
+    /// Creates a new JSON lines sink.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::sink::{JsonlSink, OutputSink};
+    /// #[derive(serde::Serialize)]
+    /// struct Record {
+    ///     x: f64,
+    /// }
+    /// let mut sink = JsonlSink::new(Vec::new());
+    /// sink.write(Record { x: 1. }).unwrap();
+    /// ```
+    pub fn new(write: W) -> JsonlSink<W> {
+        JsonlSink {
+            writer: BufWriter::new(write),
+        }
+    }
+
+    /// Flushes the underlying writer.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        self.writer.flush().map_err(Error::from)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl<W: Write + Send> ParquetSink<W> {
+    /// Creates a new parquet sink.
+    pub fn new(write: W) -> ParquetSink<W> {
+        ParquetSink {
+            writer: Some(crate::output::ParquetWriter::new(write)),
+        }
+    }
+
+    /// Writes any buffered rows and the parquet footer.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.into_inner()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: 'static + Write + Seek + Debug + Send> LasSink<W> {
+    /// Creates a new las sink.
+    ///
+    /// Nothing is written until the first record, because the extra bytes
+    /// depend on its fields.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::sink::{LasSink, OutputSink};
+    /// use std::io::Cursor;
+    /// #[derive(serde::Serialize)]
+    /// struct Record {
+    ///     x: f64,
+    ///     y: f64,
+    ///     z: f64,
+    ///     sigma: f64,
+    /// }
+    /// let mut sink = LasSink::new(Cursor::new(Vec::new()), las::Builder::from((1, 4)));
+    /// sink.write(Record { x: 1., y: 2., z: 3., sigma: 0.1 }).unwrap();
+    /// sink.finish().unwrap();
+    /// ```
+    pub fn new(write: W, builder: las::Builder) -> LasSink<W> {
+        LasSink {
+            builder: Some(builder),
+            write: Some(write),
+            writer: None,
+            extra_bytes: Vec::new(),
+        }
+    }
+
+    /// Writes the header, if no records were written, and closes the las writer.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        if self.writer.is_none() && self.builder.is_some() {
+            self.start(&Map::new())?;
+        }
+        if let Some(mut writer) = self.writer.take() {
+            writer.close()?;
+        }
+        Ok(())
+    }
+
+    fn start(&mut self, object: &Map<String, Value>) -> Result<(), Error> {
+        self.extra_bytes = object
+            .iter()
+            .filter(|(name, value)| {
+                !matches!(name.as_str(), "x" | "y" | "z" | "time")
+                    && matches!(value, Value::Number(_) | Value::Bool(_) | Value::Null)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        let mut builder = self
+            .builder
+            .take()
+            .expect("las sink should only be started once");
+        builder.point_format.has_gps_time = true;
+        builder.point_format.has_color = false;
+        builder.point_format.has_waveform = false;
+        builder.point_format.has_nir = false;
+        builder.point_format.extra_bytes = 0;
+        builder
+            .vlrs
+            .retain(|vlr| !(vlr.user_id == "LASF_Spec" && vlr.record_id == 4));
+        for name in &self.extra_bytes {
+            add_extra_bytes(&mut builder, name);
+        }
+        let write = self
+            .write
+            .take()
+            .expect("las sink should only be started once");
+        self.writer = Some(las::Writer::new(write, builder.into_header()?)?);
+        Ok(())
+    }
+}
+
+impl<T> OutputSink<T> for Vec<T> {
+    fn write(&mut self, record: T) -> Result<(), Error> {
+        self.push(record);
+        Ok(())
+    }
+}
+
+impl<T, S: OutputSink<T> + ?Sized> OutputSink<T> for Box<S> {
+    fn write(&mut self, record: T) -> Result<(), Error> {
+        (**self).write(record)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        (**self).finish()
+    }
+}
+
+impl<T: Serialize, W: Write> OutputSink<T> for CsvSink<W> {
+    fn write(&mut self, record: T) -> Result<(), Error> {
+        self.writer.serialize(record).map_err(Error::from)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        CsvSink::finish(self)
+    }
+}
+
+impl<T: Serialize, W: Write> OutputSink<T> for JsonlSink<W> {
+    fn write(&mut self, record: T) -> Result<(), Error> {
+        serde_json::to_writer(&mut self.writer, &record)?;
+        writeln!(self.writer).map_err(Error::from)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        JsonlSink::finish(self)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl<T: Serialize, W: Write + Send> OutputSink<T> for ParquetSink<W> {
+    fn write(&mut self, record: T) -> Result<(), Error> {
+        self.writer
+            .as_mut()
+            .ok_or_else(|| Error::Output("the parquet sink is finished".to_string()))?
+            .serialize(record)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        ParquetSink::finish(self)
+    }
+}
+
+impl<T: Serialize, W: 'static + Write + Seek + Debug + Send> OutputSink<T> for LasSink<W> {
+    fn write(&mut self, record: T) -> Result<(), Error> {
+        let object = match serde_json::to_value(record)? {
+            Value::Object(object) => object,
+            value => {
+                return Err(Error::Output(format!(
+                    "can only write structs to las, got {}",
+                    value
+                )))
+            }
+        };
+        if self.writer.is_none() {
+            if self.builder.is_none() {
+                return Err(Error::Output("the las sink is finished".to_string()));
+            }
+            self.start(&object)?;
+        }
+        let coordinate = |name: &str| {
+            object
+                .get(name)
+                .and_then(Value::as_f64)
+                .ok_or_else(|| Error::Output(format!("las records need a numeric {} field", name)))
+        };
+        let mut extra_bytes = Vec::with_capacity(8 * self.extra_bytes.len());
+        for name in &self.extra_bytes {
+            let value = match object.get(name) {
+                Some(Value::Bool(value)) => f64::from(u8::from(*value)),
+                Some(value) => value.as_f64().unwrap_or(f64::NAN),
+                None => f64::NAN,
+            };
+            extra_bytes.extend(value.to_le_bytes());
+        }
+        let point = las::Point {
+            x: coordinate("x")?,
+            y: coordinate("y")?,
+            z: coordinate("z")?,
+            gps_time: Some(object.get("time").and_then(Value::as_f64).unwrap_or(0.)),
+            extra_bytes,
+            ..Default::default()
+        };
+        let writer = self.writer.as_mut().expect("writer was just started");
+        las::Write::write(writer, point).map_err(Error::from)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        LasSink::finish(self)
+    }
+}
+
+impl<T, F: FnMut(T) -> Result<(), Error>> OutputSink<T> for Callback<F> {
+    fn write(&mut self, record: T) -> Result<(), Error> {
+        (self.0)(record)
+    }
+}
+
+/// Adds an unscaled double extra bytes dimension to a las builder.
+///
+/// The dimension is described after any existing extra bytes descriptors and
+/// its size is added to the point format, so points written with this builder
+/// should append the value's little-endian bytes to their extra bytes.
+///
+/// # Examples
+///
+/// ```
+/// let mut builder = las::Builder::from((1, 4));
+/// leeward::sink::add_extra_bytes(&mut builder, "scan_angle");
+/// assert_eq!(8, builder.point_format.extra_bytes);
+/// assert_eq!(192, builder.vlrs[0].data.len());
+/// ```
+pub fn add_extra_bytes(builder: &mut las::Builder, name: &str) {
+    builder.point_format.extra_bytes += 8;
+    let descriptor = extra_bytes_descriptor(name);
+    if let Some(vlr) = builder
+        .vlrs
+        .iter_mut()
+        .find(|vlr| vlr.user_id == "LASF_Spec" && vlr.record_id == 4)
+    {
+        vlr.data.extend(descriptor);
+    } else {
+        builder.vlrs.push(las::Vlr {
+            user_id: "LASF_Spec".to_string(),
+            record_id: 4,
+            description: "leeward extra bytes".to_string(),
+            data: descriptor.to_vec(),
+        });
+    }
+}
+
+/// Returns a las 1.4 extra bytes descriptor for an unscaled double.
+fn extra_bytes_descriptor(name: &str) -> [u8; 192] {
+    let mut descriptor = [0; 192];
+    descriptor[2] = 10; // double
+    let name = name.as_bytes();
+    let len = name.len().min(32);
+    descriptor[4..4 + len].copy_from_slice(&name[..len]);
+    descriptor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LasSink, OutputSink};
+    use serde::Serialize;
+    use std::{convert::TryInto, fs::File};
+
+    #[derive(Serialize)]
+    struct Record {
+        x: f64,
+        y: f64,
+        z: f64,
+        time: f64,
+        sigma: Option<f64>,
+        quality: &'static str,
+    }
+
+    #[test]
+    fn las_extra_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("records.las");
+        {
+            let file = File::create(&path).unwrap();
+            let mut sink = LasSink::new(file, las::Builder::from((1, 4)));
+            for sigma in [Some(0.25), None] {
+                sink.write(Record {
+                    x: 1.,
+                    y: 2.,
+                    z: 3.,
+                    time: 4.,
+                    sigma,
+                    quality: "ok",
+                })
+                .unwrap();
+            }
+            sink.finish().unwrap();
+        }
+        let mut reader = las::Reader::from_path(&path).unwrap();
+        let header = las::Read::header(&reader);
+        assert_eq!(8, header.point_format().extra_bytes);
+        assert!(header
+            .vlrs()
+            .iter()
+            .any(|vlr| vlr.user_id == "LASF_Spec" && vlr.record_id == 4));
+        let points: Vec<las::Point> = las::Read::points(&mut reader)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(Some(4.), points[0].gps_time);
+        assert_eq!(0.25f64.to_le_bytes().to_vec(), points[0].extra_bytes);
+        assert!(f64::from_le_bytes(points[1].extra_bytes[..].try_into().unwrap()).is_nan());
+    }
+}
@@ -0,0 +1,313 @@
+//! Spatial indexing of points for neighbor searches.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{spatial::KdTree, Point};
+//! let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+//! let tree = KdTree::from_measurements(&measurements);
+//! let point = Point::new(measurements[0].x(), measurements[0].y(), measurements[0].z());
+//! let neighbors = tree.nearest(point, 5);
+//! assert_eq!(0, neighbors[0].0);
+//! ```
+
+use crate::{Measurement, Point, PointSource};
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+/// A k-d tree over projected coordinates.
+///
+/// The tree can be built over all three dimensions, or over just x and y for
+/// "2.5D" queries against surfaces. Queries return indices into the points
+/// (or measurements) the tree was built from, along with distances.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    points: Vec<Point>,
+    indices: Vec<usize>,
+    dimensions: usize,
+}
+
+#[derive(Debug, PartialEq)]
+struct Neighbor {
+    distance: f64,
+    index: usize,
+}
+
+impl KdTree {
+    /// Creates a new three dimensional tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Point, spatial::KdTree};
+    /// let tree = KdTree::new(vec![Point::new(0., 0., 0.), Point::new(1., 1., 1.)]);
+    /// assert_eq!(2, tree.len());
+    /// ```
+    pub fn new(points: Vec<Point>) -> KdTree {
+        KdTree::with_dimensions(points, 3)
+    }
+
+    /// Creates a new tree that only uses the x and y coordinates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Point, spatial::KdTree};
+    /// let tree = KdTree::new_xy(vec![Point::new(0., 0., 0.), Point::new(1., 1., 100.)]);
+    /// let neighbors = tree.within(Point::new(1., 1., 0.), 0.1);
+    /// assert_eq!(vec![(1, 0.)], neighbors);
+    /// ```
+    pub fn new_xy(points: Vec<Point>) -> KdTree {
+        KdTree::with_dimensions(points, 2)
+    }
+
+    /// Creates a three dimensional tree from the projected coordinates of measurements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::spatial::KdTree;
+    /// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+    /// let tree = KdTree::from_measurements(&measurements);
+    /// ```
+    pub fn from_measurements<L: PointSource>(measurements: &[Measurement<L>]) -> KdTree {
+        KdTree::new(measurements.iter().map(|m| m.lasish().point()).collect())
+    }
+
+    fn with_dimensions(points: Vec<Point>, dimensions: usize) -> KdTree {
+        let mut tree = KdTree {
+            indices: (0..points.len()).collect(),
+            points,
+            dimensions,
+        };
+        let len = tree.indices.len();
+        tree.build(0, len, 0);
+        tree
+    }
+
+    /// Returns the number of points in this tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::spatial::KdTree;
+    /// assert_eq!(0, KdTree::new(Vec::new()).len());
+    /// ```
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns true if this tree has no points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::spatial::KdTree;
+    /// assert!(KdTree::new(Vec::new()).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns the point with the given index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Point, spatial::KdTree};
+    /// let tree = KdTree::new(vec![Point::new(1., 2., 3.)]);
+    /// assert_eq!(Point::new(1., 2., 3.), tree.point(0));
+    /// ```
+    pub fn point(&self, index: usize) -> Point {
+        self.points[index]
+    }
+
+    /// Returns the indices of and distances to the `k` nearest points, nearest first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Point, spatial::KdTree};
+    /// let tree = KdTree::new(vec![Point::new(0., 0., 0.), Point::new(3., 4., 0.)]);
+    /// assert_eq!(vec![(1, 5.)], tree.nearest(Point::new(0., 0., 0.), 2)[1..]);
+    /// ```
+    pub fn nearest(&self, point: Point, k: usize) -> Vec<(usize, f64)> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.search_nearest(point, k, 0, self.indices.len(), 0, &mut heap);
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|neighbor| (neighbor.index, neighbor.distance))
+            .collect()
+    }
+
+    /// Returns the indices of and distances to all points within `radius`, nearest first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::{Point, spatial::KdTree};
+    /// let tree = KdTree::new(vec![Point::new(0., 0., 0.), Point::new(3., 4., 0.)]);
+    /// assert_eq!(1, tree.within(Point::new(0., 0., 0.), 4.).len());
+    /// ```
+    pub fn within(&self, point: Point, radius: f64) -> Vec<(usize, f64)> {
+        let mut neighbors = Vec::new();
+        self.search_within(point, radius, 0, self.indices.len(), 0, &mut neighbors);
+        neighbors.sort();
+        neighbors
+            .into_iter()
+            .map(|neighbor| (neighbor.index, neighbor.distance))
+            .collect()
+    }
+
+    fn build(&mut self, start: usize, end: usize, depth: usize) {
+        if end - start <= 1 {
+            return;
+        }
+        let axis = depth % self.dimensions;
+        let middle = (start + end) / 2;
+        let points = &self.points;
+        self.indices[start..end].select_nth_unstable_by(middle - start, |&a, &b| {
+            points[a][axis].total_cmp(&points[b][axis])
+        });
+        self.build(start, middle, depth + 1);
+        self.build(middle + 1, end, depth + 1);
+    }
+
+    fn distance(&self, a: Point, b: Point) -> f64 {
+        (0..self.dimensions)
+            .map(|i| (a[i] - b[i]).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn search_nearest(
+        &self,
+        point: Point,
+        k: usize,
+        start: usize,
+        end: usize,
+        depth: usize,
+        heap: &mut BinaryHeap<Neighbor>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let middle = (start + end) / 2;
+        let index = self.indices[middle];
+        let distance = self.distance(point, self.points[index]);
+        if heap.len() < k {
+            heap.push(Neighbor { distance, index });
+        } else if heap.peek().map(|n| distance < n.distance).unwrap_or(false) {
+            let _ = heap.pop();
+            heap.push(Neighbor { distance, index });
+        }
+        let axis = depth % self.dimensions;
+        let delta = point[axis] - self.points[index][axis];
+        let (near, far) = if delta < 0. {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+        self.search_nearest(point, k, near.0, near.1, depth + 1, heap);
+        if heap.len() < k
+            || heap
+                .peek()
+                .map(|n| delta.abs() < n.distance)
+                .unwrap_or(true)
+        {
+            self.search_nearest(point, k, far.0, far.1, depth + 1, heap);
+        }
+    }
+
+    fn search_within(
+        &self,
+        point: Point,
+        radius: f64,
+        start: usize,
+        end: usize,
+        depth: usize,
+        neighbors: &mut Vec<Neighbor>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let middle = (start + end) / 2;
+        let index = self.indices[middle];
+        let distance = self.distance(point, self.points[index]);
+        if distance <= radius {
+            neighbors.push(Neighbor { distance, index });
+        }
+        let axis = depth % self.dimensions;
+        let delta = point[axis] - self.points[index][axis];
+        if delta <= radius {
+            self.search_within(point, radius, start, middle, depth + 1, neighbors);
+        }
+        if delta >= -radius {
+            self.search_within(point, radius, middle + 1, end, depth + 1, neighbors);
+        }
+    }
+}
+
+impl Eq for Neighbor {}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Neighbor) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Neighbor) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KdTree;
+    use crate::{rng::Rng, Point};
+
+    fn points() -> Vec<Point> {
+        let mut rng = Rng::new(42);
+        let mut next = || rng.next_f64() * 100.;
+        (0..500)
+            .map(|_| Point::new(next(), next(), next()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let points = points();
+        let tree = KdTree::new(points.clone());
+        for query in points.iter().step_by(50) {
+            let query = query + Point::new(0.5, -0.5, 0.25);
+            let mut expected: Vec<(usize, f64)> = points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (i, (p - query).norm()))
+                .collect();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+            assert_eq!(expected[..7].to_vec(), tree.nearest(query, 7));
+            let within: Vec<(usize, f64)> =
+                expected.into_iter().take_while(|n| n.1 <= 15.).collect();
+            assert_eq!(within, tree.within(query, 15.));
+        }
+    }
+
+    #[test]
+    fn xy() {
+        let points = points();
+        let tree = KdTree::new_xy(points.clone());
+        let query = Point::new(50., 50., -1000.);
+        let (index, distance) = tree.nearest(query, 1)[0];
+        let expected = points
+            .iter()
+            .map(|p| ((p.x - 50.).powi(2) + (p.y - 50.).powi(2)).sqrt())
+            .fold(f64::INFINITY, f64::min);
+        assert_eq!(expected, distance);
+        assert_eq!(points[index], tree.point(index));
+    }
+}
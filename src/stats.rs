@@ -0,0 +1,79 @@
+//! Small statistical helpers.
+
+use std::f64::consts::PI;
+
+/// Returns the error function, with a maximum error of about 1.2e-7.
+///
+/// Uses the Chebyshev fit from Numerical Recipes.
+pub(crate) fn erf(x: f64) -> f64 {
+    let t = 1. / (1. + 0.5 * x.abs());
+    let tau = t
+        * (-x.powi(2) - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    if x >= 0. {
+        1. - tau
+    } else {
+        tau - 1.
+    }
+}
+
+/// The number of dimensions of a standard normal distribution.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Dimensions {
+    One,
+    Two,
+    Three,
+}
+
+/// Returns the radius that contains `confidence` of a standard normal distribution in one, two, or three dimensions.
+///
+/// In one dimension this is the two-sided quantile, e.g. 1.96 for 0.95.
+pub(crate) fn chi_quantile(dimensions: Dimensions, confidence: f64) -> f64 {
+    let cdf = |r: f64| match dimensions {
+        Dimensions::One => erf(r / 2f64.sqrt()),
+        Dimensions::Two => 1. - (-r.powi(2) / 2.).exp(),
+        Dimensions::Three => erf(r / 2f64.sqrt()) - (2. / PI).sqrt() * r * (-r.powi(2) / 2.).exp(),
+    };
+    let (mut low, mut high) = (0., 40.);
+    for _ in 0..100 {
+        let middle = (low + high) / 2.;
+        if cdf(middle) < confidence {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dimensions;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn chi_quantile() {
+        assert_relative_eq!(
+            1.959964,
+            super::chi_quantile(Dimensions::One, 0.95),
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(
+            2.447747,
+            super::chi_quantile(Dimensions::Two, 0.95),
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(
+            2.795483,
+            super::chi_quantile(Dimensions::Three, 0.95),
+            epsilon = 1e-5
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Relative accuracy between overlapping flightlines.
+//!
+//! Each point in a strip is compared against a plane fit to the nearby points
+//! of a reference strip. Only smooth surfaces, where the plane fits well, are
+//! used. The vertical differences are then summarized, and regressed against
+//! cross-track distance and along-track surface slope to estimate the roll,
+//! pitch, and yaw signatures of a boresight misalignment.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::{strip::{self, Options}, tabular::{self, Format}};
+//! let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+//! let reference = tabular::read_points("data/points.las", Format::Las).unwrap();
+//! let options = Options { radius: 30., max_roughness: 10., ..Default::default() };
+//! let samples = strip::samples(&measurements, &reference, &options);
+//! let summary = strip::summarize(&samples).unwrap();
+//! ```
+
+use crate::{spatial::KdTree, utils, Error, Measurement, Point, PointSource};
+use nalgebra::{DMatrix, DVector};
+use serde::Serialize;
+
+/// Options for matching a strip to a reference strip.
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    /// The horizontal search radius for reference points, in meters.
+    pub radius: f64,
+
+    /// The minimum number of reference points needed to fit a plane.
+    pub min_points: usize,
+
+    /// The maximum root-mean-square residual of a plane fit, in meters.
+    ///
+    /// Planes that fit worse than this aren't smooth surfaces, and are skipped.
+    pub max_roughness: f64,
+}
+
+/// The comparison of one strip point to the reference surface.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Sample {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+
+    /// The point elevation minus the reference plane elevation.
+    pub dz: f64,
+
+    /// The point's distance from nadir, positive to the right of the platform.
+    pub cross_track: f64,
+
+    /// The slope of the reference plane in the direction of flight.
+    pub along_slope: f64,
+
+    /// The root-mean-square distance of the reference points from their plane, see [utils::fit_plane].
+    pub roughness: f64,
+
+    /// The number of reference points used to fit the plane.
+    pub count: usize,
+}
+
+/// Interswath statistics for a strip.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Summary {
+    /// The number of samples on smooth surfaces.
+    pub count: usize,
+
+    /// The mean vertical difference.
+    pub mean_dz: f64,
+
+    /// The root-mean-square vertical difference.
+    pub rms_dz: f64,
+
+    /// The standard deviation of the vertical differences.
+    pub std_dz: f64,
+
+    /// The constant vertical offset, after removing the other signatures.
+    pub offset: f64,
+
+    /// The slope of dz with cross-track distance, in radians, the signature of a roll misalignment.
+    pub roll: f64,
+
+    /// The along-track shift in meters implied by dz on along-track slopes, the signature of a pitch misalignment.
+    pub along_track: f64,
+
+    /// The rotation in radians implied by an along-track shift that grows with cross-track distance, the signature of a yaw misalignment.
+    pub yaw: f64,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            radius: 1.,
+            min_points: 5,
+            max_roughness: 0.1,
+        }
+    }
+}
+
+/// Compares each measurement against planes fit to the reference points.
+///
+/// Measurements without enough reference points nearby, or on rough surfaces, are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{strip::{self, Options}, tabular::{self, Format}};
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let reference = tabular::read_points("data/points.csv", Format::Csv).unwrap();
+/// let samples = strip::samples(&measurements, &reference, &Options::default());
+/// assert!(samples.is_empty());
+/// ```
+pub fn samples<L: PointSource>(
+    measurements: &[Measurement<L>],
+    reference: &[Point],
+    options: &Options,
+) -> Vec<Sample> {
+    let tree = KdTree::new_xy(reference.to_vec());
+    let mut samples = Vec::new();
+    for measurement in measurements {
+        let point = measurement.lasish().point();
+        let neighbors = tree.within(point, options.radius);
+        if neighbors.len() < options.min_points.max(3) {
+            continue;
+        }
+        let neighbors: Vec<Point> = neighbors
+            .into_iter()
+            .map(|(index, _)| tree.point(index))
+            .collect();
+        let plane = match utils::fit_plane(&neighbors) {
+            Ok(plane) if plane.normal.z > 0. => plane,
+            _ => continue,
+        };
+        if plane.rms > options.max_roughness {
+            continue;
+        }
+        // Projected coordinates are east, north, and yaw is the heading from north.
+        let yaw = measurement.yaw();
+        let normal = plane.normal;
+        let (east_slope, north_slope) = (-normal.x / normal.z, -normal.y / normal.z);
+        let elevation = plane.centroid.z
+            + east_slope * (point.x - plane.centroid.x)
+            + north_slope * (point.y - plane.centroid.y);
+        samples.push(Sample {
+            x: point.x,
+            y: point.y,
+            z: point.z,
+            dz: point.z - elevation,
+            cross_track: (measurement.config().navigation_frame.matrix()
+                * measurement.body_frame())
+            .y,
+            along_slope: east_slope * yaw.sin() + north_slope * yaw.cos(),
+            roughness: plane.rms,
+            count: neighbors.len(),
+        });
+    }
+    samples
+}
+
+/// Summarizes samples into interswath statistics and misalignment signatures.
+///
+/// The signatures come from a least-squares fit of `dz = offset + roll *
+/// cross_track - along_track * along_slope - yaw * cross_track *
+/// along_slope`.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::{strip::{self, Options}, tabular::{self, Format}};
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let reference = tabular::read_points("data/points.las", Format::Las).unwrap();
+/// let options = Options { radius: 30., max_roughness: 10., ..Default::default() };
+/// let samples = strip::samples(&measurements, &reference, &options);
+/// let summary = strip::summarize(&samples).unwrap();
+/// assert_eq!(samples.len(), summary.count);
+/// ```
+pub fn summarize(samples: &[Sample]) -> Result<Summary, Error> {
+    let count = samples.len();
+    if count < 4 {
+        return Err(Error::Invalid(format!(
+            "not enough samples on smooth surfaces to summarize: {}",
+            count
+        )));
+    }
+    let mean_dz = samples.iter().map(|s| s.dz).sum::<f64>() / count as f64;
+    let rms_dz = (samples.iter().map(|s| s.dz.powi(2)).sum::<f64>() / count as f64).sqrt();
+    let std_dz = (samples
+        .iter()
+        .map(|s| (s.dz - mean_dz).powi(2))
+        .sum::<f64>()
+        / (count - 1) as f64)
+        .sqrt();
+    let design = DMatrix::from_fn(count, 4, |i, j| {
+        let sample = &samples[i];
+        match j {
+            0 => 1.,
+            1 => sample.cross_track,
+            2 => -sample.along_slope,
+            _ => -sample.cross_track * sample.along_slope,
+        }
+    });
+    let dz = DVector::from_iterator(count, samples.iter().map(|s| s.dz));
+    let coefficients = (design.transpose() * &design)
+        .try_inverse()
+        .ok_or(Error::SingularMatrix)?
+        * design.transpose()
+        * dz;
+    Ok(Summary {
+        count,
+        mean_dz,
+        rms_dz,
+        std_dz,
+        offset: coefficients[0],
+        roll: coefficients[1],
+        along_track: coefficients[2],
+        yaw: coefficients[3],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Options;
+    use crate::Point;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn offset() {
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let reference: Vec<Point> = measurements
+            .iter()
+            .map(|m| Point::new(m.x(), m.y(), m.z()))
+            .collect();
+        let shifted: Vec<Point> = reference
+            .iter()
+            .map(|p| p + Point::new(0., 0., 0.5))
+            .collect();
+        let options = Options {
+            radius: 30.,
+            max_roughness: 10.,
+            ..Default::default()
+        };
+        let samples = super::samples(&measurements, &reference, &options);
+        let summary = super::summarize(&samples).unwrap();
+        let shifted = super::summarize(&super::samples(&measurements, &shifted, &options)).unwrap();
+        assert_eq!(summary.count, shifted.count);
+        assert_relative_eq!(summary.mean_dz - 0.5, shifted.mean_dz, epsilon = 1e-6);
+        assert_relative_eq!(summary.offset - 0.5, shifted.offset, epsilon = 1e-6);
+        assert_relative_eq!(summary.roll, shifted.roll, epsilon = 1e-9);
+        assert_relative_eq!(summary.std_dz, shifted.std_dz, epsilon = 1e-6);
+    }
+}
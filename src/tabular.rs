@@ -0,0 +1,198 @@
+//! Lidar points from tabular sources, e.g. CSV or Parquet files.
+//!
+//! Tabular sources must have `x`, `y`, `z`, `time`, and `scan_angle` columns.
+//! As with las, the scan angle is in degrees.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::tabular;
+//! let points = tabular::read_csv("data/points.csv").unwrap();
+//! let (measurements, _) = leeward::measurements_from_points(
+//!     &["data/sbet.out"],
+//!     points,
+//!     "data/config.toml",
+//!     &Default::default(),
+//! ).unwrap();
+//! ```
+
+use crate::{Error, Point, PointSource};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, str::FromStr};
+
+/// A lidar point from a tabular source.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TabularPoint {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub time: Option<f64>,
+    pub scan_angle: f64,
+}
+
+/// The format of a point source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// A las (or laz, if the las crate supports it) file.
+    #[default]
+    Las,
+
+    /// A CSV file with a header row.
+    Csv,
+
+    /// A Parquet file, requires the `parquet` feature.
+    Parquet,
+}
+
+/// Reads just the xyz coordinates of points in any supported format.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::tabular::{self, Format};
+/// let points = tabular::read_points("data/points.las", Format::Las).unwrap();
+/// assert_eq!(points[0], tabular::read_points("data/points.csv", Format::Csv).unwrap()[0]);
+/// ```
+pub fn read_points<P: AsRef<Path>>(path: P, format: Format) -> Result<Vec<Point>, Error> {
+    match format {
+        Format::Las => las::Read::points(&mut las::Reader::from_path(path)?)
+            .map(|r| r.map(|point| point.point()).map_err(Error::from))
+            .collect(),
+        Format::Csv => Ok(read_csv(path)?.iter().map(PointSource::point).collect()),
+        Format::Parquet => Ok(read_parquet(path)?.iter().map(PointSource::point).collect()),
+    }
+}
+
+/// Reads tabular points from a CSV file with a header row.
+///
+/// # Examples
+///
+/// ```
+/// use leeward::tabular;
+/// let points = tabular::read_csv("data/points.csv").unwrap();
+/// ```
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn read_csv<P: AsRef<Path>>(path: P) -> Result<Vec<TabularPoint>, Error> {
+    csv::Reader::from_path(path)?
+        .deserialize()
+        .map(|r| r.map_err(Error::from))
+        .collect()
+}
+
+/// Reads tabular points from a Parquet file.
+///
+/// Columns can be any numeric type, and `time` may be nullable.
+#[cfg(feature = "parquet")]
+pub fn read_parquet<P: AsRef<Path>>(path: P) -> Result<Vec<TabularPoint>, Error> {
+    use parquet::{file::reader::SerializedFileReader, record::Field};
+    use std::fs::File;
+
+    fn missing(column: &str) -> Error {
+        Error::Tabular(format!("missing {} column", column))
+    }
+
+    fn to_f64(field: &Field) -> Result<Option<f64>, Error> {
+        match *field {
+            Field::Null => Ok(None),
+            Field::Byte(n) => Ok(Some(f64::from(n))),
+            Field::Short(n) => Ok(Some(f64::from(n))),
+            Field::Int(n) => Ok(Some(f64::from(n))),
+            Field::Long(n) => Ok(Some(n as f64)),
+            Field::UByte(n) => Ok(Some(f64::from(n))),
+            Field::UShort(n) => Ok(Some(f64::from(n))),
+            Field::UInt(n) => Ok(Some(f64::from(n))),
+            Field::ULong(n) => Ok(Some(n as f64)),
+            Field::Float(n) => Ok(Some(f64::from(n))),
+            Field::Double(n) => Ok(Some(n)),
+            _ => Err(Error::Tabular(format!(
+                "unsupported parquet field: {}",
+                field
+            ))),
+        }
+    }
+
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let mut points = Vec::new();
+    for result in reader {
+        let row = result?;
+        let (mut x, mut y, mut z, mut time, mut scan_angle) = (None, None, None, None, None);
+        for (name, field) in row.get_column_iter() {
+            let value = to_f64(field)?;
+            match name.as_str() {
+                "x" => x = value,
+                "y" => y = value,
+                "z" => z = value,
+                "time" => time = value,
+                "scan_angle" => scan_angle = value,
+                _ => {}
+            }
+        }
+        points.push(TabularPoint {
+            x: x.ok_or_else(|| missing("x"))?,
+            y: y.ok_or_else(|| missing("y"))?,
+            z: z.ok_or_else(|| missing("z"))?,
+            time,
+            scan_angle: scan_angle.ok_or_else(|| missing("scan_angle"))?,
+        });
+    }
+    Ok(points)
+}
+
+/// Reads tabular points from a Parquet file.
+///
+/// Always returns an error, since leeward was built without the `parquet` feature.
+#[cfg(not(feature = "parquet"))]
+pub fn read_parquet<P: AsRef<Path>>(_: P) -> Result<Vec<TabularPoint>, Error> {
+    Err(Error::FeatureDisabled("parquet"))
+}
+
+impl PointSource for TabularPoint {
+    type Extra = ();
+
+    fn time(&self) -> Option<f64> {
+        self.time
+    }
+
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn z(&self) -> f64 {
+        self.z
+    }
+
+    fn scan_angle(&self) -> f64 {
+        self.scan_angle
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Format, Error> {
+        match s {
+            "las" => Ok(Format::Las),
+            "csv" => Ok(Format::Csv),
+            "parquet" => Ok(Format::Parquet),
+            _ => Err(Error::Parse(format!("unknown point format: {}", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn read_csv() {
+        let points = super::read_csv("data/points.csv").unwrap();
+        assert_eq!(10, points.len());
+        assert_eq!(320000.34, points[0].x);
+        assert_eq!(Some(400825.80571932), points[0].time);
+        assert_eq!(22., points[0].scan_angle);
+    }
+}
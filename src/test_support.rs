@@ -0,0 +1,235 @@
+//! Generators for random-but-valid inputs, for tests that can't ship sbet and las fixtures.
+//!
+//! A [Generator] makes configs, trajectories, and measurements from a seed, so
+//! the same seed always gives the same inputs. Measurements are made with the
+//! [simulator](crate::simulate), so they exactly follow the lidar equation for
+//! their config. Requires the `test_support` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::test_support::Generator;
+//! let mut generator = Generator::new(42);
+//! let config = generator.config();
+//! let trajectory = generator.trajectory(0.1);
+//! let measurements = generator.measurements(&trajectory, &config).unwrap();
+//! assert!(!measurements.is_empty());
+//! assert!(measurements.iter().all(|m| m.residuals().norm() < 1e-3));
+//! ```
+
+use crate::{
+    rng::Rng,
+    simulate::{self, Options, Terrain},
+    Config, Error, Measurement, Trajectory, DEFAULT_SEED,
+};
+use std::str::FromStr;
+
+/// The uncertainties of a typical airborne system, which every generated config uses.
+const CONFIG: &str = r#"
+lever_arm = [0, 0, 0]
+beam_divergence = 0.25e-3
+
+[boresight]
+roll = 0
+pitch = 0
+yaw = 0
+
+[uncertainty]
+gnss_x = 0.05
+gnss_y = 0.05
+gnss_z = 0.1
+roll = 4.36332313e-5
+pitch = 4.36332313e-5
+yaw = 5.235988e-5
+boresight_roll = 1.745329e-5
+boresight_pitch = 1.745329e-5
+boresight_yaw = 6.981317e-5
+lever_arm_x = 0.02
+lever_arm_y = 0.02
+lever_arm_z = 0.02
+range = 0.02
+scan_angle = 1.745329e-5
+"#;
+
+/// The trajectory's sample rate, in hertz.
+const TRAJECTORY_RATE: f64 = 200.;
+
+/// The number of pulses per second in generated measurements.
+const PULSE_RATE: f64 = 2000.;
+
+/// Generates configs, trajectories, and measurements from a seed.
+#[derive(Clone, Copy, Debug)]
+pub struct Generator {
+    rng: Rng,
+}
+
+impl Generator {
+    /// Creates a new generator from a seed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::test_support::Generator;
+    /// let generator = Generator::new(42);
+    /// ```
+    pub fn new(seed: u64) -> Generator {
+        Generator {
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Generates a config with a small boresight and lever arm.
+    ///
+    /// The boresight angles are within half a degree of zero and the lever arm
+    /// components are within half a meter of zero. The UTM zone isn't set, so
+    /// it's taken from the trajectory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::test_support::Generator;
+    /// let config = Generator::new(42).config();
+    /// assert!(config.boresight.roll.abs() < 0.5f64.to_radians());
+    /// ```
+    pub fn config(&mut self) -> Config {
+        let mut config = Config::from_str(CONFIG).expect("the generator's config is valid");
+        let boresight = 0.5f64.to_radians();
+        config.boresight.roll = self.uniform(-boresight, boresight);
+        config.boresight.pitch = self.uniform(-boresight, boresight);
+        config.boresight.yaw = self.uniform(-boresight, boresight);
+        config.lever_arm.x = self.uniform(-0.5, 0.5);
+        config.lever_arm.y = self.uniform(-0.5, 0.5);
+        config.lever_arm.z = self.uniform(-0.5, 0.5);
+        config
+    }
+
+    /// Generates a straight and level flightline that lasts for `duration` seconds.
+    ///
+    /// The flightline starts somewhere in the continental United States, at
+    /// 1000 to 3000 meters above the ellipsoid, with a random heading and a
+    /// speed of 50 to 80 meters per second. The attitude wobbles by up to a
+    /// couple of degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::test_support::Generator;
+    /// let trajectory = Generator::new(42).trajectory(1.);
+    /// assert_eq!(201, trajectory.points().len());
+    /// ```
+    pub fn trajectory(&mut self, duration: f64) -> Trajectory {
+        let start = sbet::Point {
+            time: self.uniform(0., 604_800.),
+            latitude: self.uniform(30., 48.).to_radians(),
+            longitude: self.uniform(-120., -80.).to_radians(),
+            altitude: self.uniform(1000., 3000.),
+            ..Default::default()
+        };
+        let heading = self.uniform(-180., 180.).to_radians();
+        let speed = self.uniform(50., 80.);
+        let (north, east) = (speed * heading.cos(), speed * heading.sin());
+        let mut attitude = [0.; 6];
+        for value in &mut attitude {
+            *value = self.uniform(-1., 1.).to_radians();
+        }
+        // Roughly meters per radian of latitude and longitude.
+        let radius = 6_378_137. + start.altitude;
+        let count = (duration * TRAJECTORY_RATE).round() as usize + 1;
+        let points = (0..count)
+            .map(|i| {
+                let t = i as f64 / TRAJECTORY_RATE;
+                sbet::Point {
+                    time: start.time + t,
+                    latitude: start.latitude + north * t / radius,
+                    longitude: start.longitude + east * t / (radius * start.latitude.cos()),
+                    // Sbet velocities are in the wander frame, with y west.
+                    x_velocity: north,
+                    y_velocity: -east,
+                    roll: attitude[0] + attitude[1] * t.sin(),
+                    pitch: attitude[2] + attitude[3] * t.sin(),
+                    yaw: heading + attitude[4] + attitude[5] * t.sin(),
+                    ..start
+                }
+            })
+            .collect();
+        Trajectory::from_points(points)
+    }
+
+    /// Generates measurements by simulating pulses along the trajectory onto a horizontal plane at zero elevation.
+    ///
+    /// The measurements use the simulated (las) scan angles, see
+    /// [Measurement::use_las_scan_angle].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::test_support::Generator;
+    /// let mut generator = Generator::new(42);
+    /// let config = generator.config();
+    /// let trajectory = generator.trajectory(0.1);
+    /// let measurements = generator.measurements(&trajectory, &config).unwrap();
+    /// assert!(measurements.iter().all(|m| m.z().abs() < 1e-3));
+    /// ```
+    pub fn measurements(
+        &mut self,
+        trajectory: &Trajectory,
+        config: &Config,
+    ) -> Result<Vec<Measurement<las::Point>>, Error> {
+        let options = Options {
+            pulse_rate: PULSE_RATE,
+            scan_rate: self.uniform(20., 80.),
+            field_of_view: self.uniform(20., 60.).to_radians(),
+            ..Default::default()
+        };
+        simulate::simulate(trajectory, config.clone(), &Terrain::Plane(0.), &options)?
+            .into_iter()
+            .map(|point| {
+                let mut measurement = Measurement::new(trajectory, point, config.clone())?;
+                measurement.use_las_scan_angle(true);
+                Ok(measurement)
+            })
+            .collect()
+    }
+
+    fn uniform(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.rng.next_f64()
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Generator {
+        Generator::new(DEFAULT_SEED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Generator;
+    use crate::Point;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn reproducible() {
+        let mut a = Generator::new(1);
+        let mut b = Generator::new(1);
+        assert_eq!(a.config(), b.config());
+        assert_eq!(a.trajectory(0.1).points(), b.trajectory(0.1).points());
+    }
+
+    #[test]
+    fn measurements_follow_the_lidar_equation() {
+        for seed in 0..20 {
+            let mut generator = Generator::new(seed);
+            let config = generator.config();
+            let trajectory = generator.trajectory(0.05);
+            let measurements = generator.measurements(&trajectory, &config).unwrap();
+            assert!(!measurements.is_empty(), "seed {}", seed);
+            for measurement in &measurements {
+                assert!(measurement.residuals().norm() < 1e-3, "seed {}", seed);
+                assert_relative_eq!(0., measurement.z(), epsilon = 1e-3);
+                let tpu = measurement.tpu(Point::new(0., 0., 1.)).unwrap();
+                assert!(tpu.total.is_finite() && tpu.total > 0., "seed {}", seed);
+            }
+        }
+    }
+}
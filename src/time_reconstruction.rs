@@ -0,0 +1,234 @@
+//! Assigning approximate times to points that don't have them.
+//!
+//! Legacy point formats (e.g. las point format 0) don't store gps time, so
+//! their points can't be matched to the trajectory. Points are (usually)
+//! stored in the order they were fired, so times can be recovered from the
+//! point order, either between time-tagged points or by counting pulses.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::TimeReconstruction;
+//! assert_eq!(TimeReconstruction::Interpolate, "interpolate".parse().unwrap());
+//! assert_eq!(TimeReconstruction::Start(400825.), "start:400825".parse().unwrap());
+//! ```
+
+use crate::{decimation::scan_lines, Error};
+use std::str::FromStr;
+
+/// How to assign times to points that don't have them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TimeReconstruction {
+    /// Leave points without times alone, i.e. they're handled by [MissingPose](crate::MissingPose).
+    #[default]
+    Off,
+
+    /// Interpolate by point order between the time-tagged points on either side, within a scan line.
+    ///
+    /// If the config has a pulse rate, points that aren't between two
+    /// time-tagged points on their scan line are extrapolated from the nearest
+    /// time-tagged point by counting pulses.
+    Interpolate,
+
+    /// Count pulses at the config's pulse rate from this start time, for files without any times.
+    ///
+    /// Each first return (or point without a return number) starts a new
+    /// pulse, and later returns share their pulse's time.
+    Start(f64),
+}
+
+impl TimeReconstruction {
+    /// Assigns times to the points that don't have them, returning how many were assigned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::TimeReconstruction;
+    /// let mut points = vec![las::Point::default(); 3];
+    /// points[0].gps_time = Some(10.);
+    /// points[2].gps_time = Some(11.);
+    /// assert_eq!(1, TimeReconstruction::Interpolate.reconstruct(&mut points, None).unwrap());
+    /// assert_eq!(Some(10.5), points[1].gps_time);
+    /// ```
+    pub fn reconstruct(
+        &self,
+        points: &mut [las::Point],
+        pulse_rate: Option<f64>,
+    ) -> Result<usize, Error> {
+        if let Some(pulse_rate) = pulse_rate {
+            if pulse_rate.is_nan() || pulse_rate <= 0. {
+                return Err(Error::Config(format!(
+                    "pulse rate must be positive: {}",
+                    pulse_rate
+                )));
+            }
+        }
+        let count = match *self {
+            TimeReconstruction::Off => 0,
+            TimeReconstruction::Interpolate => interpolate(points, pulse_rate),
+            TimeReconstruction::Start(start) => {
+                let pulse_rate = pulse_rate.ok_or_else(|| {
+                    Error::Config(
+                        "reconstructing times from a start time needs a pulse rate".to_string(),
+                    )
+                })?;
+                let pulses = pulse_indices(points);
+                let mut count = 0;
+                for (point, pulse) in points.iter_mut().zip(pulses) {
+                    if point.gps_time.is_none() {
+                        point.gps_time = Some(start + pulse as f64 / pulse_rate);
+                        count += 1;
+                    }
+                }
+                count
+            }
+        };
+        Ok(count)
+    }
+}
+
+/// Returns the index of each point's pulse, counting from the first point's.
+fn pulse_indices(points: &[las::Point]) -> Vec<usize> {
+    let mut pulse = 0;
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, point)| {
+            if i > 0 && point.return_number <= 1 {
+                pulse += 1;
+            }
+            pulse
+        })
+        .collect()
+}
+
+fn interpolate(points: &mut [las::Point], pulse_rate: Option<f64>) -> usize {
+    let lines = scan_lines(points);
+    let pulses = pulse_indices(points);
+    let mut previous = vec![None; points.len()];
+    let mut last = None;
+    for (i, point) in points.iter().enumerate() {
+        if point.gps_time.is_some() {
+            last = Some(i);
+        }
+        previous[i] = last;
+    }
+    let mut next = vec![None; points.len()];
+    last = None;
+    for (i, point) in points.iter().enumerate().rev() {
+        if point.gps_time.is_some() {
+            last = Some(i);
+        }
+        next[i] = last;
+    }
+    let time = |i: usize| points[i].gps_time.expect("tagged points have times");
+    let mut times = vec![None; points.len()];
+    for i in 0..points.len() {
+        if points[i].gps_time.is_some() {
+            continue;
+        }
+        times[i] = match (previous[i], next[i]) {
+            (Some(before), Some(after))
+                if lines[before] == lines[i] && lines[after] == lines[i] =>
+            {
+                let fraction = (i - before) as f64 / (after - before) as f64;
+                Some(time(before) + (time(after) - time(before)) * fraction)
+            }
+            (before, after) => pulse_rate.and_then(|pulse_rate| {
+                let nearest = match (before, after) {
+                    (Some(before), Some(after)) => {
+                        if i - before <= after - i {
+                            before
+                        } else {
+                            after
+                        }
+                    }
+                    (before, after) => before.or(after)?,
+                };
+                Some(time(nearest) + (pulses[i] as f64 - pulses[nearest] as f64) / pulse_rate)
+            }),
+        };
+    }
+    let mut count = 0;
+    for (point, time) in points.iter_mut().zip(times) {
+        if time.is_some() {
+            point.gps_time = time;
+            count += 1;
+        }
+    }
+    count
+}
+
+impl FromStr for TimeReconstruction {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<TimeReconstruction, Error> {
+        let parse_error = || Error::Parse(format!("invalid time reconstruction: {}", s));
+        match s.split_once(':') {
+            None if s == "off" => Ok(TimeReconstruction::Off),
+            None if s == "interpolate" => Ok(TimeReconstruction::Interpolate),
+            Some(("start", start)) => start
+                .parse()
+                .map(TimeReconstruction::Start)
+                .map_err(|_| parse_error()),
+            _ => Err(parse_error()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimeReconstruction;
+
+    fn points(scan_angles: &[f32]) -> Vec<las::Point> {
+        scan_angles
+            .iter()
+            .map(|&scan_angle| las::Point {
+                scan_angle,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interpolate_within_scan_lines() {
+        // Two scan lines, with the second line's first point tagged.
+        let mut points = points(&[-2., -1., 0., 1., 2., 1., 0.]);
+        points[0].gps_time = Some(0.);
+        points[2].gps_time = Some(2.);
+        points[5].gps_time = Some(5.);
+        let interpolate = TimeReconstruction::Interpolate;
+        assert_eq!(1, interpolate.reconstruct(&mut points, None).unwrap());
+        assert_eq!(Some(1.), points[1].gps_time);
+        assert_eq!(None, points[4].gps_time);
+        assert_eq!(3, interpolate.reconstruct(&mut points, Some(1.)).unwrap());
+        assert_eq!(Some(3.), points[3].gps_time);
+        assert_eq!(Some(4.), points[4].gps_time);
+        assert_eq!(Some(6.), points[6].gps_time);
+    }
+
+    #[test]
+    fn start() {
+        let mut points = points(&[0.; 3]);
+        let start = TimeReconstruction::Start(100.);
+        assert!(start.reconstruct(&mut points, None).is_err());
+        assert!(start.reconstruct(&mut points, Some(0.)).is_err());
+        assert_eq!(3, start.reconstruct(&mut points, Some(4.)).unwrap());
+        assert_eq!(Some(100.5), points[2].gps_time);
+    }
+
+    #[test]
+    fn start_counts_pulses() {
+        // Two pulses with two returns each, then a single return.
+        let start = TimeReconstruction::Start(100.);
+        let mut returns = points(&[0.; 5]);
+        for (point, return_number) in returns.iter_mut().zip([1, 2, 1, 2, 1].iter()) {
+            point.return_number = *return_number;
+        }
+        assert_eq!(5, start.reconstruct(&mut returns, Some(4.)).unwrap());
+        let times: Vec<_> = returns
+            .iter()
+            .map(|point| point.gps_time.unwrap())
+            .collect();
+        assert_eq!(vec![100., 100., 100.25, 100.25, 100.5], times);
+    }
+}
@@ -1,14 +1,40 @@
-use anyhow::Error;
+use crate::{convert, utils, Error};
 use sbet::{Point, Reader};
-use std::{collections::HashMap, path::Path};
+use serde::Serialize;
+use std::{collections::HashMap, io::Read, path::Path};
 
 /// A platform's trajectory.
 #[derive(Debug)]
 pub struct Trajectory {
-    points: HashMap<i64, Point>,
+    points: Vec<Point>,
+    index: HashMap<i64, usize>,
     scale: f64,
 }
 
+/// The rates of change of a platform's attitude and position.
+///
+/// Position rates are in the north, east, down navigation frame.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Default)]
+pub struct Rates {
+    /// Roll rate, in radians per second.
+    pub roll: f64,
+
+    /// Pitch rate, in radians per second.
+    pub pitch: f64,
+
+    /// Yaw rate, in radians per second.
+    pub yaw: f64,
+
+    /// North velocity, in meters per second.
+    pub north: f64,
+
+    /// East velocity, in meters per second.
+    pub east: f64,
+
+    /// Down velocity, in meters per second.
+    pub down: f64,
+}
+
 impl Trajectory {
     /// Reads a trajectory from a path.
     ///
@@ -19,25 +45,204 @@ impl Trajectory {
     /// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
     /// ```
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Trajectory, Error> {
-        let reader = Reader::from_path(path)?;
-        let mut scale = 0.;
-        let mut last_time: Option<f64> = None;
+        Trajectory::from_paths(&[path])
+    }
+
+    /// Reads a trajectory from a path, only keeping points between `start` and `end` (inclusive).
+    ///
+    /// Points outside of the range are discarded as they are read, so a small
+    /// time window out of a large mission trajectory is cheap to hold onto.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_path_with_range("data/sbet.out", 400825.5, 400825.9).unwrap();
+    /// assert!(trajectory.get(400825.80571932).is_some());
+    /// assert!(trajectory.get(400825.2).is_none());
+    /// ```
+    pub fn from_path_with_range<P: AsRef<Path>>(
+        path: P,
+        start: f64,
+        end: f64,
+    ) -> Result<Trajectory, Error> {
+        Trajectory::from_paths_with_range(&[path], start, end)
+    }
+
+    /// Reads a trajectory from several paths, e.g. a mission delivered as multiple sbet segments.
+    ///
+    /// The points from all files are merged into time order, and duplicate
+    /// epochs (e.g. overlap between consecutive segments) are dropped. Gaps between
+    /// segments are left as gaps, i.e. there are no trajectory points for times within a gap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_paths(&["data/sbet.out", "data/sbet.out"]).unwrap();
+    /// assert!(trajectory.get(400825.80571932).is_some());
+    /// ```
+    pub fn from_paths<P: AsRef<Path>>(paths: &[P]) -> Result<Trajectory, Error> {
+        Trajectory::from_paths_with_range(paths, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    /// Reads a trajectory from several paths, only keeping points between `start` and `end` (inclusive).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_paths_with_range(&["data/sbet.out"], 400825.5, 400825.9).unwrap();
+    /// assert!(trajectory.get(400825.80571932).is_some());
+    /// ```
+    pub fn from_paths_with_range<P: AsRef<Path>>(
+        paths: &[P],
+        start: f64,
+        end: f64,
+    ) -> Result<Trajectory, Error> {
         let mut points = vec![];
-        for result in reader {
-            let point = result?;
-            let time = point.time;
-            if let Some(last_time) = last_time {
-                scale = (time - last_time).max(scale);
-            }
-            last_time = Some(time);
-            points.push(point);
+        for path in paths {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("read_sbet", path = %path.as_ref().display()).entered();
+            read_points(Reader::from_path(path)?, start, end, &mut points)?;
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(points = points.len(), "read trajectory");
+        Ok(Trajectory::from_points(points))
+    }
+
+    /// Reads a trajectory from anything that implements `Read`, e.g. stdin or a network stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let file = std::fs::File::open("data/sbet.out").unwrap();
+    /// let trajectory = Trajectory::from_reader(std::io::BufReader::new(file)).unwrap();
+    /// assert!(trajectory.get(400825.80571932).is_some());
+    /// ```
+    pub fn from_reader<R: Read>(read: R) -> Result<Trajectory, Error> {
+        let mut points = vec![];
+        read_points(Reader(read), f64::NEG_INFINITY, f64::INFINITY, &mut points)?;
+        Ok(Trajectory::from_points(points))
+    }
+
+    /// Reads a trajectory from the bytes of an sbet file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let bytes = std::fs::read("data/sbet.out").unwrap();
+    /// let trajectory = Trajectory::from_bytes(&bytes).unwrap();
+    /// assert!(trajectory.get(400825.80571932).is_some());
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Trajectory, Error> {
+        Trajectory::from_reader(bytes)
+    }
+
+    pub(crate) fn from_points(mut points: Vec<Point>) -> Trajectory {
+        points.sort_by(|a, b| a.time.total_cmp(&b.time));
+        points.dedup_by(|a, b| a.time == b.time);
+        let scale = scale(&points);
+        Trajectory::with_scale(points, scale)
+    }
+
+    fn with_scale(points: Vec<Point>, scale: f64) -> Trajectory {
+        let index = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| (index(point.time, scale), i))
+            .collect();
+        Trajectory {
+            points,
+            index,
+            scale,
+        }
+    }
+
+    /// Returns a new trajectory that only contains the points between `start` and `end` (inclusive).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+    /// let subset = trajectory.subset(400825.5, 400825.9);
+    /// assert!(subset.get(400825.80571932).is_some());
+    /// assert!(subset.get(400825.2).is_none());
+    /// ```
+    pub fn subset(&self, start: f64, end: f64) -> Trajectory {
+        let points = self
+            .points
+            .iter()
+            .filter(|point| point.time >= start && point.time <= end)
+            .copied()
+            .collect();
+        Trajectory::with_scale(points, self.scale)
+    }
+
+    /// Returns a new trajectory with its attitude smoothed by a centered moving average.
+    ///
+    /// Each point's roll, pitch, and yaw are replaced with the mean of the
+    /// points within `window / 2` seconds of it, so noisy attitude at high
+    /// sbet rates doesn't alias into the tpu. Yaw is averaged as a unit
+    /// vector, so it's safe across the ±π wrap. Positions and times are
+    /// unchanged, and a window that isn't positive returns an unsmoothed copy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+    /// let smoothed = trajectory.smooth(0.05);
+    /// assert_eq!(trajectory.points().len(), smoothed.points().len());
+    /// ```
+    pub fn smooth(&self, window: f64) -> Trajectory {
+        if window.is_nan() || window <= 0. {
+            return Trajectory::with_scale(self.points.clone(), self.scale);
         }
-        let mut map = HashMap::new();
-        for point in points {
-            let index = index(point.time, scale);
-            map.insert(index, point);
+        let half = window / 2.;
+        let mut points = self.points.clone();
+        let (mut start, mut end) = (0, 0);
+        let mut sums = [0.; 4];
+        for (point, smoothed) in self.points.iter().zip(&mut points) {
+            while end < self.points.len() && self.points[end].time <= point.time + half {
+                let other = &self.points[end];
+                sums[0] += other.roll;
+                sums[1] += other.pitch;
+                sums[2] += other.yaw.sin();
+                sums[3] += other.yaw.cos();
+                end += 1;
+            }
+            while self.points[start].time < point.time - half {
+                let other = &self.points[start];
+                sums[0] -= other.roll;
+                sums[1] -= other.pitch;
+                sums[2] -= other.yaw.sin();
+                sums[3] -= other.yaw.cos();
+                start += 1;
+            }
+            let count = (end - start) as f64;
+            smoothed.roll = sums[0] / count;
+            smoothed.pitch = sums[1] / count;
+            smoothed.yaw = sums[2].atan2(sums[3]);
         }
-        Ok(Trajectory { points: map, scale })
+        Trajectory::with_scale(points, self.scale)
+    }
+
+    /// Returns this trajectory's points, in time order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+    /// let points = trajectory.points();
+    /// assert!(points[0].time < points[1].time);
+    /// ```
+    pub fn points(&self) -> &[Point] {
+        &self.points
     }
 
     /// Gets an sbet point for the given time.
@@ -52,10 +257,245 @@ impl Trajectory {
     /// ```
     pub fn get(&self, time: f64) -> Option<&Point> {
         let index = index(time, self.scale);
-        self.points.get(&index)
+        self.index.get(&index).map(|&i| &self.points[i])
+    }
+
+    /// Gets the sbet point closest in time to the given time, no matter how far away it is.
+    ///
+    /// Returns `None` only if the trajectory is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+    /// let point = trajectory.nearest(600825.80571932).unwrap();
+    /// assert_eq!(400825.9965316785, point.time);
+    /// ```
+    pub fn nearest(&self, time: f64) -> Option<&Point> {
+        let i = self.points.partition_point(|point| point.time < time);
+        let after = self.points.get(i);
+        let before = i.checked_sub(1).and_then(|i| self.points.get(i));
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                if time - before.time <= after.time - time {
+                    Some(before)
+                } else {
+                    Some(after)
+                }
+            }
+            (before, after) => before.or(after),
+        }
+    }
+
+    /// Returns one trajectory point per `interval` seconds, e.g. for plotting.
+    ///
+    /// The first point is always kept, then each point at least `interval`
+    /// seconds after the last kept point. An interval of zero keeps every point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+    /// assert_eq!(trajectory.points().len(), trajectory.decimate(0.).len());
+    /// let points = trajectory.decimate(0.1);
+    /// assert!(points[1].time - points[0].time >= 0.1);
+    /// ```
+    pub fn decimate(&self, interval: f64) -> Vec<&Point> {
+        let mut last: Option<f64> = None;
+        self.points
+            .iter()
+            .filter(|point| match last {
+                Some(time) if point.time - time < interval => false,
+                _ => {
+                    last = Some(point.time);
+                    true
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the UTM zone at the middle of this trajectory's longitude range.
+    ///
+    /// Returns `None` if the trajectory is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+    /// assert_eq!(Some(11), trajectory.utm_zone());
+    /// ```
+    pub fn utm_zone(&self) -> Option<u8> {
+        let (min, max) = self.points.iter().fold(None, |range, point| match range {
+            Some((min, max)) => Some((point.longitude.min(min), point.longitude.max(max))),
+            None => Some((point.longitude, point.longitude)),
+        })?;
+        Some(convert::utm_zone((min + max) / 2.))
+    }
+
+    /// Returns the platform's rates at the given time, numerically differentiated from the trajectory.
+    ///
+    /// The rates are the differences between the trajectory points on either
+    /// side of the time (or the last two points, at the end of the trajectory).
+    /// Returns `None` if there's no trajectory point for the time, as for [Trajectory::get].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Trajectory;
+    /// let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+    /// let rates = trajectory.rates(400825.80571932).unwrap();
+    /// assert!(rates.north < -100.);
+    /// assert!(trajectory.rates(600825.80571932).is_none());
+    /// ```
+    pub fn rates(&self, time: f64) -> Option<Rates> {
+        self.get(time)?;
+        if self.points.len() < 2 {
+            return None;
+        }
+        let i = self
+            .points
+            .partition_point(|point| point.time <= time)
+            .clamp(1, self.points.len() - 1);
+        Some(Rates::between(&self.points[i - 1], &self.points[i]))
+    }
+}
+
+impl Rates {
+    fn between(before: &Point, after: &Point) -> Rates {
+        let dt = after.time - before.time;
+        // Yaw wraps around at ±π.
+        let angle_rate = |before: f64, after: f64| utils::angle_difference(after, before) / dt;
+        let geodetic =
+            |point: &Point| crate::Point::new(point.longitude, point.latitude, point.altitude);
+        let velocity = convert::ecef_to_navigation_matrix(geodetic(before))
+            * (convert::geodetic_to_ecef(geodetic(after))
+                - convert::geodetic_to_ecef(geodetic(before)))
+            / dt;
+        Rates {
+            roll: angle_rate(before.roll, after.roll),
+            pitch: angle_rate(before.pitch, after.pitch),
+            yaw: angle_rate(before.yaw, after.yaw),
+            north: velocity.x,
+            east: velocity.y,
+            down: velocity.z,
+        }
     }
 }
 
+/// Returns the largest spacing between consecutive (sorted) points, ignoring gaps.
+///
+/// A gap is any spacing larger than `MAX_GAP_FACTOR` times the median spacing.
+fn read_points<R: Read>(
+    reader: Reader<R>,
+    start: f64,
+    end: f64,
+    points: &mut Vec<Point>,
+) -> Result<(), Error> {
+    for result in reader {
+        let point = result?;
+        if point.time >= start && point.time <= end {
+            points.push(point);
+        }
+    }
+    Ok(())
+}
+
+fn scale(points: &[Point]) -> f64 {
+    let mut deltas: Vec<f64> = points
+        .windows(2)
+        .map(|window| window[1].time - window[0].time)
+        .collect();
+    if deltas.is_empty() {
+        return 0.;
+    }
+    deltas.sort_by(|a, b| a.total_cmp(b));
+    let median = deltas[deltas.len() / 2];
+    deltas
+        .into_iter()
+        .filter(|&delta| delta <= median * MAX_GAP_FACTOR)
+        .fold(0., f64::max)
+}
+
+const MAX_GAP_FACTOR: f64 = 10.;
+
 fn index(time: f64, scale: f64) -> i64 {
     (time / scale).round() as i64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Trajectory;
+    use sbet::Point;
+
+    fn point(time: f64) -> Point {
+        Point {
+            time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn from_points_with_gap() {
+        let mut points: Vec<Point> = (0..10).map(|i| point(f64::from(i) * 0.005)).collect();
+        points.extend((0..10).map(|i| point(100. + f64::from(i) * 0.005)));
+        points.reverse();
+        let trajectory = Trajectory::from_points(points);
+        assert_eq!(0.005, (trajectory.scale * 1e6).round() / 1e6);
+        assert!(trajectory.get(0.02).is_some());
+        assert!(trajectory.get(50.).is_none());
+        assert!(trajectory.get(100.02).is_some());
+        assert_eq!(9. * 0.005, trajectory.nearest(50.).unwrap().time);
+        assert_eq!(100., trajectory.nearest(60.).unwrap().time);
+        assert_eq!(0., trajectory.nearest(-1.).unwrap().time);
+    }
+
+    #[test]
+    fn from_paths_deduplicates() {
+        let one = Trajectory::from_path("data/sbet.out").unwrap();
+        let two = Trajectory::from_paths(&["data/sbet.out", "data/sbet.out"]).unwrap();
+        assert_eq!(one.points.len(), two.points.len());
+        assert_eq!(one.index.len(), two.index.len());
+        assert_eq!(one.scale, two.scale);
+    }
+
+    #[test]
+    fn smooth() {
+        let points: Vec<Point> = (0..100)
+            .map(|i| Point {
+                roll: if i % 2 == 0 { 0.01 } else { -0.01 },
+                yaw: if i % 2 == 0 { 3.1 } else { -3.1 },
+                ..point(f64::from(i) * 0.005)
+            })
+            .collect();
+        let trajectory = Trajectory::from_points(points).smooth(0.1);
+        let point = trajectory.points()[50];
+        assert!(point.roll.abs() < 1e-3);
+        assert!((point.yaw.abs() - std::f64::consts::PI).abs() < 1e-2);
+        for window in [0., -0.01, -100., f64::NAN] {
+            let unsmoothed = trajectory.smooth(window);
+            assert_eq!(trajectory.points(), unsmoothed.points());
+        }
+    }
+
+    #[test]
+    fn rates() {
+        let trajectory = Trajectory::from_path("data/sbet.out").unwrap();
+        let points = trajectory.points();
+        for point in [points[0], points[100], points[points.len() - 1]] {
+            let rates = trajectory.rates(point.time).unwrap();
+            // The sbet velocities are in the wander frame, with y west and z up.
+            let speed = rates.north.hypot(rates.east);
+            assert!((speed - point.x_velocity.hypot(point.y_velocity)).abs() < 0.1);
+            assert!((rates.down + point.z_velocity).abs() < 0.01);
+        }
+        let mut before = point(0.);
+        before.yaw = 3.1;
+        let mut after = point(0.1);
+        after.yaw = -3.1;
+        let rates = super::Rates::between(&before, &after);
+        assert!((rates.yaw - (2. * std::f64::consts::PI - 6.2) / 0.1).abs() < 1e-9);
+    }
+}
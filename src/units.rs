@@ -0,0 +1,353 @@
+//! Units for angles and lengths in config files.
+//!
+//! Config values are radians and meters, but angles and lengths can also be
+//! written as a string with a unit, or with the unit as a suffix on the key:
+//!
+//! ```toml
+//! beam_divergence = "0.25 mrad"
+//! lever_arm = ["10 cm", 0, "-1.2 m"]
+//!
+//! [boresight]
+//! roll = "0.5 deg"
+//! pitch_deg = -0.2
+//! yaw = 0
+//! ```
+//!
+//! The units are `rad`, `mrad`, `urad` (or `µrad`), `deg` (or `°`), `arcsec`,
+//! `mas` (milliarcseconds), `m`, `cm`, and `mm`. Values are converted when the config is loaded, so a config
+//! written back out is in radians and meters. An angle unit on a length, or
+//! the other way around, is an error.
+//!
+//! A unit suffix on a scan angle uncertainty `table` or `polynomial` applies to
+//! the scan angle as well as the uncertainty, so `scan_angle_deg = { table =
+//! [[-20, 2], [20, 2]] }` is 2° at ±20°.
+
+use crate::Error;
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Quantity {
+    Angle,
+    Length,
+}
+
+/// The config keys that hold angles, in radians.
+const ANGLES: [&str; 14] = [
+    "roll",
+    "pitch",
+    "yaw",
+    "boresight_roll",
+    "boresight_pitch",
+    "boresight_yaw",
+    "scan_angle",
+    "beam_divergence",
+    "max_incidence_angle",
+    "mirror_tilt",
+    "deviation",
+    "offset",
+    "rotation",
+    "rotation_rate",
+];
+
+/// The config keys that hold lengths, in meters.
+const LENGTHS: [&str; 10] = [
+    "lever_arm",
+    "gnss_x",
+    "gnss_y",
+    "gnss_z",
+    "lever_arm_x",
+    "lever_arm_y",
+    "lever_arm_z",
+    "range",
+    "translation",
+    "translation_rate",
+];
+
+/// The units, with their quantity and their size in radians or meters.
+const UNITS: [(&str, Quantity, f64); 11] = [
+    ("rad", Quantity::Angle, 1.),
+    ("mrad", Quantity::Angle, 1e-3),
+    ("urad", Quantity::Angle, 1e-6),
+    ("µrad", Quantity::Angle, 1e-6),
+    ("deg", Quantity::Angle, PI / 180.),
+    ("°", Quantity::Angle, PI / 180.),
+    ("arcsec", Quantity::Angle, PI / 648_000.),
+    ("mas", Quantity::Angle, PI / 648_000_000.),
+    ("m", Quantity::Length, 1.),
+    ("cm", Quantity::Length, 1e-2),
+    ("mm", Quantity::Length, 1e-3),
+];
+
+/// Converts every value with a unit in a config table, and its overrides, into radians or meters.
+pub(crate) fn convert(table: &mut toml::Table) -> Result<(), Error> {
+    let keys: Vec<String> = table.keys().cloned().collect();
+    for key in keys {
+        let (name, unit) = match split_suffix(&key) {
+            Some((name, _)) if table.contains_key(name) => {
+                return Err(Error::Config(format!("both {} and {} are set", name, key)))
+            }
+            Some((name, unit)) => (name.to_string(), Some(unit)),
+            None => (key.clone(), None),
+        };
+        let mut value = table.remove(&key).expect("the key is in the table");
+        if let Some(quantity) = quantity(&name) {
+            convert_value(&name, quantity, &mut value, unit)?;
+        } else {
+            match &mut value {
+                toml::Value::Table(table) => convert(table)?,
+                toml::Value::Array(array) => {
+                    for value in array {
+                        if let toml::Value::Table(table) = value {
+                            convert(table)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let _ = table.insert(name, value);
+    }
+    Ok(())
+}
+
+/// Returns true if a config key holds an angle, in radians.
+pub(crate) fn is_angle(key: &str) -> bool {
+    quantity(key) == Some(Quantity::Angle)
+}
+
+fn quantity(key: &str) -> Option<Quantity> {
+    if ANGLES.contains(&key) {
+        Some(Quantity::Angle)
+    } else if LENGTHS.contains(&key) {
+        Some(Quantity::Length)
+    } else {
+        None
+    }
+}
+
+/// Splits a key like `roll_deg` into its name and unit, if the name holds a quantity.
+fn split_suffix(key: &str) -> Option<(&str, (Quantity, f64))> {
+    let (name, suffix) = key.rsplit_once('_')?;
+    let unit = UNITS.iter().find(|(unit, _, _)| *unit == suffix)?;
+    quantity(name).map(|_| (name, (unit.1, unit.2)))
+}
+
+fn convert_value(
+    key: &str,
+    quantity: Quantity,
+    value: &mut toml::Value,
+    unit: Option<(Quantity, f64)>,
+) -> Result<(), Error> {
+    let check = |unit_quantity: Quantity| {
+        if unit_quantity == quantity {
+            Ok(())
+        } else {
+            Err(Error::Config(format!(
+                "{} is {}, but its unit is for {}",
+                key,
+                quantity.name(),
+                unit_quantity.name()
+            )))
+        }
+    };
+    match value {
+        toml::Value::Array(array) => {
+            for value in array {
+                convert_value(key, quantity, value, unit)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            let mut converted = false;
+            for (name, value) in table.iter_mut() {
+                match (name.as_str(), value) {
+                    // Constraints, e.g. [constraints.lever_arm_x], bound the value in the same units.
+                    ("min", value) | ("max", value) => {
+                        convert_value(key, quantity, value, unit)?;
+                        converted = true;
+                    }
+                    // Scan angle uncertainty tables are (scan angle, uncertainty) pairs, and both are angles.
+                    ("table", value) => {
+                        convert_value(key, quantity, value, unit)?;
+                        converted = true;
+                    }
+                    // The polynomial's variable is the scan angle in the same unit, so
+                    // each coefficient scales by the unit to the power of one minus its order.
+                    ("polynomial", toml::Value::Array(coefficients)) => {
+                        for (order, coefficient) in coefficients.iter_mut().enumerate() {
+                            if coefficient.is_str() {
+                                return Err(Error::Config(format!(
+                                    "{} polynomial coefficients can't have units, use a unit suffix on the key",
+                                    key
+                                )));
+                            }
+                            let unit = unit.map(|(quantity, factor)| {
+                                (quantity, factor.powi(1 - order as i32))
+                            });
+                            convert_value(key, quantity, coefficient, unit)?;
+                        }
+                        converted = true;
+                    }
+                    _ => {}
+                }
+            }
+            if unit.is_some() && !converted {
+                return Err(Error::Config(format!(
+                    "{} has a unit, but its table has no values to convert",
+                    key
+                )));
+            }
+        }
+        toml::Value::String(s) => {
+            if unit.is_some() {
+                return Err(Error::Config(format!(
+                    "{} has a unit in both its key and its value",
+                    key
+                )));
+            }
+            let (number, factor) = parse(s).ok_or_else(|| {
+                Error::Config(format!("invalid value with a unit for {}: {}", key, s))
+            })?;
+            check(factor.0)?;
+            *value = toml::Value::Float(number * factor.1);
+        }
+        toml::Value::Integer(n) => {
+            if let Some((unit_quantity, factor)) = unit {
+                check(unit_quantity)?;
+                *value = toml::Value::Float(*n as f64 * factor);
+            }
+        }
+        toml::Value::Float(n) => {
+            if let Some((unit_quantity, factor)) = unit {
+                check(unit_quantity)?;
+                *n *= factor;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Parses a number with a unit, e.g. `0.5 deg` or `12mm`.
+fn parse(s: &str) -> Option<(f64, (Quantity, f64))> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E')))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number.parse().ok()?;
+    let unit = unit.trim();
+    UNITS
+        .iter()
+        .find(|(name, _, _)| *name == unit)
+        .map(|&(_, quantity, factor)| (number, (quantity, factor)))
+}
+
+impl Quantity {
+    fn name(&self) -> &'static str {
+        match self {
+            Quantity::Angle => "an angle",
+            Quantity::Length => "a length",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Error};
+
+    const UNCERTAINTY: &str = "[uncertainty]\ngnss_x = 0.05\ngnss_y = 0.05\ngnss_z = 0.1\n\
+        roll = 0\npitch = 0\nyaw = 0\nboresight_roll = 0\nboresight_pitch = 0\n\
+        boresight_yaw = 0\nlever_arm_x = 0\nlever_arm_y = 0\nlever_arm_z = 0\nrange = 0.02\n\
+        scan_angle = 0\n";
+
+    fn parse_config(lever_arm: &str, boresight: &str) -> Result<Config, Error> {
+        format!(
+            "utm_zone = 11\nbeam_divergence = \"0.3 mrad\"\n{}\n[boresight]\n{}\n{}",
+            lever_arm, boresight, UNCERTAINTY
+        )
+        .parse()
+    }
+
+    #[test]
+    fn angles() {
+        let config = parse_config(
+            "lever_arm = [0, 0, 0]",
+            "roll = \"0.5 deg\"\npitch_deg = -90\nyaw = \"2mrad\"",
+        )
+        .unwrap();
+        assert!((config.boresight.roll - 0.5f64.to_radians()).abs() < 1e-15);
+        assert!((config.boresight.pitch + std::f64::consts::FRAC_PI_2).abs() < 1e-15);
+        assert!((config.boresight.yaw - 2e-3).abs() < 1e-15);
+        assert!((config.beam_divergence - 0.3e-3).abs() < 1e-15);
+    }
+
+    #[test]
+    fn lengths() {
+        let boresight = "roll = 0\npitch = 0\nyaw = 0";
+        let config = parse_config("lever_arm = [\"10 cm\", 0, \"-12 mm\"]", boresight).unwrap();
+        assert!((config.lever_arm.x - 0.1).abs() < 1e-15);
+        assert!((config.lever_arm.z + 0.012).abs() < 1e-15);
+        let config = parse_config("lever_arm_mm = [100, 0, -12]", boresight).unwrap();
+        assert!((config.lever_arm.x - 0.1).abs() < 1e-15);
+    }
+
+    #[test]
+    fn invalid_units() {
+        let boresight = |roll: &str| {
+            parse_config(
+                "lever_arm = [0, 0, 0]",
+                &format!("{}\npitch = 0\nyaw = 0", roll),
+            )
+        };
+        assert!(boresight("roll = 0").is_ok());
+        assert!(boresight("roll = \"1 cm\"").is_err());
+        assert!(boresight("roll = \"1 furlong\"").is_err());
+        assert!(boresight("roll = \"one deg\"").is_err());
+        assert!(boresight("roll_m = 1").is_err());
+        assert!(boresight("roll = 0\nroll_deg = 1").is_err());
+        assert!(boresight("roll_deg = \"1 deg\"").is_err());
+    }
+
+    #[test]
+    fn scan_angle_uncertainty() {
+        use crate::config::ScanAngleUncertainty;
+
+        let scan_angle = |value: &str| {
+            let uncertainty = UNCERTAINTY.replace("scan_angle = 0\n", &format!("{}\n", value));
+            format!(
+                "utm_zone = 11\nbeam_divergence = 0\nlever_arm = [0, 0, 0]\n\
+                [boresight]\nroll = 0\npitch = 0\nyaw = 0\n{}",
+                uncertainty
+            )
+            .parse::<Config>()
+            .map(|config| config.uncertainty.scan_angle)
+        };
+        let degree = 1f64.to_radians();
+        match scan_angle("scan_angle_deg = { polynomial = [1.0, 0.5, 2.0] }").unwrap() {
+            ScanAngleUncertainty::Polynomial { polynomial } => {
+                assert!((polynomial[0] - degree).abs() < 1e-15);
+                assert!((polynomial[1] - 0.5).abs() < 1e-15);
+                assert!((polynomial[2] - 2. / degree).abs() < 1e-12);
+            }
+            other => panic!("expected a polynomial, got {:?}", other),
+        }
+        match scan_angle("scan_angle_deg = { table = [[-20, 2], [0, 1], [20, 2]] }").unwrap() {
+            ScanAngleUncertainty::Table { table } => {
+                assert!((table[0].0 + 20. * degree).abs() < 1e-15);
+                assert!((table[1].1 - degree).abs() < 1e-15);
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+        match scan_angle("scan_angle = { table = [[\"-20 deg\", \"30 urad\"], [0, 1e-5]] }")
+            .unwrap()
+        {
+            ScanAngleUncertainty::Table { table } => {
+                assert!((table[0].0 + 20. * degree).abs() < 1e-15);
+                assert!((table[0].1 - 3e-5).abs() < 1e-15);
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+        assert!(scan_angle("scan_angle = { polynomial = [\"1 deg\"] }").is_err());
+        assert!(scan_angle("scan_angle_deg = { other = 1 }").is_err());
+    }
+}
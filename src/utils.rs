@@ -1,64 +1,284 @@
 //! Utility functions.
 
-use crate::{Lasish, Matrix3, Measurement, Point};
+use crate::{spatial::KdTree, Error, Matrix3, Measurement, Point, PointSource};
 use nalgebra::{Dyn, OMatrix, U3};
+use std::f64::consts::PI;
 
-/// Fits a bunch of measurements to a plane in the platform's body frame.
+/// Wraps an angle, in radians, into `[-π, π)`.
 ///
-// Returns each measurement projected onto the plane, with the z value being the distance from the plane.
+/// Angles already in range are returned unchanged.
 ///
 /// # Examples
 ///
 /// ```
 /// # use leeward::utils;
-/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
-/// let points = utils::fit_to_plane_in_body_frame(&measurements);
-/// assert_eq!(measurements.len(), points.len());
+/// use std::f64::consts::PI;
+/// assert!((utils::normalize_angle(3. * PI / 2.) + PI / 2.).abs() < 1e-12);
+/// assert_eq!(-PI, utils::normalize_angle(PI));
+/// assert_eq!(0.1, utils::normalize_angle(0.1));
 /// ```
-pub fn fit_to_plane_in_body_frame<L: Lasish>(measurements: &[Measurement<L>]) -> Vec<Point> {
-    let mut points = OMatrix::<f64, Dyn, U3>::zeros(measurements.len());
-    for (i, measurement) in measurements.iter().enumerate() {
-        let body_frame = measurement.body_frame();
-        points[(i, 0)] = body_frame.x;
-        points[(i, 1)] = body_frame.y;
-        points[(i, 2)] = body_frame.z;
+pub fn normalize_angle(angle: f64) -> f64 {
+    if (-PI..PI).contains(&angle) {
+        angle
+    } else {
+        (angle + PI).rem_euclid(2. * PI) - PI
     }
-    let centroid = points.row_mean();
-    for (i, mean) in centroid.iter().enumerate() {
-        points.set_column(i, &points.column(i).add_scalar(-mean));
+}
+
+/// Returns the shortest signed rotation from `b` to `a`, in radians, in `[-π, π)`.
+///
+/// Use this instead of `a - b` for angles, like yaw, that wrap around at ±π.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::utils;
+/// let difference = utils::angle_difference(-3.1, 3.1);
+/// assert!((difference - (2. * std::f64::consts::PI - 6.2)).abs() < 1e-12);
+/// ```
+pub fn angle_difference(a: f64, b: f64) -> f64 {
+    normalize_angle(a - b)
+}
+
+/// A least-squares plane fit to points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaneFit {
+    /// The plane's unit normal, pointing up.
+    pub normal: Point,
+
+    /// The centroid of the points, which is on the plane.
+    pub centroid: Point,
+
+    /// The root mean square distance of the points from the plane.
+    pub rms: f64,
+
+    /// The plane's axes, as columns: the direction of most spread, the
+    /// in-plane direction perpendicular to it, and the normal.
+    ///
+    /// The axes are a right-handed orthonormal basis.
+    pub axes: Matrix3,
+
+    /// Each point in plane coordinates: its offsets from the centroid along the
+    /// two in-plane axes, and its signed distance from the plane.
+    pub points: Vec<Point>,
+}
+
+/// Fits a plane to points.
+///
+/// The plane goes through the points' centroid, and its normal is the
+/// direction of least spread from the singular value decomposition of the
+/// centered points, pointing up (towards positive z). Returns an error if there
+/// are fewer than three points.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::{utils, Point};
+/// let points = [
+///     Point::new(0., 0., 1.),
+///     Point::new(1., 0., 1.),
+///     Point::new(0., 2., 1.),
+///     Point::new(1., 2., 1.),
+/// ];
+/// let plane = utils::fit_plane(&points).unwrap();
+/// assert!((plane.normal.z - 1.).abs() < 1e-12);
+/// assert!(plane.rms < 1e-12);
+/// assert!((plane.centroid - Point::new(0.5, 1., 1.)).norm() < 1e-12);
+/// assert!(utils::fit_plane(&points[..2]).is_err());
+/// ```
+pub fn fit_plane(points: &[Point]) -> Result<PlaneFit, Error> {
+    if points.len() < 3 {
+        return Err(Error::Invalid(format!(
+            "need at least three points to fit a plane, got {}",
+            points.len()
+        )));
     }
-    let svd = points.transpose().svd(true, false);
-    let u = svd.u.unwrap();
-    let angle_to_x_axis = u.column(0).dot(&Point::new(1., 0., 0.)).acos();
-    let rotation_to_zy_plane = Matrix3::new(
-        angle_to_x_axis.cos(),
-        -angle_to_x_axis.sin(),
-        0.,
-        angle_to_x_axis.sin(),
-        angle_to_x_axis.cos(),
-        0.,
-        0.,
-        0.,
-        1.,
-    );
-    let points_as_matrix = points * rotation_to_zy_plane;
-    let mut points = Vec::new();
-    for i in 0..points_as_matrix.nrows() {
-        points.push(Point::new(
-            points_as_matrix[(i, 0)],
-            points_as_matrix[(i, 1)],
-            points_as_matrix[(i, 2)],
-        ));
+    let mut matrix = OMatrix::<f64, U3, Dyn>::zeros(points.len());
+    for (i, point) in points.iter().enumerate() {
+        matrix.set_column(i, point);
     }
-    points
+    let centroid: Point = matrix.column_mean();
+    for mut column in matrix.column_iter_mut() {
+        column -= centroid;
+    }
+    let u = matrix
+        .clone()
+        .svd(true, false)
+        .u
+        .expect("u was requested from the svd");
+    // Singular values are sorted, so the columns go from most to least spread.
+    let first: Point = u.column(0).into_owned();
+    let second: Point = u.column(1).into_owned();
+    // Flipping the second axis flips the normal and keeps the axes right-handed.
+    let second = if first.cross(&second).z < 0. {
+        -second
+    } else {
+        second
+    };
+    let normal = first.cross(&second);
+    let axes = Matrix3::from_columns(&[first, second, normal]);
+    let points: Vec<Point> = matrix
+        .column_iter()
+        .map(|column| axes.transpose() * column)
+        .collect();
+    let rms =
+        (points.iter().map(|point| point.z.powi(2)).sum::<f64>() / points.len() as f64).sqrt();
+    Ok(PlaneFit {
+        normal,
+        centroid,
+        rms,
+        axes,
+        points,
+    })
+}
+
+/// Fits a plane to measurements in the platform's body frame.
+///
+/// See [fit_plane].
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::utils;
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let plane = utils::fit_to_plane_in_body_frame(&measurements).unwrap();
+/// assert_eq!(measurements.len(), plane.points.len());
+/// assert!((plane.normal.norm() - 1.).abs() < 1e-12);
+/// ```
+pub fn fit_to_plane_in_body_frame<L: PointSource>(
+    measurements: &[Measurement<L>],
+) -> Result<PlaneFit, Error> {
+    let points: Vec<Point> = measurements
+        .iter()
+        .map(|measurement| measurement.body_frame())
+        .collect();
+    fit_plane(&points)
+}
+
+/// Returns the local roughness around each measurement.
+///
+/// The roughness is the rms distance of the measurements within `radius`
+/// meters of a measurement, including itself, from their best fit plane, see
+/// [fit_plane]. Large residuals on rough surfaces can be terrain, rather than
+/// sensor error. Measurements with fewer than three neighbors within the
+/// radius have no roughness.
+///
+/// # Examples
+///
+/// ```
+/// # use leeward::utils;
+/// let measurements = leeward::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+/// let roughness = utils::roughness(&measurements, 5.);
+/// assert_eq!(measurements.len(), roughness.len());
+/// ```
+pub fn roughness<L: PointSource>(measurements: &[Measurement<L>], radius: f64) -> Vec<Option<f64>> {
+    let tree = KdTree::from_measurements(measurements);
+    measurements
+        .iter()
+        .map(|measurement| {
+            let neighbors: Vec<Point> = tree
+                .within(measurement.lasish().point(), radius)
+                .into_iter()
+                .map(|(index, _)| tree.point(index))
+                .collect();
+            fit_plane(&neighbors).ok().map(|plane| plane.rms)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::{Matrix3, Point};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn angles_around_the_wrap() {
+        for &angle in &[PI - 1e-9, PI, PI + 1e-9, -PI, -PI - 1e-9, 3. * PI, -5. * PI] {
+            let normalized = super::normalize_angle(angle);
+            assert!((-PI..PI).contains(&normalized), "{}", angle);
+            assert!((normalized.sin() - angle.sin()).abs() < 1e-9, "{}", angle);
+            assert!((normalized.cos() - angle.cos()).abs() < 1e-9, "{}", angle);
+        }
+        assert!((super::angle_difference(-PI + 0.01, PI - 0.01) - 0.02).abs() < 1e-12);
+        assert!((super::angle_difference(PI - 0.01, -PI + 0.01) + 0.02).abs() < 1e-12);
+    }
+
     #[test]
     fn best_fitting_plane() {
         let measurements =
             crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
-        let _points = super::fit_to_plane_in_body_frame(&measurements);
+        let plane = super::fit_to_plane_in_body_frame(&measurements).unwrap();
+        let distances = measurements
+            .iter()
+            .map(|measurement| (measurement.body_frame() - plane.centroid).dot(&plane.normal));
+        let rms = (distances.map(|d| d * d).sum::<f64>() / measurements.len() as f64).sqrt();
+        assert!((plane.rms - rms).abs() < 1e-6);
+    }
+
+    #[test]
+    fn roughness() {
+        use crate::PointSource;
+        let measurements =
+            crate::measurements("data/sbet.out", "data/points.las", "data/config.toml").unwrap();
+        let points: Vec<Point> = measurements.iter().map(|m| m.lasish().point()).collect();
+        let everything = super::fit_plane(&points).unwrap().rms;
+        for roughness in super::roughness(&measurements, 1e6) {
+            assert!((roughness.unwrap() - everything).abs() < 1e-9);
+        }
+        assert!(super::roughness(&measurements, 1e-3)
+            .iter()
+            .all(Option::is_none));
+    }
+
+    /// Points on a grid in a plane through `origin`, spanned by `a` and `b`.
+    fn grid(origin: Point, a: Point, b: Point) -> Vec<Point> {
+        let mut points = Vec::new();
+        for i in 0..5 {
+            for j in 0..3 {
+                points.push(origin + f64::from(i) * a + f64::from(j) * b);
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn synthetic_planes() {
+        let origin = Point::new(100., -50., 2000.);
+        for (a, b) in [
+            (Point::new(1., 0., 0.), Point::new(0., 1., 0.)),
+            (Point::new(0., 0., 1.), Point::new(0., 1., 0.)),
+            (Point::new(1., 1., 0.5), Point::new(-1., 1., 0.)),
+            (Point::new(0.2, -0.3, 1.), Point::new(1., 0.1, 0.)),
+        ]
+        .iter()
+        {
+            let points = grid(origin, *a, *b);
+            let plane = super::fit_plane(&points).unwrap();
+            let normal = a.cross(b).normalize();
+            assert!((plane.normal.dot(&normal).abs() - 1.).abs() < 1e-9);
+            assert!(plane.rms < 1e-9);
+            assert!((plane.axes.transpose() * plane.axes - Matrix3::identity()).norm() < 1e-12);
+            assert!((plane.axes.determinant() - 1.).abs() < 1e-12);
+            for (point, projected) in points.iter().zip(&plane.points) {
+                assert!(projected.z.abs() < 1e-9);
+                assert!(((point - plane.centroid).norm() - projected.norm()).abs() < 1e-9);
+                assert!((plane.centroid + plane.axes * projected - point).norm() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn distance_from_a_synthetic_plane() {
+        let a = Point::new(1., 1., 0.5);
+        let b = Point::new(-1., 1., 0.);
+        let mut points = grid(Point::zeros(), a, b);
+        let normal = a.cross(&b).normalize();
+        points[7] += 0.1 * normal;
+        let plane = super::fit_plane(&points).unwrap();
+        let distances: Vec<f64> = plane.points.iter().map(|point| point.z).collect();
+        let outlier = distances[7] * plane.normal.dot(&normal).signum();
+        assert!(outlier > 0.09 && outlier < 0.1);
+        assert!(plane.rms > 0.02);
+        assert!(distances.iter().sum::<f64>().abs() < 1e-9);
     }
 }
@@ -0,0 +1,232 @@
+//! The dimensions and variables of the lidar equation.
+//!
+//! Both have a snake case name that round-trips through [Display](std::fmt::Display),
+//! [FromStr](std::str::FromStr), and serde, so command-line flags and config
+//! files can name them directly:
+//!
+//! ```
+//! use leeward::Variable;
+//! let variables: Vec<Variable> = "boresight_roll,boresight_pitch"
+//!     .split(',')
+//!     .map(|s| s.parse().unwrap())
+//!     .collect();
+//! assert_eq!(vec![Variable::BoresightRoll, Variable::BoresightPitch], variables);
+//! ```
+
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// The three dimensions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    X,
+    Y,
+    Z,
+}
+
+impl Dimension {
+    /// All three dimensions, in order.
+    pub const ALL: [Dimension; 3] = [Dimension::X, Dimension::Y, Dimension::Z];
+
+    /// Iterate over all three dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Dimension;
+    /// let mut iter = Dimension::iter();
+    /// assert_eq!(Dimension::X, iter.next().unwrap());
+    /// assert_eq!(Dimension::Y, iter.next().unwrap());
+    /// assert_eq!(Dimension::Z, iter.next().unwrap());
+    /// assert_eq!(None, iter.next());
+    /// ```
+    pub fn iter() -> DimensionIter {
+        DimensionIter { index: 0 }
+    }
+}
+
+/// An iterator over the dimensions.
+#[derive(Debug)]
+pub struct DimensionIter {
+    index: usize,
+}
+
+impl Iterator for DimensionIter {
+    type Item = Dimension;
+    fn next(&mut self) -> Option<Dimension> {
+        let dimension = Dimension::ALL.get(self.index).copied();
+        if dimension.is_some() {
+            self.index += 1;
+        }
+        dimension
+    }
+}
+
+impl std::fmt::Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Dimension::X => "x",
+            Dimension::Y => "y",
+            Dimension::Z => "z",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Dimension {
+    type Err = Error;
+
+    /// Parses a dimension from its lowercase name, e.g. `x`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::Dimension;
+    /// assert_eq!(Dimension::Z, "z".parse().unwrap());
+    /// assert_eq!("z", Dimension::Z.to_string());
+    /// ```
+    fn from_str(s: &str) -> Result<Dimension, Error> {
+        Dimension::iter()
+            .find(|dimension| dimension.to_string() == s)
+            .ok_or_else(|| Error::Parse(format!("unknown dimension: {}", s)))
+    }
+}
+
+/// The variables in the lidar equation.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variable {
+    Range,
+    ScanAngle,
+    BoresightRoll,
+    BoresightPitch,
+    BoresightYaw,
+    LeverArmX,
+    LeverArmY,
+    LeverArmZ,
+    Roll,
+    Pitch,
+    Yaw,
+    GnssX,
+    GnssY,
+    GnssZ,
+    ScanAngleOffset,
+    ScanAngleScale,
+}
+
+/// An iterator over the variables.
+#[derive(Debug)]
+pub struct VariableIter {
+    index: usize,
+}
+
+impl Variable {
+    /// All of the variables, in the order of the columns of the Jacobian.
+    pub const ALL: [Variable; 16] = [
+        Variable::Range,
+        Variable::ScanAngle,
+        Variable::BoresightRoll,
+        Variable::BoresightPitch,
+        Variable::BoresightYaw,
+        Variable::LeverArmX,
+        Variable::LeverArmY,
+        Variable::LeverArmZ,
+        Variable::Roll,
+        Variable::Pitch,
+        Variable::Yaw,
+        Variable::GnssX,
+        Variable::GnssY,
+        Variable::GnssZ,
+        Variable::ScanAngleOffset,
+        Variable::ScanAngleScale,
+    ];
+
+    /// Iterate over all of the variables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use leeward::Variable;
+    /// assert_eq!(Some(Variable::Range), Variable::iter().next());
+    /// assert_eq!(Variable::ALL.len(), Variable::iter().count());
+    /// ```
+    pub fn iter() -> VariableIter {
+        VariableIter { index: 0 }
+    }
+}
+
+impl Iterator for VariableIter {
+    type Item = Variable;
+    fn next(&mut self) -> Option<Variable> {
+        let variable = Variable::ALL.get(self.index).copied();
+        if variable.is_some() {
+            self.index += 1;
+        }
+        variable
+    }
+}
+
+impl std::fmt::Display for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Variable::Range => "range",
+            Variable::ScanAngle => "scan_angle",
+            Variable::BoresightRoll => "boresight_roll",
+            Variable::BoresightPitch => "boresight_pitch",
+            Variable::BoresightYaw => "boresight_yaw",
+            Variable::LeverArmX => "lever_arm_x",
+            Variable::LeverArmY => "lever_arm_y",
+            Variable::LeverArmZ => "lever_arm_z",
+            Variable::Roll => "roll",
+            Variable::Pitch => "pitch",
+            Variable::Yaw => "yaw",
+            Variable::GnssX => "gnss_x",
+            Variable::GnssY => "gnss_y",
+            Variable::GnssZ => "gnss_z",
+            Variable::ScanAngleOffset => "scan_angle_offset",
+            Variable::ScanAngleScale => "scan_angle_scale",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Variable {
+    type Err = Error;
+
+    /// Parses a variable from its snake case name, e.g. `boresight_roll`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use leeward::Variable;
+    /// assert_eq!(Variable::LeverArmX, "lever_arm_x".parse().unwrap());
+    /// assert_eq!("lever_arm_x", Variable::LeverArmX.to_string());
+    /// ```
+    fn from_str(s: &str) -> Result<Variable, Error> {
+        Variable::iter()
+            .find(|variable| variable.to_string() == s)
+            .ok_or_else(|| Error::Parse(format!("unknown variable: {}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dimension, Variable};
+
+    #[test]
+    fn names_round_trip() {
+        for variable in Variable::iter() {
+            assert_eq!(variable, variable.to_string().parse().unwrap());
+            let json = serde_json::to_string(&variable).unwrap();
+            assert_eq!(format!("\"{}\"", variable), json);
+        }
+        for dimension in Dimension::iter() {
+            assert_eq!(dimension, dimension.to_string().parse().unwrap());
+            let json = serde_json::to_string(&dimension).unwrap();
+            assert_eq!(format!("\"{}\"", dimension), json);
+        }
+        assert!("boresight".parse::<Variable>().is_err());
+        assert!("w".parse::<Dimension>().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! A JavaScript-friendly API for WebAssembly.
+//!
+//! There's no filesystem in the browser, so the trajectory and config are
+//! passed in as bytes and a string, e.g. from `fetch` or a file input.
+//!
+//! # Examples
+//!
+//! ```
+//! use leeward::wasm::{LeewardWasm, WasmPoint};
+//! let sbet = std::fs::read("data/sbet.out").unwrap();
+//! let config = std::fs::read_to_string("data/config.toml").unwrap();
+//! let leeward = LeewardWasm::new(&sbet, &config).unwrap();
+//! let point = WasmPoint::new(320000.34, 4181319.35, 2687.58, 22., 400825.8057);
+//! let tpu = leeward.tpu(&point).unwrap();
+//! assert!(tpu.total > 0.);
+//! ```
+
+use crate::{Config, Measurement, Point, PointSource, Trajectory};
+use wasm_bindgen::prelude::*;
+
+/// Computes measurements from an in-memory trajectory and config.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct LeewardWasm {
+    trajectory: Trajectory,
+    config: Config,
+}
+
+/// A lidar point, with its scan angle in degrees.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct WasmPoint {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub scan_angle: f64,
+    pub time: f64,
+}
+
+/// The total propagated uncertainty of a point.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct WasmTpu {
+    pub horizontal: f64,
+    pub vertical: f64,
+    pub total: f64,
+    pub incidence_angle: f64,
+}
+
+#[wasm_bindgen]
+impl LeewardWasm {
+    /// Creates a new leeward from the bytes of an sbet file and a config toml string.
+    #[wasm_bindgen(constructor)]
+    pub fn new(sbet: &[u8], config: &str) -> Result<LeewardWasm, JsError> {
+        Ok(LeewardWasm {
+            trajectory: Trajectory::from_bytes(sbet)?,
+            config: config.parse()?,
+        })
+    }
+
+    /// Computes the total propagated uncertainty of a point on a horizontal surface.
+    pub fn tpu(&self, point: &WasmPoint) -> Result<WasmTpu, JsError> {
+        self.tpu_with_normal(point, 0., 0., 1.)
+    }
+
+    /// Computes the total propagated uncertainty of a point on a surface with the given normal.
+    #[wasm_bindgen(js_name = tpuWithNormal)]
+    pub fn tpu_with_normal(
+        &self,
+        point: &WasmPoint,
+        normal_x: f64,
+        normal_y: f64,
+        normal_z: f64,
+    ) -> Result<WasmTpu, JsError> {
+        let measurement = Measurement::new(&self.trajectory, *point, self.config.clone())?;
+        let tpu = measurement.tpu(Point::new(normal_x, normal_y, normal_z))?;
+        Ok(WasmTpu {
+            horizontal: tpu.horizontal,
+            vertical: tpu.vertical,
+            total: tpu.total,
+            incidence_angle: tpu.incidence_angle,
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl WasmPoint {
+    /// Creates a new point.
+    #[wasm_bindgen(constructor)]
+    pub fn new(x: f64, y: f64, z: f64, scan_angle: f64, time: f64) -> WasmPoint {
+        WasmPoint {
+            x,
+            y,
+            z,
+            scan_angle,
+            time,
+        }
+    }
+}
+
+impl PointSource for WasmPoint {
+    type Extra = ();
+
+    fn time(&self) -> Option<f64> {
+        Some(self.time)
+    }
+
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn z(&self) -> f64 {
+        self.z
+    }
+
+    fn scan_angle(&self) -> f64 {
+        self.scan_angle
+    }
+}